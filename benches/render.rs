@@ -0,0 +1,61 @@
+//! Benchmarks for the stages between a keymap.c and its SVG: parsing, board
+//! detection, and rendering. Run with `cargo bench`.
+use criterion::{Criterion, black_box, criterion_group, criterion_main};
+use keyball44_viz::{
+    generate_single_card_svg_with_options, generate_svg_with_options, geometry, parse_layers,
+};
+
+#[path = "../tests/common/mod.rs"]
+mod common;
+use common::{DEMO_KEYMAP, large_keymap, options_for};
+
+fn parsing(c: &mut Criterion) {
+    let large = large_keymap(32);
+    let mut group = c.benchmark_group("parse");
+    group.bench_function("demo", |b| b.iter(|| parse_layers(black_box(DEMO_KEYMAP))));
+    group.bench_function("32 layers", |b| b.iter(|| parse_layers(black_box(&large))));
+    group.finish();
+}
+
+fn layout(c: &mut Criterion) {
+    let layers = parse_layers(DEMO_KEYMAP).unwrap();
+    let mut group = c.benchmark_group("layout");
+    group.bench_function("detect board", |b| {
+        b.iter(|| geometry::detect(black_box(&layers)))
+    });
+    group.bench_function("position names", |b| {
+        let geometry = options_for(&layers).geometry;
+        b.iter(|| black_box(&geometry).position_names())
+    });
+    group.finish();
+}
+
+fn rendering(c: &mut Criterion) {
+    let demo = parse_layers(DEMO_KEYMAP).unwrap();
+    let large = parse_layers(&large_keymap(10)).unwrap();
+    let (demo_options, large_options) = (options_for(&demo), options_for(&large));
+    let mut group = c.benchmark_group("render");
+    group.bench_function("demo", |b| {
+        b.iter(|| generate_svg_with_options(black_box(&demo), &demo_options))
+    });
+    group.bench_function("demo single card", |b| {
+        b.iter(|| generate_single_card_svg_with_options(black_box(&demo), &demo_options))
+    });
+    group.bench_function("10 layers", |b| {
+        b.iter(|| generate_svg_with_options(black_box(&large), &large_options))
+    });
+    group.finish();
+}
+
+fn end_to_end(c: &mut Criterion) {
+    let source = large_keymap(10);
+    c.bench_function("end to end/10 layers", |b| {
+        b.iter(|| {
+            let layers = parse_layers(black_box(&source)).unwrap();
+            generate_svg_with_options(&layers, &options_for(&layers))
+        })
+    });
+}
+
+criterion_group!(benches, parsing, layout, rendering, end_to_end);
+criterion_main!(benches);
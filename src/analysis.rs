@@ -0,0 +1,1229 @@
+//! Static checks over parsed layers that suggest keymap cleanups.
+use crate::geometry::{Geometry, Half, KeyPosition};
+use crate::{
+    Layer, extract_layer_number, is_empty_key, keycodes, layer_argument, layer_symbol_table,
+};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+
+/// How closely a layer matches an earlier one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Similarity {
+    /// Every key is the same
+    Identical,
+    /// Every key of one layer that is not transparent is the same key on
+    /// the other layer
+    IdenticalIgnoringTransparent,
+}
+
+/// A layer that duplicates an earlier layer.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DuplicateLayer {
+    /// Index of the duplicate layer
+    pub layer: usize,
+    /// Index of the earlier layer it duplicates
+    pub original: usize,
+    pub similarity: Similarity,
+}
+
+/// Returns `true` for keys that fall through to the layer below.
+fn is_transparent(key: &str) -> bool {
+    is_empty_key(key) || key == "KC_TRNS" || key == "KC_TRANSPARENT"
+}
+
+/// Describes a layer for reports, e.g. `layer 3 (_NAV)`.
+pub fn layer_reference(layer: &Layer) -> String {
+    match layer.display_name() {
+        Some(name) => format!("layer {} ({})", layer.index, name),
+        None => format!("layer {}", layer.index),
+    }
+}
+
+fn compare_layers(a: &Layer, b: &Layer) -> Option<Similarity> {
+    let (a_keys, b_keys): (Vec<&String>, Vec<&String>) = (
+        a.keys.iter().flatten().collect(),
+        b.keys.iter().flatten().collect(),
+    );
+    if a_keys.len() != b_keys.len() {
+        return None;
+    }
+
+    if a_keys == b_keys {
+        return Some(Similarity::Identical);
+    }
+
+    // The positions of the keys that are not transparent. Layers that are
+    // entirely transparent match anything; skip them
+    let opaque = |keys: &[&String]| -> BTreeSet<usize> {
+        keys.iter()
+            .enumerate()
+            .filter(|(_, key)| !is_transparent(key))
+            .map(|(position, _)| position)
+            .collect()
+    };
+    let (a_opaque, b_opaque) = (opaque(&a_keys), opaque(&b_keys));
+    if a_opaque.is_empty() || b_opaque.is_empty() {
+        return None;
+    }
+
+    // Sparse layers that fill complementary positions are not duplicates:
+    // one layer's keys must all be keys of the other
+    let nested = a_opaque.is_subset(&b_opaque) || b_opaque.is_subset(&a_opaque);
+    let compatible = a_keys
+        .iter()
+        .zip(&b_keys)
+        .all(|(a, b)| a == b || is_transparent(a) || is_transparent(b));
+
+    (nested && compatible).then_some(Similarity::IdenticalIgnoringTransparent)
+}
+
+/// Finds layers that repeat an earlier layer and could be consolidated.
+///
+/// Each layer is reported at most once, against the first earlier layer it
+/// matches.
+///
+/// # Arguments
+///
+/// * `layers` - The parsed keymap layers
+///
+/// # Returns
+///
+/// The duplicate layers in layer order
+pub fn find_duplicate_layers(layers: &[Layer]) -> Vec<DuplicateLayer> {
+    layers
+        .iter()
+        .enumerate()
+        .filter_map(|(pos, layer)| {
+            layers[..pos].iter().find_map(|original| {
+                compare_layers(original, layer).map(|similarity| DuplicateLayer {
+                    layer: layer.index,
+                    original: original.index,
+                    similarity,
+                })
+            })
+        })
+        .collect()
+}
+
+/// How a key moves between layers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum EdgeKind {
+    /// Active while held (`MO`, `LT`, `LM`)
+    Momentary,
+    /// Active while held, toggled on tap (`TT`)
+    TapToggle,
+    /// Toggled on and off (`TG`)
+    Toggle,
+    /// Active for the next key press (`OSL`)
+    OneShot,
+    /// Switches to the layer (`TO`, `DF`)
+    Switch,
+    /// Keeps the current layer on after its activating key is released
+    Lock,
+}
+
+impl EdgeKind {
+    /// The kind's name, as in `--emit json`.
+    pub fn name(self) -> &'static str {
+        match self {
+            EdgeKind::Momentary => "momentary",
+            EdgeKind::TapToggle => "tap-toggle",
+            EdgeKind::Toggle => "toggle",
+            EdgeKind::OneShot => "one-shot",
+            EdgeKind::Switch => "switch",
+            EdgeKind::Lock => "lock",
+        }
+    }
+}
+
+/// A key on layer `from` that activates layer `to`.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct LayerEdge {
+    pub from: usize,
+    pub to: usize,
+    pub kind: EdgeKind,
+    /// The keycode creating the edge
+    pub key: String,
+    /// The modifiers `LM` holds along with the layer, as written (e.g.
+    /// `MOD_LSFT`); `None` for other keys
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub modifier: Option<String>,
+}
+
+impl LayerEdge {
+    /// Text labeling the edge in a layer graph: its kind, and the modifier
+    /// of `LM` keys, e.g. `momentary + MOD_LSFT`.
+    pub fn label(&self) -> String {
+        match &self.modifier {
+            Some(modifier) => format!("{} + {}", self.kind.name(), modifier),
+            None => self.kind.name().to_string(),
+        }
+    }
+}
+
+/// The modifier argument of an `LM(layer, mod)` key.
+fn layer_mod_modifier(key: &str) -> Option<String> {
+    let args = key.trim().strip_prefix("LM(")?.strip_suffix(')')?;
+    let (_, modifier) = args.split_once(',')?;
+    Some(modifier.trim().to_string()).filter(|modifier| !modifier.is_empty())
+}
+
+fn edge_kind(key: &str) -> Option<EdgeKind> {
+    let name = key.split('(').next()?.trim();
+    match name {
+        "MO" | "LT" | "LM" => Some(EdgeKind::Momentary),
+        "TT" => Some(EdgeKind::TapToggle),
+        "TG" => Some(EdgeKind::Toggle),
+        "OSL" => Some(EdgeKind::OneShot),
+        "TO" | "DF" => Some(EdgeKind::Switch),
+        _ => None,
+    }
+}
+
+/// Collects every layer-switching key as an edge between layers.
+///
+/// Layer lock keys produce a `Lock` edge from their layer to itself.
+///
+/// # Arguments
+///
+/// * `layers` - The parsed keymap layers
+///
+/// # Returns
+///
+/// The edges in layer and key order
+pub fn layer_edges(layers: &[Layer]) -> Vec<LayerEdge> {
+    let mut edges = Vec::new();
+    let symbols = layer_symbol_table(layers);
+
+    for layer in layers {
+        for key in layer.keys.iter().flatten() {
+            let edge = if keycodes::is_layer_lock(key) {
+                Some((layer.index, EdgeKind::Lock))
+            } else {
+                extract_layer_number(key, &symbols).zip(edge_kind(key))
+            };
+
+            if let Some((to, kind)) = edge {
+                edges.push(LayerEdge {
+                    from: layer.index,
+                    to,
+                    kind,
+                    key: key.clone(),
+                    modifier: layer_mod_modifier(key),
+                });
+            }
+        }
+    }
+
+    edges
+}
+
+/// Finds layers that no chain of layer keys starting at the first layer
+/// activates.
+///
+/// Such layers may still be reached from code (e.g. tri-layer or
+/// `layer_state_set_user`), so callers should report them as hints.
+///
+/// # Arguments
+///
+/// * `layers` - The parsed keymap layers
+///
+/// # Returns
+///
+/// Indices of the unreachable layers in layer order
+pub fn unreachable_layers(layers: &[Layer]) -> Vec<usize> {
+    let Some(base) = layers.first() else {
+        return Vec::new();
+    };
+
+    let edges = layer_edges(layers);
+    let mut reached = BTreeSet::from([base.index]);
+    let mut pending = vec![base.index];
+
+    while let Some(from) = pending.pop() {
+        // Self-loops such as lock edges lead nowhere new
+        for edge in edges
+            .iter()
+            .filter(|edge| edge.from == from && edge.to != from)
+        {
+            if reached.insert(edge.to) {
+                pending.push(edge.to);
+            }
+        }
+    }
+
+    layers
+        .iter()
+        .map(|layer| layer.index)
+        .filter(|index| !reached.contains(index))
+        .collect()
+}
+
+/// A key holding a layer whose own key at the same position isn't
+/// transparent, shadowing the held key there.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HoldConflict {
+    /// Index of the layer with the layer key
+    pub layer: usize,
+    /// Index of the position among the LAYOUT arguments
+    pub position: usize,
+    /// The layer key, e.g. `LT(2, KC_SPC)`
+    pub key: String,
+    /// Index of the layer the key holds
+    pub target: usize,
+    /// The key the held layer has at the position
+    pub shadow: String,
+}
+
+/// Finds held layer keys (`MO`, `LT`, `LM`, `TT`) whose position is taken
+/// by another key on the layer they hold.
+///
+/// The thumb holding a layer stays on its key, so that position on the held
+/// layer is usually left transparent; anything else there is easy to miss
+/// and unreachable while the layer is held. Keys repeating the layer key are
+/// not reported.
+///
+/// # Arguments
+///
+/// * `layers` - The parsed keymap layers
+///
+/// # Returns
+///
+/// The conflicts in layer and key order
+pub fn hold_conflicts(layers: &[Layer]) -> Vec<HoldConflict> {
+    let symbols = layer_symbol_table(layers);
+    let mut conflicts = Vec::new();
+
+    for layer in layers {
+        for (position, key) in layer.keys.iter().flatten().enumerate() {
+            if !matches!(
+                edge_kind(key),
+                Some(EdgeKind::Momentary | EdgeKind::TapToggle)
+            ) {
+                continue;
+            }
+            let Some(target) = extract_layer_number(key, &symbols) else {
+                continue;
+            };
+            let Some(shadow) = layers
+                .iter()
+                .filter(|other| other.index == target && target != layer.index)
+                .find_map(|other| other.keys.iter().flatten().nth(position))
+            else {
+                continue;
+            };
+            if is_transparent(shadow) || shadow == key {
+                continue;
+            }
+            conflicts.push(HoldConflict {
+                layer: layer.index,
+                position,
+                key: key.clone(),
+                target,
+                shadow: shadow.clone(),
+            });
+        }
+    }
+
+    conflicts
+}
+
+/// How base variants differ at one key position.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VariantChange {
+    /// Different letters with the same hold behavior, as expected between
+    /// alpha layouts
+    Letters,
+    /// Symbols, modifiers, or other keys that differ, which usually should
+    /// stay put across variants
+    Other,
+}
+
+/// A key position where the base variants differ.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VariantDifference {
+    /// Index of the key in LAYOUT argument order
+    pub position: usize,
+    /// The key on each variant, in the order of [`BaseVariants::layers`]
+    pub keys: Vec<String>,
+    pub change: VariantChange,
+}
+
+/// Base layers the keymap switches between with `DF()`, such as QWERTY and
+/// Colemak, and where they differ.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BaseVariants {
+    /// The first layer and every `DF()` target, in layer order
+    pub layers: Vec<usize>,
+    /// Positions where the variants have different keys
+    pub differences: Vec<VariantDifference>,
+}
+
+impl BaseVariants {
+    /// Number of differences of the given kind.
+    pub fn count(&self, change: VariantChange) -> usize {
+        self.differences
+            .iter()
+            .filter(|difference| difference.change == change)
+            .count()
+    }
+}
+
+/// Splits a key into its hold behavior and the keycode it taps, e.g.
+/// `LCTL_T(KC_A)` into `("LCTL_T(", "KC_A")` and `KC_A` into `("", "KC_A")`.
+pub(crate) fn split_tap(key: &str) -> (&str, &str) {
+    let key = key.trim();
+    let Some(inner) = key.strip_suffix(')') else {
+        return ("", key);
+    };
+    match inner.rfind([',', '(']) {
+        Some(idx) => (&key[..=idx], inner[idx + 1..].trim()),
+        None => ("", key),
+    }
+}
+
+/// Returns `true` for the letter keycodes `KC_A` through `KC_Z`.
+fn is_letter(key: &str) -> bool {
+    key.strip_prefix("KC_")
+        .is_some_and(|name| name.len() == 1 && name.chars().all(|c| c.is_ascii_uppercase()))
+}
+
+/// Compares the base layers a keymap switches between with `DF()`.
+///
+/// Positions where every variant taps a letter and holds the same thing are
+/// expected alpha differences; any other difference is reported separately,
+/// since symbols and modifiers usually stay in place across variants. Keys
+/// that are transparent on a variant and the `DF()` keys themselves are not
+/// compared.
+///
+/// # Arguments
+///
+/// * `layers` - The parsed keymap layers
+///
+/// # Returns
+///
+/// The variants and their differences, or `None` if the keymap has a single
+/// base layer
+pub fn base_variants(layers: &[Layer]) -> Option<BaseVariants> {
+    let base = layers.first()?;
+    let mut variants = vec![base.index];
+    for edge in layer_edges(layers) {
+        if edge.key.trim_start().starts_with("DF(")
+            && layers.iter().any(|layer| layer.index == edge.to)
+            && !variants.contains(&edge.to)
+        {
+            variants.push(edge.to);
+        }
+    }
+    if variants.len() < 2 {
+        return None;
+    }
+    variants.sort_unstable();
+
+    let keys: Vec<Vec<&String>> = variants
+        .iter()
+        .filter_map(|index| layers.iter().find(|layer| layer.index == *index))
+        .map(|layer| layer.keys.iter().flatten().collect())
+        .collect();
+    let key_count = keys.iter().map(Vec::len).min().unwrap_or_default();
+
+    let mut differences = Vec::new();
+    for position in 0..key_count {
+        let at: Vec<&String> = keys.iter().map(|keys| keys[position]).collect();
+        // The keys switching between variants differ by design
+        let is_switch = |key: &&String| key.trim_start().starts_with("DF(");
+        if at.iter().all(|key| *key == at[0])
+            || at.iter().any(|key| is_transparent(key))
+            || at.iter().all(is_switch)
+        {
+            continue;
+        }
+
+        let (hold, _) = split_tap(at[0]);
+        let letters = at.iter().all(|key| {
+            let (key_hold, tap) = split_tap(key);
+            key_hold == hold && is_letter(tap)
+        });
+        differences.push(VariantDifference {
+            position,
+            keys: at.into_iter().cloned().collect(),
+            change: if letters {
+                VariantChange::Letters
+            } else {
+                VariantChange::Other
+            },
+        });
+    }
+
+    Some(BaseVariants {
+        layers: variants,
+        differences,
+    })
+}
+
+/// QMK's default `TAPPING_TOGGLE`: taps of a `TT` key that toggle its layer.
+const DEFAULT_TAPPING_TOGGLE: u32 = 5;
+
+/// Reads `TAPPING_TOGGLE` from the keymap and config.h sources.
+///
+/// # Arguments
+///
+/// * `sources` - The keymap.c source and, if available, its config.h
+///
+/// # Returns
+///
+/// The number of taps of a `TT` key that toggle its layer, or QMK's default
+pub fn tapping_toggle(sources: &[&str]) -> u32 {
+    let define = Regex::new(r"(?m)^\s*#\s*define\s+TAPPING_TOGGLE\s+(\d+)").unwrap();
+    sources
+        .iter()
+        .filter_map(|source| define.captures(source))
+        .filter_map(|caps| caps[1].parse().ok())
+        .next_back()
+        .unwrap_or(DEFAULT_TAPPING_TOGGLE)
+}
+
+/// Readable name of the layer a layer key refers to, e.g. `NAV` for `_NAV`.
+fn layer_label(layers: &[Layer], target: &str) -> String {
+    match layers.iter().find(|layer| layer.matches_selector(target)) {
+        Some(Layer {
+            name: Some(name), ..
+        }) => name.trim_start_matches('_').to_string(),
+        Some(layer) => format!("layer {}", layer.index),
+        None => match target.parse::<usize>() {
+            Ok(index) => format!("layer {}", index),
+            Err(_) => target.trim_start_matches('_').to_string(),
+        },
+    }
+}
+
+/// Explains what each layer-switching key does, in plain language for
+/// readers who don't know QMK keycodes.
+///
+/// Each entry pairs the action with its effect, e.g. `("Hold left thumb 2",
+/// "NAV")` or `("On NAV, tap right thumb 1", "switch to BASE")`. Keys on the
+/// base layer are described without naming it.
+///
+/// # Arguments
+///
+/// * `layers` - The parsed keymap layers
+/// * `geometry` - The board, used to describe where each key sits
+/// * `tapping_toggle` - Taps of a `TT` key that toggle its layer (see
+///   [`tapping_toggle`])
+///
+/// # Returns
+///
+/// The entries in layer and key order
+pub fn layer_key_docs(
+    layers: &[Layer],
+    geometry: &Geometry,
+    tapping_toggle: u32,
+) -> Vec<(String, String)> {
+    let positions = geometry.position_descriptions();
+    let taps = match tapping_toggle {
+        1 => "tap".to_string(),
+        2 => "double-tap".to_string(),
+        n => format!("tap {} times", n),
+    };
+    let mut docs = Vec::new();
+
+    for (layer_pos, layer) in layers.iter().enumerate() {
+        let on_layer = layer_label(layers, &layer.index.to_string());
+        for (pos, key) in layer.keys.iter().flatten().enumerate() {
+            let key = key.trim();
+            let position = positions
+                .get(pos)
+                .cloned()
+                .unwrap_or_else(|| format!("key {}", pos + 1));
+
+            let mut actions: Vec<(&str, String)> = Vec::new();
+            if keycodes::is_layer_lock(key) {
+                actions.push(("tap", format!("keep {} on", on_layer)));
+            } else if let (Some(open), Some(close)) = (key.find('('), key.rfind(')'))
+                && open < close
+            {
+                let args = &key[open + 1..close];
+                let (target, rest) = match args.split_once(',') {
+                    Some((target, rest)) => (target.trim(), Some(rest.trim())),
+                    None => (args.trim(), None),
+                };
+                let target = layer_label(layers, target);
+                match (&key[..open], rest) {
+                    ("MO", None) => actions.push(("hold", target)),
+                    ("LT", Some(tap)) => actions.push((
+                        "hold",
+                        format!(
+                            "{}; tap for {}",
+                            target,
+                            tap.strip_prefix("KC_").unwrap_or(tap)
+                        ),
+                    )),
+                    ("LM", Some(_)) => {
+                        let modifiers = keycodes::layer_mod(key)
+                            .map(|layer_mod| layer_mod.modifiers.join("+"))
+                            .unwrap_or_default();
+                        actions.push(("hold", format!("{} with {} held", target, modifiers)));
+                    }
+                    ("TT", None) => {
+                        actions.push(("hold", target.clone()));
+                        actions.push((taps.as_str(), format!("toggle {}", target)));
+                    }
+                    ("TG", None) => actions.push(("tap", format!("toggle {}", target))),
+                    ("OSL", None) => actions.push(("tap", format!("{} for the next key", target))),
+                    ("TO", None) => actions.push(("tap", format!("switch to {}", target))),
+                    ("DF", None) => {
+                        actions.push(("tap", format!("make {} the default layer", target)))
+                    }
+                    _ => {}
+                }
+            }
+
+            for (verb, effect) in actions {
+                let action = if layer_pos == 0 {
+                    let mut verb = verb.to_string();
+                    verb[..1].make_ascii_uppercase();
+                    format!("{} {}", verb, position)
+                } else {
+                    format!("On {}, {} {}", on_layer, verb, position)
+                };
+                docs.push((action, effect));
+            }
+        }
+    }
+
+    docs
+}
+
+/// The keys on a board's thumb positions, side by side across layers.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ThumbKeys {
+    /// Column headings: each layer's name, or `layer N`
+    pub layers: Vec<String>,
+    /// Each thumb position's description, e.g. `left thumb 1`, and its key
+    /// on every layer
+    pub rows: Vec<(String, Vec<String>)>,
+}
+
+/// Collects what every layer puts on the thumb keys, the most contested keys
+/// of a small split board.
+///
+/// # Arguments
+///
+/// * `layers` - The parsed keymap layers
+/// * `geometry` - The board; thumb keys are the last row of a split board
+///
+/// # Returns
+///
+/// The table, without rows if the board has no thumb keys
+pub fn thumb_keys(layers: &[Layer], geometry: &Geometry) -> ThumbKeys {
+    let descriptions = geometry.position_descriptions();
+    let keys: Vec<Vec<&String>> = layers
+        .iter()
+        .map(|layer| layer.keys.iter().flatten().collect())
+        .collect();
+    let rows = geometry
+        .key_locations()
+        .into_iter()
+        .zip(descriptions)
+        .enumerate()
+        .filter(|(_, ((_, row, _), _))| row.is_none())
+        .map(|(pos, (_, description))| {
+            let assignments = keys
+                .iter()
+                .map(|keys| {
+                    keys.get(pos)
+                        .map_or(String::new(), |key| key.trim().to_string())
+                })
+                .collect();
+            (description, assignments)
+        })
+        .collect();
+    ThumbKeys {
+        layers: layers
+            .iter()
+            .map(|layer| layer_label(layers, &layer.index.to_string()))
+            .collect(),
+        rows,
+    }
+}
+
+/// A layer that repeats one hand of the base layer, mirrored, under the
+/// other hand, as swap-hands layers for one-handed typing do.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MirroredLayer {
+    /// Index of the mirrored layer
+    pub layer: usize,
+    /// The half the mirrored keys are on
+    pub half: Half,
+    /// `(base position, mirrored position)` key indices of every base key
+    /// found at its mirror image
+    pub pairs: Vec<(usize, usize)>,
+}
+
+/// Fewest base keys a layer has to mirror to count as a mirrored layer.
+const MIN_MIRRORED_KEYS: usize = 6;
+
+/// Finds layers that mirror one hand of the base layer onto the other.
+///
+/// A layer mirrors a hand when at least three quarters of that hand's
+/// assigned base keys reappear at their mirror image positions (see
+/// [`Geometry::mirror_pairs`]), and at least [`MIN_MIRRORED_KEYS`] of them.
+/// Keys the base layer already has at both positions don't count.
+///
+/// # Arguments
+///
+/// * `layers` - The parsed layers; the first is the base layer
+/// * `geometry` - The split board the layers are drawn on
+///
+/// # Returns
+///
+/// The mirrored layers in layer order
+pub fn mirrored_layers(layers: &[Layer], geometry: &Geometry) -> Vec<MirroredLayer> {
+    let Some((base, rest)) = layers.split_first() else {
+        return Vec::new();
+    };
+    let base: Vec<&String> = base.keys.iter().flatten().collect();
+    let mirror_pairs = geometry.mirror_pairs();
+
+    let mut mirrored = Vec::new();
+    for layer in rest {
+        let keys: Vec<&String> = layer.keys.iter().flatten().collect();
+        for half in [Half::Left, Half::Right] {
+            // Base keys of the other hand and where their mirror image lies
+            // on this half
+            let candidates: Vec<(usize, usize)> = mirror_pairs
+                .iter()
+                .map(|&(left, right)| match half {
+                    Half::Left => (right, left),
+                    Half::Right => (left, right),
+                })
+                .filter(|&(from, to)| {
+                    base.get(from)
+                        .is_some_and(|key| !is_transparent(key) && !is_empty_key(key))
+                        && base.get(from) != base.get(to)
+                })
+                .collect();
+            let pairs: Vec<(usize, usize)> = candidates
+                .iter()
+                .copied()
+                .filter(|&(from, to)| keys.get(to).is_some() && keys.get(to) == base.get(from))
+                .collect();
+            if pairs.len() >= MIN_MIRRORED_KEYS && pairs.len() * 4 >= candidates.len() * 3 {
+                mirrored.push(MirroredLayer {
+                    layer: layer.index,
+                    half,
+                    pairs,
+                });
+            }
+        }
+    }
+    mirrored
+}
+
+/// What a key does, as named in alt text and JSON output; alt text describes
+/// the kinds in this order.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum KeyCategory {
+    Letter,
+    Digit,
+    Symbol,
+    FunctionKey,
+    Arrow,
+    Navigation,
+    Editing,
+    Modifier,
+    LayerKey,
+    Media,
+    Mouse,
+    System,
+    Other,
+    /// Falls through to the layer below
+    Transparent,
+    /// Does nothing (`KC_NO`)
+    Unassigned,
+}
+
+impl KeyCategory {
+    fn name(self) -> &'static str {
+        match self {
+            KeyCategory::Letter => "letters",
+            KeyCategory::Digit => "digits",
+            KeyCategory::Symbol => "symbols",
+            KeyCategory::FunctionKey => "function keys",
+            KeyCategory::Arrow => "arrows",
+            KeyCategory::Navigation => "page up/down, home, and end",
+            KeyCategory::Editing => "editing keys",
+            KeyCategory::Modifier => "modifiers",
+            KeyCategory::LayerKey => "layer keys",
+            KeyCategory::Media => "media keys",
+            KeyCategory::Mouse => "mouse keys",
+            KeyCategory::System => "system keys",
+            KeyCategory::Other => "other keys",
+            KeyCategory::Transparent => "transparent keys",
+            KeyCategory::Unassigned => "unassigned keys",
+        }
+    }
+
+    /// Categorizes a keycode by what it types when tapped; layer and
+    /// modifier keys are categorized by what they hold.
+    pub fn of(key: &str) -> KeyCategory {
+        if is_transparent(key) {
+            return KeyCategory::Transparent;
+        }
+        if key == "KC_NO" || key == "XXXXXXX" {
+            return KeyCategory::Unassigned;
+        }
+        if layer_argument(key).is_some()
+            || keycodes::layer_mod(key).is_some()
+            || keycodes::is_layer_lock(key)
+        {
+            return KeyCategory::LayerKey;
+        }
+        if keycodes::is_modifier(key) {
+            return KeyCategory::Modifier;
+        }
+        if keycodes::system_keycode(key).is_some()
+            || ["QK_", "RGB_", "BL_", "RESET", "EE_"]
+                .iter()
+                .any(|prefix| key.starts_with(prefix))
+        {
+            return KeyCategory::System;
+        }
+        if keycodes::pointer_keycode(key).is_some()
+            || ["KC_BTN", "KC_MS_", "KC_WH_", "KC_ACL", "MS_"]
+                .iter()
+                .any(|prefix| key.starts_with(prefix))
+        {
+            return KeyCategory::Mouse;
+        }
+
+        let (_, tap) = split_tap(key);
+        if is_letter(tap) {
+            return KeyCategory::Letter;
+        }
+        if key.starts_with("S(") || key.starts_with("LSFT(") {
+            return KeyCategory::Symbol;
+        }
+        let name = tap.strip_prefix("KC_").unwrap_or(tap);
+        match name {
+            "LEFT" | "RIGHT" | "RGHT" | "UP" | "DOWN" => KeyCategory::Arrow,
+            "PGUP" | "PGDN" | "PAGE_UP" | "PAGE_DOWN" | "HOME" | "END" => KeyCategory::Navigation,
+            "TAB" | "ENT" | "ENTER" | "BSPC" | "BACKSPACE" | "DEL" | "DELETE" | "SPC" | "SPACE"
+            | "ESC" | "ESCAPE" | "INS" | "INSERT" => KeyCategory::Editing,
+            "MUTE" | "VOLU" | "VOLD" | "MPLY" | "MNXT" | "MPRV" | "MSTP" | "BRIU" | "BRID"
+            | "AUDIO_MUTE" | "AUDIO_VOL_UP" | "AUDIO_VOL_DOWN" => KeyCategory::Media,
+            _ if name.len() == 1 && name.chars().all(|c| c.is_ascii_digit()) => KeyCategory::Digit,
+            _ if name.starts_with('F') && name[1..].parse::<u8>().is_ok() => {
+                KeyCategory::FunctionKey
+            }
+            _ if keycodes::basic_keycode(tap).is_some() => KeyCategory::Symbol,
+            _ => KeyCategory::Other,
+        }
+    }
+}
+
+/// Names a row of a board by its place among the main (non-thumb) rows,
+/// e.g. `home`, if the board's rows have names.
+fn row_name(row: usize, rows: usize) -> Option<&'static str> {
+    let names: &[&str] = match rows {
+        3 => &["top", "home", "bottom"],
+        4 => &["number", "top", "home", "bottom"],
+        _ => &[],
+    };
+    names.get(row - 1).copied()
+}
+
+/// Where a key sits: its half and main-block row, when the board has them.
+type KeyLocation = (Option<Half>, Option<usize>);
+
+/// Describes where a set of keys sits, e.g. `on the right home row` or
+/// `across both halves`.
+fn describe_region(locations: &[KeyLocation], rows: usize) -> String {
+    let mut halves: Vec<Option<Half>> = Vec::new();
+    for (half, _) in locations {
+        if !halves.contains(half) {
+            halves.push(*half);
+        }
+    }
+    let key_rows: BTreeSet<Option<usize>> = locations.iter().map(|(_, row)| *row).collect();
+    let half = match halves[..] {
+        [Some(Half::Left)] => "left ",
+        [Some(Half::Right)] => "right ",
+        _ => "",
+    };
+
+    match key_rows.iter().collect::<Vec<_>>()[..] {
+        [None] => format!("on the {}thumbs", half),
+        [Some(row)] => match (row_name(*row, rows), halves.len() > 1) {
+            (Some(name), true) => format!("across the {} row", name),
+            (Some(name), false) => format!("on the {}{} row", half, name),
+            // Numbered rows read "row 5", without an article
+            (None, true) => format!("across row {}", row),
+            (None, false) if half.is_empty() => format!("on row {}", row),
+            (None, false) => format!("on row {} of the {}half", row, half),
+        },
+        _ if halves.len() > 1 => "across both halves".to_string(),
+        _ if half.is_empty() => "across the board".to_string(),
+        _ => format!("on the {}half", half),
+    }
+}
+
+/// Writes a concise description of a layer for alt text, e.g. `Layer 3 –
+/// NAV: arrows on the right home row; page up/down, home, and end on the
+/// right top row; the rest transparent.`
+///
+/// Keys are grouped by what they type (letters, digits, arrows, layer keys,
+/// ...) and each group is located by half and row.
+///
+/// # Arguments
+///
+/// * `layer` - The layer to describe
+/// * `geometry` - The board, used to locate the keys
+///
+/// # Returns
+///
+/// One sentence describing the layer
+pub fn layer_alt_text(layer: &Layer, geometry: &Geometry) -> String {
+    let locations = geometry.key_locations();
+    let rows = locations
+        .iter()
+        .filter_map(|(_, row, _)| *row)
+        .max()
+        .unwrap_or(0);
+
+    let mut categories: BTreeMap<KeyCategory, Vec<KeyLocation>> = BTreeMap::new();
+    let mut transparent = 0;
+    for (key, &(half, row, _)) in layer.keys.iter().flatten().zip(&locations) {
+        let key = key.trim();
+        if is_transparent(key) || key == "KC_NO" || key == "XXXXXXX" {
+            transparent += 1;
+            continue;
+        }
+        categories
+            .entry(KeyCategory::of(key))
+            .or_default()
+            .push((half, row));
+    }
+
+    let mut parts: Vec<String> = categories
+        .iter()
+        .map(|(category, keys)| format!("{} {}", category.name(), describe_region(keys, rows)))
+        .collect();
+    if layer.index != 0 && transparent > 0 && !parts.is_empty() {
+        parts.push("the rest transparent".to_string());
+    }
+
+    let title = crate::layer_title(layer);
+    if parts.is_empty() {
+        format!("{}: no keys assigned.", title)
+    } else {
+        format!("{}: {}.", title, parts.join("; "))
+    }
+}
+
+/// How often a keycode is used and where.
+#[derive(Debug, Clone, PartialEq)]
+pub struct KeycodeUsage {
+    pub keycode: String,
+    /// Number of keys assigned this keycode across all layers
+    pub count: usize,
+    /// Indices of the layers using it, in order
+    pub layers: Vec<usize>,
+}
+
+/// Lists every distinct keycode used in the keymap.
+///
+/// Empty placeholder keys are left out.
+///
+/// # Arguments
+///
+/// * `layers` - The parsed keymap layers
+///
+/// # Returns
+///
+/// The keycodes, most used first and alphabetically among equal counts
+pub fn keycode_usage(layers: &[Layer]) -> Vec<KeycodeUsage> {
+    let mut usage: BTreeMap<&str, (usize, BTreeSet<usize>)> = BTreeMap::new();
+
+    for layer in layers {
+        for key in layer.keys.iter().flatten() {
+            if is_empty_key(key) {
+                continue;
+            }
+            let entry = usage.entry(key.as_str()).or_default();
+            entry.0 += 1;
+            entry.1.insert(layer.index);
+        }
+    }
+
+    let mut usage: Vec<KeycodeUsage> = usage
+        .into_iter()
+        .map(|(keycode, (count, layers))| KeycodeUsage {
+            keycode: keycode.to_string(),
+            count,
+            layers: layers.into_iter().collect(),
+        })
+        .collect();
+    // Stable sort keeps the alphabetical order among equal counts
+    usage.sort_by_key(|u| std::cmp::Reverse(u.count));
+    usage
+}
+
+/// Key counts of a single layer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LayerStats {
+    /// Index of the layer
+    pub layer: usize,
+    /// Number of keys in the layer's LAYOUT
+    pub total_keys: usize,
+    /// Keys with an assignment
+    pub assigned_keys: usize,
+    /// Empty placeholder keys
+    pub unassigned_keys: usize,
+    /// Keys that activate another layer
+    pub layer_keys: usize,
+    /// Distinct keycodes among the assigned keys
+    pub distinct_keycodes: usize,
+}
+
+impl LayerStats {
+    /// The statistics as `(metric, value)` pairs, e.g. for long-format exports.
+    pub fn metrics(&self) -> [(&'static str, usize); 5] {
+        [
+            ("total_keys", self.total_keys),
+            ("assigned_keys", self.assigned_keys),
+            ("unassigned_keys", self.unassigned_keys),
+            ("layer_keys", self.layer_keys),
+            ("distinct_keycodes", self.distinct_keycodes),
+        ]
+    }
+}
+
+/// Counts the keys of every layer.
+///
+/// # Arguments
+///
+/// * `layers` - The parsed keymap layers
+///
+/// # Returns
+///
+/// One entry per layer, in layer order
+pub fn layer_stats(layers: &[Layer]) -> Vec<LayerStats> {
+    layers
+        .iter()
+        .map(|layer| {
+            let assigned: Vec<&String> = layer
+                .keys
+                .iter()
+                .flatten()
+                .filter(|key| !is_empty_key(key))
+                .collect();
+            let total_keys = layer.keys.iter().map(Vec::len).sum();
+            LayerStats {
+                layer: layer.index,
+                total_keys,
+                assigned_keys: assigned.len(),
+                unassigned_keys: total_keys - assigned.len(),
+                layer_keys: assigned
+                    .iter()
+                    .filter(|key| layer_argument(key).is_some())
+                    .count(),
+                distinct_keycodes: assigned.iter().collect::<BTreeSet<_>>().len(),
+            }
+        })
+        .collect()
+}
+
+/// Effort of the main rows of a 3x6 split half, top to bottom, from the outer
+/// pinky column to the inner index column.
+const SPLIT_3X6_EFFORT: [[f32; 6]; 3] = [
+    [4.0, 2.4, 2.0, 1.6, 1.8, 2.8],
+    [3.0, 1.4, 1.1, 1.0, 1.0, 2.0],
+    [4.0, 2.8, 2.4, 1.8, 1.6, 2.6],
+];
+
+/// Effort of thumb keys from the inner edge of the half outward.
+const THUMB_EFFORT: [f32; 5] = [1.2, 1.4, 2.0, 2.8, 3.5];
+
+/// Relative frequency of each letter in English text, in percent.
+const LETTER_FREQUENCIES: [(char, f32); 26] = [
+    ('E', 12.70),
+    ('T', 9.06),
+    ('A', 8.17),
+    ('O', 7.51),
+    ('I', 6.97),
+    ('N', 6.75),
+    ('S', 6.33),
+    ('H', 6.09),
+    ('R', 5.99),
+    ('D', 4.25),
+    ('L', 4.03),
+    ('C', 2.78),
+    ('U', 2.76),
+    ('M', 2.41),
+    ('W', 2.36),
+    ('F', 2.23),
+    ('G', 2.02),
+    ('Y', 1.97),
+    ('P', 1.93),
+    ('B', 1.29),
+    ('V', 0.98),
+    ('K', 0.77),
+    ('J', 0.15),
+    ('X', 0.15),
+    ('Q', 0.10),
+    ('Z', 0.07),
+];
+
+/// How hard each key position is to reach, by position name (see
+/// [`Geometry::position_names`]). 1.0 is a resting home-row finger; higher is
+/// harder. Positions the model leaves out are not scored.
+///
+/// ```toml
+/// [positions]
+/// "L-R2C4" = 1.0
+/// "thumb-L5" = 1.2
+/// ```
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct EffortModel {
+    pub positions: BTreeMap<String, f32>,
+}
+
+impl EffortModel {
+    /// The built-in model for split boards with three rows of six keys per
+    /// half above a thumb row, such as the Keyball44 and Corne.
+    ///
+    /// # Arguments
+    ///
+    /// * `geometry` - The board the model is built for
+    ///
+    /// # Returns
+    ///
+    /// The model, or `None` if the board doesn't have that shape
+    pub fn split_3x6(geometry: &Geometry) -> Option<EffortModel> {
+        let [main @ .., thumbs] = geometry.rows.as_slice() else {
+            return None;
+        };
+        let half_len = |row: &[KeyPosition], half| row.iter().filter(|p| p.half == half).count();
+        let is_3x6 = main.len() == 3
+            && main
+                .iter()
+                .all(|row| half_len(row, Half::Left) == 6 && half_len(row, Half::Right) == 6);
+        if !is_3x6 {
+            return None;
+        }
+
+        let mut positions = BTreeMap::new();
+        for (row, efforts) in SPLIT_3X6_EFFORT.iter().enumerate() {
+            for (column, &effort) in efforts.iter().enumerate() {
+                // Right half columns count from the inner edge
+                positions.insert(format!("L-R{}C{}", row + 1, column + 1), effort);
+                positions.insert(format!("R-R{}C{}", row + 1, 6 - column), effort);
+            }
+        }
+        let thumb_count = [half_len(thumbs, Half::Left), half_len(thumbs, Half::Right)];
+        for (from_inner, &effort) in THUMB_EFFORT.iter().enumerate() {
+            if from_inner < thumb_count[0] {
+                positions.insert(format!("thumb-L{}", thumb_count[0] - from_inner), effort);
+            }
+            if from_inner < thumb_count[1] {
+                positions.insert(format!("thumb-R{}", from_inner + 1), effort);
+            }
+        }
+
+        Some(EffortModel { positions })
+    }
+
+    /// The effort of every key of a board.
+    ///
+    /// # Arguments
+    ///
+    /// * `geometry` - The board whose position names are looked up
+    ///
+    /// # Returns
+    ///
+    /// One effort per key in LAYOUT order, `None` where the model has none
+    pub fn efforts(&self, geometry: &Geometry) -> Vec<Option<f32>> {
+        geometry
+            .position_names()
+            .iter()
+            .map(|name| self.positions.get(name).copied())
+            .collect()
+    }
+}
+
+/// Letter-frequency-weighted effort of typing on a layer.
+#[derive(Debug, Clone, PartialEq)]
+pub struct EffortScore {
+    /// Average effort per typed letter; lower is easier
+    pub score: f32,
+    /// Letters found on scored positions of the layer
+    pub letters: usize,
+    /// Letters the layer doesn't have on a scored position
+    pub missing: Vec<char>,
+}
+
+/// The letter a key types when tapped, including mod-taps and layer-taps
+/// such as `LGUI_T(KC_A)` or `LT(1, KC_A)`.
+fn tapped_letter(key: &str) -> Option<char> {
+    let tap = match (key.find('('), key.rfind(')')) {
+        (Some(open), Some(close)) if open < close => key[open + 1..close].rsplit(',').next()?,
+        _ => key,
+    };
+    let letter = tap.trim().strip_prefix("KC_")?;
+    let mut chars = letter.chars();
+    match (chars.next(), chars.next()) {
+        (Some(c), None) if c.is_ascii_uppercase() => Some(c),
+        _ => None,
+    }
+}
+
+/// Scores a layer against the English letter frequencies.
+///
+/// # Arguments
+///
+/// * `layer` - The layer to score, usually the base layer
+/// * `geometry` - The board the layer is placed on
+/// * `model` - The effort of each position
+///
+/// # Returns
+///
+/// The score, or `None` if no letter sits on a scored position
+pub fn effort_score(
+    layer: &Layer,
+    geometry: &Geometry,
+    model: &EffortModel,
+) -> Option<EffortScore> {
+    let efforts = model.efforts(geometry);
+    let mut letter_efforts: BTreeMap<char, f32> = BTreeMap::new();
+    for (key, effort) in layer.keys.iter().flatten().zip(&efforts) {
+        if let (Some(letter), Some(effort)) = (tapped_letter(key), effort) {
+            // A letter placed twice is typed with the easier key
+            let entry = letter_efforts.entry(letter).or_insert(*effort);
+            *entry = entry.min(*effort);
+        }
+    }
+
+    let (mut weighted, mut total) = (0.0, 0.0);
+    let mut missing = Vec::new();
+    for (letter, frequency) in LETTER_FREQUENCIES {
+        match letter_efforts.get(&letter) {
+            Some(effort) => {
+                weighted += frequency * effort;
+                total += frequency;
+            }
+            None => missing.push(letter),
+        }
+    }
+    missing.sort_unstable();
+
+    (total > 0.0).then(|| EffortScore {
+        score: weighted / total,
+        letters: letter_efforts.len(),
+        missing,
+    })
+}
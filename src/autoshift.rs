@@ -0,0 +1,199 @@
+//! Auto Shift settings: which keys send their shifted version when held.
+use regex::Regex;
+
+/// QMK's default `AUTO_SHIFT_TIMEOUT` in milliseconds.
+const DEFAULT_TIMEOUT_MS: u32 = 175;
+
+/// Keys shifted by the "special" group, by every name QMK accepts.
+const SPECIAL_KEYS: [&str; 22] = [
+    "KC_MINS",
+    "KC_MINUS",
+    "KC_EQL",
+    "KC_EQUAL",
+    "KC_LBRC",
+    "KC_LEFT_BRACKET",
+    "KC_RBRC",
+    "KC_RIGHT_BRACKET",
+    "KC_BSLS",
+    "KC_BACKSLASH",
+    "KC_SCLN",
+    "KC_SEMICOLON",
+    "KC_QUOT",
+    "KC_QUOTE",
+    "KC_GRV",
+    "KC_GRAVE",
+    "KC_COMM",
+    "KC_COMMA",
+    "KC_DOT",
+    "KC_SLSH",
+    "KC_SLASH",
+    "KC_NUBS",
+];
+
+/// Auto Shift settings read from `config.h` defines and the keymap's
+/// callbacks.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AutoShift {
+    /// How long a key is held before it is shifted (`AUTO_SHIFT_TIMEOUT`)
+    pub timeout_ms: u32,
+    /// Letters are shifted (no `NO_AUTO_SHIFT_ALPHA`)
+    pub alpha: bool,
+    /// Digits are shifted (no `NO_AUTO_SHIFT_NUMERIC`)
+    pub numeric: bool,
+    /// Punctuation is shifted (no `NO_AUTO_SHIFT_SPECIAL`)
+    pub special: bool,
+    /// Tab is shifted (no `NO_AUTO_SHIFT_TAB`)
+    pub tab: bool,
+    /// Keycodes the auto-shift callbacks return `true` for
+    pub added: Vec<String>,
+    /// Keycodes the auto-shift callbacks return `false` for
+    pub removed: Vec<String>,
+}
+
+impl Default for AutoShift {
+    fn default() -> Self {
+        AutoShift {
+            timeout_ms: DEFAULT_TIMEOUT_MS,
+            alpha: true,
+            numeric: true,
+            special: true,
+            tab: true,
+            added: Vec::new(),
+            removed: Vec::new(),
+        }
+    }
+}
+
+impl AutoShift {
+    /// Returns `true` if holding the key sends its shifted version.
+    pub fn is_shifted(&self, key: &str) -> bool {
+        let key = key.trim();
+        if self.removed.iter().any(|k| k == key) {
+            return false;
+        }
+        if self.added.iter().any(|k| k == key) {
+            return true;
+        }
+
+        let Some(name) = key.strip_prefix("KC_") else {
+            return false;
+        };
+        let is_single = |f: fn(&char) -> bool| name.len() == 1 && name.chars().all(|c| f(&c));
+        (self.alpha && is_single(char::is_ascii_uppercase))
+            || (self.numeric && is_single(char::is_ascii_digit))
+            || (self.special && SPECIAL_KEYS.contains(&key))
+            || (self.tab && key == "KC_TAB")
+    }
+
+    /// Summarizes the settings for the metadata panel, e.g.
+    /// `175 ms hold; letters, digits, punctuation, tab; +2 custom`.
+    pub fn summary(&self) -> String {
+        let groups: Vec<&str> = [
+            (self.alpha, "letters"),
+            (self.numeric, "digits"),
+            (self.special, "punctuation"),
+            (self.tab, "tab"),
+        ]
+        .into_iter()
+        .filter_map(|(enabled, name)| enabled.then_some(name))
+        .collect();
+
+        let mut summary = format!("{} ms hold", self.timeout_ms);
+        if !groups.is_empty() {
+            summary.push_str(&format!("; {}", groups.join(", ")));
+        }
+        if !self.added.is_empty() {
+            summary.push_str(&format!("; +{} custom", self.added.len()));
+        }
+        if !self.removed.is_empty() {
+            summary.push_str(&format!("; -{} excluded", self.removed.len()));
+        }
+        summary
+    }
+}
+
+/// Collects the `case` labels of a callback's switch, split by whether the
+/// cases return `true` or `false`.
+fn callback_cases(source: &str, callback: &str) -> (Vec<String>, Vec<String>) {
+    let (mut shifted, mut unshifted) = (Vec::new(), Vec::new());
+    let Some(start) = Regex::new(&format!(r"\bbool\s+{}\s*\([^)]*\)\s*\{{", callback))
+        .unwrap()
+        .find(source)
+    else {
+        return (shifted, unshifted);
+    };
+
+    // The function body ends at the brace closing the one after the signature
+    let mut depth = 0;
+    let mut end = source.len();
+    for (idx, ch) in source[start.end() - 1..].char_indices() {
+        match ch {
+            '{' => depth += 1,
+            '}' => {
+                depth -= 1;
+                if depth == 0 {
+                    end = start.end() - 1 + idx;
+                    break;
+                }
+            }
+            _ => {}
+        }
+    }
+
+    let statement = Regex::new(r"case\s+(\w+)\s*:|return\s+(true|false)\s*;").unwrap();
+    let mut pending = Vec::new();
+    for caps in statement.captures_iter(&source[start.end()..end]) {
+        if let Some(case) = caps.get(1) {
+            pending.push(case.as_str().to_string());
+        } else if &caps[2] == "true" {
+            shifted.append(&mut pending);
+        } else {
+            unshifted.append(&mut pending);
+        }
+    }
+
+    (shifted, unshifted)
+}
+
+/// Reads the Auto Shift settings.
+///
+/// Timeouts and `NO_AUTO_SHIFT_*` groups come from `#define`s, and per-key
+/// choices from the `case` labels of `get_custom_auto_shifted_key` and
+/// `get_auto_shifted_key`.
+///
+/// # Arguments
+///
+/// * `sources` - The keymap.c source and, if available, its config.h
+///
+/// # Returns
+///
+/// The settings, with QMK's defaults for anything not configured
+pub fn parse_auto_shift(sources: &[&str]) -> AutoShift {
+    let mut auto_shift = AutoShift::default();
+    let define = Regex::new(r"(?m)^\s*#\s*define\s+(\w+)(?:[ \t]+(\S+))?").unwrap();
+
+    for source in sources {
+        for caps in define.captures_iter(source) {
+            match &caps[1] {
+                "AUTO_SHIFT_TIMEOUT" => {
+                    if let Some(timeout) = caps.get(2).and_then(|v| v.as_str().parse().ok()) {
+                        auto_shift.timeout_ms = timeout;
+                    }
+                }
+                "NO_AUTO_SHIFT_ALPHA" => auto_shift.alpha = false,
+                "NO_AUTO_SHIFT_NUMERIC" => auto_shift.numeric = false,
+                "NO_AUTO_SHIFT_SPECIAL" => auto_shift.special = false,
+                "NO_AUTO_SHIFT_TAB" => auto_shift.tab = false,
+                _ => {}
+            }
+        }
+
+        for callback in ["get_custom_auto_shifted_key", "get_auto_shifted_key"] {
+            let (added, removed) = callback_cases(source, callback);
+            auto_shift.added.extend(added);
+            auto_shift.removed.extend(removed);
+        }
+    }
+
+    auto_shift
+}
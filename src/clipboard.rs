@@ -0,0 +1,145 @@
+//! Copying a rendered keymap to the system clipboard through the platform's
+//! clipboard tool: `pbcopy` and `osascript` on macOS, PowerShell on Windows,
+//! and `wl-copy` (Wayland) or `xclip` (X11) elsewhere.
+use anyhow::{Context, Result, bail};
+use std::io::Write;
+#[cfg(any(target_os = "macos", target_os = "windows"))]
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+/// What is placed on the clipboard.
+#[derive(Debug, Clone, Copy)]
+pub enum Content<'a> {
+    /// The SVG document, copied as text
+    Svg(&'a str),
+    /// A PNG image, copied as an image
+    Png(&'a [u8]),
+}
+
+/// Runs a clipboard tool, feeding it `input` on stdin.
+fn run(program: &str, args: &[&str], input: Option<&[u8]>) -> Result<()> {
+    let mut child = Command::new(program)
+        .args(args)
+        .stdin(if input.is_some() {
+            Stdio::piped()
+        } else {
+            Stdio::null()
+        })
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .spawn()
+        .with_context(|| format!("Failed to run {}; is it installed?", program))?;
+    if let (Some(input), Some(mut stdin)) = (input, child.stdin.take()) {
+        stdin
+            .write_all(input)
+            .with_context(|| format!("Failed to write to {}", program))?;
+    }
+    let output = child
+        .wait_with_output()
+        .with_context(|| format!("Failed to run {}", program))?;
+    if !output.status.success() {
+        bail!(
+            "{} failed: {}",
+            program,
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    Ok(())
+}
+
+/// A file in the temp directory holding `data` while a tool reads it,
+/// removed when dropped.
+#[cfg(any(target_os = "macos", target_os = "windows"))]
+struct TempFile(PathBuf);
+
+#[cfg(any(target_os = "macos", target_os = "windows"))]
+impl TempFile {
+    fn new(extension: &str, data: &[u8]) -> Result<Self> {
+        let path = std::env::temp_dir().join(format!(
+            "keyball44-viz-clipboard-{}.{}",
+            std::process::id(),
+            extension
+        ));
+        std::fs::write(&path, data).context(format!("Failed to write {:?}", path))?;
+        Ok(TempFile(path))
+    }
+
+    fn path(&self) -> &Path {
+        &self.0
+    }
+}
+
+#[cfg(any(target_os = "macos", target_os = "windows"))]
+impl Drop for TempFile {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.0);
+    }
+}
+
+#[cfg(target_os = "macos")]
+fn copy_content(content: Content) -> Result<()> {
+    match content {
+        Content::Svg(svg) => run("pbcopy", &[], Some(svg.as_bytes())),
+        Content::Png(png) => {
+            let file = TempFile::new("png", png)?;
+            let script = format!(
+                "set the clipboard to (read (POSIX file \"{}\") as «class PNGf»)",
+                file.path().display()
+            );
+            run("osascript", &["-e", &script], None)
+        }
+    }
+}
+
+#[cfg(target_os = "windows")]
+fn copy_content(content: Content) -> Result<()> {
+    // PowerShell reads the content from a file, which avoids code page
+    // conversions of piped text
+    let (file, script) = match content {
+        Content::Svg(svg) => (
+            TempFile::new("svg", svg.as_bytes())?,
+            "Set-Clipboard -Value ([IO.File]::ReadAllText('{}'))",
+        ),
+        Content::Png(png) => (
+            TempFile::new("png", png)?,
+            "Add-Type -AssemblyName System.Windows.Forms, System.Drawing; \
+             [Windows.Forms.Clipboard]::SetImage([Drawing.Image]::FromFile('{}'))",
+        ),
+    };
+    let script = script.replace("{}", &file.path().display().to_string());
+    run(
+        "powershell",
+        &["-NoProfile", "-STA", "-Command", &script],
+        None,
+    )
+}
+
+#[cfg(not(any(target_os = "macos", target_os = "windows")))]
+fn copy_content(content: Content) -> Result<()> {
+    let (mime, data) = match content {
+        Content::Svg(svg) => ("text/plain;charset=utf-8", svg.as_bytes()),
+        Content::Png(png) => ("image/png", png),
+    };
+    if std::env::var_os("WAYLAND_DISPLAY").is_some() {
+        run("wl-copy", &["--type", mime], Some(data))
+    } else {
+        run(
+            "xclip",
+            &["-selection", "clipboard", "-t", mime, "-i"],
+            Some(data),
+        )
+    }
+}
+
+/// Places a rendered keymap on the system clipboard.
+///
+/// # Arguments
+///
+/// * `content` - The SVG text or PNG image to copy
+///
+/// # Returns
+///
+/// `Ok(())`, or an error if the platform's clipboard tool is missing or fails
+pub fn copy(content: Content) -> Result<()> {
+    copy_content(content).context("Failed to copy to the clipboard")
+}
@@ -0,0 +1,100 @@
+//! Combos declared in a keymap.c's `key_combos` array: keys pressed together
+//! for another keycode.
+use crate::{i18n, initializer, parse_keys_with_parens};
+use regex::Regex;
+use std::collections::HashMap;
+
+/// A combo: keys pressed together, and what they produce.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Combo {
+    /// Name of the key array the combo refers to, e.g. `jk_combo`
+    pub name: String,
+    /// The keys to press together, in declaration order
+    pub keys: Vec<String>,
+    /// The keycode sent, or `None` for `COMBO_ACTION` combos handled in
+    /// `process_combo_event`
+    pub output: Option<String>,
+}
+
+impl Combo {
+    /// The combo as a label/value panel entry, e.g. `("J + K", "ESC")`.
+    ///
+    /// # Arguments
+    ///
+    /// * `strings` - Text for combos handled in `process_combo_event`
+    pub fn entry(&self, strings: &i18n::Translations) -> (String, String) {
+        let legend = |key: &str| key.strip_prefix("KC_").unwrap_or(key).to_string();
+        let keys: Vec<String> = self.keys.iter().map(|key| legend(key)).collect();
+        let output = match &self.output {
+            Some(output) => legend(output),
+            None => strings.combo_action(&self.name),
+        };
+        (keys.join(" + "), output)
+    }
+}
+
+/// Parses the combos of a keymap.c.
+///
+/// Key arrays are read from `const uint16_t PROGMEM name[] = {..., COMBO_END};`
+/// declarations, and combos from the `COMBO(name, keycode)` and
+/// `COMBO_ACTION(name)` entries of `combo_t key_combos[]`, with or without
+/// designated indices such as `[JK_ESC] = COMBO(...)`. Entries naming an
+/// undeclared key array are skipped.
+///
+/// # Arguments
+///
+/// * `content` - The keymap.c source
+///
+/// # Returns
+///
+/// The combos in declaration order, empty if the keymap has none
+pub fn parse_combos(content: &str) -> Vec<Combo> {
+    let content = Regex::new(r"(?s)/\*.*?\*/")
+        .unwrap()
+        .replace_all(content, "");
+    let content = Regex::new(r"//[^\n]*").unwrap().replace_all(&content, "");
+
+    let Some(start) = Regex::new(r"\bcombo_t\s+key_combos\s*\[[^\]]*\]\s*=")
+        .unwrap()
+        .find(&content)
+    else {
+        return Vec::new();
+    };
+    let Some(body) = initializer(&content, start.end()) else {
+        return Vec::new();
+    };
+
+    let array_regex =
+        Regex::new(r"\buint16_t\s+(?:PROGMEM\s+)?(\w+)\s*\[\s*\]\s*(?:PROGMEM\s*)?=").unwrap();
+    let arrays: HashMap<&str, Vec<String>> = array_regex
+        .captures_iter(&content)
+        .filter_map(|caps| {
+            let whole = caps.get(0).unwrap();
+            let keys = parse_keys_with_parens(initializer(&content, whole.end())?)
+                .into_iter()
+                .filter(|key| key != "COMBO_END")
+                .collect();
+            Some((caps.get(1).unwrap().as_str(), keys))
+        })
+        .collect();
+
+    let entry_regex =
+        Regex::new(r"(?s)^(?:\[[^\]]*\]\s*=\s*)?(COMBO|COMBO_ACTION)\s*\((.*)\)$").unwrap();
+    parse_keys_with_parens(body)
+        .iter()
+        .filter_map(|entry| {
+            let caps = entry_regex.captures(entry)?;
+            let args = parse_keys_with_parens(&caps[2]);
+            let (name, output) = match (&caps[1], args.as_slice()) {
+                ("COMBO", [name, output]) => (name, Some(output.clone())),
+                ("COMBO_ACTION", [name]) => (name, None),
+                _ => return None,
+            };
+            Some(Combo {
+                name: name.clone(),
+                keys: arrays.get(name.as_str())?.clone(),
+                output,
+            })
+        })
+        .collect()
+}
@@ -0,0 +1,146 @@
+//! Loading, validation, and JSON Schemas for configuration files.
+//!
+//! Config files are TOML, or JSON when the file name ends in `.json`.
+use crate::Geometry;
+use crate::groups::KeyGroups;
+use crate::i18n::Translations;
+use crate::keycodes::LegendOverrides;
+use crate::notes::KeymapNotes;
+use crate::publish::PublishConfig;
+use crate::theme::Theme;
+use anyhow::{Context, Result, anyhow};
+use clap::ValueEnum;
+use serde::de::DeserializeOwned;
+use std::path::Path;
+
+/// The kinds of configuration files the tool reads.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum ConfigKind {
+    /// Color theme
+    Theme,
+    /// Keyboard geometry
+    Geometry,
+    /// Legend overrides for keycodes
+    Legends,
+    /// Translations of generated text
+    Translations,
+    /// Labeled groups of key positions
+    Groups,
+    /// Layer names, key notes, and groups kept next to the keymap
+    Notes,
+    /// Stages of the `publish` pipeline
+    Publish,
+}
+
+impl ConfigKind {
+    /// All config kinds.
+    pub const ALL: [ConfigKind; 7] = [
+        ConfigKind::Theme,
+        ConfigKind::Geometry,
+        ConfigKind::Legends,
+        ConfigKind::Translations,
+        ConfigKind::Groups,
+        ConfigKind::Notes,
+        ConfigKind::Publish,
+    ];
+
+    /// Short name of the kind, also used for its schema file name.
+    pub fn name(self) -> &'static str {
+        match self {
+            ConfigKind::Theme => "theme",
+            ConfigKind::Geometry => "geometry",
+            ConfigKind::Legends => "legends",
+            ConfigKind::Translations => "translations",
+            ConfigKind::Groups => "groups",
+            ConfigKind::Notes => "notes",
+            ConfigKind::Publish => "publish",
+        }
+    }
+}
+
+/// Returns the JSON Schema describing a config file format.
+pub fn schema(kind: ConfigKind) -> serde_json::Value {
+    let schema = match kind {
+        ConfigKind::Theme => schemars::schema_for!(Theme),
+        ConfigKind::Geometry => schemars::schema_for!(Geometry),
+        ConfigKind::Legends => schemars::schema_for!(LegendOverrides),
+        ConfigKind::Translations => schemars::schema_for!(Translations),
+        ConfigKind::Groups => schemars::schema_for!(KeyGroups),
+        ConfigKind::Notes => schemars::schema_for!(KeymapNotes),
+        ConfigKind::Publish => schemars::schema_for!(PublishConfig),
+    };
+    schema.to_value()
+}
+
+/// Deserializes a config file, choosing JSON or TOML by the file extension.
+///
+/// # Arguments
+///
+/// * `path` - The file name, used to pick the format and in error messages
+/// * `content` - The file's contents
+///
+/// # Returns
+///
+/// The parsed value, or an error pointing at the offending line and column
+pub fn parse<T: DeserializeOwned>(path: &Path, content: &str) -> Result<T> {
+    let is_json = path
+        .extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("json"));
+
+    if is_json {
+        serde_json::from_str(content).map_err(|err| anyhow!("{}: {}", path.display(), err))
+    } else {
+        toml::from_str(content).map_err(|err| anyhow!("{}: {}", path.display(), err))
+    }
+}
+
+/// Reads and deserializes a config file.
+pub fn load<T: DeserializeOwned>(path: &Path) -> Result<T> {
+    let content =
+        std::fs::read_to_string(path).context(format!("Failed to read config file: {:?}", path))?;
+    parse(path, &content)
+}
+
+/// Parses a config file of the given kind and checks its values.
+///
+/// # Arguments
+///
+/// * `kind` - The expected format
+/// * `path` - The file name, used to pick the format and in error messages
+/// * `content` - The file's contents
+///
+/// # Returns
+///
+/// `Ok(())` if the file is valid, otherwise an error describing the first problem
+pub fn validate(kind: ConfigKind, path: &Path, content: &str) -> Result<()> {
+    let context = || format!("{}: invalid {} file", path.display(), kind.name());
+
+    match kind {
+        ConfigKind::Theme => parse::<Theme>(path, content)?
+            .validate()
+            .with_context(context),
+        ConfigKind::Geometry => parse::<Geometry>(path, content)?
+            .validate()
+            .with_context(context),
+        ConfigKind::Legends => {
+            let overrides: LegendOverrides = parse(path, content)?;
+            match overrides.legends.iter().find(|(_, o)| o.legend.is_empty()) {
+                Some((keycode, _)) => Err(anyhow!("legends.{}: legend must not be empty", keycode))
+                    .with_context(context),
+                None => Ok(()),
+            }
+        }
+        ConfigKind::Translations => parse::<Translations>(path, content)?
+            .validate()
+            .with_context(context),
+        ConfigKind::Groups => parse::<KeyGroups>(path, content)?
+            .validate()
+            .with_context(context),
+        ConfigKind::Notes => parse::<KeymapNotes>(path, content)?
+            .validate()
+            .with_context(context),
+        ConfigKind::Publish => parse::<PublishConfig>(path, content)?
+            .validate()
+            .with_context(context),
+    }
+}
@@ -0,0 +1,147 @@
+//! Custom keycodes declared in a keymap.c's `enum custom_keycodes`, and the
+//! strings `process_record_user` types for them.
+use crate::keycodes::{LegendOverride, LegendOverrides};
+use regex::Regex;
+use std::collections::BTreeMap;
+
+/// A keycode the keymap declares itself, handled in `process_record_user`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CustomKeycode {
+    pub name: String,
+    /// The string a `SEND_STRING` macro types when the key is pressed
+    pub macro_text: Option<String>,
+}
+
+impl CustomKeycode {
+    /// What the key does, e.g. `macro typing "me@example.com"`, shown in its
+    /// tooltip after the keycode.
+    pub fn description(&self) -> String {
+        match &self.macro_text {
+            Some(text) => format!("macro typing \"{}\"", text),
+            None => "custom keycode".to_string(),
+        }
+    }
+}
+
+/// Initializers that start the keycodes of a firmware's user range.
+const USER_RANGES: [&str; 4] = ["SAFE_RANGE", "QK_USER", "QK_KB", "NEW_SAFE_RANGE"];
+
+/// Parses the custom keycodes of a keymap.c.
+///
+/// Custom keycodes are the members of enums starting at `SAFE_RANGE` (or
+/// `QK_USER`, `QK_KB`, `NEW_SAFE_RANGE`). A `case NAME:` label followed by
+/// `SEND_STRING("...")` or `send_string("...")` before the next label makes
+/// the keycode a macro typing that string.
+///
+/// # Arguments
+///
+/// * `content` - The keymap.c source
+///
+/// # Returns
+///
+/// The custom keycodes in declaration order, empty if the keymap has none
+pub fn parse_custom_keycodes(content: &str) -> Vec<CustomKeycode> {
+    let content = Regex::new(r"(?s)/\*.*?\*/")
+        .unwrap()
+        .replace_all(content, "");
+    let content = Regex::new(r"//[^\n]*").unwrap().replace_all(&content, "");
+
+    let enum_regex = Regex::new(r"\benum\b\s*\w*\s*\{([^}]*)\}").unwrap();
+    let names: Vec<String> = enum_regex
+        .captures_iter(&content)
+        .filter(|caps| {
+            caps[1].split(',').any(|member| {
+                member
+                    .split_once('=')
+                    .is_some_and(|(_, value)| USER_RANGES.contains(&value.trim()))
+            })
+        })
+        .flat_map(|caps| {
+            caps[1]
+                .split(',')
+                .map(|member| member.split('=').next().unwrap_or_default().trim())
+                .filter(|name| !name.is_empty())
+                .map(str::to_string)
+                .collect::<Vec<_>>()
+        })
+        .collect();
+
+    let send_regex = Regex::new(r#"(?i)\bsend_string\s*\(\s*"((?:[^"\\]|\\.)*)""#).unwrap();
+    // `default:` labels end the last case body
+    let label_regex = Regex::new(r"\b(?:case\s+(\w+)|default)\s*:").unwrap();
+    let labels: Vec<(Option<&str>, usize, usize)> = label_regex
+        .captures_iter(&content)
+        .map(|caps| {
+            let whole = caps.get(0).unwrap();
+            (
+                caps.get(1).map(|name| name.as_str()),
+                whole.start(),
+                whole.end(),
+            )
+        })
+        .collect();
+
+    names
+        .into_iter()
+        .map(|name| {
+            let macro_text = labels
+                .iter()
+                .position(|(label, _, _)| *label == Some(name.as_str()))
+                .and_then(|mut index| {
+                    // Stacked labels share the body after the last of them
+                    while index + 1 < labels.len()
+                        && content[labels[index].2..labels[index + 1].1]
+                            .trim()
+                            .is_empty()
+                    {
+                        index += 1;
+                    }
+                    let end = labels.get(index + 1).map_or(content.len(), |label| label.1);
+                    send_regex
+                        .captures(&content[labels[index].2..end])
+                        .map(|caps| caps[1].replace("\\\"", "\""))
+                });
+            CustomKeycode { name, macro_text }
+        })
+        .collect()
+}
+
+/// Legends of the custom keycodes: their alias if one is given, otherwise
+/// their name, with a tooltip saying what the key does.
+///
+/// # Arguments
+///
+/// * `custom` - The keymap's custom keycodes (see [`parse_custom_keycodes`])
+/// * `aliases` - Display names keyed by keycode, e.g. from `--aliases`; they
+///   also apply to keycodes not declared in the keymap
+///
+/// # Returns
+///
+/// A legend override for every custom keycode and alias
+pub fn legend_overrides(
+    custom: &[CustomKeycode],
+    aliases: &BTreeMap<String, String>,
+) -> LegendOverrides {
+    let mut legends: BTreeMap<String, LegendOverride> = custom
+        .iter()
+        .map(|keycode| {
+            (
+                keycode.name.clone(),
+                LegendOverride {
+                    legend: keycode.name.clone(),
+                    description: Some(keycode.description()),
+                },
+            )
+        })
+        .collect();
+    for (keycode, alias) in aliases {
+        let entry = legends
+            .entry(keycode.clone())
+            .or_insert_with(|| LegendOverride {
+                legend: String::new(),
+                description: None,
+            });
+        entry.legend = alias.clone();
+    }
+    LegendOverrides { legends }
+}
@@ -0,0 +1,145 @@
+//! Object-like `#define` macros of a keymap.c and the headers it includes,
+//! such as `#define NAV MO(1)`, expanded in the keycodes of its layers.
+use crate::Layer;
+use regex::Regex;
+use std::collections::BTreeMap;
+
+/// Expansions fail past this many nested macros, which only self-referencing
+/// defines reach.
+const MAX_DEPTH: usize = 8;
+
+fn identifier_regex() -> Regex {
+    Regex::new(r"\b[A-Za-z_]\w*\b").unwrap()
+}
+
+/// The keycode macros of a keymap, by name.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct KeycodeDefines {
+    macros: BTreeMap<String, String>,
+}
+
+impl KeycodeDefines {
+    /// Adds the macros of another source, replacing those of the same name.
+    pub fn extend(&mut self, other: KeycodeDefines) {
+        self.macros.extend(other.macros);
+    }
+
+    /// Returns `true` if no keycode macros were found.
+    pub fn is_empty(&self) -> bool {
+        self.macros.is_empty()
+    }
+
+    /// Expands the macros in a keycode, including those used as arguments
+    /// such as `LT(1, MY_SPC)`.
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - A keycode as written in a LAYOUT block
+    ///
+    /// # Returns
+    ///
+    /// The expanded keycode, or `key` unchanged if it uses no macros
+    pub fn expand(&self, key: &str) -> String {
+        self.expand_with(key, &identifier_regex())
+    }
+
+    fn expand_with(&self, key: &str, identifier: &Regex) -> String {
+        let mut expanded = key.to_string();
+        for _ in 0..MAX_DEPTH {
+            let next = identifier
+                .replace_all(&expanded, |caps: &regex::Captures| {
+                    self.macros
+                        .get(&caps[0])
+                        .cloned()
+                        .unwrap_or_else(|| caps[0].to_string())
+                })
+                .into_owned();
+            if next == expanded {
+                break;
+            }
+            expanded = next;
+        }
+        expanded
+    }
+
+    /// Expands the macros in every key of the layers.
+    ///
+    /// # Arguments
+    ///
+    /// * `layers` - The parsed layers, expanded in place
+    ///
+    /// # Returns
+    ///
+    /// The name of the macro each key written as a single macro was expanded
+    /// from, keyed by the expanded keycode; the first macro wins when several
+    /// expand to the same keycode
+    pub fn expand_layers(&self, layers: &mut [Layer]) -> BTreeMap<String, String> {
+        let mut aliases = BTreeMap::new();
+        if self.is_empty() {
+            return aliases;
+        }
+        let identifier = identifier_regex();
+        for key in layers
+            .iter_mut()
+            .flat_map(|layer| layer.keys.iter_mut().flatten())
+        {
+            let expanded = self.expand_with(key, &identifier);
+            if expanded == *key {
+                continue;
+            }
+            if self.macros.contains_key(key.trim()) {
+                aliases
+                    .entry(expanded.clone())
+                    .or_insert_with(|| key.trim().to_string());
+            }
+            *key = expanded;
+        }
+        aliases
+    }
+}
+
+/// Parses the keycode macros of a keymap.c or header.
+///
+/// Object-like defines with a single-line body count; function-like macros,
+/// defines continued with `\`, and bodies that are numbers or strings (layer
+/// numbers and config values such as `TAPPING_TERM`) are skipped.
+///
+/// # Arguments
+///
+/// * `content` - The C source
+///
+/// # Returns
+///
+/// The macros, empty if the source defines none
+pub fn parse_defines(content: &str) -> KeycodeDefines {
+    let content = Regex::new(r"(?s)/\*.*?\*/")
+        .unwrap()
+        .replace_all(content, "");
+    let content = Regex::new(r"//[^\n]*").unwrap().replace_all(&content, "");
+
+    let define_regex =
+        Regex::new(r"(?m)^[ \t]*#[ \t]*define[ \t]+(\w+)[ \t]+([^\n]*?)[ \t]*$").unwrap();
+    let number_regex = Regex::new(r"^(?:0[xX][0-9a-fA-F]+|\d+)[uUlL]*$").unwrap();
+    let macros = define_regex
+        .captures_iter(&content)
+        .filter(|caps| {
+            let body = &caps[2];
+            !body.is_empty()
+                && !body.ends_with('\\')
+                && !body.starts_with('"')
+                && !number_regex.is_match(body)
+        })
+        .map(|caps| (caps[1].to_string(), caps[2].to_string()))
+        .collect();
+    KeycodeDefines { macros }
+}
+
+/// The headers a keymap.c includes with quotes, e.g. `keycodes.h` for
+/// `#include "keycodes.h"`, as paths relative to the keymap.
+pub fn local_includes(content: &str) -> Vec<String> {
+    Regex::new(r#"(?m)^[ \t]*#[ \t]*include[ \t]+"([^"]+)""#)
+        .unwrap()
+        .captures_iter(content)
+        .map(|caps| caps[1].to_string())
+        .collect()
+}
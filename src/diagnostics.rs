@@ -0,0 +1,288 @@
+//! Coded warnings whose severity can be configured per code, the way
+//! compilers treat lints: each diagnostic can be allowed (silenced), left as
+//! a warning, or denied, which makes the run fail once it has finished.
+use crate::failure::ErrorFormat;
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::fmt::Display;
+use std::sync::OnceLock;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// Name accepted by `--allow`, `--warn`, and `--deny` for every diagnostic.
+pub const ALL_WARNINGS: &str = "warnings";
+
+/// Stage of the run a diagnostic comes from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Category {
+    Parse,
+    Validation,
+    Lint,
+    Render,
+}
+
+impl Category {
+    pub fn name(self) -> &'static str {
+        match self {
+            Category::Parse => "parse",
+            Category::Validation => "validation",
+            Category::Lint => "lint",
+            Category::Render => "render",
+        }
+    }
+}
+
+/// How a diagnostic is reported.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Level {
+    /// Not reported
+    Allow,
+    /// Reported as a warning
+    Warn,
+    /// Reported as an error, failing the run
+    Deny,
+}
+
+impl Level {
+    pub fn name(self) -> &'static str {
+        match self {
+            Level::Allow => "allow",
+            Level::Warn => "warning",
+            Level::Deny => "error",
+        }
+    }
+}
+
+/// A kind of diagnostic, identified by its code or name.
+#[derive(Debug, PartialEq, Eq)]
+pub struct Lint {
+    pub code: &'static str,
+    pub name: &'static str,
+    pub category: Category,
+}
+
+pub const FIRMWARE_EXTRACTION: Lint = Lint {
+    code: "KV0001",
+    name: "firmware-extraction",
+    category: Category::Parse,
+};
+pub const SKIPPED_REVISION: Lint = Lint {
+    code: "KV0002",
+    name: "skipped-revision",
+    category: Category::Parse,
+};
+pub const FALLBACK_BOARD: Lint = Lint {
+    code: "KV0010",
+    name: "fallback-board",
+    category: Category::Validation,
+};
+pub const DISABLED_FEATURE: Lint = Lint {
+    code: "KV0011",
+    name: "disabled-feature",
+    category: Category::Validation,
+};
+pub const UNREACHABLE_LAYER: Lint = Lint {
+    code: "KV0012",
+    name: "unreachable-layer",
+    category: Category::Validation,
+};
+pub const DUPLICATE_LAYER: Lint = Lint {
+    code: "KV0020",
+    name: "duplicate-layer",
+    category: Category::Lint,
+};
+pub const BASE_VARIANT_DIFFERENCE: Lint = Lint {
+    code: "KV0021",
+    name: "base-variant-difference",
+    category: Category::Lint,
+};
+pub const UNFORMATTED_LAYOUT: Lint = Lint {
+    code: "KV0022",
+    name: "unformatted-layout",
+    category: Category::Lint,
+};
+pub const SHADOWED_HOLD: Lint = Lint {
+    code: "KV0023",
+    name: "shadowed-hold",
+    category: Category::Lint,
+};
+pub const KEY_COUNT_MISMATCH: Lint = Lint {
+    code: "KV0030",
+    name: "key-count-mismatch",
+    category: Category::Render,
+};
+
+/// Every diagnostic, in code order.
+pub const LINTS: [&Lint; 10] = [
+    &FIRMWARE_EXTRACTION,
+    &SKIPPED_REVISION,
+    &FALLBACK_BOARD,
+    &DISABLED_FEATURE,
+    &UNREACHABLE_LAYER,
+    &DUPLICATE_LAYER,
+    &BASE_VARIANT_DIFFERENCE,
+    &UNFORMATTED_LAYOUT,
+    &SHADOWED_HOLD,
+    &KEY_COUNT_MISMATCH,
+];
+
+/// Looks up a diagnostic by code (case-insensitive) or name, returning its
+/// code, or [`ALL_WARNINGS`] for `warnings`. Used as a clap value parser.
+pub fn parse_lint(value: &str) -> Result<&'static str, String> {
+    if value == ALL_WARNINGS {
+        return Ok(ALL_WARNINGS);
+    }
+    LINTS
+        .iter()
+        .find(|lint| lint.code.eq_ignore_ascii_case(value) || lint.name == value)
+        .map(|lint| lint.code)
+        .ok_or_else(|| {
+            let known: Vec<&str> = LINTS.iter().map(|lint| lint.code).collect();
+            format!(
+                "unknown diagnostic {}; expected {} or one of {}",
+                value,
+                ALL_WARNINGS,
+                known.join(", ")
+            )
+        })
+}
+
+/// Levels read from a `--diagnostics` file, by code or name.
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct LevelConfig {
+    #[serde(default)]
+    pub allow: Vec<String>,
+    #[serde(default)]
+    pub warn: Vec<String>,
+    #[serde(default)]
+    pub deny: Vec<String>,
+}
+
+/// The level of each diagnostic for a run.
+#[derive(Debug, Default)]
+pub struct Levels {
+    all: Option<Level>,
+    codes: BTreeMap<&'static str, Level>,
+}
+
+impl Levels {
+    /// Sets the level of one diagnostic by code, or of all of them for
+    /// [`ALL_WARNINGS`]. A diagnostic's own level beats the one for all, and
+    /// otherwise the more severe level wins.
+    pub fn set(&mut self, code: &'static str, level: Level) {
+        if code == ALL_WARNINGS {
+            self.all = Some(self.all.map_or(level, |current| current.max(level)));
+        } else {
+            let current = self.codes.entry(code).or_insert(level);
+            *current = (*current).max(level);
+        }
+    }
+
+    /// Adds the levels of a `--diagnostics` file.
+    ///
+    /// # Returns
+    ///
+    /// An error naming the first unknown diagnostic
+    pub fn extend(&mut self, config: &LevelConfig) -> Result<(), String> {
+        for (names, level) in [
+            (&config.allow, Level::Allow),
+            (&config.warn, Level::Warn),
+            (&config.deny, Level::Deny),
+        ] {
+            for name in names {
+                self.set(parse_lint(name)?, level);
+            }
+        }
+        Ok(())
+    }
+
+    /// The level a diagnostic is reported at.
+    pub fn level(&self, lint: &Lint) -> Level {
+        self.codes
+            .get(lint.code)
+            .copied()
+            .or(self.all)
+            .unwrap_or(Level::Warn)
+    }
+}
+
+/// Levels and output format of the running command, with a count of denied
+/// diagnostics reported so far.
+struct Session {
+    levels: Levels,
+    format: ErrorFormat,
+    denied: AtomicUsize,
+}
+
+static SESSION: OnceLock<Session> = OnceLock::new();
+
+/// Sets the levels and output format of diagnostics for the rest of the run.
+/// Diagnostics emitted before, or without, this are reported as text
+/// warnings.
+pub fn configure(levels: Levels, format: ErrorFormat) {
+    let _ = SESSION.set(Session {
+        levels,
+        format,
+        denied: AtomicUsize::new(0),
+    });
+}
+
+/// Reports a diagnostic on stderr at its configured level.
+///
+/// # Arguments
+///
+/// * `lint` - The kind of diagnostic
+/// * `message` - What was found, without a trailing period
+pub fn emit(lint: &Lint, message: impl Display) {
+    let (level, format) = match SESSION.get() {
+        Some(session) => (session.levels.level(lint), session.format),
+        None => (Level::Warn, ErrorFormat::Text),
+    };
+    match level {
+        Level::Allow => return,
+        Level::Warn => {}
+        Level::Deny => {
+            if let Some(session) = SESSION.get() {
+                session.denied.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+    }
+
+    match format {
+        ErrorFormat::Text => {
+            let prefix = if level == Level::Deny {
+                "Error"
+            } else {
+                "Warning"
+            };
+            eprintln!("{}[{}]: {}", prefix, lint.code, message);
+        }
+        ErrorFormat::Json => {
+            let report = serde_json::json!({
+                "diagnostic": {
+                    "code": lint.code,
+                    "name": lint.name,
+                    "category": lint.category.name(),
+                    "level": level.name(),
+                    "message": message.to_string(),
+                }
+            });
+            eprintln!("{}", report);
+        }
+    }
+}
+
+/// Returns `true` unless a diagnostic is allowed, so checks whose only output
+/// is the diagnostic can be skipped.
+pub fn enabled(lint: &Lint) -> bool {
+    SESSION
+        .get()
+        .is_none_or(|session| session.levels.level(lint) != Level::Allow)
+}
+
+/// Number of denied diagnostics reported so far.
+pub fn denied() -> usize {
+    SESSION
+        .get()
+        .map_or(0, |session| session.denied.load(Ordering::Relaxed))
+}
@@ -0,0 +1,371 @@
+//! Key-by-key comparison of two keymaps.
+//!
+//! Keycodes are compared as parsed expressions rather than strings, so a
+//! change inside a wrapper such as `LT(2, KC_SPC)` → `LT(3, KC_SPC)` is
+//! reported as "hold layer changed 2→3" instead of a whole-key change.
+use crate::{Layer, is_empty_key};
+
+/// A keycode expression: a bare keycode or a macro call such as `LT(1, KC_A)`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Keycode {
+    Plain(String),
+    Call { name: String, args: Vec<Keycode> },
+}
+
+impl Keycode {
+    fn parse(key: &str) -> Keycode {
+        let key = key.trim();
+        let call = key.strip_suffix(')').and_then(|rest| rest.split_once('('));
+
+        match call {
+            Some((name, args)) if is_identifier(name.trim()) => Keycode::Call {
+                name: name.trim().to_string(),
+                args: split_args(args).into_iter().map(Keycode::parse).collect(),
+            },
+            _ => Keycode::Plain(key.to_string()),
+        }
+    }
+}
+
+impl std::fmt::Display for Keycode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Keycode::Plain(key) => write!(f, "{}", key),
+            Keycode::Call { name, args } => {
+                let args: Vec<String> = args.iter().map(Keycode::to_string).collect();
+                write!(f, "{}({})", name, args.join(", "))
+            }
+        }
+    }
+}
+
+fn is_identifier(name: &str) -> bool {
+    !name.is_empty() && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+/// Splits macro arguments at top-level commas.
+fn split_args(args: &str) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let mut depth = 0;
+    let mut start = 0;
+
+    for (i, ch) in args.char_indices() {
+        match ch {
+            '(' => depth += 1,
+            ')' => depth -= 1,
+            ',' if depth == 0 => {
+                parts.push(&args[start..i]);
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    parts.push(&args[start..]);
+
+    parts
+}
+
+/// Human-readable names for the arguments of common QMK keycode macros.
+fn argument_roles(name: &str) -> &'static [&'static str] {
+    match name {
+        "LT" => &["hold layer", "tap"],
+        "MT" => &["hold mods", "tap"],
+        "LM" => &["layer", "mods"],
+        "MO" | "TO" | "TG" | "DF" | "OSL" | "TT" | "PDF" => &["layer"],
+        "OSM" => &["mods"],
+        "TD" => &["tap dance"],
+        "C" | "S" | "A" | "G" | "LCTL" | "LSFT" | "LALT" | "LGUI" | "RCTL" | "RSFT" | "RALT"
+        | "RGUI" => &["key"],
+        _ => &[],
+    }
+}
+
+/// How a key's assignment changed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChangeKind {
+    /// A key was assigned where there was none
+    Added,
+    /// The key's assignment was cleared
+    Removed,
+    /// The key was assigned something else
+    Changed,
+}
+
+impl ChangeKind {
+    pub fn name(self) -> &'static str {
+        match self {
+            ChangeKind::Added => "added",
+            ChangeKind::Removed => "removed",
+            ChangeKind::Changed => "changed",
+        }
+    }
+}
+
+/// Returns `true` for keys that do nothing of their own: missing,
+/// transparent, or `KC_NO`.
+fn is_unassigned(key: &str) -> bool {
+    is_empty_key(key) || matches!(key, "KC_TRNS" | "KC_TRANSPARENT" | "KC_NO" | "XXXXXXX")
+}
+
+/// A key that differs between two versions of a layer.
+#[derive(Debug, Clone, PartialEq)]
+pub struct KeyChange {
+    /// Index of the layer containing the key in the new keymap
+    pub layer: usize,
+    /// Index of the key among the layer's LAYOUT arguments
+    pub position: usize,
+    pub old: String,
+    pub new: String,
+    /// What changed, e.g. `hold layer changed 2→3`
+    pub details: Vec<String>,
+}
+
+impl KeyChange {
+    /// Returns `true` if the key changed as a whole rather than in some of
+    /// its arguments.
+    pub fn is_whole_key(&self) -> bool {
+        self.details.len() == 1 && self.details[0].starts_with("changed ")
+    }
+
+    /// Whether the key was added, removed, or changed; a key going from or
+    /// to transparent or `KC_NO` counts as added or removed.
+    pub fn kind(&self) -> ChangeKind {
+        match (is_unassigned(&self.old), is_unassigned(&self.new)) {
+            (true, false) => ChangeKind::Added,
+            (false, true) => ChangeKind::Removed,
+            _ => ChangeKind::Changed,
+        }
+    }
+}
+
+/// A layer present in only one of two keymaps.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LayerChange {
+    /// Index of the layer in the keymap that has it
+    pub layer: usize,
+    pub name: Option<String>,
+    /// [`ChangeKind::Added`] for layers only in the new keymap,
+    /// [`ChangeKind::Removed`] for layers only in the old one
+    pub kind: ChangeKind,
+}
+
+/// Pairs each layer of `new` with its version in `old`.
+///
+/// A layer whose name both keymaps use is paired by name, so inserting a
+/// layer into the layer enum doesn't shift the layers after it; other layers
+/// are paired by index with a layer that isn't paired by name either.
+fn pair_layers<'a>(old: &'a [Layer], new: &'a [Layer]) -> Vec<(&'a Layer, Option<&'a Layer>)> {
+    let shared_name = |layer: &'a Layer, other: &[Layer]| -> Option<&'a String> {
+        layer
+            .name
+            .as_ref()
+            .filter(|name| other.iter().any(|o| o.name.as_ref() == Some(*name)))
+    };
+
+    new.iter()
+        .map(|layer| {
+            let partner = match shared_name(layer, old) {
+                Some(name) => old.iter().find(|o| o.name.as_ref() == Some(name)),
+                None => old
+                    .iter()
+                    .find(|o| o.index == layer.index && shared_name(o, new).is_none()),
+            };
+            (layer, partner)
+        })
+        .collect()
+}
+
+/// Lists the layers added to or removed from a keymap.
+///
+/// Layers are paired as in [`diff_layers`].
+///
+/// # Arguments
+///
+/// * `old` - Layers of the original keymap
+/// * `new` - Layers of the updated keymap
+///
+/// # Returns
+///
+/// The added layers in the new keymap's order, then the removed ones in the
+/// old keymap's order
+pub fn layer_changes(old: &[Layer], new: &[Layer]) -> Vec<LayerChange> {
+    let pairs = pair_layers(old, new);
+    let change = |layer: &Layer, kind| LayerChange {
+        layer: layer.index,
+        name: layer.name.clone(),
+        kind,
+    };
+
+    let added = pairs
+        .iter()
+        .filter(|(_, partner)| partner.is_none())
+        .map(|(layer, _)| change(layer, ChangeKind::Added));
+    let removed = old
+        .iter()
+        .filter(|layer| {
+            !pairs
+                .iter()
+                .any(|(_, partner)| partner.is_some_and(|p| p.index == layer.index))
+        })
+        .map(|layer| change(layer, ChangeKind::Removed));
+    added.chain(removed).collect()
+}
+
+/// Describes the difference between two keycodes.
+///
+/// # Arguments
+///
+/// * `old` - The keycode before the change
+/// * `new` - The keycode after the change
+///
+/// # Returns
+///
+/// One entry per changed argument when both keys use the same macro with the
+/// same number of arguments, a single whole-key entry otherwise, or nothing if
+/// the keycodes are equivalent
+pub fn describe_key_change(old: &str, new: &str) -> Vec<String> {
+    compare(&Keycode::parse(old), &Keycode::parse(new), "")
+}
+
+fn compare(old: &Keycode, new: &Keycode, role: &str) -> Vec<String> {
+    if old == new {
+        return Vec::new();
+    }
+
+    let prefix = if role.is_empty() {
+        String::new()
+    } else {
+        format!("{} ", role)
+    };
+
+    match (old, new) {
+        (
+            Keycode::Call {
+                name: old_name,
+                args: old_args,
+            },
+            Keycode::Call {
+                name: new_name,
+                args: new_args,
+            },
+        ) if old_name == new_name && old_args.len() == new_args.len() => {
+            let roles = argument_roles(old_name);
+            old_args
+                .iter()
+                .zip(new_args)
+                .enumerate()
+                .flat_map(|(i, (old_arg, new_arg))| {
+                    let arg_role = match roles.get(i) {
+                        Some(arg_role) => arg_role.to_string(),
+                        None => format!("{} argument {}", old_name, i + 1),
+                    };
+                    let nested = if role.is_empty() {
+                        arg_role
+                    } else {
+                        format!("{} {}", role, arg_role)
+                    };
+                    compare(old_arg, new_arg, &nested)
+                })
+                .collect()
+        }
+        _ => vec![format!("{}changed {}→{}", prefix, old, new)],
+    }
+}
+
+/// Compares two keymaps key by key.
+///
+/// Layers are matched by name where both keymaps name them and by index
+/// otherwise, and keys by LAYOUT argument position. Keys present in only one
+/// version of a layer are reported as changed from or to an empty string;
+/// layers present in only one keymap are left to [`layer_changes`].
+///
+/// # Arguments
+///
+/// * `old` - Layers of the original keymap
+/// * `new` - Layers of the updated keymap
+///
+/// # Returns
+///
+/// Every changed key of the layers in both keymaps, in the new keymap's
+/// layer order and position order
+pub fn diff_layers(old: &[Layer], new: &[Layer]) -> Vec<KeyChange> {
+    let keys = |layer: &Layer| -> Vec<String> { layer.keys.iter().flatten().cloned().collect() };
+
+    let mut changes = Vec::new();
+    for (new_layer, old_layer) in pair_layers(old, new) {
+        let Some(old_layer) = old_layer else {
+            continue;
+        };
+        let (old_keys, new_keys) = (keys(old_layer), keys(new_layer));
+
+        for position in 0..old_keys.len().max(new_keys.len()) {
+            let old_key = old_keys.get(position).cloned().unwrap_or_default();
+            let new_key = new_keys.get(position).cloned().unwrap_or_default();
+            let details = describe_key_change(&old_key, &new_key);
+
+            if !details.is_empty() {
+                changes.push(KeyChange {
+                    layer: new_layer.index,
+                    position,
+                    old: old_key,
+                    new: new_key,
+                    details,
+                });
+            }
+        }
+    }
+
+    changes
+}
+
+/// Lists what a conversion lost or altered, e.g. an export followed by an
+/// import of the result.
+///
+/// Keycodes are compared as parsed expressions, so spacing differences such
+/// as `LT(1,KC_A)` → `LT(1, KC_A)` are not losses. Layer names, notes, and
+/// encoder bindings are compared as well.
+///
+/// # Arguments
+///
+/// * `original` - Layers before the conversion
+/// * `converted` - Layers read back after the conversion
+///
+/// # Returns
+///
+/// One description per difference, or nothing if the keymaps are equivalent
+pub fn roundtrip_losses(original: &[Layer], converted: &[Layer]) -> Vec<String> {
+    let mut losses = Vec::new();
+    if original.len() != converted.len() {
+        losses.push(format!(
+            "{} layer(s) became {}",
+            original.len(),
+            converted.len()
+        ));
+    }
+
+    for (before, after) in original.iter().zip(converted) {
+        if before.name != after.name {
+            losses.push(format!(
+                "layer {} name changed {:?}→{:?}",
+                before.index, before.name, after.name
+            ));
+        }
+        if before.notes != after.notes {
+            losses.push(format!("layer {} notes changed", before.index));
+        }
+        if before.encoders != after.encoders {
+            losses.push(format!("layer {} encoder bindings changed", before.index));
+        }
+    }
+
+    for change in diff_layers(original, converted) {
+        losses.push(format!(
+            "layer {} key {}: {}",
+            change.layer,
+            change.position,
+            change.details.join("; ")
+        ));
+    }
+
+    losses
+}
@@ -0,0 +1,234 @@
+//! The `doctor` subcommand: environment and input sanity checks.
+use keyball44_viz::{Geometry, geometry, parse_layers};
+use regex::Regex;
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+
+/// Monospace fonts requested by the key legend style, in preference order.
+const LEGEND_FONTS: [&str; 4] = ["SF Mono", "Monaco", "Inconsolata", "Fira Code"];
+
+#[derive(Debug, PartialEq)]
+enum Status {
+    Ok,
+    Warn,
+    Fail,
+    Skip,
+}
+
+struct Check {
+    name: &'static str,
+    status: Status,
+    detail: String,
+    fix: Option<String>,
+}
+
+impl Check {
+    fn new(name: &'static str, status: Status, detail: impl Into<String>) -> Self {
+        Check {
+            name,
+            status,
+            detail: detail.into(),
+            fix: None,
+        }
+    }
+
+    fn with_fix(mut self, fix: impl Into<String>) -> Self {
+        self.fix = Some(fix.into());
+        self
+    }
+}
+
+fn check_keymap(keymap_file: &Path, content: Option<&str>, board: Option<&Geometry>) -> Vec<Check> {
+    let Some(content) = content else {
+        return vec![
+            Check::new(
+                "keymap readable",
+                Status::Fail,
+                format!("cannot read {:?}", keymap_file),
+            )
+            .with_fix("check the path and file permissions"),
+        ];
+    };
+
+    let mut checks = vec![Check::new(
+        "keymap readable",
+        Status::Ok,
+        format!("{:?}", keymap_file),
+    )];
+
+    let layers = match parse_layers(content) {
+        Ok(layers) if !layers.is_empty() => layers,
+        Ok(_) => {
+            checks.push(
+                Check::new("keymap parses", Status::Fail, "no layers found")
+                    .with_fix("make sure the file defines `const uint16_t PROGMEM keymaps[]...` with LAYOUT(...) entries"),
+            );
+            return checks;
+        }
+        Err(err) => {
+            checks.push(Check::new(
+                "keymap parses",
+                Status::Fail,
+                format!("{:#}", err),
+            ));
+            return checks;
+        }
+    };
+    checks.push(Check::new(
+        "keymap parses",
+        Status::Ok,
+        format!("{} layers", layers.len()),
+    ));
+
+    let geometry = match board {
+        Some(geometry) => {
+            checks.push(Check::new(
+                "board",
+                Status::Ok,
+                format!("{} (from --board)", geometry.name),
+            ));
+            geometry.clone()
+        }
+        None => match geometry::detect(&layers) {
+            Some(detection) => {
+                checks.push(Check::new(
+                    "board",
+                    Status::Ok,
+                    format!("detected {}: {}", detection.geometry.name, detection.reason),
+                ));
+                detection.geometry
+            }
+            None => {
+                checks.push(
+                    Check::new(
+                        "board",
+                        Status::Warn,
+                        "no built-in board matches, rendering as an ortholinear grid",
+                    )
+                    .with_fix("pass --board to choose the geometry explicitly"),
+                );
+                geometry::fallback(&layers)
+            }
+        },
+    };
+
+    let mismatched: Vec<String> = layers
+        .iter()
+        .filter_map(|layer| {
+            let count = layer.keys.iter().map(Vec::len).sum::<usize>();
+            (count != geometry.key_count())
+                .then(|| format!("layer {} has {} keys", layer.index, count))
+        })
+        .collect();
+    if mismatched.is_empty() {
+        checks.push(Check::new(
+            "key counts",
+            Status::Ok,
+            format!("all layers match the {} layout", geometry.name),
+        ));
+    } else {
+        checks.push(
+            Check::new("key counts", Status::Fail, mismatched.join("; ")).with_fix(format!(
+                "each layer needs {} keys for {}; pass --board if this is a different keyboard",
+                geometry.key_count(),
+                geometry.name
+            )),
+        );
+    }
+
+    checks.push(check_includes(keymap_file, content));
+    checks
+}
+
+fn check_includes(keymap_file: &Path, content: &str) -> Check {
+    let include_regex = Regex::new(r#"^\s*#\s*include\s+"([^"]+)""#).unwrap();
+    let base_dir = keymap_file.parent().unwrap_or(Path::new("."));
+
+    let missing: Vec<String> = content
+        .lines()
+        .filter_map(|line| include_regex.captures(line))
+        .map(|caps| caps[1].to_string())
+        .filter(|include| !base_dir.join(include).exists())
+        .collect();
+
+    if missing.is_empty() {
+        Check::new("include files", Status::Ok, "all local includes found")
+    } else {
+        Check::new("include files", Status::Warn, format!("not found next to the keymap: {}", missing.join(", ")))
+            .with_fix("headers from the QMK tree (e.g. quantum.h) are expected to be missing; copy local headers next to keymap.c")
+    }
+}
+
+fn check_fonts() -> Check {
+    let Ok(output) = Command::new("fc-list").args([":", "family"]).output() else {
+        return Check::new(
+            "legend font",
+            Status::Skip,
+            "fc-list not available to query installed fonts",
+        );
+    };
+
+    let families = String::from_utf8_lossy(&output.stdout);
+    match LEGEND_FONTS.iter().find(|font| families.contains(*font)) {
+        Some(font) => Check::new("legend font", Status::Ok, format!("{} is installed", font)),
+        None => Check::new(
+            "legend font",
+            Status::Warn,
+            "none of the legend fonts are installed",
+        )
+        .with_fix(format!(
+            "install one of {} or legends fall back to the generic monospace font",
+            LEGEND_FONTS.join(", ")
+        )),
+    }
+}
+
+fn check_output_dir(output_file: &Path) -> Check {
+    let dir = match output_file.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
+    };
+
+    let probe = dir.join(".keyball44-viz-doctor");
+    match fs::write(&probe, b"") {
+        Ok(()) => {
+            let _ = fs::remove_file(&probe);
+            Check::new(
+                "output directory",
+                Status::Ok,
+                format!("{:?} is writable", dir),
+            )
+        }
+        Err(err) => Check::new(
+            "output directory",
+            Status::Fail,
+            format!("{:?}: {}", dir, err),
+        )
+        .with_fix("create the directory or choose another path with --output-file"),
+    }
+}
+
+/// Runs all checks, printing a report, and returns whether none failed.
+pub fn run(keymap_file: &Path, output_file: &Path, board: Option<&Geometry>) -> bool {
+    let content = fs::read_to_string(keymap_file).ok();
+
+    let mut checks = check_keymap(keymap_file, content.as_deref(), board);
+    checks.push(check_fonts());
+    checks.push(check_output_dir(output_file));
+
+    for check in &checks {
+        let tag = match check.status {
+            Status::Ok => "ok",
+            Status::Warn => "warn",
+            Status::Fail => "FAIL",
+            Status::Skip => "skip",
+        };
+        println!("[{:>4}] {}: {}", tag, check.name, check.detail);
+        if let Some(fix) = &check.fix {
+            println!("       fix: {}", fix);
+        }
+    }
+
+    checks.iter().all(|check| check.status != Status::Fail)
+}
@@ -0,0 +1,120 @@
+//! A structured form of a parsed keymap, for tools such as web viewers and
+//! diff scripts that want the parse result without a C parser of their own.
+use crate::analysis::{KeyCategory, LayerEdge, layer_edges};
+use crate::encoder::EncoderBinding;
+use crate::geometry::Geometry;
+use crate::{Layer, extract_layer_number, layer_symbol_table};
+use serde::Serialize;
+
+/// One key of a layer with what the parser made of it.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Key {
+    /// The keycode as written in the keymap
+    pub code: String,
+    /// What the key does
+    pub kind: KeyCategory,
+    /// Canonical name of the key's position on the board (e.g. `L-R2C3`)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub position: Option<String>,
+    /// The layer the key activates, for layer keys
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub target_layer: Option<usize>,
+}
+
+/// A layer with its keys classified.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct KeymapLayer {
+    pub index: usize,
+    /// The layer's symbolic name (e.g. `_NAV`), if it has one
+    pub name: Option<String>,
+    /// The LAYOUT macro the layer was written with
+    pub layout_macro: Option<String>,
+    /// Keys by row, as written in the keymap
+    pub keys: Vec<Vec<Key>>,
+    /// Notes from `// viz:` comments
+    pub notes: Vec<String>,
+    /// Encoder bindings, one per encoder
+    pub encoders: Vec<EncoderBinding>,
+}
+
+/// A layer key as an edge of the layer graph.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Edge {
+    #[serde(flatten)]
+    pub edge: LayerEdge,
+    /// The edge's kind and modifier, e.g. `momentary + MOD_LSFT`
+    pub label: String,
+}
+
+/// A parsed keymap and the board it was matched to.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Keymap {
+    /// Name of the board the keys are placed on
+    pub board: String,
+    pub layers: Vec<KeymapLayer>,
+    /// Every layer key, from the layer it's on to the layer it activates
+    pub edges: Vec<Edge>,
+}
+
+impl Keymap {
+    /// Classifies the keys of parsed layers.
+    ///
+    /// # Arguments
+    ///
+    /// * `layers` - The parsed layers
+    /// * `geometry` - The board the layers are drawn on, which names the key
+    ///   positions when its key count matches
+    ///
+    /// # Returns
+    ///
+    /// The keymap with every key's kind, position, and target layer, and
+    /// the layer graph those keys make
+    pub fn new(layers: &[Layer], geometry: &Geometry) -> Self {
+        let edges = layer_edges(layers)
+            .into_iter()
+            .map(|edge| Edge {
+                label: edge.label(),
+                edge,
+            })
+            .collect();
+        let symbols = layer_symbol_table(layers);
+        let names = geometry.position_names();
+        let layers = layers
+            .iter()
+            .map(|layer| {
+                let mut position = 0;
+                let keys = layer
+                    .keys
+                    .iter()
+                    .map(|row| {
+                        row.iter()
+                            .map(|code| {
+                                let key = Key {
+                                    code: code.clone(),
+                                    kind: KeyCategory::of(code),
+                                    position: names.get(position).cloned(),
+                                    target_layer: extract_layer_number(code, &symbols),
+                                };
+                                position += 1;
+                                key
+                            })
+                            .collect()
+                    })
+                    .collect();
+                KeymapLayer {
+                    index: layer.index,
+                    name: layer.name.clone(),
+                    layout_macro: layer.layout_macro.clone(),
+                    keys,
+                    notes: layer.notes.clone(),
+                    encoders: layer.encoders.clone(),
+                }
+            })
+            .collect();
+        Keymap {
+            board: geometry.name.clone(),
+            layers,
+            edges,
+        }
+    }
+}
@@ -0,0 +1,118 @@
+//! Parsing of the `encoder_map` array that assigns keycodes to rotary
+//! encoders per layer.
+use crate::parse_keys_with_parens;
+use regex::Regex;
+use serde::Serialize;
+
+/// The keycodes sent when an encoder is turned.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct EncoderBinding {
+    /// Keycode sent on a counter-clockwise turn
+    pub ccw: String,
+    /// Keycode sent on a clockwise turn
+    pub cw: String,
+}
+
+impl EncoderBinding {
+    /// Both directions of the binding, e.g. `↺ KC_VOLD, ↻ KC_VOLU`.
+    pub fn describe(&self) -> String {
+        format!("↺ {}, ↻ {}", self.ccw, self.cw)
+    }
+}
+
+/// One layer's entry in the encoder map.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct EncoderLayer {
+    /// The designator inside `[...]` (a layer index or name), if any
+    pub designator: Option<String>,
+    /// One binding per encoder
+    pub bindings: Vec<EncoderBinding>,
+}
+
+/// Removes `//` and `/* */` comments from C source.
+fn strip_comments(source: &str) -> String {
+    let block = Regex::new(r"(?s)/\*.*?\*/").unwrap();
+    let line = Regex::new(r"//[^\n]*").unwrap();
+    line.replace_all(&block.replace_all(source, " "), "")
+        .into_owned()
+}
+
+/// Returns the text between the bracket at `open` and its matching close.
+fn enclosed(text: &str, open: usize, open_char: char, close_char: char) -> Option<&str> {
+    let mut depth = 0;
+    for (idx, ch) in text[open..].char_indices() {
+        if ch == open_char {
+            depth += 1;
+        } else if ch == close_char {
+            depth -= 1;
+            if depth == 0 {
+                return Some(&text[open + 1..open + idx]);
+            }
+        }
+    }
+    None
+}
+
+/// Parses the `ENCODER_CCW_CW(ccw, cw)` bindings of one layer entry.
+fn parse_bindings(entry: &str) -> Vec<EncoderBinding> {
+    let mut bindings = Vec::new();
+    let mut rest = entry;
+
+    while let Some(start) = rest.find("ENCODER_CCW_CW") {
+        let Some(open) = rest[start..].find('(').map(|offset| start + offset) else {
+            break;
+        };
+        let Some(args) = enclosed(rest, open, '(', ')') else {
+            break;
+        };
+        if let [ccw, cw] = parse_keys_with_parens(args).as_slice() {
+            bindings.push(EncoderBinding {
+                ccw: ccw.clone(),
+                cw: cw.clone(),
+            });
+        }
+        rest = &rest[open + args.len() + 2..];
+    }
+
+    bindings
+}
+
+/// Parses the `encoder_map` array of a keymap, if it has one.
+///
+/// # Arguments
+///
+/// * `content` - The keymap.c source
+///
+/// # Returns
+///
+/// The map's entries in source order; empty if there is no encoder map
+pub(crate) fn parse_encoder_map(content: &str) -> Vec<EncoderLayer> {
+    let source = strip_comments(content);
+    let start_regex = Regex::new(r"\bencoder_map\s*\[[^=]*=\s*\{").unwrap();
+    let Some(start) = start_regex.find(&source) else {
+        return Vec::new();
+    };
+    let Some(body) = enclosed(&source, start.end() - 1, '{', '}') else {
+        return Vec::new();
+    };
+
+    let designator_regex = Regex::new(r"^\s*,?\s*\[\s*(\w+)\s*\]\s*=\s*$").unwrap();
+    let mut layers = Vec::new();
+    let mut rest = body;
+
+    while let Some(open) = rest.find('{') {
+        let designator = designator_regex
+            .captures(&rest[..open])
+            .map(|caps| caps[1].to_string());
+        let Some(entry) = enclosed(rest, open, '{', '}') else {
+            break;
+        };
+        layers.push(EncoderLayer {
+            designator,
+            bindings: parse_bindings(entry),
+        });
+        rest = &rest[open + entry.len() + 2..];
+    }
+
+    layers
+}
@@ -0,0 +1,116 @@
+//! Error categories, process exit codes, and machine-readable error output.
+use clap::ValueEnum;
+use keyball44_viz::parse_error::ParseError;
+use std::process::ExitCode;
+
+/// Broad category of a failure, each with its own process exit code.
+///
+/// Exit code 2 is left to clap for command line usage errors.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorKind {
+    /// Unexpected failure that doesn't fit another category (exit code 1)
+    Other,
+    /// Reading input or writing output failed (exit code 3)
+    Io,
+    /// The keymap or another input file couldn't be parsed (exit code 4)
+    Parse,
+    /// The input parsed but failed validation (exit code 5)
+    Validation,
+    /// Producing the output document failed (exit code 6)
+    Render,
+}
+
+impl ErrorKind {
+    pub fn exit_code(self) -> u8 {
+        match self {
+            ErrorKind::Other => 1,
+            ErrorKind::Io => 3,
+            ErrorKind::Parse => 4,
+            ErrorKind::Validation => 5,
+            ErrorKind::Render => 6,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            ErrorKind::Other => "other",
+            ErrorKind::Io => "io",
+            ErrorKind::Parse => "parse",
+            ErrorKind::Validation => "validation",
+            ErrorKind::Render => "render",
+        }
+    }
+}
+
+/// How errors are reported on stderr.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum ErrorFormat {
+    /// Human-readable message with its causes
+    Text,
+    /// A single JSON object with the kind, exit code, message, and causes
+    Json,
+}
+
+/// An error tagged with its category.
+#[derive(Debug)]
+pub struct Failure {
+    pub kind: ErrorKind,
+    pub error: anyhow::Error,
+}
+
+impl From<anyhow::Error> for Failure {
+    fn from(error: anyhow::Error) -> Self {
+        Failure {
+            kind: ErrorKind::Other,
+            error,
+        }
+    }
+}
+
+/// Tags the error of a `Result` with an `ErrorKind`.
+pub trait ResultExt<T> {
+    fn kind(self, kind: ErrorKind) -> Result<T, Failure>;
+}
+
+impl<T, E: Into<anyhow::Error>> ResultExt<T> for Result<T, E> {
+    fn kind(self, kind: ErrorKind) -> Result<T, Failure> {
+        self.map_err(|error| Failure {
+            kind,
+            error: error.into(),
+        })
+    }
+}
+
+/// Prints `failure` in the requested format and returns its exit code.
+pub fn report(failure: &Failure, format: ErrorFormat) -> ExitCode {
+    let code = failure.kind.exit_code();
+
+    match format {
+        ErrorFormat::Text => eprintln!("Error: {:?}", failure.error),
+        ErrorFormat::Json => {
+            let causes: Vec<String> = failure
+                .error
+                .chain()
+                .skip(1)
+                .map(|c| c.to_string())
+                .collect();
+            let mut report = serde_json::json!({
+                "kind": failure.kind.name(),
+                "code": code,
+                "message": failure.error.to_string(),
+                "causes": causes,
+            });
+            // Errors in a keymap source carry their location
+            if let Some(error) = failure.error.downcast_ref::<ParseError>() {
+                report["message"] = error.message.clone().into();
+                report["line"] = error.line.into();
+                report["column"] = error.column.into();
+                report["snippet"] = error.snippet.clone().into();
+            }
+            let report = serde_json::json!({ "error": report });
+            eprintln!("{}", report);
+        }
+    }
+
+    ExitCode::from(code)
+}
@@ -0,0 +1,387 @@
+//! Experimental recovery of keymaps from compiled Keyball44 firmware images.
+//!
+//! QMK stores the `keymaps` array as a flat table of little-endian `uint16_t`
+//! keycodes, one `MATRIX_ROWS x MATRIX_COLS` block per layer. This module loads
+//! an Intel HEX, UF2, or raw binary image, scans it for a run of blocks that
+//! decode to plausible keycodes, and maps each block back onto the LAYOUT
+//! argument order so the result can be rendered like a parsed keymap.c.
+use crate::{Geometry, Layer};
+use anyhow::{Context, Result, anyhow, bail};
+
+/// Number of keycodes per layer in the Keyball44 matrix (8 rows x 6 columns).
+const MATRIX_SIZE: usize = 48;
+
+/// Matrix index for each LAYOUT_universal argument, in argument order.
+///
+/// The right half is wired mirrored, so its columns count down from the outer
+/// edge. The two matrix slots without a switch (left row 3 col 0 and right
+/// row 3 col 2) are not part of the layout.
+const KEYBALL44_MATRIX_MAP: [usize; 46] = [
+    0, 1, 2, 3, 4, 5, 29, 28, 27, 26, 25, 24, // Row 0
+    6, 7, 8, 9, 10, 11, 35, 34, 33, 32, 31, 30, // Row 1
+    12, 13, 14, 15, 16, 17, 41, 40, 39, 38, 37, 36, // Row 2
+    19, 20, 21, 22, 23, 47, 46, 45, 43, 42, // Row 3
+];
+
+/// UF2 block magic numbers and payload layout.
+const UF2_MAGIC_START0: u32 = 0x0A32_4655;
+const UF2_MAGIC_START1: u32 = 0x9E5D_5157;
+const UF2_BLOCK_SIZE: usize = 512;
+
+/// Keyball specific keycodes starting at `QK_KB_0`.
+const KEYBALL_KEYCODES: [&str; 16] = [
+    "KBC_RST", "KBC_SAVE", "CPI_I100", "CPI_D100", "CPI_I1K", "CPI_D1K", "SCRL_TO", "SCRL_MO",
+    "SCRL_DVI", "SCRL_DVD", "AML_TO", "AML_I50", "AML_D50", "SSNP_VRT", "SSNP_HOR", "SSNP_FRE",
+];
+
+/// Loads a firmware file into a flat memory image.
+///
+/// Intel HEX (text starting with `:`) and UF2 images are decoded according to
+/// their record addresses; gaps are filled with `0xFF` like erased flash. Any
+/// other input is treated as a raw binary image.
+///
+/// # Arguments
+///
+/// * `data` - The raw bytes of the firmware file
+///
+/// # Returns
+///
+/// * `Result<Vec<u8>>` - The flattened memory image, or an error if a record is malformed
+pub fn load_image(data: &[u8]) -> Result<Vec<u8>> {
+    if data.first() == Some(&b':') {
+        load_intel_hex(data)
+    } else if data.len() >= 8 && read_u32(data, 0) == UF2_MAGIC_START0 {
+        load_uf2(data)
+    } else {
+        Ok(data.to_vec())
+    }
+}
+
+/// Extracts keymap layers from a compiled Keyball44 firmware file.
+///
+/// The image is scanned for the longest run of consecutive layer blocks whose
+/// keycodes all decode, where the first block looks like a base layer (it
+/// contains at least a dozen letter or number keys). Decoded keycodes use the
+/// same spelling as keymap.c sources, so the result feeds the regular renderer.
+///
+/// # Arguments
+///
+/// * `data` - The raw bytes of an Intel HEX, UF2, or binary firmware file
+///
+/// # Returns
+///
+/// * `Result<Vec<Layer>>` - The recovered layers, or an error if no keymap table was found
+pub fn extract_layers(data: &[u8]) -> Result<Vec<Layer>> {
+    let image = load_image(data).context("Failed to load firmware image")?;
+    let words: Vec<u16> = image
+        .chunks_exact(2)
+        .map(|pair| u16::from_le_bytes([pair[0], pair[1]]))
+        .collect();
+
+    let last_start = words.len().checked_sub(MATRIX_SIZE).ok_or(anyhow!(
+        "Firmware image is too small to hold a keymap table"
+    ))?;
+    // Matrix slots without a switch
+    let holes: Vec<usize> = (0..MATRIX_SIZE)
+        .filter(|idx| !KEYBALL44_MATRIX_MAP.contains(idx))
+        .collect();
+
+    let mut best: Option<(usize, usize)> = None; // (start word, layer count)
+    for start in 0..=last_start {
+        if !looks_like_base_layer(&words[start..start + MATRIX_SIZE], &holes) {
+            continue;
+        }
+
+        let count = words[start..]
+            .chunks_exact(MATRIX_SIZE)
+            .take_while(|block| block.iter().all(|&code| decode_keycode(code).is_some()))
+            .count();
+
+        if count > 0 && best.is_none_or(|(_, best_count)| count > best_count) {
+            best = Some((start, count));
+        }
+    }
+
+    let (start, count) = best.ok_or(anyhow!("No keymap table found in firmware image"))?;
+
+    let layers = words[start..]
+        .chunks_exact(MATRIX_SIZE)
+        .take(count)
+        .enumerate()
+        .map(|(index, block)| {
+            let mut args = KEYBALL44_MATRIX_MAP
+                .iter()
+                .map(|&matrix_idx| decode_keycode(block[matrix_idx]).unwrap_or_default());
+            let keys = Geometry::keyball44()
+                .row_lengths()
+                .into_iter()
+                .map(|len| args.by_ref().take(len).collect())
+                .collect();
+            Layer {
+                index,
+                name: None,
+                title: None,
+                keys,
+                notes: Vec::new(),
+                layout_macro: None,
+                source: None,
+                encoders: Vec::new(),
+            }
+        })
+        .collect();
+
+    Ok(layers)
+}
+
+/// Returns `true` if a block of `MATRIX_SIZE` keycodes can be a base layer:
+/// its matrix `holes` are empty and it has a dozen letter or number keys.
+fn looks_like_base_layer(block: &[u16], holes: &[usize]) -> bool {
+    const MIN_ALPHANUMERICS: usize = 12;
+
+    // Layout slots without a switch are always KC_NO in a real keymap table
+    let holes_empty = holes.iter().all(|&idx| block[idx] == 0);
+
+    // KC_A through KC_0
+    let alphanumerics = block
+        .iter()
+        .filter(|&&code| (0x04..=0x27).contains(&code))
+        .count();
+
+    holes_empty && alphanumerics >= MIN_ALPHANUMERICS
+}
+
+fn read_u32(data: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes([
+        data[offset],
+        data[offset + 1],
+        data[offset + 2],
+        data[offset + 3],
+    ])
+}
+
+fn place(image: &mut Vec<u8>, address: usize, bytes: &[u8]) {
+    if image.len() < address + bytes.len() {
+        image.resize(address + bytes.len(), 0xFF);
+    }
+    image[address..address + bytes.len()].copy_from_slice(bytes);
+}
+
+fn load_intel_hex(data: &[u8]) -> Result<Vec<u8>> {
+    let text = std::str::from_utf8(data).context("Intel HEX file is not valid text")?;
+    let mut image = Vec::new();
+    let mut base_address = 0usize;
+    let mut min_address = usize::MAX;
+    let mut records = Vec::new();
+
+    for (line_no, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+
+        let hex = line
+            .strip_prefix(':')
+            .ok_or(anyhow!("Line {}: missing ':' record marker", line_no + 1))?;
+        let bytes = (0..hex.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(hex.get(i..i + 2).unwrap_or("?"), 16))
+            .collect::<Result<Vec<u8>, _>>()
+            .context(format!("Line {}: invalid hex digits", line_no + 1))?;
+
+        if bytes.len() < 5 || bytes.len() != bytes[0] as usize + 5 {
+            bail!("Line {}: record length mismatch", line_no + 1);
+        }
+        if bytes.iter().fold(0u8, |sum, b| sum.wrapping_add(*b)) != 0 {
+            bail!("Line {}: checksum mismatch", line_no + 1);
+        }
+
+        let offset = u16::from_be_bytes([bytes[1], bytes[2]]) as usize;
+        let payload = &bytes[4..bytes.len() - 1];
+        if matches!(bytes[3], 0x02 | 0x04) && payload.len() != 2 {
+            bail!("Line {}: malformed address record", line_no + 1);
+        }
+        match bytes[3] {
+            0x00 => {
+                min_address = min_address.min(base_address + offset);
+                records.push((base_address + offset, payload.to_vec()));
+            }
+            0x01 => break,
+            0x02 => base_address = (u16::from_be_bytes([payload[0], payload[1]]) as usize) << 4,
+            0x04 => base_address = (u16::from_be_bytes([payload[0], payload[1]]) as usize) << 16,
+            _ => {}
+        }
+    }
+
+    // Rebase onto the lowest address so flash offsets don't allocate gigabytes
+    for (address, payload) in records {
+        place(&mut image, address - min_address, &payload);
+    }
+
+    Ok(image)
+}
+
+fn load_uf2(data: &[u8]) -> Result<Vec<u8>> {
+    let mut image = Vec::new();
+    let mut blocks = Vec::new();
+
+    for (block_no, block) in data.chunks(UF2_BLOCK_SIZE).enumerate() {
+        if block.len() != UF2_BLOCK_SIZE
+            || read_u32(block, 0) != UF2_MAGIC_START0
+            || read_u32(block, 4) != UF2_MAGIC_START1
+        {
+            bail!("UF2 block {} is malformed", block_no);
+        }
+
+        let address = read_u32(block, 12) as usize;
+        let size = (read_u32(block, 16) as usize).min(476);
+        blocks.push((address, &block[32..32 + size]));
+    }
+
+    let min_address = blocks
+        .iter()
+        .map(|(address, _)| *address)
+        .min()
+        .unwrap_or(0);
+    for (address, payload) in blocks {
+        place(&mut image, address - min_address, payload);
+    }
+
+    Ok(image)
+}
+
+fn basic_keycode(code: u16) -> Option<String> {
+    const LETTERS: &str = "ABCDEFGHIJKLMNOPQRSTUVWXYZ";
+    const NAMED: [(u16, &str); 49] = [
+        (0x28, "KC_ENT"),
+        (0x29, "KC_ESC"),
+        (0x2A, "KC_BSPC"),
+        (0x2B, "KC_TAB"),
+        (0x2C, "KC_SPC"),
+        (0x2D, "KC_MINS"),
+        (0x2E, "KC_EQL"),
+        (0x2F, "KC_LBRC"),
+        (0x30, "KC_RBRC"),
+        (0x31, "KC_BSLS"),
+        (0x32, "KC_NUHS"),
+        (0x33, "KC_SCLN"),
+        (0x34, "KC_QUOT"),
+        (0x35, "KC_GRV"),
+        (0x36, "KC_COMM"),
+        (0x37, "KC_DOT"),
+        (0x38, "KC_SLSH"),
+        (0x39, "KC_CAPS"),
+        (0x46, "KC_PSCR"),
+        (0x47, "KC_SCRL"),
+        (0x48, "KC_PAUS"),
+        (0x49, "KC_INS"),
+        (0x4A, "KC_HOME"),
+        (0x4B, "KC_PGUP"),
+        (0x4C, "KC_DEL"),
+        (0x4D, "KC_END"),
+        (0x4E, "KC_PGDN"),
+        (0x4F, "KC_RGHT"),
+        (0x50, "KC_LEFT"),
+        (0x51, "KC_DOWN"),
+        (0x52, "KC_UP"),
+        (0x53, "KC_NUM"),
+        (0x64, "KC_NUBS"),
+        (0x65, "KC_APP"),
+        (0xA5, "KC_PWR"),
+        (0xA8, "KC_MUTE"),
+        (0xA9, "KC_VOLU"),
+        (0xAA, "KC_VOLD"),
+        (0xAB, "KC_MNXT"),
+        (0xAC, "KC_MPRV"),
+        (0xAE, "KC_MPLY"),
+        (0xD1, "KC_BTN1"),
+        (0xD2, "KC_BTN2"),
+        (0xD3, "KC_BTN3"),
+        (0xE0, "KC_LCTL"),
+        (0xE1, "KC_LSFT"),
+        (0xE2, "KC_LALT"),
+        (0xE3, "KC_LGUI"),
+        (0xE4, "KC_RCTL"),
+    ];
+
+    match code {
+        0x00 => Some("XXXXXXX".to_string()),
+        0x01 => Some("_______".to_string()),
+        0x04..=0x1D => Some(format!("KC_{}", &LETTERS[(code - 0x04) as usize..][..1])),
+        0x1E..=0x26 => Some(format!("KC_{}", code - 0x1D)),
+        0x27 => Some("KC_0".to_string()),
+        0x3A..=0x45 => Some(format!("KC_F{}", code - 0x39)),
+        0xE5 => Some("KC_RSFT".to_string()),
+        0xE6 => Some("KC_RALT".to_string()),
+        0xE7 => Some("KC_RGUI".to_string()),
+        _ => NAMED
+            .iter()
+            .find(|(value, _)| *value == code)
+            .map(|(_, name)| name.to_string()),
+    }
+}
+
+fn mod_name(mods: u16) -> String {
+    let side = if mods & 0x10 != 0 { "R" } else { "L" };
+    let names: Vec<String> = [(0x01, "CTL"), (0x02, "SFT"), (0x04, "ALT"), (0x08, "GUI")]
+        .iter()
+        .filter(|(bit, _)| mods & bit != 0)
+        .map(|(_, name)| format!("MOD_{}{}", side, name))
+        .collect();
+    names.join(" | ")
+}
+
+/// Decodes a 16-bit QMK keycode into its keymap.c spelling.
+///
+/// Returns `None` for values that don't correspond to a keycode this decoder
+/// knows, which the extractor uses to reject non-keymap data.
+fn decode_keycode(code: u16) -> Option<String> {
+    let layer_fn = |name: &str, base: u16| format!("{}({})", name, code - base);
+
+    match code {
+        0x0000..=0x00FF => basic_keycode(code),
+        0x0100..=0x1FFF => {
+            let wrappers = [(0x01, "C"), (0x02, "S"), (0x04, "A"), (0x08, "G")];
+            let mods = (code >> 8) & 0x1F;
+            let mut key = basic_keycode(code & 0xFF)?;
+            for (bit, wrapper) in wrappers {
+                if mods & bit != 0 {
+                    let wrapper = if mods & 0x10 != 0 {
+                        format!("R{}", wrapper)
+                    } else {
+                        wrapper.to_string()
+                    };
+                    key = format!("{}({})", wrapper, key);
+                }
+            }
+            Some(key)
+        }
+        0x2000..=0x3FFF => Some(format!(
+            "MT({}, {})",
+            mod_name((code >> 8) & 0x1F),
+            basic_keycode(code & 0xFF)?
+        )),
+        0x4000..=0x4FFF => Some(format!(
+            "LT({}, {})",
+            (code >> 8) & 0x0F,
+            basic_keycode(code & 0xFF)?
+        )),
+        0x5000..=0x51FF => Some(format!(
+            "LM({}, {})",
+            (code >> 5) & 0x0F,
+            mod_name(code & 0x1F)
+        )),
+        0x5200..=0x521F => Some(layer_fn("TO", 0x5200)),
+        0x5220..=0x523F => Some(layer_fn("MO", 0x5220)),
+        0x5240..=0x525F => Some(layer_fn("DF", 0x5240)),
+        0x5260..=0x527F => Some(layer_fn("TG", 0x5260)),
+        0x5280..=0x529F => Some(layer_fn("OSL", 0x5280)),
+        0x52A0..=0x52BF => Some(format!("OSM({})", mod_name(code & 0x1F))),
+        0x52C0..=0x52DF => Some(layer_fn("TT", 0x52C0)),
+        0x5700..=0x57FF => Some(layer_fn("TD", 0x5700)),
+        0x7C00 => Some("QK_BOOT".to_string()),
+        0x7C7B => Some("QK_LLCK".to_string()),
+        0x7E00..=0x7E0F => Some(KEYBALL_KEYCODES[(code - 0x7E00) as usize].to_string()),
+        0x7E40..=0x7E7F => Some(format!("QK_USER_{}", code - 0x7E40)),
+        _ => None,
+    }
+}
@@ -0,0 +1,315 @@
+//! Formatter that aligns the keycodes of LAYOUT blocks into visual columns.
+//!
+//! Only the key lines between a `LAYOUT(` line and its closing `)` are
+//! rewritten; everything else in the file is kept byte for byte.
+use crate::diff::roundtrip_losses;
+use crate::geometry::{Geometry, Half};
+use crate::{
+    blank_block_comments, follow_parens, keymaps_start_regex, parse_keys_with_parens, parse_layers,
+};
+use anyhow::{Context, Result, bail};
+use regex::Regex;
+
+/// Spaces added between the left and right halves of a row.
+const HALF_SEPARATOR: usize = 4;
+
+/// A LAYOUT block whose key lines can be rewritten.
+struct Block {
+    /// Line index of the first key line
+    start: usize,
+    /// Line index one past the last key line
+    end: usize,
+    /// Leading whitespace of the first key line
+    indent: String,
+    /// What follows the keys on the last key line when the block's `)`
+    /// closes there, e.g. `),`
+    tail: Option<String>,
+    /// Keys as written, one entry per source line
+    lines: Vec<Vec<String>>,
+}
+
+/// Result of formatting a keymap.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Formatted {
+    /// The formatted source
+    pub content: String,
+    /// Number of LAYOUT blocks that were aligned
+    pub formatted_blocks: usize,
+    /// Layer indices of blocks left untouched because they contain comments
+    pub skipped_blocks: Vec<usize>,
+}
+
+/// A LAYOUT block whose closing `)` hasn't been reached yet.
+struct OpenBlock {
+    /// Line index of the first key line
+    start: usize,
+    /// Parentheses open in the block, counting its own
+    depth: usize,
+    /// The block contains comments
+    has_comment: bool,
+    /// Keys on the `LAYOUT(` line itself, which aren't rewritten
+    keys_on_layout_line: bool,
+}
+
+/// Finds the LAYOUT blocks of the keymaps array, following their parentheses
+/// like the parser does, so a `)` after the last key closes a block too.
+/// Returns the blocks that can be formatted and the layer indices of those
+/// that contain comments.
+///
+/// # Arguments
+///
+/// * `lines` - The source lines
+/// * `code_lines` - The same lines with block comments blanked out (see
+///   [`blank_block_comments`])
+fn find_blocks(lines: &[&str], code_lines: &[&str]) -> (Vec<Block>, Vec<usize>) {
+    let layout_regex = Regex::new(r"LAYOUT(?:_\w+)?\s*\(").unwrap();
+    let keymaps_regex = keymaps_start_regex();
+    let mut blocks = Vec::new();
+    let mut skipped = Vec::new();
+    let mut layer = 0;
+    let mut in_keymaps = false;
+    let mut current: Option<OpenBlock> = None;
+
+    for (idx, line) in lines.iter().enumerate() {
+        let trimmed = line.trim();
+        if !in_keymaps && keymaps_regex.is_match(trimmed) {
+            in_keymaps = true;
+        }
+        if !in_keymaps {
+            continue;
+        }
+
+        let code_line = code_lines[idx];
+        let (rest, code, mut open) = match current.take() {
+            Some(open) => (*line, code_line, open),
+            None if trimmed.starts_with("};") => break,
+            None => match layout_regex.find(code_line) {
+                Some(found) => (
+                    &line[found.end()..],
+                    &code_line[found.end()..],
+                    OpenBlock {
+                        start: idx + 1,
+                        depth: 1,
+                        has_comment: false,
+                        keys_on_layout_line: false,
+                    },
+                ),
+                None => continue,
+            },
+        };
+
+        open.has_comment |= rest.contains("//") || rest != code;
+        let code = code.find("//").map_or(code, |comment| &code[..comment]);
+        let (close, _) = follow_parens(code, &mut open.depth);
+        let keys = code[..close.unwrap_or(code.len())].trim();
+        if idx + 1 == open.start {
+            open.keys_on_layout_line = !keys.is_empty();
+        }
+        let Some(close) = close else {
+            current = Some(open);
+            continue;
+        };
+
+        // The block's last key line, when its `)` follows the keys
+        let closes_key_line = idx >= open.start && !keys.is_empty();
+        let end = if closes_key_line { idx + 1 } else { idx };
+        let key_lines: Vec<&str> = lines[open.start.min(end)..end]
+            .iter()
+            .copied()
+            .filter(|line| !line.trim().is_empty())
+            .collect();
+
+        if open.has_comment {
+            skipped.push(layer);
+        } else if !open.keys_on_layout_line
+            && let Some(first) = key_lines.first()
+        {
+            let indent_len = first.len() - first.trim_start().len();
+            let last = key_lines.len() - 1;
+            blocks.push(Block {
+                start: open.start,
+                end,
+                indent: first[..indent_len].to_string(),
+                tail: closes_key_line.then(|| line[close..].trim_end().to_string()),
+                lines: key_lines
+                    .iter()
+                    .enumerate()
+                    .map(|(i, line)| {
+                        let line = if closes_key_line && i == last {
+                            &line[..close]
+                        } else {
+                            line
+                        };
+                        parse_keys_with_parens(line.trim())
+                    })
+                    .collect(),
+            });
+        }
+        layer += 1;
+    }
+
+    (blocks, skipped)
+}
+
+/// Assigns every key a (row, column) cell.
+///
+/// Keys follow the geometry's rows and are placed in the column of their
+/// horizontal position, so keys stacked on the board line up in the source.
+/// When the key count doesn't match the geometry, or its LAYOUT arguments
+/// don't run row by row across the board, the block's own line breaks are
+/// kept and keys are aligned by their position within the line.
+fn assign_cells(
+    keys: usize,
+    source_lines: &[Vec<String>],
+    geometry: &Geometry,
+) -> Vec<(usize, usize)> {
+    let as_written = || {
+        source_lines
+            .iter()
+            .enumerate()
+            .flat_map(|(row, line)| (0..line.len()).map(move |column| (row, column)))
+            .collect()
+    };
+    if keys != geometry.key_count() {
+        return as_written();
+    }
+
+    // Columns are the distinct horizontal positions, counted in quarter units
+    let slot = |x: f32| (x * 4.0).round() as i64;
+    let mut columns: Vec<i64> = geometry.positions().map(|(_, p)| slot(p.x)).collect();
+    columns.sort_unstable();
+    columns.dedup();
+
+    let cells: Vec<(usize, usize)> = geometry
+        .positions()
+        .map(|(row, p)| (row, columns.binary_search(&slot(p.x)).unwrap_or(0)))
+        .collect();
+    // The cells are written line by line, which must keep the argument order
+    if cells.windows(2).all(|pair| pair[0] < pair[1]) {
+        cells
+    } else {
+        as_written()
+    }
+}
+
+/// Renders a block's keys as aligned lines.
+fn format_block(block: &Block, geometry: &Geometry) -> Vec<String> {
+    let keys: Vec<&String> = block.lines.iter().flatten().collect();
+    let cells = assign_cells(keys.len(), &block.lines, geometry);
+    let rows = cells.iter().map(|&(row, _)| row + 1).max().unwrap_or(0);
+    let columns = cells
+        .iter()
+        .map(|&(_, column)| column + 1)
+        .max()
+        .unwrap_or(0);
+
+    // Column where the right half starts, if the geometry places keys there
+    let right_start = (keys.len() == geometry.key_count())
+        .then(|| {
+            geometry
+                .positions()
+                .zip(&cells)
+                .filter(|((_, p), _)| p.half == Half::Right)
+                .map(|(_, &(_, column))| column)
+                .min()
+        })
+        .flatten();
+
+    // Each cell holds the key and its trailing comma
+    let mut grid = vec![vec![None; columns]; rows];
+    for (idx, (key, &(row, column))) in keys.iter().zip(&cells).enumerate() {
+        let separator = if idx + 1 < keys.len() { "," } else { "" };
+        grid[row][column] = Some(format!("{}{}", key, separator));
+    }
+
+    let mut widths = vec![0; columns];
+    for row in &grid {
+        for (column, cell) in row.iter().enumerate() {
+            if let Some(cell) = cell {
+                widths[column] = widths[column].max(cell.chars().count());
+            }
+        }
+    }
+
+    grid.iter()
+        .filter(|row| row.iter().any(Option::is_some))
+        .map(|row| {
+            let mut line = block.indent.clone();
+            for (column, cell) in row.iter().enumerate() {
+                if column > 0 {
+                    line.push(' ');
+                }
+                if Some(column) == right_start && column > 0 {
+                    line.push_str(&" ".repeat(HALF_SEPARATOR));
+                }
+                let text = cell.as_deref().unwrap_or("");
+                line.push_str(text);
+                line.push_str(&" ".repeat(widths[column] - text.chars().count()));
+            }
+            line.trim_end().to_string()
+        })
+        .collect()
+}
+
+/// Aligns the keycodes of every LAYOUT block into columns.
+///
+/// Blocks containing comments are left as they are so no comment is lost.
+/// The formatted source is parsed again and must give the same layers.
+///
+/// # Arguments
+///
+/// * `content` - The keymap.c source
+/// * `geometry` - Board geometry whose rows and key positions drive the layout
+///
+/// # Returns
+///
+/// The formatted source along with which blocks were changed or skipped, or
+/// an error if the source doesn't parse or formatting would change its layers
+pub fn format_layouts(content: &str, geometry: &Geometry) -> Result<Formatted> {
+    let newline = if content.contains("\r\n") {
+        "\r\n"
+    } else {
+        "\n"
+    };
+    let lines: Vec<&str> = content.lines().collect();
+    let code_content = blank_block_comments(content);
+    let code_lines: Vec<&str> = code_content.lines().collect();
+    let (blocks, skipped_blocks) = find_blocks(&lines, &code_lines);
+
+    let mut output: Vec<String> = Vec::with_capacity(lines.len());
+    let mut next = 0;
+    for block in &blocks {
+        output.extend(lines[next..block.start].iter().map(|line| line.to_string()));
+        let mut formatted = format_block(block, geometry);
+        if let (Some(last), Some(tail)) = (formatted.last_mut(), &block.tail) {
+            last.push_str(tail);
+        }
+        output.extend(formatted);
+        next = block.end;
+    }
+    output.extend(lines[next..].iter().map(|line| line.to_string()));
+
+    let mut formatted = output.join(newline);
+    if content.ends_with('\n') {
+        formatted.push_str(newline);
+    }
+
+    if formatted != content {
+        let before = parse_layers(content)?;
+        let after = parse_layers(&formatted)
+            .context("Formatting produced a keymap that doesn't parse; nothing was changed")?;
+        let losses = roundtrip_losses(&before, &after);
+        if !losses.is_empty() {
+            bail!(
+                "Formatting would change the keymap ({}); nothing was changed",
+                losses.join("; ")
+            );
+        }
+    }
+
+    Ok(Formatted {
+        content: formatted,
+        formatted_blocks: blocks.len(),
+        skipped_blocks,
+    })
+}
@@ -0,0 +1,735 @@
+//! Keyboard geometries describing where each LAYOUT argument sits on the board.
+use crate::Layer;
+use anyhow::{Result, bail};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+/// Which half of a split keyboard a key belongs to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum Half {
+    Left,
+    Right,
+}
+
+/// Position and size of a single key in key units.
+///
+/// One unit is a key plus the spacing after it, so `x` and `y` scale with the
+/// rendered key size. Right-half keys are additionally shifted by the split gap.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct KeyPosition {
+    pub x: f32,
+    pub y: f32,
+    pub half: Half,
+    /// Width in key units (e.g. 1.5 or 2 for wide modifiers)
+    #[serde(default = "one", skip_serializing_if = "is_one")]
+    pub w: f32,
+    /// Height in key units
+    #[serde(default = "one", skip_serializing_if = "is_one")]
+    pub h: f32,
+    /// Second rectangle joined with the first to form a non-rectangular key
+    /// such as an ISO Enter or a stepped cap
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub secondary: Option<KeyArea>,
+    /// Clockwise rotation in degrees about the key's center, e.g. for angled
+    /// thumb keys
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub r: f32,
+}
+
+/// A rectangle relative to its key's position, in key units (like KLE's
+/// `x2`/`y2`/`w2`/`h2`).
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct KeyArea {
+    #[serde(default)]
+    pub x: f32,
+    #[serde(default)]
+    pub y: f32,
+    pub w: f32,
+    pub h: f32,
+}
+
+fn one() -> f32 {
+    1.0
+}
+
+fn is_one(value: &f32) -> bool {
+    *value == 1.0
+}
+
+fn is_zero(value: &f32) -> bool {
+    *value == 0.0
+}
+
+impl KeyPosition {
+    /// A regular 1u key.
+    pub fn new(x: f32, y: f32, half: Half) -> Self {
+        KeyPosition {
+            x,
+            y,
+            half,
+            w: 1.0,
+            h: 1.0,
+            secondary: None,
+            r: 0.0,
+        }
+    }
+
+    /// Right edge of the key, including its secondary rectangle.
+    pub fn right(&self) -> f32 {
+        let secondary = self
+            .secondary
+            .as_ref()
+            .map_or(f32::MIN, |area| self.x + area.x + area.w);
+        (self.x + self.w).max(secondary)
+    }
+
+    /// Bottom edge of the key, including its secondary rectangle.
+    pub fn bottom(&self) -> f32 {
+        let secondary = self
+            .secondary
+            .as_ref()
+            .map_or(f32::MIN, |area| self.y + area.y + area.h);
+        (self.y + self.h).max(secondary)
+    }
+}
+
+/// What a non-key element of a board is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum ControlKind {
+    Trackball,
+    /// A rotary encoder, labeled with its `encoder_map` bindings
+    Encoder,
+}
+
+/// A trackball or rotary encoder, drawn as a circle among the keys.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct Control {
+    pub kind: ControlKind,
+    /// Left edge in key units
+    pub x: f32,
+    /// Top edge in key units
+    pub y: f32,
+    pub half: Half,
+    /// Diameter in key units
+    #[serde(default = "one", skip_serializing_if = "is_one")]
+    pub size: f32,
+}
+
+impl Control {
+    /// A control at `x`, `y` with a diameter of one key.
+    pub fn new(kind: ControlKind, x: f32, y: f32, half: Half) -> Self {
+        Control {
+            kind,
+            x,
+            y,
+            half,
+            size: 1.0,
+        }
+    }
+}
+
+/// How a LAYOUT macro's arguments run over a board's positions.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "kebab-case")]
+pub enum KeyOrder {
+    /// Row by row, in the order the rows and their keys are listed
+    #[default]
+    RowMajor,
+    /// Column by column from the left, each column from the top; the left
+    /// half's columns come before the right half's
+    ColumnMajor,
+    /// The left half's keys row by row, then the right half's
+    PerHalf,
+    /// For each argument, the index of its position when the rows are read
+    /// in the order listed (e.g. `[0, 3, 1, 4, 2, 5]`)
+    Custom(Vec<usize>),
+}
+
+impl KeyOrder {
+    fn is_row_major(&self) -> bool {
+        *self == KeyOrder::RowMajor
+    }
+}
+
+/// Physical arrangement of a keyboard's keys.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct Geometry {
+    /// Short identifier used with `--board` (e.g. `keyball44`)
+    #[schemars(length(min = 1))]
+    pub name: String,
+    /// Human-readable description
+    #[serde(default)]
+    pub description: String,
+    /// LAYOUT macro names this keyboard's keymaps use
+    #[serde(default)]
+    pub layout_macros: Vec<String>,
+    /// Key positions grouped by physical row
+    pub rows: Vec<Vec<KeyPosition>>,
+    /// Order in which the LAYOUT macro's arguments visit the positions
+    #[serde(default, skip_serializing_if = "KeyOrder::is_row_major")]
+    pub order: KeyOrder,
+    /// Trackballs and encoders; encoders take the `encoder_map` bindings in
+    /// the order they are listed
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub controls: Vec<Control>,
+}
+
+/// Builds a row at height `y` from explicit left and right half x positions.
+fn split_row(y: f32, left: &[f32], right: &[f32]) -> Vec<KeyPosition> {
+    let left = left.iter().map(|&x| KeyPosition::new(x, y, Half::Left));
+    let right = right.iter().map(|&x| KeyPosition::new(x, y, Half::Right));
+    left.chain(right).collect()
+}
+
+/// `count` consecutive x positions starting at `start`.
+fn columns(start: f32, count: usize) -> Vec<f32> {
+    (0..count).map(|i| start + i as f32).collect()
+}
+
+impl Default for Geometry {
+    fn default() -> Self {
+        Geometry::keyball44()
+    }
+}
+
+impl Geometry {
+    /// The Keyball44: 3x6 per half plus a thumb row, with the trackball
+    /// occupying the gap in the right half's bottom row.
+    pub fn keyball44() -> Self {
+        // The left bottom row is staggered right by 2 units; the right half
+        // starts 8 units in to leave room for it
+        let main = |y| split_row(y, &columns(0.0, 6), &columns(8.0, 6));
+        Geometry {
+            name: "keyball44".to_string(),
+            description: "Keyball44 (3x6 + thumbs, right trackball)".to_string(),
+            layout_macros: vec![
+                "LAYOUT_universal".to_string(),
+                "LAYOUT_right_ball".to_string(),
+                "LAYOUT_left_ball".to_string(),
+                "LAYOUT_no_ball".to_string(),
+            ],
+            rows: vec![
+                main(0.0),
+                main(1.0),
+                main(2.0),
+                split_row(3.0, &columns(2.0, 5), &[7.0, 8.0, 9.0, 11.0, 12.0]),
+            ],
+            order: KeyOrder::RowMajor,
+            controls: vec![Control::new(ControlKind::Trackball, 10.0, 3.0, Half::Right)],
+        }
+    }
+
+    /// The Keyball39: 3x5 per half, with a bottom row of six keys per side
+    /// reaching one column further in than the alpha rows.
+    pub fn keyball39() -> Self {
+        let main = |y| split_row(y, &columns(0.0, 5), &columns(7.0, 5));
+        Geometry {
+            name: "keyball39".to_string(),
+            description: "Keyball39 (3x5 + bottom row, right trackball)".to_string(),
+            layout_macros: vec![
+                "LAYOUT_universal".to_string(),
+                "LAYOUT_right_ball".to_string(),
+            ],
+            rows: vec![
+                main(0.0),
+                main(1.0),
+                main(2.0),
+                split_row(3.0, &columns(0.0, 6), &columns(6.0, 6)),
+            ],
+            order: KeyOrder::RowMajor,
+            controls: Vec::new(),
+        }
+    }
+
+    /// The Corne (crkbd) with six columns per half and three thumb keys.
+    pub fn corne() -> Self {
+        let main = |y| split_row(y, &columns(0.0, 6), &columns(7.0, 6));
+        Geometry {
+            name: "corne".to_string(),
+            description: "Corne / crkbd (3x6 + 3 thumbs)".to_string(),
+            layout_macros: vec!["LAYOUT_split_3x6_3".to_string()],
+            rows: vec![
+                main(0.0),
+                main(1.0),
+                main(2.0),
+                split_row(3.0, &columns(3.0, 3), &columns(7.0, 3)),
+            ],
+            order: KeyOrder::RowMajor,
+            controls: Vec::new(),
+        }
+    }
+
+    /// The five-column Corne variant and similar 36-key splits.
+    pub fn corne_3x5() -> Self {
+        let main = |y| split_row(y, &columns(0.0, 5), &columns(6.0, 5));
+        Geometry {
+            name: "corne-3x5".to_string(),
+            description: "Corne 5-column (3x5 + 3 thumbs)".to_string(),
+            layout_macros: vec!["LAYOUT_split_3x5_3".to_string()],
+            rows: vec![
+                main(0.0),
+                main(1.0),
+                main(2.0),
+                split_row(3.0, &columns(2.0, 3), &columns(6.0, 3)),
+            ],
+            order: KeyOrder::RowMajor,
+            controls: Vec::new(),
+        }
+    }
+
+    /// The Lily58: 4x6 per half, an inner key on the bottom alpha row, and
+    /// four thumb keys per side.
+    pub fn lily58() -> Self {
+        let main = |y| split_row(y, &columns(0.0, 6), &columns(9.0, 6));
+        Geometry {
+            name: "lily58".to_string(),
+            description: "Lily58 (4x6 + inner keys + 4 thumbs)".to_string(),
+            layout_macros: vec!["LAYOUT".to_string()],
+            rows: vec![
+                main(0.0),
+                main(1.0),
+                main(2.0),
+                split_row(3.0, &columns(0.0, 7), &columns(8.0, 7)),
+                split_row(4.0, &columns(3.0, 4), &columns(8.0, 4)),
+            ],
+            order: KeyOrder::RowMajor,
+            controls: Vec::new(),
+        }
+    }
+
+    /// A plain ortholinear grid with one row per entry of `row_lengths`, used
+    /// when a keymap matches no known board.
+    ///
+    /// # Arguments
+    ///
+    /// * `row_lengths` - Number of keys on each row
+    ///
+    /// # Returns
+    ///
+    /// A geometry with every row starting at the left edge, one unit apart
+    pub fn ortho(row_lengths: &[usize]) -> Self {
+        let key_count: usize = row_lengths.iter().sum();
+        Geometry {
+            name: "ortho".to_string(),
+            description: format!("Ortholinear grid ({} keys)", key_count),
+            layout_macros: Vec::new(),
+            rows: row_lengths
+                .iter()
+                .enumerate()
+                .map(|(y, &len)| {
+                    columns(0.0, len)
+                        .into_iter()
+                        .map(|x| KeyPosition::new(x, y as f32, Half::Left))
+                        .collect()
+                })
+                .collect(),
+            order: KeyOrder::RowMajor,
+            controls: Vec::new(),
+        }
+    }
+
+    /// All geometries shipped with the tool.
+    pub fn builtins() -> Vec<Geometry> {
+        vec![
+            Geometry::keyball44(),
+            Geometry::corne(),
+            Geometry::corne_3x5(),
+            Geometry::lily58(),
+            Geometry::keyball39(),
+        ]
+    }
+
+    /// Looks up a built-in geometry by name (case-insensitive).
+    pub fn by_name(name: &str) -> Option<Geometry> {
+        Geometry::builtins()
+            .into_iter()
+            .find(|geometry| geometry.name.eq_ignore_ascii_case(name))
+    }
+
+    /// Number of keys on each physical row.
+    pub fn row_lengths(&self) -> Vec<usize> {
+        self.rows.iter().map(Vec::len).collect()
+    }
+
+    /// Total number of keys, i.e. the LAYOUT macro's argument count.
+    pub fn key_count(&self) -> usize {
+        self.rows.iter().map(Vec::len).sum()
+    }
+
+    /// The first row of `keys` whose length differs from the board's row.
+    ///
+    /// # Arguments
+    ///
+    /// * `keys` - A layer's keys, row by row
+    ///
+    /// # Returns
+    ///
+    /// The row index, or `None` if every row fits or the layer has a
+    /// different number of rows than the board
+    pub fn uneven_row(&self, keys: &[Vec<String>]) -> Option<usize> {
+        if keys.len() != self.rows.len() {
+            return None;
+        }
+        keys.iter()
+            .zip(&self.rows)
+            .position(|(keys, row)| keys.len() != row.len())
+    }
+
+    /// Pads or trims a layer's keys to the board's key count. Rows are fitted
+    /// one by one when the layer has as many rows as the board, so a short
+    /// row doesn't shift the keys after it; otherwise the last keys are
+    /// padded or dropped.
+    ///
+    /// # Arguments
+    ///
+    /// * `keys` - A layer's keys, row by row
+    /// * `empty` - The keycode to pad with
+    pub fn fit_keys(&self, keys: &mut Vec<Vec<String>>, empty: &str) {
+        if keys.len() == self.rows.len() {
+            for (keys, row) in keys.iter_mut().zip(&self.rows) {
+                keys.resize(row.len(), empty.to_string());
+            }
+            return;
+        }
+
+        let mut flat: Vec<String> = keys.drain(..).flatten().collect();
+        flat.resize(self.key_count(), empty.to_string());
+        let mut flat = flat.into_iter();
+        *keys = self
+            .rows
+            .iter()
+            .map(|row| flat.by_ref().take(row.len()).collect())
+            .collect();
+    }
+
+    /// Height of the board in rows, including its controls.
+    pub fn height(&self) -> f32 {
+        let controls = self.controls.iter().map(|control| control.y + control.size);
+        self.positions()
+            .map(|(_, position)| position.bottom())
+            .chain(controls)
+            .fold(0.0, f32::max)
+    }
+
+    /// Number of encoders among the controls.
+    pub fn encoder_count(&self) -> usize {
+        self.controls
+            .iter()
+            .filter(|control| control.kind == ControlKind::Encoder)
+            .count()
+    }
+
+    /// Checks that the geometry has keys at finite, distinct positions.
+    pub fn validate(&self) -> Result<()> {
+        if self.name.trim().is_empty() {
+            bail!("name: must not be empty");
+        }
+        if self.key_count() == 0 {
+            bail!("rows: the geometry has no keys");
+        }
+
+        let mut seen: Vec<(usize, usize, &KeyPosition)> = Vec::new();
+        for (row, positions) in self.rows.iter().enumerate() {
+            for (col, position) in positions.iter().enumerate() {
+                if !position.x.is_finite() || !position.y.is_finite() {
+                    bail!("rows[{}][{}]: x and y must be finite numbers", row, col);
+                }
+                if !position.r.is_finite() {
+                    bail!("rows[{}][{}]: r must be a finite number", row, col);
+                }
+                let sizes = [
+                    Some((position.w, position.h)),
+                    position.secondary.as_ref().map(|a| (a.w, a.h)),
+                ];
+                if sizes
+                    .into_iter()
+                    .flatten()
+                    .any(|(w, h)| !(w > 0.0 && h > 0.0 && w.is_finite() && h.is_finite()))
+                {
+                    bail!("rows[{}][{}]: w and h must be positive numbers", row, col);
+                }
+                if let Some((other_row, other_col, _)) = seen.iter().find(|(_, _, other)| {
+                    other.half == position.half && other.x == position.x && other.y == position.y
+                }) {
+                    bail!(
+                        "rows[{}][{}]: overlaps the key at rows[{}][{}]",
+                        row,
+                        col,
+                        other_row,
+                        other_col
+                    );
+                }
+                seen.push((row, col, position));
+            }
+        }
+
+        for (index, control) in self.controls.iter().enumerate() {
+            if !control.x.is_finite() || !control.y.is_finite() {
+                bail!("controls[{}]: x and y must be finite numbers", index);
+            }
+            if !(control.size > 0.0 && control.size.is_finite()) {
+                bail!("controls[{}]: size must be a positive number", index);
+            }
+        }
+
+        if let KeyOrder::Custom(order) = &self.order {
+            if order.len() != self.key_count() {
+                bail!(
+                    "order: lists {} positions, but the geometry has {} keys",
+                    order.len(),
+                    self.key_count()
+                );
+            }
+            let mut used = vec![false; order.len()];
+            for (argument, &position) in order.iter().enumerate() {
+                match used.get_mut(position) {
+                    None => bail!("order[{}]: there is no position {}", argument, position),
+                    Some(true) => {
+                        bail!("order[{}]: position {} is listed twice", argument, position)
+                    }
+                    Some(used) => *used = true,
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// For each LAYOUT argument, the index of its position when the rows are
+    /// read in the order listed.
+    ///
+    /// A custom order that doesn't cover every position (see
+    /// [`Geometry::validate`]) falls back to row order.
+    pub fn argument_order(&self) -> Vec<usize> {
+        let listed: Vec<(usize, &KeyPosition)> = self.listed_positions().collect();
+        let mut order: Vec<usize> = (0..listed.len()).collect();
+        match &self.order {
+            KeyOrder::RowMajor => {}
+            KeyOrder::ColumnMajor => order.sort_by(|&a, &b| {
+                let (a, b) = (listed[a].1, listed[b].1);
+                (a.half as u8, a.x, a.y)
+                    .partial_cmp(&(b.half as u8, b.x, b.y))
+                    .unwrap_or(std::cmp::Ordering::Equal)
+            }),
+            KeyOrder::PerHalf => order.sort_by_key(|&i| listed[i].1.half as u8),
+            KeyOrder::Custom(custom) => {
+                let mut sorted = custom.clone();
+                sorted.sort_unstable();
+                if sorted == order {
+                    order = custom.clone();
+                }
+            }
+        }
+        order
+    }
+
+    /// Reorders values given per position, in the order the rows are listed,
+    /// into LAYOUT argument order.
+    pub(crate) fn in_argument_order<T: Clone>(&self, listed: &[T]) -> Vec<T> {
+        self.argument_order()
+            .into_iter()
+            .filter_map(|i| listed.get(i).cloned())
+            .collect()
+    }
+
+    /// Locates every key as `(half, row, column)` in LAYOUT argument order.
+    ///
+    /// Rows and columns are 1-based and columns count from the left within the
+    /// half. The half is `None` on boards without a right half, and the row is
+    /// `None` on the thumb row of a split board (its last row).
+    pub(crate) fn key_locations(&self) -> Vec<(Option<Half>, Option<usize>, usize)> {
+        let split = [Half::Left, Half::Right]
+            .iter()
+            .all(|half| self.positions().any(|(_, p)| p.half == *half));
+        let thumb_row = (split && self.rows.len() > 1).then(|| self.rows.len() - 1);
+
+        let mut locations = Vec::with_capacity(self.key_count());
+        for (row, positions) in self.rows.iter().enumerate() {
+            for position in positions {
+                let column = 1 + positions
+                    .iter()
+                    .filter(|other| other.half == position.half && other.x < position.x)
+                    .count();
+                locations.push((
+                    split.then_some(position.half),
+                    (Some(row) != thumb_row).then_some(row + 1),
+                    column,
+                ));
+            }
+        }
+        self.in_argument_order(&locations)
+    }
+
+    /// Canonical names of the key positions, in LAYOUT argument order.
+    ///
+    /// Keys of a split board are named by half, 1-based row, and 1-based
+    /// column counted from the left within the half (e.g. `L-R3C4`), except on
+    /// the last row, whose keys are thumb keys numbered from the left within
+    /// the half (e.g. `thumb-R2`). Boards without a right half drop the half
+    /// (e.g. `R3C4`).
+    pub fn position_names(&self) -> Vec<String> {
+        self.key_locations()
+            .into_iter()
+            .map(|(half, row, column)| {
+                let half = match half {
+                    Some(Half::Left) => "L",
+                    Some(Half::Right) => "R",
+                    None => "",
+                };
+                match row {
+                    Some(row) if half.is_empty() => format!("R{}C{}", row, column),
+                    Some(row) => format!("{}-R{}C{}", half, row, column),
+                    None => format!("thumb-{}{}", half, column),
+                }
+            })
+            .collect()
+    }
+
+    /// Plain-language descriptions of the key positions for readers who
+    /// don't know the canonical names, in LAYOUT argument order.
+    ///
+    /// Keys are numbered as in [`Geometry::position_names`], e.g.
+    /// `left thumb 2` for `thumb-L2` or `right row 1, key 3` for `R-R1C3`.
+    pub fn position_descriptions(&self) -> Vec<String> {
+        self.key_locations()
+            .into_iter()
+            .map(|(half, row, column)| {
+                let half = match half {
+                    Some(Half::Left) => "left ",
+                    Some(Half::Right) => "right ",
+                    None => "",
+                };
+                match row {
+                    Some(row) => format!("{}row {}, key {}", half, row, column),
+                    None => format!("{}thumb {}", half, column),
+                }
+            })
+            .collect()
+    }
+
+    /// Pairs each left-half key with its mirror image on the right half, as
+    /// `(left, right)` key indices in LAYOUT argument order.
+    ///
+    /// Within each row, the left half's keys counted from the outer edge
+    /// pair with the right half's keys counted from its outer edge; keys
+    /// left over on the longer side have no mirror image.
+    pub fn mirror_pairs(&self) -> Vec<(usize, usize)> {
+        let mut pairs = Vec::new();
+        let mut offset = 0;
+        for row in &self.rows {
+            let half = |half: Half| {
+                let mut keys: Vec<(usize, f32)> = row
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, position)| position.half == half)
+                    .map(|(index, position)| (offset + index, position.x))
+                    .collect();
+                keys.sort_by(|a, b| a.1.total_cmp(&b.1));
+                keys
+            };
+            let (left, mut right) = (half(Half::Left), half(Half::Right));
+            right.reverse();
+            pairs.extend(left.iter().zip(&right).map(|(l, r)| (l.0, r.0)));
+            offset += row.len();
+        }
+
+        // Translate listed positions into arguments
+        let mut argument = vec![0; self.key_count()];
+        for (i, position) in self.argument_order().into_iter().enumerate() {
+            argument[position] = i;
+        }
+        pairs
+            .into_iter()
+            .map(|(left, right)| (argument[left], argument[right]))
+            .collect()
+    }
+
+    /// Iterates over `(row, position)` for every key in LAYOUT argument order.
+    pub fn positions(&self) -> impl Iterator<Item = (usize, &KeyPosition)> {
+        let listed: Vec<(usize, &KeyPosition)> = self.listed_positions().collect();
+        self.in_argument_order(&listed).into_iter()
+    }
+
+    /// Iterates over `(row, position)` for every key in the order the rows
+    /// are listed.
+    fn listed_positions(&self) -> impl Iterator<Item = (usize, &KeyPosition)> {
+        self.rows
+            .iter()
+            .enumerate()
+            .flat_map(|(row, positions)| positions.iter().map(move |position| (row, position)))
+    }
+}
+
+/// A geometry inferred from the parsed keymap and why it was chosen.
+#[derive(Debug, Clone)]
+pub struct Detection {
+    pub geometry: Geometry,
+    pub reason: String,
+}
+
+/// The layer whose key count most layers share, the first such on a tie, so a
+/// single layer with a stray or missing key doesn't decide the board.
+fn typical_layer(layers: &[Layer]) -> Option<&Layer> {
+    let count = |layer: &Layer| layer.keys.iter().map(Vec::len).sum::<usize>();
+    let sharing = |layer: &Layer| layers.iter().filter(|l| count(l) == count(layer)).count();
+    layers.iter().rev().max_by_key(|layer| sharing(layer))
+}
+
+/// Infers the board from the LAYOUT macro name and key count of the layers.
+///
+/// A built-in geometry qualifies when its key count equals that of most
+/// layers; among several, one that lists their LAYOUT macro wins.
+///
+/// # Arguments
+///
+/// * `layers` - The parsed keymap layers
+///
+/// # Returns
+///
+/// The detected geometry and a description of the evidence, or `None` if no
+/// built-in geometry has a matching key count
+pub fn detect(layers: &[Layer]) -> Option<Detection> {
+    let layer = typical_layer(layers)?;
+    let key_count = layer.keys.iter().map(Vec::len).sum::<usize>();
+    let layout_macro = layer.layout_macro.as_deref().unwrap_or("LAYOUT");
+
+    let candidates: Vec<Geometry> = Geometry::builtins()
+        .into_iter()
+        .filter(|geometry| geometry.key_count() == key_count)
+        .collect();
+
+    let by_macro = candidates
+        .iter()
+        .position(|geometry| geometry.layout_macros.iter().any(|m| m == layout_macro));
+
+    let (geometry, evidence) = match by_macro {
+        Some(idx) => (
+            candidates[idx].clone(),
+            format!("{} with {} keys", layout_macro, key_count),
+        ),
+        None => (
+            candidates.first()?.clone(),
+            format!("{} keys per layer", key_count),
+        ),
+    };
+
+    Some(Detection {
+        reason: format!("{} matches {}", evidence, geometry.description),
+        geometry,
+    })
+}
+
+/// The geometry used when [`detect`] finds no board: an ortholinear grid with
+/// the rows as written in the source, taken from the first layer with the key
+/// count most layers share.
+pub fn fallback(layers: &[Layer]) -> Geometry {
+    let row_lengths: Vec<usize> =
+        typical_layer(layers).map_or(Vec::new(), |l| l.keys.iter().map(Vec::len).collect());
+    Geometry::ortho(&row_lengths)
+}
@@ -0,0 +1,235 @@
+//! Board geometries read from QMK `info.json` files in a QMK tree or at a
+//! URL, cached in the user's cache directory so watch and batch runs don't
+//! download and parse the same keyboard again.
+use crate::geometry::Geometry;
+use crate::info_json::{is_info_json, parse_info_json};
+use anyhow::{Context, Result, bail};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// Files QMK describes a keyboard in, most specific first.
+const INFO_FILES: [&str; 2] = ["keyboard.json", "info.json"];
+
+/// Returns `true` if a `--geometry` source is an `http(s)://` URL rather than
+/// a path.
+pub fn is_url(source: &Path) -> bool {
+    source
+        .to_str()
+        .is_some_and(|source| source.starts_with("https://") || source.starts_with("http://"))
+}
+
+/// Finds the file describing the layouts of a keyboard directory in a QMK
+/// tree, e.g. `keyboards/crkbd/rev1`.
+///
+/// QMK shares a keyboard's layouts between its revisions, so the directory's
+/// parents are searched too, up to the `keyboards` directory.
+///
+/// # Arguments
+///
+/// * `dir` - The keyboard directory
+///
+/// # Returns
+///
+/// The first `keyboard.json` or `info.json` with a `layouts` section, or an
+/// error if there is none
+pub fn info_json_in(dir: &Path) -> Result<PathBuf> {
+    for dir in dir.ancestors() {
+        if dir.file_name().is_some_and(|name| name == "keyboards") {
+            break;
+        }
+        for file in INFO_FILES.iter().map(|name| dir.join(name)) {
+            if fs::read_to_string(&file).is_ok_and(|content| is_info_json(&content)) {
+                return Ok(file);
+            }
+        }
+    }
+    bail!("No keyboard.json or info.json with layouts in {:?}", dir)
+}
+
+/// The keyboard a QMK file or URL describes: its path below `keyboards/`
+/// (e.g. `crkbd/rev1`), or else the name of its directory.
+fn keyboard_name(source: &str) -> String {
+    let parent = source
+        .trim_end_matches('/')
+        .rsplit_once('/')
+        .map_or("", |(parent, _)| parent);
+    match parent.rsplit_once("/keyboards/") {
+        Some((_, keyboard)) => keyboard.to_string(),
+        None => parent.rsplit('/').next().unwrap_or_default().to_string(),
+    }
+}
+
+/// 64-bit FNV-1a hash, stable across runs and Rust versions so cache entries
+/// stay valid.
+fn fnv1a64(parts: &[&str]) -> u64 {
+    parts
+        .iter()
+        .flat_map(|part| part.bytes().chain([0]))
+        .fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+            (hash ^ u64::from(byte)).wrapping_mul(0x0000_0100_0000_01b3)
+        })
+}
+
+/// Downloads a file with `curl`.
+fn fetch(url: &str) -> Result<String> {
+    let output = Command::new("curl")
+        .args(["--fail", "--silent", "--show-error", "--location", url])
+        .output()
+        .context("Failed to run curl; is it installed and on PATH?")?;
+    if !output.status.success() {
+        bail!(
+            "Failed to download {}: {}",
+            url,
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    String::from_utf8(output.stdout).context(format!("{} is not UTF-8", url))
+}
+
+/// Geometries parsed from QMK `info.json` files, stored as JSON files named
+/// after the keyboard and revision, e.g. `crkbd_rev1@5f0c6e1d2a9b8c7f.json`.
+///
+/// The revision of a local file is a hash of its content, so edits are picked
+/// up without refreshing. A URL is trusted to name a fixed revision, such as a
+/// commit of the QMK repository; refresh the cache when it names a branch.
+#[derive(Debug, Clone)]
+pub struct GeometryCache {
+    /// Directory of the entries, or `None` to cache nothing
+    dir: Option<PathBuf>,
+    /// Re-read every source and overwrite its entry
+    refresh: bool,
+}
+
+impl GeometryCache {
+    /// The cache in the user's cache directory: `$XDG_CACHE_HOME` or
+    /// `~/.cache` (`%LOCALAPPDATA%` on Windows, `~/Library/Caches` on macOS).
+    ///
+    /// # Arguments
+    ///
+    /// * `refresh` - Re-read every source instead of using cached geometries
+    pub fn user(refresh: bool) -> Self {
+        let env = |name: &str| std::env::var_os(name).filter(|value| !value.is_empty());
+        let base = if cfg!(windows) {
+            env("LOCALAPPDATA").map(PathBuf::from)
+        } else if cfg!(target_os = "macos") {
+            env("HOME").map(|home| Path::new(&home).join("Library/Caches"))
+        } else {
+            env("XDG_CACHE_HOME")
+                .map(PathBuf::from)
+                .or_else(|| env("HOME").map(|home| Path::new(&home).join(".cache")))
+        };
+        GeometryCache {
+            dir: base.map(|base| base.join("keyball44-viz").join("geometry")),
+            refresh,
+        }
+    }
+
+    /// A cache keeping its entries in `dir`.
+    pub fn at(dir: PathBuf, refresh: bool) -> Self {
+        GeometryCache {
+            dir: Some(dir),
+            refresh,
+        }
+    }
+
+    /// Loads the geometry of a QMK `info.json` file, keyboard directory, or
+    /// URL.
+    ///
+    /// # Arguments
+    ///
+    /// * `source` - The `info.json` or `keyboard.json`, the keyboard's
+    ///   directory in a QMK tree, or an `http(s)://` URL of the file
+    /// * `layout_macro` - The keymap's LAYOUT macro, selecting the layout
+    ///
+    /// # Returns
+    ///
+    /// The cached geometry if there is one, else the parsed geometry, or an
+    /// error if the source can't be read or has no usable layout
+    pub fn load(&self, source: &Path, layout_macro: Option<&str>) -> Result<Geometry> {
+        if is_url(source) {
+            let url = source.to_string_lossy();
+            return self.get_or_parse(&keyboard_name(&url), &url, layout_macro, || fetch(&url));
+        }
+
+        let file = if source.is_dir() {
+            info_json_in(source)?
+        } else {
+            source.to_path_buf()
+        };
+        let content = fs::read_to_string(&file).context(format!("Failed to read {:?}", file))?;
+        self.load_content(&file, &content, layout_macro)
+    }
+
+    /// Loads the geometry of a QMK `info.json` file that was already read.
+    ///
+    /// # Arguments
+    ///
+    /// * `file` - The `info.json` or `keyboard.json`, naming the keyboard
+    /// * `content` - The file's content
+    /// * `layout_macro` - The keymap's LAYOUT macro, selecting the layout
+    ///
+    /// # Returns
+    ///
+    /// The cached geometry if there is one, else the parsed geometry, or an
+    /// error if the file has no usable layout
+    pub fn load_content(
+        &self,
+        file: &Path,
+        content: &str,
+        layout_macro: Option<&str>,
+    ) -> Result<Geometry> {
+        let keyboard = keyboard_name(&file.to_string_lossy().replace('\\', "/"));
+        self.get_or_parse(&keyboard, content, layout_macro, || Ok(content.to_string()))
+    }
+
+    /// Returns the entry keyed by `keyboard` and `revision`, parsing and
+    /// storing the content `read` returns when there is none.
+    fn get_or_parse(
+        &self,
+        keyboard: &str,
+        revision: &str,
+        layout_macro: Option<&str>,
+        read: impl FnOnce() -> Result<String>,
+    ) -> Result<Geometry> {
+        let key = fnv1a64(&[
+            env!("CARGO_PKG_VERSION"),
+            revision,
+            layout_macro.unwrap_or_default(),
+        ]);
+        let slug: String = keyboard
+            .chars()
+            .map(|c| {
+                if c.is_ascii_alphanumeric() || c == '-' {
+                    c
+                } else {
+                    '_'
+                }
+            })
+            .collect();
+        let entry = self
+            .dir
+            .as_ref()
+            .map(|dir| dir.join(format!("{}@{:016x}.json", slug, key)));
+
+        // A missing or unreadable entry is parsed again
+        if let Some(entry) = entry.as_ref().filter(|_| !self.refresh)
+            && let Some(geometry) = fs::read_to_string(entry)
+                .ok()
+                .and_then(|json| serde_json::from_str::<Geometry>(&json).ok())
+        {
+            return Ok(geometry);
+        }
+
+        let geometry = parse_info_json(&read()?, layout_macro)?;
+        // The cache only saves work; failing to store an entry is not an error
+        if let Some(entry) = entry
+            && let Some(dir) = entry.parent()
+            && fs::create_dir_all(dir).is_ok()
+            && let Ok(json) = serde_json::to_string(&geometry)
+        {
+            let _ = fs::write(entry, json);
+        }
+        Ok(geometry)
+    }
+}
@@ -0,0 +1,178 @@
+//! Reading earlier versions of files from git.
+use anyhow::{Context, Result, bail};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// Reads a file as it was at a git revision.
+///
+/// Shells out to `git show <rev>:<file>` from the file's directory, so the
+/// file may live in any repository and any subdirectory of it.
+///
+/// # Arguments
+///
+/// * `rev` - Any revision git understands, e.g. `HEAD~1` or a tag
+/// * `path` - Path of the file in the working tree
+///
+/// # Returns
+///
+/// The file's contents at `rev`, or an error if git is unavailable, the
+/// revision is unknown, or the file didn't exist at that revision
+pub fn show_file(rev: &str, path: &Path) -> Result<String> {
+    let file_name = path
+        .file_name()
+        .and_then(|name| name.to_str())
+        .ok_or(anyhow::anyhow!("Invalid file name: {:?}", path))?;
+    let dir = match path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
+    };
+
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(dir)
+        .arg("show")
+        .arg(format!("{}:./{}", rev, file_name))
+        .output()
+        .context("Failed to run git; is it installed and on PATH?")?;
+
+    if !output.status.success() {
+        bail!(
+            "git show {}:{} failed: {}",
+            rev,
+            path.display(),
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+
+    String::from_utf8(output.stdout).context(format!("{:?} at {} is not UTF-8", path, rev))
+}
+
+/// A commit that changed a file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Commit {
+    /// Full commit hash, usable as a revision
+    pub hash: String,
+    /// Abbreviated hash for display
+    pub short_hash: String,
+    /// Author date as `YYYY-MM-DD`
+    pub date: String,
+    /// First line of the commit message
+    pub subject: String,
+}
+
+/// Lists the commits that changed a file, following renames.
+///
+/// # Arguments
+///
+/// * `path` - Path of the file in the working tree
+/// * `limit` - Maximum number of commits, counting back from the newest
+///
+/// # Returns
+///
+/// The commits, oldest first, or an error if git is unavailable or the file
+/// isn't tracked
+pub fn file_history(path: &Path, limit: usize) -> Result<Vec<Commit>> {
+    let file_name = path
+        .file_name()
+        .and_then(|name| name.to_str())
+        .ok_or(anyhow::anyhow!("Invalid file name: {:?}", path))?;
+    let dir = match path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
+    };
+
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(dir)
+        .arg("log")
+        .arg("--follow")
+        .arg(format!("--max-count={}", limit))
+        .arg("--date=short")
+        .arg("--format=%H%x09%h%x09%ad%x09%s")
+        .arg("--")
+        .arg(file_name)
+        .output()
+        .context("Failed to run git; is it installed and on PATH?")?;
+
+    if !output.status.success() {
+        bail!(
+            "git log {} failed: {}",
+            path.display(),
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+
+    let log = String::from_utf8(output.stdout).context("git log output is not UTF-8")?;
+    let mut commits: Vec<Commit> = log
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.splitn(4, '\t');
+            Some(Commit {
+                hash: fields.next()?.to_string(),
+                short_hash: fields.next()?.to_string(),
+                date: fields.next()?.to_string(),
+                subject: fields.next().unwrap_or_default().to_string(),
+            })
+        })
+        .collect();
+    if commits.is_empty() {
+        bail!("{} has no commits in git history", path.display());
+    }
+    commits.reverse();
+
+    Ok(commits)
+}
+
+/// Commits files, adding them first.
+///
+/// # Arguments
+///
+/// * `dir` - A directory of the repository; `paths` are relative to it
+/// * `paths` - The files to add and commit
+/// * `message` - The commit message
+///
+/// # Returns
+///
+/// `true` once committed, `false` if none of the files changed, or an error
+/// if git is unavailable or refuses
+pub fn commit(dir: &Path, paths: &[PathBuf], message: &str) -> Result<bool> {
+    let run = |args: &[&std::ffi::OsStr]| -> Result<std::process::Output> {
+        let output = Command::new("git")
+            .arg("-C")
+            .arg(dir)
+            .args(args)
+            .output()
+            .context("Failed to run git; is it installed and on PATH?")?;
+        if !output.status.success() {
+            bail!(
+                "git {} failed: {}",
+                args[0].to_string_lossy(),
+                String::from_utf8_lossy(&output.stderr).trim()
+            );
+        }
+        Ok(output)
+    };
+
+    let mut add: Vec<&std::ffi::OsStr> = vec!["add".as_ref(), "--".as_ref()];
+    add.extend(paths.iter().map(|path| path.as_os_str()));
+    run(&add)?;
+    let mut staged: Vec<&std::ffi::OsStr> = vec![
+        "diff".as_ref(),
+        "--cached".as_ref(),
+        "--name-only".as_ref(),
+        "--".as_ref(),
+    ];
+    staged.extend(paths.iter().map(|path| path.as_os_str()));
+    if run(&staged)?.stdout.trim_ascii().is_empty() {
+        return Ok(false);
+    }
+    let mut commit: Vec<&std::ffi::OsStr> = vec![
+        "commit".as_ref(),
+        "-m".as_ref(),
+        message.as_ref(),
+        "--".as_ref(),
+    ];
+    commit.extend(paths.iter().map(|path| path.as_os_str()));
+    run(&commit)?;
+    Ok(true)
+}
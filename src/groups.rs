@@ -0,0 +1,106 @@
+//! Key groups: named clusters of positions, such as a numpad or a navigation
+//! cluster, outlined and labeled on chosen layers to call them out on
+//! cheatsheets.
+use crate::{Geometry, Layer};
+use anyhow::{Result, bail};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+/// Key groups read from a config file, e.g.
+/// `groups = [{ label = "Numpad", positions = ["R-R1C2", "R-R1C3"], layers = ["NUM"] }]`.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct KeyGroups {
+    #[serde(default)]
+    pub groups: Vec<KeyGroup>,
+}
+
+/// One group of keys.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct KeyGroup {
+    /// Label drawn above the outline
+    pub label: String,
+    /// Position names of the grouped keys (see [`Geometry::position_names`])
+    pub positions: Vec<String>,
+    /// Names or numbers of the layers the group is drawn on; leading
+    /// underscores and case are ignored. Every layer when left out
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub layers: Vec<String>,
+}
+
+impl KeyGroup {
+    /// Returns `true` if the group is drawn on `layer`.
+    pub fn shows_on(&self, layer: &Layer) -> bool {
+        let normalize = |name: &str| name.trim_start_matches('_').to_ascii_uppercase();
+        self.layers.is_empty()
+            || self.layers.iter().any(|wanted| {
+                let wanted = normalize(wanted);
+                wanted == layer.index.to_string()
+                    || layer
+                        .name
+                        .as_deref()
+                        .is_some_and(|name| normalize(name) == wanted)
+            })
+    }
+}
+
+impl KeyGroups {
+    /// Checks that every group has a label and at least one position.
+    pub fn validate(&self) -> Result<()> {
+        for (i, group) in self.groups.iter().enumerate() {
+            if group.label.trim().is_empty() {
+                bail!("groups[{}]: label must not be empty", i);
+            }
+            if group.positions.is_empty() {
+                bail!(
+                    "groups[{}] ({}): positions must not be empty",
+                    i,
+                    group.label
+                );
+            }
+        }
+        Ok(())
+    }
+
+    /// Checks that every grouped position exists on a board.
+    ///
+    /// # Returns
+    ///
+    /// `Ok(())`, or an error naming the first unknown position and its group
+    pub fn check_positions(&self, geometry: &Geometry) -> Result<()> {
+        let names = geometry.position_names();
+        for group in &self.groups {
+            if let Some(unknown) = group.positions.iter().find(|p| !names.contains(p)) {
+                bail!(
+                    "group {}: {} has no position {}; list them with `keyball44-viz positions`",
+                    group.label,
+                    geometry.name,
+                    unknown
+                );
+            }
+        }
+        Ok(())
+    }
+
+    /// The LAYOUT indices of each group drawn on `layer`, with its label.
+    pub(crate) fn on_layer<'a>(
+        &'a self,
+        layer: &Layer,
+        geometry: &Geometry,
+    ) -> Vec<(&'a str, Vec<usize>)> {
+        let names = geometry.position_names();
+        self.groups
+            .iter()
+            .filter(|group| group.shows_on(layer))
+            .map(|group| {
+                let indices = group
+                    .positions
+                    .iter()
+                    .filter_map(|p| names.iter().position(|name| name == p))
+                    .collect();
+                (group.label.as_str(), indices)
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,162 @@
+//! Key usage heatmaps: press counts per key, from a keylogger's CSV export or
+//! counted from a text corpus, drawn as a color wash over the base layer.
+use crate::geometry::Geometry;
+use crate::scale::ValueScale;
+use crate::{KeyDecorator, Layer, keycodes};
+use anyhow::{Result, bail};
+use svg::node::element::Rectangle;
+
+/// Keycodes typing each character other than letters and digits, by every
+/// name QMK accepts, with the shifted characters typed on the same key.
+const CHARACTER_KEYCODES: [(&str, &[&str]); 14] = [
+    (" ", &["KC_SPC", "KC_SPACE"]),
+    ("\n", &["KC_ENT", "KC_ENTER"]),
+    ("\t", &["KC_TAB"]),
+    ("-_", &["KC_MINS", "KC_MINUS"]),
+    ("=+", &["KC_EQL", "KC_EQUAL"]),
+    ("[{", &["KC_LBRC", "KC_LEFT_BRACKET"]),
+    ("]}", &["KC_RBRC", "KC_RIGHT_BRACKET"]),
+    ("\\|", &["KC_BSLS", "KC_BACKSLASH"]),
+    (";:", &["KC_SCLN", "KC_SEMICOLON"]),
+    ("'\"", &["KC_QUOT", "KC_QUOTE"]),
+    ("`~", &["KC_GRV", "KC_GRAVE"]),
+    (",<", &["KC_COMM", "KC_COMMA"]),
+    (".>", &["KC_DOT"]),
+    ("/?", &["KC_SLSH", "KC_SLASH"]),
+];
+
+/// Shifted digits, in the order of the digit keys `KC_1` through `KC_0`.
+const SHIFTED_DIGITS: &str = "!@#$%^&*()";
+
+/// The keycode of the key typing `c` on a US layout, e.g. `KC_A` for `a` and
+/// `A`, or `KC_1` for `!`.
+fn keycode_of(c: char) -> Option<String> {
+    if c.is_ascii_alphanumeric() {
+        return Some(format!("KC_{}", c.to_ascii_uppercase()));
+    }
+    if let Some(position) = SHIFTED_DIGITS.find(c) {
+        return Some(format!("KC_{}", (position + 1) % 10));
+    }
+    CHARACTER_KEYCODES
+        .iter()
+        .find(|(chars, _)| chars.contains(c))
+        .map(|(_, names)| names[0].to_string())
+}
+
+/// Returns `true` if `key` taps `keycode`, including through a mod-tap or
+/// layer-tap and under any name QMK accepts for it.
+fn taps(key: &str, keycode: &str) -> bool {
+    let tap = keycodes::key_action(key).tap;
+    tap == keycode
+        || CHARACTER_KEYCODES
+            .iter()
+            .any(|(_, names)| names.contains(&keycode) && names.contains(&tap.as_str()))
+}
+
+/// Counts how often each key of a layer is pressed to type a text.
+///
+/// # Arguments
+///
+/// * `text` - The corpus, typed on a US layout; shifted characters count for
+///   the key they are typed on
+/// * `layer` - The layer the text is typed on, usually the base layer
+///
+/// # Returns
+///
+/// One count per key in LAYOUT order, `None` for keys the text never presses
+pub fn count_corpus(text: &str, layer: &Layer) -> Vec<Option<f32>> {
+    let keys: Vec<&String> = layer.keys.iter().flatten().collect();
+    let mut counts = vec![None; keys.len()];
+    for c in text.chars() {
+        let Some(keycode) = keycode_of(c) else {
+            continue;
+        };
+        if let Some(index) = keys.iter().position(|key| taps(key, &keycode)) {
+            *counts[index].get_or_insert(0.0) += 1.0;
+        }
+    }
+    counts
+}
+
+/// Reads per-key press counts from a CSV of `key,count` lines, as exported
+/// by keyloggers. The key is a position name such as `L-R2C4` (see
+/// [`Geometry::position_names`]), a LAYOUT argument index, or a keycode of
+/// the layer. A header line and blank lines are skipped.
+///
+/// # Arguments
+///
+/// * `content` - The CSV contents
+/// * `layer` - The layer whose keycodes name keys, usually the base layer
+/// * `geometry` - The board whose position names are looked up
+///
+/// # Returns
+///
+/// One count per key in LAYOUT order, `None` for keys without a count, or an
+/// error naming the first line that doesn't parse
+pub fn parse_counts(content: &str, layer: &Layer, geometry: &Geometry) -> Result<Vec<Option<f32>>> {
+    let names = geometry.position_names();
+    let keys: Vec<&String> = layer.keys.iter().flatten().collect();
+    let mut counts = vec![None; keys.len().max(names.len())];
+
+    for (number, line) in content.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+        let Some((key, count)) = line.rsplit_once(',') else {
+            bail!("line {}: expected key,count, got \"{}\"", number + 1, line);
+        };
+        let key = key.trim().trim_matches('"');
+        let Ok(count) = count.trim().parse::<f32>() else {
+            if number == 0 {
+                continue;
+            }
+            bail!("line {}: \"{}\" is not a count", number + 1, count.trim());
+        };
+        if !count.is_finite() || count < 0.0 {
+            bail!("line {}: counts must not be negative", number + 1);
+        }
+
+        let index = key
+            .parse::<usize>()
+            .ok()
+            .or_else(|| names.iter().position(|name| name == key))
+            .or_else(|| keys.iter().position(|k| k.as_str() == key));
+        match index.and_then(|index| counts.get_mut(index)) {
+            Some(slot) => *slot.get_or_insert(0.0) += count,
+            None => bail!("line {}: no key \"{}\" on the board", number + 1, key),
+        }
+    }
+    Ok(counts)
+}
+
+/// A decorator washing each key of one layer with the color of its press
+/// count, blended so the legends stay readable and selectable.
+///
+/// # Arguments
+///
+/// * `layer` - Index of the layer the counts belong to; other layers are left
+///   undecorated
+/// * `counts` - One count per key in LAYOUT order, as returned by
+///   [`parse_counts`] or [`count_corpus`]
+/// * `scale` - The color scale, which should also be passed in
+///   [`crate::RenderOptions::scales`] so its legend is drawn
+pub fn heatmap_overlay(layer: usize, counts: Vec<Option<f32>>, scale: ValueScale) -> KeyDecorator {
+    KeyDecorator::new(move |key| {
+        if key.layer != layer {
+            return Vec::new();
+        }
+        let Some(count) = counts.get(key.index).copied().flatten() else {
+            return Vec::new();
+        };
+        let wash = Rectangle::new()
+            .set("class", "key-heat")
+            .set("x", key.bounds.x)
+            .set("y", key.bounds.y)
+            .set("width", key.bounds.width)
+            .set("height", key.bounds.height)
+            .set("rx", 4)
+            .set("fill", scale.color(count));
+        vec![Box::new(wash) as Box<dyn svg::Node>]
+    })
+}
@@ -0,0 +1,173 @@
+//! Timeline pages showing how a keymap evolved over several revisions.
+use crate::output::{escape_html, svg_data_uri};
+use crate::{Layer, RenderOptions, diff, generate_layer_thumbnail_svg};
+
+/// One version of a keymap on the timeline.
+#[derive(Debug, Clone)]
+pub struct Revision {
+    /// Short name of the revision, e.g. an abbreviated commit hash or a file name
+    pub label: String,
+    /// Longer description, e.g. the commit date and subject
+    pub description: String,
+    pub layers: Vec<Layer>,
+}
+
+/// Changed keys listed in full before the rest are collapsed.
+const VISIBLE_CHANGES: usize = 8;
+
+const TIMELINE_STYLE: &str = r#"
+  body { margin: 0; padding: 24px; background: #faf8f3; color: #2c3e50;
+         font-family: -apple-system, BlinkMacSystemFont, 'Segoe UI', sans-serif; }
+  h1 { font-size: 24px; margin: 0 0 24px; }
+  .revision { position: relative; margin-left: 16px; padding: 0 0 32px 24px;
+              border-left: 2px solid #bdc3c7; }
+  .revision::before { content: ""; position: absolute; left: -7px; top: 4px; width: 12px;
+                      height: 12px; border-radius: 50%; background: #3f6f96; }
+  .revision h2 { font-size: 17px; margin: 0; }
+  .revision h2 code { color: #3f6f96; }
+  .description { color: #5d6d7e; font-size: 13px; margin: 2px 0 10px; }
+  .summary { font-size: 14px; margin: 0 0 6px; }
+  .changes { font: 12px 'SF Mono', 'Monaco', 'Inconsolata', 'Fira Code', monospace;
+             margin: 0 0 10px; padding-left: 18px; }
+  .changes .detail { color: #5d6d7e; }
+  details summary { cursor: pointer; font-size: 13px; color: #3f6f96; }
+  .thumbnails { display: flex; flex-wrap: wrap; gap: 12px; }
+  figure { margin: 0; }
+  figure img { display: block; height: 120px; background: #ffffff;
+               border: 1px solid #bdc3c7; border-radius: 6px; }
+  figcaption { font-size: 12px; color: #5d6d7e; margin-top: 2px; }
+"#;
+
+/// Describes the changes from one revision to the next as an HTML fragment.
+fn change_summary(old: &Revision, new: &Revision, position_names: &[String]) -> String {
+    let changes = diff::diff_layers(&old.layers, &new.layers);
+    let layer_changes = diff::layer_changes(&old.layers, &new.layers);
+    let mut notes = Vec::new();
+    for kind in [diff::ChangeKind::Added, diff::ChangeKind::Removed] {
+        let count = layer_changes.iter().filter(|c| c.kind == kind).count();
+        if count > 0 {
+            notes.push(format!("{} layer(s) {}", count, kind.name()));
+        }
+    }
+    if changes.is_empty() && notes.is_empty() {
+        return "<p class=\"summary\">No key changes</p>\n".to_string();
+    }
+
+    let mut layers: Vec<usize> = changes.iter().map(|change| change.layer).collect();
+    layers.dedup();
+    notes.insert(
+        0,
+        format!(
+            "{} key(s) changed on {} layer(s)",
+            changes.len(),
+            layers.len()
+        ),
+    );
+    let mut html = format!("<p class=\"summary\">{}</p>\n", notes.join(", "));
+
+    let items: Vec<String> = changes
+        .iter()
+        .map(|change| {
+            let position = position_names
+                .get(change.position)
+                .cloned()
+                .unwrap_or_else(|| format!("#{}", change.position));
+            let show = |key: &str| {
+                if key.is_empty() {
+                    "(none)".to_string()
+                } else {
+                    escape_html(key)
+                }
+            };
+            let mut item = format!(
+                "<li>Layer {} {}: {} → {}",
+                change.layer,
+                position,
+                show(&change.old),
+                show(&change.new)
+            );
+            if !change.is_whole_key() {
+                item.push_str(&format!(
+                    " <span class=\"detail\">({})</span>",
+                    escape_html(&change.details.join("; "))
+                ));
+            }
+            item.push_str("</li>");
+            item
+        })
+        .collect();
+
+    let (visible, rest) = items.split_at(items.len().min(VISIBLE_CHANGES));
+    html.push_str(&format!(
+        "<ul class=\"changes\">{}</ul>\n",
+        visible.join("")
+    ));
+    if !rest.is_empty() {
+        html.push_str(&format!(
+            "<details><summary>{} more</summary><ul class=\"changes\">{}</ul></details>\n",
+            rest.len(),
+            rest.join("")
+        ));
+    }
+    html
+}
+
+/// Renders a standalone HTML page with a timeline of keymap revisions.
+///
+/// Each revision shows a thumbnail of every layer and a summary of the keys
+/// changed since the previous revision.
+///
+/// # Arguments
+///
+/// * `revisions` - The revisions, oldest first
+/// * `title` - Page heading
+/// * `options` - Options controlling colors, style, and geometry of the
+///   thumbnails; its geometry also names the changed key positions
+///
+/// # Returns
+///
+/// The HTML page
+pub fn timeline_html(revisions: &[Revision], title: &str, options: &RenderOptions) -> String {
+    let position_names = options.geometry.position_names();
+    let mut sections = String::new();
+
+    for (index, revision) in revisions.iter().enumerate() {
+        sections.push_str(&format!(
+            "<section class=\"revision\">\n<h2><code>{}</code></h2>\n\
+             <p class=\"description\">{}</p>\n",
+            escape_html(&revision.label),
+            escape_html(&revision.description)
+        ));
+
+        sections.push_str(&match index.checked_sub(1).map(|prev| &revisions[prev]) {
+            Some(previous) => change_summary(previous, revision, &position_names),
+            None => format!(
+                "<p class=\"summary\">First revision, {} layer(s)</p>\n",
+                revision.layers.len()
+            ),
+        });
+
+        sections.push_str("<div class=\"thumbnails\">\n");
+        for layer in &revision.layers {
+            let thumbnail = generate_layer_thumbnail_svg(&revision.layers, layer, options);
+            let caption = match layer.display_name() {
+                Some(name) => format!("Layer {} ({})", layer.index, name),
+                None => format!("Layer {}", layer.index),
+            };
+            sections.push_str(&format!(
+                "<figure><img src=\"{}\" alt=\"{}\"><figcaption>{}</figcaption></figure>\n",
+                svg_data_uri(&thumbnail),
+                escape_html(&caption),
+                escape_html(&caption)
+            ));
+        }
+        sections.push_str("</div>\n</section>\n");
+    }
+
+    let title = escape_html(title);
+    format!(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>{}</title>\n\
+         <style>{}</style>\n</head>\n<body>\n<h1>{}</h1>\n{}</body>\n</html>\n",
+        title, TIMELINE_STYLE, title, sections
+    )
+}
@@ -0,0 +1,250 @@
+//! Translations of the text the renderer writes into documents: titles,
+//! panel headings, labels, and warnings.
+//!
+//! A translation table sets any of the strings below; missing ones stay in
+//! English. Placeholders in braces, such as `{index}`, are filled in when the
+//! text is drawn.
+use crate::Layer;
+use anyhow::{Result, bail};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+/// Built-in translation tables by language code.
+const BUILT_IN: [(&str, &str); 3] = [
+    ("de", include_str!("../lang/de.toml")),
+    ("fr", include_str!("../lang/fr.toml")),
+    ("es", include_str!("../lang/es.toml")),
+];
+
+/// The generated text of a document in one language.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(default, deny_unknown_fields)]
+pub struct Translations {
+    /// Title of an unnamed layer, with `{index}`
+    pub layer: String,
+    /// Title of a named layer, with `{index}` and `{name}`
+    pub named_layer: String,
+    /// Heading of the metadata panel
+    pub metadata: String,
+    /// Heading of the panel explaining layer keys
+    pub layer_keys: String,
+    /// Title of the board comparing base layers
+    pub base_variants: String,
+    /// Note under the base variants title, with `{layers}`
+    pub base_variants_note: String,
+    /// Word joining the last two layers of `{layers}`
+    pub and: String,
+    /// Single card corner legend entry, with `{corner}` and `{index}`
+    pub corner_legend: String,
+    /// Names of the single card corners: top left, top right, bottom left,
+    /// bottom right
+    pub corners: [String; 4],
+    /// Banner shown when no built-in board matches, with `{count}`
+    pub fallback_board: String,
+    /// Tooltip of Auto Shift badges, with `{timeout}`
+    pub auto_shift_badge: String,
+    /// Metadata label of the enabled firmware features
+    pub features: String,
+    /// Metadata label of the Auto Shift settings
+    pub auto_shift: String,
+    /// Metadata label of duplicate layers
+    pub duplicate_layers: String,
+    /// Metadata label of the base variant summary
+    pub base_variant_summary: String,
+    /// Metadata label and scale legend title of typing effort
+    pub effort: String,
+    /// Scale legend title of the usage heatmap
+    pub key_presses: String,
+    /// Metadata label of VIA or Vial support
+    pub runtime_remapping: String,
+    /// Metadata note when VIA or Vial is enabled, with `{tool}`
+    pub runtime_remapping_note: String,
+    /// Heading of the combos panel
+    pub combos: String,
+    /// Output of a combo handled in `process_combo_event`, with `{name}`
+    pub combo_action: String,
+    /// Heading of the table of thumb keys across layers
+    pub thumb_keys: String,
+    /// Tooltip of a trackball
+    pub trackball: String,
+    /// Name of a rotary encoder, with `{index}` counting from 1
+    pub encoder: String,
+}
+
+impl Default for Translations {
+    fn default() -> Self {
+        Translations {
+            layer: "Layer {index}".to_string(),
+            named_layer: "Layer {index} – {name}".to_string(),
+            metadata: "Metadata".to_string(),
+            layer_keys: "Layer Keys".to_string(),
+            base_variants: "Base Variants".to_string(),
+            base_variants_note:
+                "Keys that differ between {layers}; dashed keys also differ in symbols or modifiers"
+                    .to_string(),
+            and: "and".to_string(),
+            corner_legend: "{corner}: Layer {index}".to_string(),
+            corners: [
+                "top-left".to_string(),
+                "top-right".to_string(),
+                "bottom-left".to_string(),
+                "bottom-right".to_string(),
+            ],
+            fallback_board: "No built-in board has {count} keys per layer; drawn as an \
+                             ortholinear grid. Pass --board to choose another geometry."
+                .to_string(),
+            auto_shift_badge: "Auto Shift: hold {timeout} ms for the shifted key".to_string(),
+            features: "Features".to_string(),
+            auto_shift: "Auto Shift".to_string(),
+            duplicate_layers: "Duplicate layers".to_string(),
+            base_variant_summary: "Base variants".to_string(),
+            effort: "Effort".to_string(),
+            key_presses: "Key presses".to_string(),
+            runtime_remapping: "Runtime remapping".to_string(),
+            runtime_remapping_note: "{tool} is enabled; keys remapped on the board may differ \
+                                     from this file"
+                .to_string(),
+            combos: "Combos".to_string(),
+            combo_action: "custom action ({name})".to_string(),
+            thumb_keys: "Thumb Keys".to_string(),
+            trackball: "Trackball".to_string(),
+            encoder: "Encoder {index}".to_string(),
+        }
+    }
+}
+
+/// Fills the `{name}` placeholders of a template.
+fn fill(template: &str, values: &[(&str, &str)]) -> String {
+    values
+        .iter()
+        .fold(template.to_string(), |text, (name, value)| {
+            text.replace(&format!("{{{}}}", name), value)
+        })
+}
+
+impl Translations {
+    /// Codes of the built-in languages besides English.
+    pub fn languages() -> impl Iterator<Item = &'static str> {
+        BUILT_IN.iter().map(|(code, _)| *code)
+    }
+
+    /// Returns the built-in translations of a language.
+    ///
+    /// # Arguments
+    ///
+    /// * `code` - A language code such as `de`; `en` is the default text
+    ///
+    /// # Returns
+    ///
+    /// The translations, or `None` if the language isn't built in
+    pub fn built_in(code: &str) -> Option<Translations> {
+        if code.eq_ignore_ascii_case("en") {
+            return Some(Translations::default());
+        }
+        BUILT_IN
+            .iter()
+            .find(|(known, _)| known.eq_ignore_ascii_case(code))
+            .map(|(_, table)| toml::from_str(table).expect("built-in translations parse"))
+    }
+
+    /// The title of a layer's section, e.g. `Layer 1 – NAV` for `_NAV`.
+    pub fn layer_title(&self, layer: &Layer) -> String {
+        let index = layer.index.to_string();
+        match layer
+            .display_name()
+            .map(|name| name.trim_start_matches('_'))
+        {
+            Some(name) if !name.is_empty() => fill(
+                &self.named_layer,
+                &[("index", index.as_str()), ("name", name)],
+            ),
+            _ => fill(&self.layer, &[("index", index.as_str())]),
+        }
+    }
+
+    /// The note under the base variants title.
+    pub fn base_variants_note(&self, layers: &[String]) -> String {
+        let layers = layers.join(&format!(" {} ", self.and));
+        fill(&self.base_variants_note, &[("layers", layers.as_str())])
+    }
+
+    /// The single card legend entry of a corner.
+    pub fn corner_legend(&self, corner: usize, index: usize) -> String {
+        fill(
+            &self.corner_legend,
+            &[
+                ("corner", self.corners[corner].as_str()),
+                ("index", index.to_string().as_str()),
+            ],
+        )
+    }
+
+    /// The banner shown when a keymap is drawn as an ortholinear grid.
+    pub fn fallback_board(&self, count: usize) -> String {
+        fill(
+            &self.fallback_board,
+            &[("count", count.to_string().as_str())],
+        )
+    }
+
+    /// The tooltip of an Auto Shift badge.
+    pub fn auto_shift_badge(&self, timeout_ms: u32) -> String {
+        fill(
+            &self.auto_shift_badge,
+            &[("timeout", timeout_ms.to_string().as_str())],
+        )
+    }
+
+    /// The metadata note shown when VIA or Vial can remap the keys.
+    pub fn runtime_remapping_note(&self, tool: &str) -> String {
+        fill(&self.runtime_remapping_note, &[("tool", tool)])
+    }
+
+    /// The output of a combo handled in `process_combo_event`.
+    pub fn combo_action(&self, name: &str) -> String {
+        fill(&self.combo_action, &[("name", name)])
+    }
+
+    /// The name of an encoder, counting from 1.
+    pub fn encoder(&self, index: usize) -> String {
+        fill(&self.encoder, &[("index", &index.to_string())])
+    }
+
+    /// Checks that no string is empty.
+    pub fn validate(&self) -> Result<()> {
+        let strings = [
+            ("layer", &self.layer),
+            ("named_layer", &self.named_layer),
+            ("metadata", &self.metadata),
+            ("layer_keys", &self.layer_keys),
+            ("base_variants", &self.base_variants),
+            ("base_variants_note", &self.base_variants_note),
+            ("and", &self.and),
+            ("corner_legend", &self.corner_legend),
+            ("fallback_board", &self.fallback_board),
+            ("auto_shift_badge", &self.auto_shift_badge),
+            ("features", &self.features),
+            ("auto_shift", &self.auto_shift),
+            ("duplicate_layers", &self.duplicate_layers),
+            ("base_variant_summary", &self.base_variant_summary),
+            ("effort", &self.effort),
+            ("key_presses", &self.key_presses),
+            ("runtime_remapping", &self.runtime_remapping),
+            ("runtime_remapping_note", &self.runtime_remapping_note),
+            ("combos", &self.combos),
+            ("combo_action", &self.combo_action),
+            ("thumb_keys", &self.thumb_keys),
+            ("trackball", &self.trackball),
+            ("encoder", &self.encoder),
+        ];
+        for (name, text) in strings {
+            if text.trim().is_empty() {
+                bail!("{}: must not be empty", name);
+            }
+        }
+        if self.corners.iter().any(|corner| corner.trim().is_empty()) {
+            bail!("corners: must not be empty");
+        }
+        Ok(())
+    }
+}
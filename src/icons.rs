@@ -0,0 +1,200 @@
+//! Icons drawn instead of text legends for common keys with `--icons`:
+//! arrows, editing keys, modifiers, media, volume, and brightness.
+use svg::node::element::{Definitions, Path, Symbol};
+
+/// Side of the square the icons' paths are drawn in.
+const VIEW_BOX: f32 = 24.0;
+
+/// The outline of a speaker shared by the volume icons.
+const SPEAKER: &str = "M4 9h4l5-4v14l-5-4H4z";
+/// The disc of the sun shared by the brightness icons.
+const SUN: &str = "M12 8a4 4 0 1 0 0 8a4 4 0 1 0 0-8z";
+
+/// A key icon, drawn as a stroked path.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Icon {
+    Left,
+    Down,
+    Up,
+    Right,
+    Backspace,
+    Delete,
+    Enter,
+    Tab,
+    Space,
+    Shift,
+    CapsLock,
+    Gui,
+    Mute,
+    VolumeUp,
+    VolumeDown,
+    BrightnessUp,
+    BrightnessDown,
+    PlayPause,
+    NextTrack,
+    PreviousTrack,
+    Stop,
+}
+
+impl Icon {
+    pub const ALL: [Icon; 21] = [
+        Icon::Left,
+        Icon::Down,
+        Icon::Up,
+        Icon::Right,
+        Icon::Backspace,
+        Icon::Delete,
+        Icon::Enter,
+        Icon::Tab,
+        Icon::Space,
+        Icon::Shift,
+        Icon::CapsLock,
+        Icon::Gui,
+        Icon::Mute,
+        Icon::VolumeUp,
+        Icon::VolumeDown,
+        Icon::BrightnessUp,
+        Icon::BrightnessDown,
+        Icon::PlayPause,
+        Icon::NextTrack,
+        Icon::PreviousTrack,
+        Icon::Stop,
+    ];
+
+    /// Element id of the icon's `<symbol>`, e.g. `icon-backspace`.
+    pub fn id(self) -> &'static str {
+        match self {
+            Icon::Left => "icon-left",
+            Icon::Down => "icon-down",
+            Icon::Up => "icon-up",
+            Icon::Right => "icon-right",
+            Icon::Backspace => "icon-backspace",
+            Icon::Delete => "icon-delete",
+            Icon::Enter => "icon-enter",
+            Icon::Tab => "icon-tab",
+            Icon::Space => "icon-space",
+            Icon::Shift => "icon-shift",
+            Icon::CapsLock => "icon-caps-lock",
+            Icon::Gui => "icon-gui",
+            Icon::Mute => "icon-mute",
+            Icon::VolumeUp => "icon-volume-up",
+            Icon::VolumeDown => "icon-volume-down",
+            Icon::BrightnessUp => "icon-brightness-up",
+            Icon::BrightnessDown => "icon-brightness-down",
+            Icon::PlayPause => "icon-play-pause",
+            Icon::NextTrack => "icon-next-track",
+            Icon::PreviousTrack => "icon-previous-track",
+            Icon::Stop => "icon-stop",
+        }
+    }
+
+    /// Path data of the icon in a 24×24 box, drawn with round caps and joins.
+    pub fn path(self) -> String {
+        match self {
+            Icon::Left => "M19 12H5M11 6l-6 6 6 6".to_string(),
+            Icon::Down => "M12 5v14M6 13l6 6 6-6".to_string(),
+            Icon::Up => "M12 19V5M6 11l6-6 6 6".to_string(),
+            Icon::Right => "M5 12h14M13 6l6 6-6 6".to_string(),
+            Icon::Backspace => "M21 5H9l-7 7 7 7h12zM17 9l-6 6M11 9l6 6".to_string(),
+            Icon::Delete => "M3 5h12l7 7-7 7H3zM7 9l6 6M13 9l-6 6".to_string(),
+            Icon::Enter => "M20 5v7a3 3 0 0 1-3 3H5M9 11l-4 4 4 4".to_string(),
+            Icon::Tab => "M3 12h15M13 7l5 5-5 5M21 6v12".to_string(),
+            Icon::Space => "M4 10v4h16v-4".to_string(),
+            Icon::Shift => "M12 3l9 9h-5v8H8v-8H3z".to_string(),
+            Icon::CapsLock => "M12 3l9 9h-5v4H8v-4H3zM8 20h8".to_string(),
+            // The looped square of the Command key
+            Icon::Gui => "M9 9h6v6H9zM9 9V6a3 3 0 1 0-3 3h3M15 9V6a3 3 0 1 1 3 3h-3\
+                          M9 15v3a3 3 0 1 1-3-3h3M15 15v3a3 3 0 1 0 3-3h-3"
+                .to_string(),
+            Icon::Mute => format!("{}M16 9l5 6M21 9l-5 6", SPEAKER),
+            Icon::VolumeUp => format!("{}M16 9a4 4 0 0 1 0 6M19 6a8 8 0 0 1 0 12", SPEAKER),
+            Icon::VolumeDown => format!("{}M16 9a4 4 0 0 1 0 6", SPEAKER),
+            Icon::BrightnessUp => format!(
+                "{}M12 2v2M12 20v2M2 12h2M20 12h2M4.9 4.9l1.4 1.4M17.7 17.7l1.4 1.4\
+                 M4.9 19.1l1.4-1.4M17.7 6.3l1.4-1.4",
+                SUN
+            ),
+            Icon::BrightnessDown => format!(
+                "{}M12 5v.5M12 18.5v.5M5 12h.5M18.5 12h.5M7 7l.4.4M16.6 16.6l.4.4\
+                 M7 17l.4-.4M16.6 7.4l.4-.4",
+                SUN
+            ),
+            Icon::PlayPause => "M4 5v14l9-7zM16 5v14M20 5v14".to_string(),
+            Icon::NextTrack => "M5 5v14l10-7zM19 5v14".to_string(),
+            Icon::PreviousTrack => "M19 5v14L9 12zM5 5v14".to_string(),
+            Icon::Stop => "M6 6h12v12H6z".to_string(),
+        }
+    }
+
+    /// The icon's `<symbol>`, drawn by `<use>` elements referring to its id.
+    pub fn symbol(self) -> Symbol {
+        Symbol::new()
+            .set("id", self.id())
+            .set("viewBox", (0, 0, VIEW_BOX as i32, VIEW_BOX as i32))
+            .add(Path::new().set("d", self.path()))
+    }
+}
+
+/// Keycodes drawn as an icon. Each entry lists every name of a keycode.
+const ICON_KEYCODES: &[(&[&str], Icon)] = &[
+    (&["KC_LEFT"], Icon::Left),
+    (&["KC_DOWN"], Icon::Down),
+    (&["KC_UP"], Icon::Up),
+    (&["KC_RGHT", "KC_RIGHT"], Icon::Right),
+    (&["KC_BSPC", "KC_BACKSPACE"], Icon::Backspace),
+    (&["KC_DEL", "KC_DELETE"], Icon::Delete),
+    (&["KC_ENT", "KC_ENTER"], Icon::Enter),
+    (&["KC_TAB"], Icon::Tab),
+    (&["KC_SPC", "KC_SPACE"], Icon::Space),
+    (
+        &["KC_LSFT", "KC_LEFT_SHIFT", "KC_RSFT", "KC_RIGHT_SHIFT"],
+        Icon::Shift,
+    ),
+    (&["KC_CAPS", "KC_CAPS_LOCK"], Icon::CapsLock),
+    (
+        &[
+            "KC_LGUI",
+            "KC_LEFT_GUI",
+            "KC_LCMD",
+            "KC_LWIN",
+            "KC_RGUI",
+            "KC_RIGHT_GUI",
+            "KC_RCMD",
+            "KC_RWIN",
+        ],
+        Icon::Gui,
+    ),
+    (&["KC_MUTE", "KC_AUDIO_MUTE"], Icon::Mute),
+    (&["KC_VOLU", "KC_AUDIO_VOL_UP"], Icon::VolumeUp),
+    (&["KC_VOLD", "KC_AUDIO_VOL_DOWN"], Icon::VolumeDown),
+    (&["KC_BRIU", "KC_BRIGHTNESS_UP"], Icon::BrightnessUp),
+    (&["KC_BRID", "KC_BRIGHTNESS_DOWN"], Icon::BrightnessDown),
+    (&["KC_MPLY", "KC_MEDIA_PLAY_PAUSE"], Icon::PlayPause),
+    (&["KC_MNXT", "KC_MEDIA_NEXT_TRACK"], Icon::NextTrack),
+    (&["KC_MPRV", "KC_MEDIA_PREV_TRACK"], Icon::PreviousTrack),
+    (&["KC_MSTP", "KC_MEDIA_STOP"], Icon::Stop),
+];
+
+/// Looks up the icon of a keycode, e.g. `KC_BSPC` → [`Icon::Backspace`].
+///
+/// # Arguments
+///
+/// * `key` - The keycode as written in the keymap
+///
+/// # Returns
+///
+/// The icon, or `None` if the keycode is drawn as text
+pub fn icon(key: &str) -> Option<Icon> {
+    let key = key.trim();
+    ICON_KEYCODES
+        .iter()
+        .find(|(names, _)| names.contains(&key))
+        .map(|&(_, icon)| icon)
+}
+
+/// The `<symbol>` of every icon, for documents drawing keys with `--icons`.
+pub fn definitions() -> Definitions {
+    Icon::ALL
+        .into_iter()
+        .fold(Definitions::new(), |defs, icon| defs.add(icon.symbol()))
+}
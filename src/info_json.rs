@@ -0,0 +1,166 @@
+//! Board geometries read from the `layouts` section of a QMK `info.json` (or
+//! `keyboard.json`), placing keys at the board's real coordinates.
+use crate::geometry::{Geometry, Half, KeyOrder, KeyPosition};
+use anyhow::{Context, Result, anyhow, bail};
+use serde::Deserialize;
+use std::collections::BTreeMap;
+
+/// Narrowest empty column, in key units, taken for the gap between the
+/// halves of a split board.
+const MIN_SPLIT_GAP: f32 = 1.0;
+
+/// The parts of an `info.json` describing the physical layouts.
+#[derive(Debug, Deserialize)]
+struct InfoJson {
+    #[serde(default)]
+    keyboard_name: Option<String>,
+    layouts: BTreeMap<String, LayoutJson>,
+}
+
+#[derive(Debug, Deserialize)]
+struct LayoutJson {
+    layout: Vec<KeyJson>,
+}
+
+/// One key, in KLE units: rotated by `r` degrees clockwise about `(rx, ry)`.
+#[derive(Debug, Deserialize)]
+struct KeyJson {
+    x: f32,
+    y: f32,
+    #[serde(default = "one")]
+    w: f32,
+    #[serde(default = "one")]
+    h: f32,
+    #[serde(default)]
+    r: f32,
+    #[serde(default)]
+    rx: f32,
+    #[serde(default)]
+    ry: f32,
+}
+
+fn one() -> f32 {
+    1.0
+}
+
+/// Returns `true` if a JSON document looks like a QMK `info.json`, i.e. has a
+/// `layouts` section, rather than a geometry config file.
+pub fn is_info_json(content: &str) -> bool {
+    serde_json::from_str::<serde_json::Value>(content)
+        .is_ok_and(|value| value.get("layouts").is_some())
+}
+
+/// Moves a rotated key to the equivalent position rotated about its own
+/// center, which is how geometries store rotation.
+fn position(key: &KeyJson) -> (f32, f32) {
+    if key.r == 0.0 {
+        return (key.x, key.y);
+    }
+    let (cx, cy) = (key.x + key.w / 2.0, key.y + key.h / 2.0);
+    let (sin, cos) = key.r.to_radians().sin_cos();
+    let (dx, dy) = (cx - key.rx, cy - key.ry);
+    (
+        key.rx + dx * cos - dy * sin - key.w / 2.0,
+        key.ry + dx * sin + dy * cos - key.h / 2.0,
+    )
+}
+
+/// Finds the widest empty column between keys, as `(start, width)`, if one
+/// is at least `MIN_SPLIT_GAP` wide.
+fn split_gap(keys: &[KeyPosition]) -> Option<(f32, f32)> {
+    let mut spans: Vec<(f32, f32)> = keys.iter().map(|k| (k.x, k.x + k.w)).collect();
+    spans.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+    let mut covered = spans.first()?.1;
+    let mut widest: Option<(f32, f32)> = None;
+    for &(start, end) in &spans[1..] {
+        let width = start - covered;
+        if width >= MIN_SPLIT_GAP && widest.is_none_or(|(_, w)| width > w) {
+            widest = Some((covered, width));
+        }
+        covered = covered.max(end);
+    }
+    widest
+}
+
+/// Reads a board geometry from a QMK `info.json`.
+///
+/// Keys start a new row whenever they move back to the left of the previous
+/// key, as LAYOUT arguments do at the end of a physical row. On split
+/// boards, found by an empty column at least one key wide, the right half is
+/// moved in next to the left one, since the split gap is added when drawing.
+///
+/// # Arguments
+///
+/// * `content` - The info.json contents
+/// * `layout_macro` - The LAYOUT macro the keymap uses; its layout is read
+///   when the file has it, otherwise the first layout by name
+///
+/// # Returns
+///
+/// The geometry, named after the keyboard, or an error if the JSON has no
+/// layouts or an empty one
+pub fn parse_info_json(content: &str, layout_macro: Option<&str>) -> Result<Geometry> {
+    let info: InfoJson = serde_json::from_str(content).context("Not a QMK info.json")?;
+    let (name, layout) = layout_macro
+        .and_then(|name| info.layouts.get_key_value(name))
+        .or_else(|| info.layouts.iter().next())
+        .ok_or_else(|| anyhow!("info.json has no layouts"))?;
+    if layout.layout.is_empty() {
+        bail!("layout {} has no keys", name);
+    }
+
+    let mut keys: Vec<KeyPosition> = layout
+        .layout
+        .iter()
+        .map(|key| {
+            let (x, y) = position(key);
+            KeyPosition {
+                w: key.w,
+                h: key.h,
+                r: key.r,
+                ..KeyPosition::new(x, y, Half::Left)
+            }
+        })
+        .collect();
+
+    // Rotation can move keys above or left of the origin
+    let min_x = keys.iter().map(|k| k.x).fold(f32::MAX, f32::min);
+    let min_y = keys.iter().map(|k| k.y).fold(f32::MAX, f32::min);
+    for key in &mut keys {
+        key.x -= min_x;
+        key.y -= min_y;
+    }
+
+    if let Some((start, width)) = split_gap(&keys) {
+        for key in keys.iter_mut().filter(|key| key.x >= start) {
+            key.half = Half::Right;
+            key.x -= width;
+        }
+    }
+
+    let mut rows: Vec<Vec<KeyPosition>> = Vec::new();
+    let mut previous: Option<(f32, Half)> = None;
+    for key in keys {
+        let moves_left = previous.is_some_and(|(x, half)| {
+            (half == key.half && key.x < x) || (half == Half::Right && key.half == Half::Left)
+        });
+        previous = Some((key.x, key.half));
+        match rows.last_mut() {
+            Some(row) if !moves_left => row.push(key),
+            _ => rows.push(vec![key]),
+        }
+    }
+
+    let keyboard = info
+        .keyboard_name
+        .unwrap_or_else(|| "info.json".to_string());
+    Ok(Geometry {
+        name: keyboard.to_ascii_lowercase().replace(' ', "-"),
+        description: format!("{} ({} from info.json)", keyboard, name),
+        layout_macros: vec![name.clone()],
+        rows,
+        order: KeyOrder::RowMajor,
+        controls: Vec::new(),
+    })
+}
@@ -0,0 +1,917 @@
+//! Recognition tables for QMK keycodes that get a readable legend and a
+//! description instead of their raw name.
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// A recognized keycode.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KeycodeInfo {
+    /// Short legend drawn on the key
+    pub legend: &'static str,
+    /// What the keycode does, shown as a tooltip
+    pub description: &'static str,
+}
+
+/// System keycodes: Magic (modifier swaps), NKRO, handedness, and
+/// EEPROM/debug controls. Each entry lists every name of a keycode.
+const SYSTEM_KEYCODES: &[(&[&str], &str, &str)] = &[
+    (
+        &[
+            "MAGIC_SWAP_CONTROL_CAPSLOCK",
+            "QK_MAGIC_SWAP_CONTROL_CAPS_LOCK",
+            "CL_SWAP",
+        ],
+        "Ctl⇄Caps",
+        "Swap Caps Lock and Left Control",
+    ),
+    (
+        &[
+            "MAGIC_UNSWAP_CONTROL_CAPSLOCK",
+            "QK_MAGIC_UNSWAP_CONTROL_CAPS_LOCK",
+            "CL_NORM",
+        ],
+        "Ctl/Caps",
+        "Unswap Caps Lock and Left Control",
+    ),
+    (
+        &[
+            "MAGIC_TOGGLE_CONTROL_CAPSLOCK",
+            "QK_MAGIC_TOGGLE_CONTROL_CAPS_LOCK",
+            "CL_TOGG",
+        ],
+        "Ctl⇄Caps?",
+        "Toggle the Caps Lock and Left Control swap",
+    ),
+    (
+        &[
+            "MAGIC_CAPSLOCK_TO_CONTROL",
+            "QK_MAGIC_CAPS_LOCK_AS_CONTROL_ON",
+            "CL_CTRL",
+        ],
+        "Caps→Ctl",
+        "Treat Caps Lock as Control",
+    ),
+    (
+        &[
+            "MAGIC_UNCAPSLOCK_TO_CONTROL",
+            "QK_MAGIC_CAPS_LOCK_AS_CONTROL_OFF",
+            "CL_CAPS",
+        ],
+        "Caps",
+        "Stop treating Caps Lock as Control",
+    ),
+    (
+        &[
+            "MAGIC_SWAP_LCTL_LGUI",
+            "QK_MAGIC_SWAP_LCTL_LGUI",
+            "CG_LSWP",
+            "LCG_SWP",
+        ],
+        "LCtl⇄LGui",
+        "Swap Left Control and Left GUI",
+    ),
+    (
+        &[
+            "MAGIC_UNSWAP_LCTL_LGUI",
+            "QK_MAGIC_UNSWAP_LCTL_LGUI",
+            "CG_LNRM",
+            "LCG_NRM",
+        ],
+        "LCtl/LGui",
+        "Unswap Left Control and Left GUI",
+    ),
+    (
+        &[
+            "MAGIC_SWAP_RCTL_RGUI",
+            "QK_MAGIC_SWAP_RCTL_RGUI",
+            "CG_RSWP",
+            "RCG_SWP",
+        ],
+        "RCtl⇄RGui",
+        "Swap Right Control and Right GUI",
+    ),
+    (
+        &[
+            "MAGIC_UNSWAP_RCTL_RGUI",
+            "QK_MAGIC_UNSWAP_RCTL_RGUI",
+            "CG_RNRM",
+            "RCG_NRM",
+        ],
+        "RCtl/RGui",
+        "Unswap Right Control and Right GUI",
+    ),
+    (
+        &["MAGIC_SWAP_CTL_GUI", "QK_MAGIC_SWAP_CTL_GUI", "CG_SWAP"],
+        "Ctl⇄Gui",
+        "Swap Control and GUI on both sides",
+    ),
+    (
+        &["MAGIC_UNSWAP_CTL_GUI", "QK_MAGIC_UNSWAP_CTL_GUI", "CG_NORM"],
+        "Ctl/Gui",
+        "Unswap Control and GUI on both sides",
+    ),
+    (
+        &["MAGIC_TOGGLE_CTL_GUI", "QK_MAGIC_TOGGLE_CTL_GUI", "CG_TOGG"],
+        "Ctl⇄Gui?",
+        "Toggle the Control and GUI swap on both sides",
+    ),
+    (
+        &[
+            "MAGIC_SWAP_LALT_LGUI",
+            "QK_MAGIC_SWAP_LALT_LGUI",
+            "AG_LSWP",
+            "LAG_SWP",
+        ],
+        "LAlt⇄LGui",
+        "Swap Left Alt and Left GUI",
+    ),
+    (
+        &[
+            "MAGIC_UNSWAP_LALT_LGUI",
+            "QK_MAGIC_UNSWAP_LALT_LGUI",
+            "AG_LNRM",
+            "LAG_NRM",
+        ],
+        "LAlt/LGui",
+        "Unswap Left Alt and Left GUI",
+    ),
+    (
+        &[
+            "MAGIC_SWAP_RALT_RGUI",
+            "QK_MAGIC_SWAP_RALT_RGUI",
+            "AG_RSWP",
+            "RAG_SWP",
+        ],
+        "RAlt⇄RGui",
+        "Swap Right Alt and Right GUI",
+    ),
+    (
+        &[
+            "MAGIC_UNSWAP_RALT_RGUI",
+            "QK_MAGIC_UNSWAP_RALT_RGUI",
+            "AG_RNRM",
+            "RAG_NRM",
+        ],
+        "RAlt/RGui",
+        "Unswap Right Alt and Right GUI",
+    ),
+    (
+        &["MAGIC_SWAP_ALT_GUI", "QK_MAGIC_SWAP_ALT_GUI", "AG_SWAP"],
+        "Alt⇄Gui",
+        "Swap Alt and GUI on both sides",
+    ),
+    (
+        &["MAGIC_UNSWAP_ALT_GUI", "QK_MAGIC_UNSWAP_ALT_GUI", "AG_NORM"],
+        "Alt/Gui",
+        "Unswap Alt and GUI on both sides",
+    ),
+    (
+        &["MAGIC_TOGGLE_ALT_GUI", "QK_MAGIC_TOGGLE_ALT_GUI", "AG_TOGG"],
+        "Alt⇄Gui?",
+        "Toggle the Alt and GUI swap on both sides",
+    ),
+    (
+        &["MAGIC_NO_GUI", "QK_MAGIC_GUI_OFF", "GU_OFF"],
+        "GUI Off",
+        "Disable the GUI keys",
+    ),
+    (
+        &["MAGIC_UNNO_GUI", "QK_MAGIC_GUI_ON", "GU_ON"],
+        "GUI On",
+        "Enable the GUI keys",
+    ),
+    (
+        &["MAGIC_TOGGLE_GUI", "QK_MAGIC_TOGGLE_GUI", "GU_TOGG"],
+        "GUI?",
+        "Toggle the GUI keys on and off",
+    ),
+    (
+        &["MAGIC_SWAP_GRAVE_ESC", "QK_MAGIC_SWAP_GRAVE_ESC", "GE_SWAP"],
+        "`⇄Esc",
+        "Swap Grave and Escape",
+    ),
+    (
+        &[
+            "MAGIC_UNSWAP_GRAVE_ESC",
+            "QK_MAGIC_UNSWAP_GRAVE_ESC",
+            "GE_NORM",
+        ],
+        "`/Esc",
+        "Unswap Grave and Escape",
+    ),
+    (
+        &[
+            "MAGIC_SWAP_BACKSLASH_BACKSPACE",
+            "QK_MAGIC_SWAP_BACKSLASH_BACKSPACE",
+            "BS_SWAP",
+        ],
+        "\\⇄Bksp",
+        "Swap Backslash and Backspace",
+    ),
+    (
+        &[
+            "MAGIC_UNSWAP_BACKSLASH_BACKSPACE",
+            "QK_MAGIC_UNSWAP_BACKSLASH_BACKSPACE",
+            "BS_NORM",
+        ],
+        "\\/Bksp",
+        "Unswap Backslash and Backspace",
+    ),
+    (
+        &[
+            "MAGIC_TOGGLE_BACKSLASH_BACKSPACE",
+            "QK_MAGIC_TOGGLE_BACKSLASH_BACKSPACE",
+            "BS_TOGG",
+        ],
+        "\\⇄Bksp?",
+        "Toggle the Backslash and Backspace swap",
+    ),
+    (
+        &["MAGIC_HOST_NKRO", "QK_MAGIC_NKRO_ON", "NK_ON"],
+        "NKRO On",
+        "Enable N-key rollover",
+    ),
+    (
+        &["MAGIC_UNHOST_NKRO", "QK_MAGIC_NKRO_OFF", "NK_OFF"],
+        "NKRO Off",
+        "Disable N-key rollover (6-key rollover)",
+    ),
+    (
+        &["MAGIC_TOGGLE_NKRO", "QK_MAGIC_TOGGLE_NKRO", "NK_TOGG"],
+        "NKRO?",
+        "Toggle N-key rollover",
+    ),
+    (
+        &["MAGIC_EE_HANDS_LEFT", "QK_MAGIC_EE_HANDS_LEFT", "EH_LEFT"],
+        "Left Hand",
+        "Store in EEPROM that this half is the left side",
+    ),
+    (
+        &["MAGIC_EE_HANDS_RIGHT", "QK_MAGIC_EE_HANDS_RIGHT", "EH_RGHT"],
+        "Right Hand",
+        "Store in EEPROM that this half is the right side",
+    ),
+    (
+        &["QK_CLEAR_EEPROM", "EE_CLR", "EEPROM_RESET", "EEP_RST"],
+        "Clear EEPROM",
+        "Reset persistent settings stored in EEPROM",
+    ),
+    (
+        &["QK_DEBUG_TOGGLE", "DB_TOGG", "DEBUG"],
+        "Debug?",
+        "Toggle debug output on the console",
+    ),
+    (
+        &["QK_REBOOT", "QK_RBT"],
+        "Reboot",
+        "Restart the keyboard without entering the bootloader",
+    ),
+    (
+        &["QK_MAKE"],
+        "Make",
+        "Type the qmk compile command for this keyboard",
+    ),
+];
+
+/// Looks up a system keycode (Magic, NKRO, handedness, EEPROM/debug).
+///
+/// # Arguments
+///
+/// * `key` - The keycode as written in the keymap
+///
+/// # Returns
+///
+/// The keycode's legend and description, or `None` if it is not a system keycode
+pub fn system_keycode(key: &str) -> Option<KeycodeInfo> {
+    let key = key.trim();
+    SYSTEM_KEYCODES
+        .iter()
+        .find(|(names, _, _)| names.contains(&key))
+        .map(|&(_, legend, description)| KeycodeInfo {
+            legend,
+            description,
+        })
+}
+
+/// Pointing device keycodes of trackball and trackpad boards: Keyball's, the
+/// Charybdis/Dilemma ones from Bastard Keyboards, and common userspace names.
+/// Each entry lists every name of a keycode.
+const POINTER_KEYCODES: &[(&[&str], &str, &str)] = &[
+    (
+        &["KBC_RST"],
+        "Ball Rst",
+        "Reset the Keyball settings to their defaults",
+    ),
+    (
+        &["KBC_SAVE"],
+        "Ball Save",
+        "Save the Keyball settings to EEPROM",
+    ),
+    (
+        &["CPI_I100"],
+        "CPI+100",
+        "Increase the trackball CPI by 100",
+    ),
+    (
+        &["CPI_D100"],
+        "CPI-100",
+        "Decrease the trackball CPI by 100",
+    ),
+    (&["CPI_I1K"], "CPI+1k", "Increase the trackball CPI by 1000"),
+    (&["CPI_D1K"], "CPI-1k", "Decrease the trackball CPI by 1000"),
+    (&["SCRL_TO"], "Scroll⇄", "Toggle scroll mode"),
+    (
+        &["SCRL_MO"],
+        "Scroll",
+        "Scroll with the trackball while held",
+    ),
+    (
+        &["SCRL_DVI"],
+        "ScrDiv+",
+        "Increase the scroll divider (slower scrolling)",
+    ),
+    (
+        &["SCRL_DVD"],
+        "ScrDiv-",
+        "Decrease the scroll divider (faster scrolling)",
+    ),
+    (
+        &["AML_TO"],
+        "AutoMouse⇄",
+        "Toggle the automatic mouse layer",
+    ),
+    (
+        &["AML_I50"],
+        "AML+50",
+        "Lengthen the automatic mouse layer timeout by 50 ms",
+    ),
+    (
+        &["AML_D50"],
+        "AML-50",
+        "Shorten the automatic mouse layer timeout by 50 ms",
+    ),
+    (&["SSNP_VRT"], "Snap ↕", "Snap scrolling to vertical"),
+    (&["SSNP_HOR"], "Snap ↔", "Snap scrolling to horizontal"),
+    (&["SSNP_FRE"], "Snap free", "Scroll freely in any direction"),
+    (
+        &["DPI_MOD", "POINTER_DEFAULT_DPI_FORWARD"],
+        "DPI+",
+        "Step the default pointer DPI up",
+    ),
+    (
+        &["DPI_RMOD", "POINTER_DEFAULT_DPI_REVERSE"],
+        "DPI-",
+        "Step the default pointer DPI down",
+    ),
+    (
+        &["S_D_MOD", "POINTER_SNIPING_DPI_FORWARD"],
+        "SnipDPI+",
+        "Step the sniping DPI up",
+    ),
+    (
+        &["S_D_RMOD", "POINTER_SNIPING_DPI_REVERSE"],
+        "SnipDPI-",
+        "Step the sniping DPI down",
+    ),
+    (
+        &["SNIPING", "SNIPING_MODE"],
+        "Sniping",
+        "Move the pointer at low DPI while held",
+    ),
+    (
+        &["SNP_TOG", "SNIPING_MODE_TOGGLE"],
+        "Snipe⇄",
+        "Toggle sniping mode",
+    ),
+    (
+        &["DRGSCRL", "DRAGSCROLL_MODE", "DRAG_SCROLL"],
+        "Drag scroll",
+        "Scroll with the trackball while held",
+    ),
+    (
+        &["DRG_TOG", "DRAGSCROLL_MODE_TOGGLE"],
+        "Drag⇄",
+        "Toggle drag scroll",
+    ),
+    (
+        &["DPI_CONFIG"],
+        "DPI",
+        "Cycle through the trackball DPI settings",
+    ),
+];
+
+/// Looks up a pointing device keycode (trackball CPI, scrolling, sniping).
+///
+/// # Arguments
+///
+/// * `key` - The keycode as written in the keymap
+///
+/// # Returns
+///
+/// The keycode's legend and description, or `None` if it is not a pointing
+/// device keycode
+pub fn pointer_keycode(key: &str) -> Option<KeycodeInfo> {
+    let key = key.trim();
+    POINTER_KEYCODES
+        .iter()
+        .find(|(names, _, _)| names.contains(&key))
+        .map(|&(_, legend, description)| KeycodeInfo {
+            legend,
+            description,
+        })
+}
+
+/// Basic keycodes with a readable legend: modifiers, editing and navigation
+/// keys, and punctuation. Each entry lists every name of a keycode. Letters,
+/// digits, and function keys are recognized by [`basic_keycode`] directly.
+const BASIC_KEYCODES: &[(&[&str], &str, &str)] = &[
+    (&["KC_LSFT", "KC_LEFT_SHIFT"], "Shift", "Left Shift"),
+    (&["KC_RSFT", "KC_RIGHT_SHIFT"], "RShift", "Right Shift"),
+    (&["KC_LCTL", "KC_LEFT_CTRL"], "Ctrl", "Left Control"),
+    (&["KC_RCTL", "KC_RIGHT_CTRL"], "RCtrl", "Right Control"),
+    (&["KC_LALT", "KC_LEFT_ALT", "KC_LOPT"], "Alt", "Left Alt"),
+    (
+        &["KC_RALT", "KC_RIGHT_ALT", "KC_ROPT", "KC_ALGR"],
+        "AltGr",
+        "Right Alt",
+    ),
+    (
+        &["KC_LGUI", "KC_LEFT_GUI", "KC_LCMD", "KC_LWIN"],
+        "Gui",
+        "Left GUI (Windows/Command)",
+    ),
+    (
+        &["KC_RGUI", "KC_RIGHT_GUI", "KC_RCMD", "KC_RWIN"],
+        "RGui",
+        "Right GUI (Windows/Command)",
+    ),
+    (&["KC_BSPC", "KC_BACKSPACE"], "⌫", "Backspace"),
+    (&["KC_DEL", "KC_DELETE"], "⌦", "Delete"),
+    (&["KC_ENT", "KC_ENTER"], "⏎", "Enter"),
+    (&["KC_TAB"], "⇥", "Tab"),
+    (&["KC_SPC", "KC_SPACE"], "␣", "Space"),
+    (&["KC_ESC", "KC_ESCAPE"], "Esc", "Escape"),
+    (&["KC_CAPS", "KC_CAPS_LOCK"], "⇪", "Caps Lock"),
+    (&["KC_INS", "KC_INSERT"], "Ins", "Insert"),
+    (&["KC_HOME"], "Home", "Home"),
+    (&["KC_END"], "End", "End"),
+    (&["KC_PGUP", "KC_PAGE_UP"], "PgUp", "Page Up"),
+    (&["KC_PGDN", "KC_PAGE_DOWN"], "PgDn", "Page Down"),
+    (&["KC_LEFT"], "←", "Left arrow"),
+    (&["KC_DOWN"], "↓", "Down arrow"),
+    (&["KC_UP"], "↑", "Up arrow"),
+    (&["KC_RGHT", "KC_RIGHT"], "→", "Right arrow"),
+    (&["KC_PSCR", "KC_PRINT_SCREEN"], "PrtSc", "Print Screen"),
+    (&["KC_SCRL", "KC_SCROLL_LOCK"], "ScrLk", "Scroll Lock"),
+    (&["KC_PAUS", "KC_PAUSE", "KC_BRK"], "Pause", "Pause/Break"),
+    (
+        &["KC_APP", "KC_APPLICATION"],
+        "Menu",
+        "Application (context menu)",
+    ),
+    (&["KC_MINS", "KC_MINUS"], "-", "Minus"),
+    (&["KC_EQL", "KC_EQUAL"], "=", "Equals"),
+    (&["KC_LBRC", "KC_LEFT_BRACKET"], "[", "Left bracket"),
+    (&["KC_RBRC", "KC_RIGHT_BRACKET"], "]", "Right bracket"),
+    (&["KC_BSLS", "KC_BACKSLASH"], "\\", "Backslash"),
+    (&["KC_SCLN", "KC_SEMICOLON"], ";", "Semicolon"),
+    (&["KC_QUOT", "KC_QUOTE"], "'", "Quote"),
+    (&["KC_GRV", "KC_GRAVE"], "`", "Grave accent"),
+    (&["KC_COMM", "KC_COMMA"], ",", "Comma"),
+    (&["KC_DOT"], ".", "Period"),
+    (&["KC_SLSH", "KC_SLASH"], "/", "Slash"),
+    (&["KC_EXLM", "KC_EXCLAIM"], "!", "Exclamation mark"),
+    (&["KC_AT"], "@", "At sign"),
+    (&["KC_HASH"], "#", "Hash"),
+    (&["KC_DLR", "KC_DOLLAR"], "$", "Dollar sign"),
+    (&["KC_PERC", "KC_PERCENT"], "%", "Percent sign"),
+    (&["KC_CIRC", "KC_CIRCUMFLEX"], "^", "Caret"),
+    (&["KC_AMPR", "KC_AMPERSAND"], "&", "Ampersand"),
+    (&["KC_ASTR", "KC_ASTERISK"], "*", "Asterisk"),
+    (&["KC_LPRN", "KC_LEFT_PAREN"], "(", "Left parenthesis"),
+    (&["KC_RPRN", "KC_RIGHT_PAREN"], ")", "Right parenthesis"),
+    (&["KC_UNDS", "KC_UNDERSCORE"], "_", "Underscore"),
+    (&["KC_PLUS"], "+", "Plus"),
+    (&["KC_LCBR", "KC_LEFT_CURLY_BRACE"], "{", "Left brace"),
+    (&["KC_RCBR", "KC_RIGHT_CURLY_BRACE"], "}", "Right brace"),
+    (&["KC_PIPE"], "|", "Pipe"),
+    (&["KC_COLN", "KC_COLON"], ":", "Colon"),
+    (
+        &["KC_DQUO", "KC_DQT", "KC_DOUBLE_QUOTE"],
+        "\"",
+        "Double quote",
+    ),
+    (&["KC_TILD", "KC_TILDE"], "~", "Tilde"),
+    (
+        &["KC_LT", "KC_LABK", "KC_LEFT_ANGLE_BRACKET"],
+        "<",
+        "Less-than sign",
+    ),
+    (
+        &["KC_GT", "KC_RABK", "KC_RIGHT_ANGLE_BRACKET"],
+        ">",
+        "Greater-than sign",
+    ),
+    (&["KC_QUES", "KC_QUESTION"], "?", "Question mark"),
+    (&["KC_MUTE", "KC_AUDIO_MUTE"], "Mute", "Mute audio"),
+    (&["KC_VOLU", "KC_AUDIO_VOL_UP"], "Vol+", "Volume up"),
+    (&["KC_VOLD", "KC_AUDIO_VOL_DOWN"], "Vol-", "Volume down"),
+    (&["KC_MPLY", "KC_MEDIA_PLAY_PAUSE"], "⏯", "Play/pause"),
+    (&["KC_MNXT", "KC_MEDIA_NEXT_TRACK"], "⏭", "Next track"),
+    (&["KC_MPRV", "KC_MEDIA_PREV_TRACK"], "⏮", "Previous track"),
+    (
+        &["KC_BRIU", "KC_BRIGHTNESS_UP"],
+        "Bri+",
+        "Screen brightness up",
+    ),
+    (
+        &["KC_BRID", "KC_BRIGHTNESS_DOWN"],
+        "Bri-",
+        "Screen brightness down",
+    ),
+];
+
+/// Legends of the digits and punctuation paired with the US layout's shifted
+/// legend on the same key.
+const SHIFTED_LEGENDS: [(&str, &str); 21] = [
+    ("1", "!"),
+    ("2", "@"),
+    ("3", "#"),
+    ("4", "$"),
+    ("5", "%"),
+    ("6", "^"),
+    ("7", "&"),
+    ("8", "*"),
+    ("9", "("),
+    ("0", ")"),
+    ("-", "_"),
+    ("=", "+"),
+    ("[", "{"),
+    ("]", "}"),
+    ("\\", "|"),
+    (";", ":"),
+    ("'", "\""),
+    ("`", "~"),
+    (",", "<"),
+    (".", ">"),
+    ("/", "?"),
+];
+
+/// Looks up a basic keycode and its readable legend, e.g. `KC_A` → "A",
+/// `KC_LSFT` → "Shift", `KC_BSPC` → "⌫".
+///
+/// # Arguments
+///
+/// * `key` - The keycode as written in the keymap
+///
+/// # Returns
+///
+/// The keycode's legend and description, or `None` if it is not a basic
+/// keycode with a readable legend
+pub fn basic_keycode(key: &str) -> Option<KeycodeInfo> {
+    // Shifted digits and punctuation, e.g. `S(KC_1)`, show the symbol they type
+    if let Some(inner) = ["S(", "LSFT("]
+        .iter()
+        .find_map(|prefix| key.trim().strip_prefix(prefix)?.strip_suffix(')'))
+    {
+        let base = basic_keycode(inner)?;
+        if base.description == "Letter" {
+            return Some(base);
+        }
+        let shifted = SHIFTED_LEGENDS
+            .iter()
+            .find(|(unshifted, _)| *unshifted == base.legend)?
+            .1;
+        return BASIC_KEYCODES
+            .iter()
+            .find(|&&(_, legend, _)| legend == shifted)
+            .map(|&(_, legend, description)| KeycodeInfo {
+                legend,
+                description,
+            });
+    }
+
+    const LETTERS: [&str; 26] = [
+        "A", "B", "C", "D", "E", "F", "G", "H", "I", "J", "K", "L", "M", "N", "O", "P", "Q", "R",
+        "S", "T", "U", "V", "W", "X", "Y", "Z",
+    ];
+    const DIGITS: [&str; 10] = ["0", "1", "2", "3", "4", "5", "6", "7", "8", "9"];
+    const FUNCTION_KEYS: [&str; 24] = [
+        "F1", "F2", "F3", "F4", "F5", "F6", "F7", "F8", "F9", "F10", "F11", "F12", "F13", "F14",
+        "F15", "F16", "F17", "F18", "F19", "F20", "F21", "F22", "F23", "F24",
+    ];
+
+    let key = key.trim();
+    if let Some(name) = key.strip_prefix("KC_") {
+        let single = |table: &[&'static str], description| {
+            table
+                .iter()
+                .find(|&&legend| legend == name)
+                .map(|&legend| KeycodeInfo {
+                    legend,
+                    description,
+                })
+        };
+        let found = single(&LETTERS, "Letter")
+            .or_else(|| single(&DIGITS, "Digit"))
+            .or_else(|| single(&FUNCTION_KEYS, "Function key"));
+        if found.is_some() {
+            return found;
+        }
+    }
+
+    BASIC_KEYCODES
+        .iter()
+        .find(|(names, _, _)| names.contains(&key))
+        .map(|&(_, legend, description)| KeycodeInfo {
+            legend,
+            description,
+        })
+}
+
+/// Modifier keycodes, by every name QMK accepts.
+const MODIFIER_KEYCODES: [&str; 28] = [
+    "KC_LSFT",
+    "KC_LEFT_SHIFT",
+    "KC_RSFT",
+    "KC_RIGHT_SHIFT",
+    "KC_LCTL",
+    "KC_LEFT_CTRL",
+    "KC_RCTL",
+    "KC_RIGHT_CTRL",
+    "KC_LALT",
+    "KC_LEFT_ALT",
+    "KC_LOPT",
+    "KC_RALT",
+    "KC_RIGHT_ALT",
+    "KC_ROPT",
+    "KC_ALGR",
+    "KC_LGUI",
+    "KC_LEFT_GUI",
+    "KC_LCMD",
+    "KC_LWIN",
+    "KC_RGUI",
+    "KC_RIGHT_GUI",
+    "KC_RCMD",
+    "KC_RWIN",
+    "KC_MEH",
+    "KC_HYPR",
+    "KC_HYPER",
+    "KC_LCAG",
+    "KC_RCAG",
+];
+
+/// Returns `true` if `key` is a modifier or a one-shot modifier (`OSM()`).
+pub fn is_modifier(key: &str) -> bool {
+    let key = key.trim();
+    MODIFIER_KEYCODES.contains(&key) || key.starts_with("OSM(")
+}
+
+/// Names of the key that locks the current layer on: QMK's Layer Lock and the
+/// common userspace `LLOCK` define.
+const LAYER_LOCK_KEYCODES: [&str; 4] = ["QK_LAYER_LOCK", "QK_LLCK", "LLOCK", "LAYER_LOCK"];
+
+/// Returns `true` if `key` is a layer lock keycode.
+pub fn is_layer_lock(key: &str) -> bool {
+    LAYER_LOCK_KEYCODES.contains(&key.trim())
+}
+
+/// A user-supplied legend for one keycode.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct LegendOverride {
+    /// Legend drawn on the key instead of the keycode
+    pub legend: String,
+    /// Optional tooltip describing the key
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+}
+
+/// Legend overrides keyed by keycode, e.g. `MY_MACRO = { legend = "Macro" }`.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct LegendOverrides {
+    #[serde(default)]
+    pub legends: BTreeMap<String, LegendOverride>,
+}
+
+/// Readable names of QMK modifier masks used by `LM()`.
+const MODIFIER_NAMES: [(&str, &str); 13] = [
+    ("MOD_LCTL", "Ctrl"),
+    ("MOD_LSFT", "Shift"),
+    ("MOD_LALT", "Alt"),
+    ("MOD_LGUI", "Gui"),
+    ("MOD_RCTL", "RCtrl"),
+    ("MOD_RSFT", "RShift"),
+    ("MOD_RALT", "AltGr"),
+    ("MOD_RGUI", "RGui"),
+    ("MOD_MEH", "Meh"),
+    ("MOD_HYPR", "Hyper"),
+    ("MOD_LCAG", "Ctrl+Alt+Gui"),
+    ("MOD_LSAG", "Shift+Alt+Gui"),
+    ("MOD_RCAG", "RCtrl+AltGr+RGui"),
+];
+
+/// A layer activated together with modifiers, as written with `LM(layer, mods)`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LayerMod {
+    /// The layer held while the key is down
+    pub layer: usize,
+    /// Readable modifier names, e.g. `["Ctrl", "Shift"]`; unknown masks are
+    /// kept as written
+    pub modifiers: Vec<String>,
+}
+
+impl LayerMod {
+    /// Short legend drawn on the key, e.g. `L2+Shift`.
+    pub fn legend(&self) -> String {
+        let mut legend = format!("L{}", self.layer);
+        for modifier in &self.modifiers {
+            legend.push('+');
+            legend.push_str(modifier);
+        }
+        legend
+    }
+
+    /// Tooltip describing the key.
+    pub fn description(&self) -> String {
+        format!(
+            "Hold layer {} with {} held",
+            self.layer,
+            self.modifiers.join("+")
+        )
+    }
+}
+
+/// Parses an `LM(layer, mods)` keycode.
+///
+/// # Arguments
+///
+/// * `key` - The keycode as written in the keymap
+///
+/// # Returns
+///
+/// The layer and modifiers, or `None` if `key` is not an `LM()` keycode
+pub fn layer_mod(key: &str) -> Option<LayerMod> {
+    let args = key.trim().strip_prefix("LM(")?.strip_suffix(')')?;
+    let (layer, mods) = args.split_once(',')?;
+    let modifiers = mods
+        .split('|')
+        .map(str::trim)
+        .filter(|m| !m.is_empty())
+        .map(|m| {
+            MODIFIER_NAMES
+                .iter()
+                .find(|(name, _)| *name == m)
+                .map_or(m.to_string(), |(_, readable)| readable.to_string())
+        })
+        .collect();
+
+    Some(LayerMod {
+        layer: layer.trim().parse().ok()?,
+        modifiers,
+    })
+}
+
+/// Mod-tap keycodes that hold a fixed modifier, e.g. `LSFT_T(KC_A)`, with
+/// the modifier's readable name.
+const MOD_TAPS: [(&str, &str); 29] = [
+    ("LCTL_T", "Ctrl"),
+    ("CTL_T", "Ctrl"),
+    ("LSFT_T", "Shift"),
+    ("SFT_T", "Shift"),
+    ("LALT_T", "Alt"),
+    ("ALT_T", "Alt"),
+    ("LOPT_T", "Alt"),
+    ("OPT_T", "Alt"),
+    ("LGUI_T", "Gui"),
+    ("GUI_T", "Gui"),
+    ("LCMD_T", "Gui"),
+    ("CMD_T", "Gui"),
+    ("LWIN_T", "Gui"),
+    ("WIN_T", "Gui"),
+    ("RCTL_T", "RCtrl"),
+    ("RSFT_T", "RShift"),
+    ("RALT_T", "AltGr"),
+    ("ROPT_T", "AltGr"),
+    ("RGUI_T", "RGui"),
+    ("RCMD_T", "RGui"),
+    ("RWIN_T", "RGui"),
+    ("C_S_T", "Ctrl+Shift"),
+    ("LCS_T", "Ctrl+Shift"),
+    ("LCA_T", "Ctrl+Alt"),
+    ("LSA_T", "Shift+Alt"),
+    ("LCAG_T", "Ctrl+Alt+Gui"),
+    ("MEH_T", "Meh"),
+    ("HYPR_T", "Hyper"),
+    ("ALL_T", "Hyper"),
+];
+
+/// What a key does when tapped and, for dual-role keys, when held.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KeyAction {
+    /// The keycode sent on a tap
+    pub tap: String,
+    /// Short name of what holding the key does, e.g. `Shift` or `L2`, for
+    /// layer-tap and mod-tap keys
+    pub hold: Option<String>,
+}
+
+/// Splits a layer-tap or mod-tap keycode into its tap and hold actions.
+///
+/// # Arguments
+///
+/// * `key` - The keycode as written in the keymap, e.g. `LT(2, KC_SPC)`,
+///   `LSFT_T(KC_A)`, or `MT(MOD_LCTL | MOD_LSFT, KC_ESC)`
+///
+/// # Returns
+///
+/// The tap keycode with the hold action (`L2`, or the layer's name without
+/// leading underscores, for layer-taps; the modifiers for mod-taps), or the
+/// key itself without a hold action for any other keycode
+pub fn key_action(key: &str) -> KeyAction {
+    let key = key.trim();
+    let plain = || KeyAction {
+        tap: key.to_string(),
+        hold: None,
+    };
+    let Some((function, args)) = key.split_once('(') else {
+        return plain();
+    };
+    let Some(args) = args.strip_suffix(')') else {
+        return plain();
+    };
+
+    let hold = match function.trim() {
+        "LT" | "MT" => {
+            let Some((hold, tap)) = args.split_once(',') else {
+                return plain();
+            };
+            let hold = hold.trim();
+            let hold = if function.trim() == "LT" {
+                match hold.parse::<usize>() {
+                    Ok(layer) => format!("L{}", layer),
+                    Err(_) => hold.trim_start_matches('_').to_string(),
+                }
+            } else {
+                hold.split('|')
+                    .map(str::trim)
+                    .map(|m| {
+                        MODIFIER_NAMES
+                            .iter()
+                            .find(|(name, _)| *name == m)
+                            .map_or(m.to_string(), |(_, readable)| readable.to_string())
+                    })
+                    .collect::<Vec<_>>()
+                    .join("+")
+            };
+            return KeyAction {
+                tap: tap.trim().to_string(),
+                hold: Some(hold),
+            };
+        }
+        function => match MOD_TAPS.iter().find(|(name, _)| *name == function) {
+            Some((_, modifier)) => modifier.to_string(),
+            None => return plain(),
+        },
+    };
+    KeyAction {
+        tap: args.trim().to_string(),
+        hold: Some(hold),
+    }
+}
+
+/// Keycode prefixes dropped from legends that don't fit their key.
+const KEYCODE_PREFIXES: [&str; 2] = ["KC_", "QK_"];
+
+/// Shortens a legend by dropping the `KC_` and `QK_` prefixes of the
+/// keycodes in it, e.g. `C(KC_PGDN)` to `C(PGDN)`.
+///
+/// # Arguments
+///
+/// * `legend` - A legend too wide for its key
+///
+/// # Returns
+///
+/// The shortened legend, or `None` if it has no prefixes to drop
+pub fn abbreviate(legend: &str) -> Option<String> {
+    let mut short = String::with_capacity(legend.len());
+    let mut rest = legend;
+    let mut word_start = true;
+    while let Some(c) = rest.chars().next() {
+        if word_start
+            && let Some(prefix) = KEYCODE_PREFIXES
+                .iter()
+                .find(|prefix| rest.len() > prefix.len() && rest.starts_with(**prefix))
+        {
+            rest = &rest[prefix.len()..];
+            word_start = false;
+            continue;
+        }
+        short.push(c);
+        word_start = !(c.is_alphanumeric() || c == '_');
+        rest = &rest[c.len_utf8()..];
+    }
+    (short != legend).then_some(short)
+}
@@ -0,0 +1,121 @@
+//! Parsing of the JSON keymaps written by QMK Configurator and `qmk c2json`.
+use crate::Layer;
+use crate::encoder::EncoderBinding;
+use crate::geometry::Geometry;
+use anyhow::{Context, Result, bail};
+use serde::Deserialize;
+use serde_json::Value;
+
+/// The parts of a QMK `keymap.json` that describe the layers.
+#[derive(Debug, Deserialize)]
+struct KeymapJson {
+    /// The LAYOUT macro the layers are written for
+    #[serde(default)]
+    layout: Option<String>,
+    /// One flat list of keycodes per layer
+    layers: Vec<Vec<String>>,
+    /// One list of encoder bindings per layer
+    #[serde(default)]
+    encoders: Vec<Vec<EncoderJson>>,
+}
+
+#[derive(Debug, Deserialize)]
+struct EncoderJson {
+    ccw: String,
+    cw: String,
+}
+
+/// Unwraps the `ANY(...)` wrapper that keymap.json uses for keycodes QMK
+/// Configurator doesn't know, such as custom keycodes.
+fn unwrap_any(key: &str) -> String {
+    let key = key.trim();
+    key.strip_prefix("ANY(")
+        .and_then(|rest| rest.strip_suffix(')'))
+        .unwrap_or(key)
+        .trim()
+        .to_string()
+}
+
+/// Splits a layer's flat key list into the rows of the built-in board with
+/// the same number of keys, preferring one written with the same LAYOUT
+/// macro, or keeps the keys on one row if no board matches.
+pub(crate) fn split_rows(keys: Vec<String>, layout_macro: Option<&str>) -> Vec<Vec<String>> {
+    let candidates: Vec<Geometry> = Geometry::builtins()
+        .into_iter()
+        .filter(|geometry| geometry.key_count() == keys.len())
+        .collect();
+    let geometry = candidates
+        .iter()
+        .find(|geometry| {
+            layout_macro.is_some_and(|m| geometry.layout_macros.iter().any(|known| known == m))
+        })
+        .or(candidates.first());
+
+    let Some(geometry) = geometry else {
+        return vec![keys];
+    };
+    let mut keys = keys.into_iter();
+    geometry
+        .row_lengths()
+        .into_iter()
+        .map(|len| keys.by_ref().take(len).collect())
+        .collect()
+}
+
+/// Parses a QMK `keymap.json`, as exported by QMK Configurator or
+/// `qmk c2json`, into the same layers [`crate::parse_layers`] reads from a
+/// keymap.c.
+///
+/// Keys are split into the rows of the built-in board with the same number
+/// of keys, and encoder bindings are attached to their layers. The JSON has
+/// no layer names, so the layers are numbered only.
+///
+/// # Arguments
+///
+/// * `content` - The keymap.json contents
+///
+/// # Returns
+///
+/// The layers in order, or an error if the JSON is not a QMK keymap
+pub fn parse_json_keymap(content: &str) -> Result<Vec<Layer>> {
+    let root: Value = serde_json::from_str(content).context("Invalid JSON")?;
+    if root.get("layout").is_some_and(Value::is_object) || root.get("data").is_some() {
+        bail!(
+            "This looks like a ZSA Oryx export rather than a QMK keymap.json; \
+             convert it with `keyball44-viz import oryx`"
+        );
+    }
+    let keymap: KeymapJson = serde_json::from_value(root).context("Invalid QMK keymap.json")?;
+    if keymap.layers.is_empty() {
+        bail!("The keymap.json has no layers");
+    }
+
+    let layout_macro = keymap.layout.as_deref();
+    let mut encoders = keymap.encoders.into_iter();
+    Ok(keymap
+        .layers
+        .into_iter()
+        .enumerate()
+        .map(|(index, keys)| {
+            let keys = keys.iter().map(|key| unwrap_any(key)).collect();
+            Layer {
+                index,
+                name: None,
+                title: None,
+                keys: split_rows(keys, layout_macro),
+                notes: Vec::new(),
+                layout_macro: keymap.layout.clone(),
+                source: None,
+                encoders: encoders
+                    .next()
+                    .unwrap_or_default()
+                    .into_iter()
+                    .map(|encoder| EncoderBinding {
+                        ccw: unwrap_any(&encoder.ccw),
+                        cw: unwrap_any(&encoder.cw),
+                    })
+                    .collect(),
+            }
+        })
+        .collect())
+}
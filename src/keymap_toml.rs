@@ -0,0 +1,145 @@
+//! An editable TOML form of the layers, for changing legends by hand without
+//! touching firmware source.
+use crate::Layer;
+use crate::encoder::EncoderBinding;
+use anyhow::{Result, anyhow, bail};
+use serde::Deserialize;
+
+/// The layers of a TOML keymap.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct KeymapToml {
+    layers: Vec<LayerToml>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct LayerToml {
+    /// The layer number; the position in the file when left out
+    #[serde(default)]
+    index: Option<usize>,
+    #[serde(default)]
+    name: Option<String>,
+    #[serde(default)]
+    layout_macro: Option<String>,
+    #[serde(default)]
+    notes: Vec<String>,
+    keys: Vec<Vec<String>>,
+    #[serde(default)]
+    encoders: Vec<EncoderToml>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct EncoderToml {
+    ccw: String,
+    cw: String,
+}
+
+/// Quotes a string as a TOML basic string.
+fn quote(text: &str) -> String {
+    toml::Value::String(text.to_string()).to_string()
+}
+
+/// Writes the layers as TOML with one line per row of keys, so the file reads
+/// like the board when edited by hand.
+///
+/// # Arguments
+///
+/// * `layers` - The parsed keymap layers
+///
+/// # Returns
+///
+/// The TOML document, which [`parse_toml_keymap`] reads back into the same
+/// layers
+pub fn to_toml(layers: &[Layer]) -> String {
+    let mut toml = String::from(
+        "# Layers exported by `keyball44-viz export toml`. Edit the legends and\n\
+         # render with `keyball44-viz --from-toml FILE`.\n",
+    );
+    for layer in layers {
+        toml.push_str("\n[[layers]]\n");
+        toml.push_str(&format!("index = {}\n", layer.index));
+        if let Some(name) = &layer.name {
+            toml.push_str(&format!("name = {}\n", quote(name)));
+        }
+        if let Some(layout_macro) = &layer.layout_macro {
+            toml.push_str(&format!("layout_macro = {}\n", quote(layout_macro)));
+        }
+        if !layer.notes.is_empty() {
+            let notes: Vec<String> = layer.notes.iter().map(|note| quote(note)).collect();
+            toml.push_str(&format!("notes = [{}]\n", notes.join(", ")));
+        }
+        toml.push_str("keys = [\n");
+        for row in &layer.keys {
+            let keys: Vec<String> = row.iter().map(|key| quote(key)).collect();
+            toml.push_str(&format!("    [{}],\n", keys.join(", ")));
+        }
+        toml.push_str("]\n");
+        if !layer.encoders.is_empty() {
+            let encoders: Vec<String> = layer
+                .encoders
+                .iter()
+                .map(|e| format!("{{ ccw = {}, cw = {} }}", quote(&e.ccw), quote(&e.cw)))
+                .collect();
+            toml.push_str(&format!("encoders = [{}]\n", encoders.join(", ")));
+        }
+    }
+    toml
+}
+
+/// Parses the TOML written by [`to_toml`], possibly edited by hand, into the
+/// same layers [`crate::parse_layers`] reads from a keymap.c.
+///
+/// # Arguments
+///
+/// * `content` - The TOML document
+///
+/// # Returns
+///
+/// The layers sorted by index, or an error if the document has no layers, a
+/// layer has no keys, or two layers share an index
+pub fn parse_toml_keymap(content: &str) -> Result<Vec<Layer>> {
+    let keymap: KeymapToml = toml::from_str(content).map_err(|err| anyhow!("{}", err))?;
+    if keymap.layers.is_empty() {
+        bail!("No layers found in the TOML keymap");
+    }
+
+    let mut layers: Vec<Layer> = keymap
+        .layers
+        .into_iter()
+        .enumerate()
+        .map(|(position, layer)| Layer {
+            index: layer.index.unwrap_or(position),
+            name: layer.name,
+            title: None,
+            keys: layer.keys,
+            notes: layer.notes,
+            layout_macro: layer.layout_macro,
+            source: None,
+            encoders: layer
+                .encoders
+                .into_iter()
+                .map(|e| EncoderBinding {
+                    ccw: e.ccw,
+                    cw: e.cw,
+                })
+                .collect(),
+        })
+        .collect();
+    layers.sort_by_key(|layer| layer.index);
+
+    if let Some(layer) = layers
+        .iter()
+        .find(|layer| layer.keys.iter().all(Vec::is_empty))
+    {
+        bail!("Layer {} has no keys", layer.index);
+    }
+    if let Some(pair) = layers
+        .windows(2)
+        .find(|pair| pair[0].index == pair[1].index)
+    {
+        bail!("Layer {} is defined twice", pair[0].index);
+    }
+    Ok(layers)
+}
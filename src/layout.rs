@@ -0,0 +1,447 @@
+//! Physical key placement driven by a board `Geometry`.
+use crate::geometry::{ControlKind, Geometry, Half, KeyArea, KeyPosition};
+use crate::{EmptyKeys, Layer, is_empty_key, keycodes};
+use anyhow::{Result, bail};
+use std::collections::BTreeMap;
+
+pub(crate) const CHAR_WIDTH: f32 = 7.0; // Approximate width per character in monospace font
+pub(crate) const KEY_PADDING: f32 = 10.0; // Padding inside the key
+
+/// Sizes and gaps (in pixels) used to place keys and layers.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LayoutMetrics {
+    /// Key height, which is also the minimum key width
+    pub key_size: f32,
+    /// Gap between neighboring keys
+    pub spacing: f32,
+    /// Gap between the left and right halves
+    pub split_gap: f32,
+    /// Vertical gap between consecutive layers, including room for the title
+    pub layer_spacing: f32,
+    /// Outer margin around the document
+    pub margin: f32,
+}
+
+impl Default for LayoutMetrics {
+    fn default() -> Self {
+        LayoutMetrics {
+            key_size: 60.0,
+            spacing: 5.0,
+            split_gap: 40.0,
+            layer_spacing: 120.0,
+            margin: 20.0,
+        }
+    }
+}
+
+impl LayoutMetrics {
+    /// Allowed `(min, max)` range of each metric, by name.
+    pub const BOUNDS: [(&'static str, f32, f32); 5] = [
+        ("key-size", 20.0, 200.0),
+        ("spacing", 0.0, 50.0),
+        ("split-gap", 0.0, 500.0),
+        ("layer-spacing", 40.0, 1000.0),
+        ("margin", 0.0, 200.0),
+    ];
+
+    /// Checks that every metric is finite and within `BOUNDS`.
+    ///
+    /// Values outside these ranges produce overlapping keys, clipped titles,
+    /// or documents too large for most viewers.
+    pub fn validate(&self) -> Result<()> {
+        let values = [
+            self.key_size,
+            self.spacing,
+            self.split_gap,
+            self.layer_spacing,
+            self.margin,
+        ];
+
+        for ((name, min, max), value) in Self::BOUNDS.iter().zip(values) {
+            if !value.is_finite() || value < *min || value > *max {
+                bail!(
+                    "{} must be between {} and {}, got {}",
+                    name,
+                    min,
+                    max,
+                    value
+                );
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Height of one row of keys including the gap below it.
+    pub(crate) fn row_pitch(&self) -> f32 {
+        self.key_size + self.spacing
+    }
+}
+
+/// Calculates the key width needed to fit the longest label in `layers`.
+pub(crate) fn compute_key_width(layers: &[Layer], metrics: &LayoutMetrics) -> f32 {
+    let max_label_len = layers
+        .iter()
+        .flat_map(|l| l.keys.iter())
+        .flat_map(|row| row.iter())
+        .map(|key| keycodes::layer_mod(key).map_or(key.len(), |lm| lm.legend().len()))
+        .max()
+        .unwrap_or(8);
+
+    (max_label_len as f32 * CHAR_WIDTH + KEY_PADDING * 2.0).max(metrics.key_size)
+}
+
+/// Screen-space rectangle occupied by a key.
+#[derive(Debug, Clone, Copy)]
+pub struct KeyBox {
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+}
+
+impl KeyBox {
+    /// The same rectangle moved down by `dy`.
+    pub fn offset_y(self, dy: f32) -> KeyBox {
+        KeyBox {
+            y: self.y + dy,
+            ..self
+        }
+    }
+}
+
+/// A key placed on the board.
+#[derive(Debug, Clone)]
+pub struct PlacedKey<'a> {
+    /// Index of the layer the key belongs to
+    pub layer: usize,
+    /// Physical row of the key in the geometry
+    pub row: usize,
+    /// Position of the key within its physical row, counting both halves
+    pub column: usize,
+    /// Index of the key among the layer's LAYOUT arguments
+    pub index: usize,
+    pub label: &'a str,
+    /// The key's main rectangle, relative to the top of the layer's first row
+    pub bounds: KeyBox,
+    /// Rectangle joined with `bounds` for non-rectangular keys
+    pub secondary: Option<KeyBox>,
+    /// Whether the key is an empty key drawn as an outline without a legend
+    pub ghost: bool,
+    /// Clockwise rotation in degrees about the center of `bounds`
+    pub rotation: f32,
+}
+
+impl<'a> PlacedKey<'a> {
+    /// The legend drawn on the key.
+    pub fn legend(&self) -> &str {
+        if self.ghost { "" } else { self.label }
+    }
+
+    /// The same key moved down by `dy`.
+    pub fn offset_y(&self, dy: f32) -> PlacedKey<'a> {
+        PlacedKey {
+            bounds: self.bounds.offset_y(dy),
+            secondary: self.secondary.map(|b| b.offset_y(dy)),
+            ..self.clone()
+        }
+    }
+}
+
+/// Horizontal offset of a key position, including the split gap for
+/// right-half keys.
+fn position_x(x: f32, half: Half, unit: f32, metrics: &LayoutMetrics) -> f32 {
+    let gap = match half {
+        Half::Left => 0.0,
+        Half::Right => metrics.split_gap,
+    };
+    metrics.margin + x * unit + gap
+}
+
+/// Converts a rectangle in key units to pixels; sizes leave out the spacing
+/// after the last unit so a 1u key is exactly `key_width` by `key_size`.
+fn unit_box(
+    x: f32,
+    y: f32,
+    w: f32,
+    h: f32,
+    half: Half,
+    unit: f32,
+    metrics: &LayoutMetrics,
+) -> KeyBox {
+    KeyBox {
+        x: position_x(x, half, unit, metrics),
+        y: y * metrics.row_pitch(),
+        width: w * unit - metrics.spacing,
+        height: h * metrics.row_pitch() - metrics.spacing,
+    }
+}
+
+/// Pixel rectangle of a key's secondary area.
+///
+/// Where the area abuts the key's main rectangle it is stretched across the
+/// spacing between them, so the two rectangles join into one shape.
+fn secondary_box(p: &KeyPosition, area: &KeyArea, unit: f32, metrics: &LayoutMetrics) -> KeyBox {
+    let mut b = unit_box(
+        p.x + area.x,
+        p.y + area.y,
+        area.w,
+        area.h,
+        p.half,
+        unit,
+        metrics,
+    );
+    let touches = |a: f32, b: f32| (a - b).abs() < 1e-3;
+
+    if touches(area.x, p.w) {
+        b.x -= metrics.spacing;
+        b.width += metrics.spacing;
+    }
+    if touches(area.x + area.w, 0.0) {
+        b.width += metrics.spacing;
+    }
+    if touches(area.y, p.h) {
+        b.y -= metrics.spacing;
+        b.height += metrics.spacing;
+    }
+    if touches(area.y + area.h, 0.0) {
+        b.height += metrics.spacing;
+    }
+    b
+}
+
+/// Total document width for keys of the given width.
+pub(crate) fn svg_width(key_width: f32, geometry: &Geometry, metrics: &LayoutMetrics) -> f32 {
+    let unit = key_width + metrics.spacing;
+    let controls = geometry
+        .controls
+        .iter()
+        .map(|c| position_x(c.x + c.size, c.half, unit, metrics));
+    let right_edge = geometry
+        .positions()
+        .map(|(_, p)| position_x(p.right(), p.half, unit, metrics))
+        .chain(controls)
+        .fold(metrics.margin, f32::max);
+    right_edge + metrics.margin
+}
+
+/// Bounding box of each half's keys on a split board, relative to the top of
+/// the first row, or nothing if the board isn't split.
+pub(crate) fn half_bounds(
+    geometry: &Geometry,
+    key_width: f32,
+    metrics: &LayoutMetrics,
+) -> Vec<(Half, KeyBox)> {
+    let unit = key_width + metrics.spacing;
+    let mut bounds = Vec::new();
+
+    for half in [Half::Left, Half::Right] {
+        let (mut left, mut top) = (f32::MAX, f32::MAX);
+        let (mut right, mut bottom) = (f32::MIN, f32::MIN);
+        for (_, p) in geometry.positions().filter(|(_, p)| p.half == half) {
+            left = left.min(p.x);
+            top = top.min(p.y);
+            right = right.max(p.right());
+            bottom = bottom.max(p.bottom());
+        }
+        if left <= right {
+            bounds.push((
+                half,
+                unit_box(left, top, right - left, bottom - top, half, unit, metrics),
+            ));
+        }
+    }
+
+    if bounds.len() < 2 {
+        bounds.clear();
+    }
+    bounds
+}
+
+/// Height of a layer's keys, including the gap below the last row.
+pub(crate) fn keys_height(geometry: &Geometry, metrics: &LayoutMetrics) -> f32 {
+    geometry.height() * metrics.row_pitch()
+}
+
+/// Places every drawn key of `layer` on the board.
+///
+/// Keys are matched to geometry positions by their LAYOUT argument order.
+/// Keys beyond the geometry continue to the right of its last position. Empty
+/// keys are omitted or ghosted according to `empty_keys`, the same way on
+/// every row and half.
+pub(crate) fn place_keys<'a>(
+    layer: &'a Layer,
+    geometry: &Geometry,
+    key_width: f32,
+    metrics: &LayoutMetrics,
+    empty_keys: EmptyKeys,
+) -> Vec<PlacedKey<'a>> {
+    let unit = key_width + metrics.spacing;
+    let mut positions = geometry.positions();
+    let mut last: Option<(usize, KeyPosition)> = None;
+    // Keys placed so far on each row, which numbers the next key's column
+    let mut row_keys: BTreeMap<usize, usize> = BTreeMap::new();
+    let mut placed = Vec::new();
+
+    for (index, label) in layer.keys.iter().flatten().enumerate() {
+        let (row, position) = match positions.next() {
+            Some((row, p)) => (row, p.clone()),
+            None => {
+                // Continue to the right of the last key with 1u keys
+                let Some((row, previous)) = &last else {
+                    break;
+                };
+                (
+                    *row,
+                    KeyPosition::new(previous.right(), previous.y, previous.half),
+                )
+            }
+        };
+        last = Some((row, position.clone()));
+
+        let next_column = row_keys.entry(row).or_insert(0);
+        let column = *next_column;
+        *next_column += 1;
+
+        let empty = is_empty_key(label);
+        if empty && empty_keys == EmptyKeys::Hide {
+            continue;
+        }
+
+        let p = &position;
+        placed.push(PlacedKey {
+            layer: layer.index,
+            row,
+            column,
+            index,
+            label,
+            bounds: unit_box(p.x, p.y, p.w, p.h, p.half, unit, metrics),
+            secondary: p
+                .secondary
+                .as_ref()
+                .map(|area| secondary_box(p, area, unit, metrics)),
+            ghost: empty && empty_keys == EmptyKeys::Ghost,
+            rotation: p.r,
+        });
+    }
+
+    placed
+}
+
+/// A trackball or encoder placed on the board.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct PlacedControl {
+    /// Index among the board's encoders, which picks the layer's binding, or
+    /// `None` for a trackball
+    pub encoder: Option<usize>,
+    pub cx: f32,
+    /// Center relative to the top of the layer's first row
+    pub cy: f32,
+    pub radius: f32,
+}
+
+/// Places the board's trackballs and encoders, each as the largest circle
+/// fitting its square of key units.
+pub(crate) fn place_controls(
+    geometry: &Geometry,
+    key_width: f32,
+    metrics: &LayoutMetrics,
+) -> Vec<PlacedControl> {
+    let unit = key_width + metrics.spacing;
+    let mut encoders = 0..;
+    geometry
+        .controls
+        .iter()
+        .map(|c| {
+            let b = unit_box(c.x, c.y, c.size, c.size, c.half, unit, metrics);
+            PlacedControl {
+                encoder: match c.kind {
+                    ControlKind::Encoder => encoders.next(),
+                    ControlKind::Trackball => None,
+                },
+                cx: b.x + b.width / 2.0,
+                cy: b.y + b.height / 2.0,
+                radius: b.width.min(b.height) / 2.0,
+            }
+        })
+        .collect()
+}
+
+/// Outline of the union of axis-aligned rectangles, as a closed polygon.
+///
+/// The plane is cut into cells along every rectangle edge; cell edges with a
+/// covered cell on one side only form the outline, which is then walked
+/// clockwise and stripped of collinear points.
+pub(crate) fn union_outline(rects: &[KeyBox]) -> Vec<(f32, f32)> {
+    let mut xs: Vec<f32> = rects.iter().flat_map(|r| [r.x, r.x + r.width]).collect();
+    let mut ys: Vec<f32> = rects.iter().flat_map(|r| [r.y, r.y + r.height]).collect();
+    for values in [&mut xs, &mut ys] {
+        values.sort_by(f32::total_cmp);
+        values.dedup();
+    }
+
+    let covered = |i: usize, j: usize| {
+        let (cx, cy) = ((xs[i] + xs[i + 1]) / 2.0, (ys[j] + ys[j + 1]) / 2.0);
+        rects
+            .iter()
+            .any(|r| cx > r.x && cx < r.x + r.width && cy > r.y && cy < r.y + r.height)
+    };
+    let cols = xs.len().saturating_sub(1);
+    let rows = ys.len().saturating_sub(1);
+    let is_covered = |i: isize, j: isize| {
+        i >= 0
+            && j >= 0
+            && (i as usize) < cols
+            && (j as usize) < rows
+            && covered(i as usize, j as usize)
+    };
+
+    // Directed boundary edges between grid points, clockwise on screen
+    let mut edges: Vec<((usize, usize), (usize, usize))> = Vec::new();
+    for i in 0..cols {
+        for j in 0..rows {
+            if !covered(i, j) {
+                continue;
+            }
+            let (ii, jj) = (i as isize, j as isize);
+            if !is_covered(ii, jj - 1) {
+                edges.push(((i, j), (i + 1, j)));
+            }
+            if !is_covered(ii + 1, jj) {
+                edges.push(((i + 1, j), (i + 1, j + 1)));
+            }
+            if !is_covered(ii, jj + 1) {
+                edges.push(((i + 1, j + 1), (i, j + 1)));
+            }
+            if !is_covered(ii - 1, jj) {
+                edges.push(((i, j + 1), (i, j)));
+            }
+        }
+    }
+
+    let Some(&(start, _)) = edges.first() else {
+        return Vec::new();
+    };
+    let mut points = vec![start];
+    let mut current = start;
+    while let Some(&(_, next)) = edges.iter().find(|(from, _)| *from == current) {
+        if next == start {
+            break;
+        }
+        points.push(next);
+        current = next;
+        if points.len() > edges.len() {
+            break;
+        }
+    }
+
+    // Drop points in the middle of straight runs
+    let n = points.len();
+    (0..n)
+        .filter(|&k| {
+            let (prev, point, next) = (points[(k + n - 1) % n], points[k], points[(k + 1) % n]);
+            !((prev.0 == point.0 && point.0 == next.0) || (prev.1 == point.1 && point.1 == next.1))
+        })
+        .map(|k| (xs[points[k].0], ys[points[k].1]))
+        .collect()
+}
@@ -1,22 +1,206 @@
 //! A CLI tool that parses QMK keymap.c files for the Keyball44 keyboard and
 //! generates an SVG visualization of all layers with color-coded keys.
 use anyhow::Result;
+use clap::ValueEnum;
+use encoder::{EncoderBinding, parse_encoder_map};
+use layout::{
+    CHAR_WIDTH, KEY_PADDING, KeyBox, compute_key_width, half_bounds, keys_height, place_controls,
+    place_keys, svg_width, union_outline,
+};
+use parse_error::{ParseError, ParseErrorKind, SourceLines};
 use regex::Regex;
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::sync::Arc;
 use svg::{
-    node::element::{Definitions, LinearGradient, Rectangle, Stop, Style, Text},
-    Document,
+    Document, Node,
+    node::element::{
+        Circle, Definitions, Group, LinearGradient, Path, Rectangle, Stop, Style, Text, Title, Use,
+        path::Data,
+    },
 };
 
+pub mod analysis;
+pub mod autoshift;
+pub mod combos;
+pub mod config;
+pub mod custom_keycodes;
+pub mod defines;
+pub mod diff;
+pub mod emit;
+pub mod encoder;
+pub mod firmware;
+pub mod format;
+pub mod geometry;
+pub mod geometry_cache;
+pub mod git;
+pub mod groups;
+pub mod heatmap;
+pub mod history;
+pub mod i18n;
+pub mod icons;
+pub mod info_json;
+pub mod keycodes;
+pub mod keymap_json;
+pub mod keymap_toml;
+pub mod layout;
+pub mod mock;
+pub mod notes;
+pub mod oryx;
+pub mod output;
+pub mod parse_error;
+pub mod publish;
+pub mod qr;
+pub mod raster;
+pub mod redact;
+pub mod rules;
+pub mod scale;
+pub mod tabs;
+pub mod tap_dance;
+pub mod theme;
+pub mod zmk;
+
+pub use geometry::Geometry;
+use geometry::Half;
+pub use layout::{LayoutMetrics, PlacedKey};
+pub use theme::Theme;
+use theme::{Fill, Gradient, LegendKind};
+
 /// Represents a single keymap layer in the keyboard layout.
 ///
 /// Each layer contains an index identifier and a 2D grid of key labels,
 /// where each inner vector represents a row of keys on the keyboard.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize)]
 pub struct Layer {
     /// The layer number/identifier (e.g., 0 for base layer, 1 for first modifier layer)
     pub index: usize,
+    /// The layer's symbolic name (e.g., `_NAV`) when one is given in the source
+    pub name: Option<String>,
+    /// A display name replacing `name` in titles, e.g. from a notes file.
+    /// Designators such as `MO(_NAV)` keep resolving through `name`.
+    pub title: Option<String>,
     /// A 2D vector representing rows and columns of key labels on this layer
     pub keys: Vec<Vec<String>>,
+    /// Free-form notes from `// viz: layer=... note="..."` comments
+    pub notes: Vec<String>,
+    /// The LAYOUT macro the layer was written with (e.g. `LAYOUT_universal`)
+    pub layout_macro: Option<String>,
+    /// Where the layer is written, when parsed from a keymap.c
+    #[serde(skip)]
+    pub source: Option<SourceLines>,
+    /// Encoder bindings from the `encoder_map` array, one per encoder
+    pub encoders: Vec<EncoderBinding>,
+}
+
+/// Matches the line declaring the `keymaps` array.
+///
+/// The match is anchored to the exact `keymaps` identifier, so other arrays
+/// declared alongside it (e.g. `encoder_map` or `keymaps_backup`) are never
+/// mistaken for it.
+pub(crate) fn keymaps_start_regex() -> Regex {
+    Regex::new(r"\buint16_t\b[^=]*\bkeymaps\s*\[").unwrap()
+}
+
+/// Replaces the text of `/* ... */` comments with spaces, keeping line
+/// breaks and byte offsets, so parentheses in comments aren't counted and
+/// positions still point into the original source.
+pub(crate) fn blank_block_comments(content: &str) -> String {
+    let mut blanked = String::with_capacity(content.len());
+    let mut chars = content.chars().peekable();
+    let (mut in_line_comment, mut in_block_comment) = (false, false);
+    while let Some(ch) = chars.next() {
+        if in_block_comment {
+            if ch == '*' && chars.peek() == Some(&'/') {
+                chars.next();
+                blanked.push_str("  ");
+                in_block_comment = false;
+            } else if ch == '\n' {
+                blanked.push('\n');
+            } else {
+                blanked.extend(std::iter::repeat_n(' ', ch.len_utf8()));
+            }
+            continue;
+        }
+        match ch {
+            '\n' => in_line_comment = false,
+            '/' if !in_line_comment => match chars.peek() {
+                Some('/') => in_line_comment = true,
+                Some('*') => {
+                    chars.next();
+                    blanked.push_str("  ");
+                    in_block_comment = true;
+                    continue;
+                }
+                _ => {}
+            },
+            _ => {}
+        }
+        blanked.push(ch);
+    }
+    blanked
+}
+
+/// Follows the parentheses of one line of a LAYOUT block.
+///
+/// # Arguments
+///
+/// * `code` - The line, or the part of it after the block's `LAYOUT(`, with
+///   comments removed
+/// * `depth` - Parentheses open before the line, counting the block's own;
+///   updated to those open after it
+///
+/// # Returns
+///
+/// The byte offset of the `)` closing the block if it is on this line, and
+/// the offsets of the `(` still open where the line (or block) ends
+pub(crate) fn follow_parens(code: &str, depth: &mut usize) -> (Option<usize>, Vec<usize>) {
+    let mut opened = Vec::new();
+    for (i, ch) in code.char_indices() {
+        match ch {
+            '(' => {
+                *depth += 1;
+                opened.push(i);
+            }
+            ')' => {
+                *depth -= 1;
+                opened.pop();
+                if *depth == 0 {
+                    return (Some(i), opened);
+                }
+            }
+            _ => {}
+        }
+    }
+    (None, opened)
+}
+
+/// The error for a LAYOUT block that is never closed: the first `(` in it
+/// left open at the end of its line, or else the block itself.
+fn unclosed_block(
+    content: &str,
+    (line, start): (usize, usize),
+    unclosed: Option<(usize, usize)>,
+) -> ParseError {
+    let text = |line: usize| content.lines().nth(line - 1).unwrap_or_default();
+    match unclosed {
+        Some((paren_line, paren)) => ParseError::at_byte(
+            ParseErrorKind::UnbalancedParens,
+            content,
+            paren_line,
+            text(paren_line),
+            paren,
+            "this `(` is never closed",
+        ),
+        None => ParseError::at_byte(
+            ParseErrorKind::UnterminatedLayout,
+            content,
+            line,
+            text(line),
+            start,
+            "this LAYOUT block is never closed; expected `)` after its last key",
+        ),
+    }
 }
 
 /// Parses QMK keymap C code to extract layer definitions.
@@ -26,6 +210,11 @@ pub struct Layer {
 /// LAYOUT macro formats (LAYOUT, LAYOUT_split_3x5_3, etc.) and parses the
 /// key definitions within each layer.
 ///
+/// Layers take their index from a designated initializer such as `[_NAV] =`
+/// or `[3] =`, so they may be declared in any order; undesignated layers
+/// follow the one before them, as in C. Bindings from an `encoder_map` array
+/// are attached to the layers they are declared for.
+///
 /// # Arguments
 ///
 /// * `content` - A string slice containing the QMK keymap C source code
@@ -36,14 +225,34 @@ pub struct Layer {
 pub fn parse_layers(content: &str) -> Result<Vec<Layer>> {
     let mut layers = Vec::new();
     let mut in_keymaps = false;
-    let mut in_layer = false;
+    // The open LAYOUT block's line and the byte offset of its macro name
+    let mut block: Option<(usize, usize)> = None;
+    // Parentheses open in the block, counting its own
+    let mut depth = 0;
+    // First `(` of the block left open at the end of its line, the likely
+    // culprit if the block never closes
+    let mut unclosed: Option<(usize, usize)> = None;
+    // Where the last block closed, blamed for a stray `)` after it
+    let mut last_close: Option<(usize, usize)> = None;
     let mut current_keys = Vec::new();
-    let mut layer_count = 0;
+    let mut current_rows = Vec::new();
+    let mut current_name = None;
+    let mut current_macro = None;
+    // Like C, an undesignated layer follows the one before it
+    let mut next_index = 0;
+    let mut current_index = None;
 
     // Regex to match LAYOUT or LAYOUT_* variants followed by (
-    let layout_regex = Regex::new(r"LAYOUT(_\w+)?\s*\(").unwrap();
+    let layout_regex = Regex::new(r"(LAYOUT(?:_\w+)?)\s*\(").unwrap();
+    // Regex to match a designated initializer such as [_NAV] = or [3] =
+    let designator_regex = Regex::new(r"\[\s*(\w+)\s*\]\s*=").unwrap();
+    let keymaps_regex = keymaps_start_regex();
+    let symbols = layer_symbols(content);
 
-    for line in content.lines() {
+    // Parentheses in block comments such as `/* hold :) */` don't count
+    let code_content = blank_block_comments(content);
+    for (number, (source_line, line)) in content.lines().zip(code_content.lines()).enumerate() {
+        let line_number = number + 1;
         let trimmed = line.trim();
 
         // Skip comments and empty lines
@@ -52,7 +261,7 @@ pub fn parse_layers(content: &str) -> Result<Vec<Layer>> {
         }
 
         // Detect start of keymaps array
-        if trimmed.contains("const uint16_t PROGMEM keymaps") {
+        if !in_keymaps && keymaps_regex.is_match(trimmed) {
             in_keymaps = true;
         }
 
@@ -60,44 +269,309 @@ pub fn parse_layers(content: &str) -> Result<Vec<Layer>> {
             continue;
         }
 
-        // Detect end of keymaps array (only the closing one, not nested ones)
-        if trimmed.starts_with("};") && !in_layer {
-            break;
+        let layout_start = layout_regex.captures(line);
+
+        // A new layer or the end of the array while a block is still open
+        if let Some(open) = block
+            && (layout_start.is_some() || trimmed.starts_with("};"))
+        {
+            return Err(unclosed_block(content, open, unclosed).into());
         }
 
-        // Detect start of a layer - match LAYOUT or LAYOUT_* variants
-        if layout_regex.is_match(trimmed) {
-            in_layer = true;
-            continue;
+        // The part of the line inside a LAYOUT block
+        let (rest, offset) = match (block, layout_start) {
+            (Some(_), _) => (line, 0),
+            (None, _) if trimmed.starts_with("};") => break,
+            (None, None) => {
+                if trimmed.starts_with(')')
+                    && let Some((close_line, close)) = last_close
+                {
+                    return Err(ParseError::at_byte(
+                        ParseErrorKind::UnbalancedParens,
+                        content,
+                        close_line,
+                        content.lines().nth(close_line - 1).unwrap_or_default(),
+                        close,
+                        "this `)` closes the LAYOUT block early, leaving the keys after it out",
+                    )
+                    .into());
+                }
+                continue;
+            }
+            (None, Some(caps)) => {
+                let whole = caps.get(0).unwrap();
+                block = Some((line_number, caps.get(1).unwrap().start()));
+                depth = 1;
+                unclosed = None;
+                current_macro = Some(caps[1].to_string());
+                let designator = designator_regex
+                    .captures(trimmed)
+                    .map(|caps| caps[1].to_string());
+                // The designator picks the layer's index: a number, or a name
+                // declared in the layer enum or defines
+                current_index = designator.as_deref().and_then(|designator| {
+                    designator.parse::<usize>().ok().or_else(|| {
+                        symbols
+                            .iter()
+                            .find(|(name, _)| name == designator)
+                            .map(|(_, index)| *index)
+                    })
+                });
+                current_name = designator.filter(|designator| designator.parse::<usize>().is_err());
+                (&line[whole.end()..], whole.end())
+            }
+        };
+
+        // Follow the parentheses up to the one closing the block
+        let code = rest.find("//").map_or(rest, |comment| &rest[..comment]);
+        let (close, opened_here) = follow_parens(code, &mut depth);
+        if let Some(&open) = opened_here.first() {
+            unclosed.get_or_insert((line_number, offset + open));
+        }
+
+        let keys = parse_keys_with_parens(code[..close.unwrap_or(code.len())].trim());
+        if !keys.is_empty() {
+            current_keys.push(keys);
+            current_rows.push(line_number);
         }
 
         // Detect end of a layer
-        if in_layer && (trimmed.starts_with("),") || trimmed == ")," || trimmed == ")") {
-            in_layer = false;
+        if let Some(close) = close {
+            let after = code[close + 1..].trim().trim_start_matches(',').trim();
+            if !after.is_empty() && !after.starts_with('}') {
+                return Err(ParseError::at_byte(
+                    ParseErrorKind::UnbalancedParens,
+                    content,
+                    line_number,
+                    source_line,
+                    offset + close,
+                    "this `)` closes the LAYOUT block before the end of the line",
+                )
+                .into());
+            }
+            let (layout_line, _) = block.take().unwrap_or_default();
+            last_close = Some((line_number, offset + close));
 
             // Add the layer
             if !current_keys.is_empty() {
+                let index = current_index.take().unwrap_or(next_index);
+                next_index = index + 1;
                 layers.push(Layer {
-                    index: layer_count,
-                    keys: current_keys.clone(),
+                    index,
+                    name: current_name.take(),
+                    title: None,
+                    keys: std::mem::take(&mut current_keys),
+                    notes: Vec::new(),
+                    layout_macro: current_macro.take(),
+                    source: Some(SourceLines {
+                        layout: layout_line,
+                        rows: std::mem::take(&mut current_rows),
+                    }),
+                    encoders: Vec::new(),
                 });
-                current_keys.clear();
-                layer_count += 1;
             }
+        }
+    }
+
+    if let Some(open) = block {
+        return Err(unclosed_block(content, open, unclosed).into());
+    }
+
+    // Designated layers may be declared out of order
+    layers.sort_by_key(|layer| layer.index);
+
+    // Name layers declared by number after the enum or define for their index
+    for layer in layers.iter_mut().filter(|layer| layer.name.is_none()) {
+        layer.name = symbols
+            .iter()
+            .find(|(_, index)| *index == layer.index)
+            .map(|(name, _)| name.clone());
+    }
+
+    // Attach notes from viz directives to the layers they select
+    for directive in parse_directives(content) {
+        let (Some(selector), Some(note)) = (directive.get("layer"), directive.get("note")) else {
             continue;
+        };
+        if let Some(layer) = layers.iter_mut().find(|l| l.matches_selector(selector)) {
+            layer.notes.push(note.clone());
+        }
+    }
+
+    // Attach encoder bindings by designator, or by position when the entries
+    // aren't designated
+    for (position, entry) in parse_encoder_map(content).into_iter().enumerate() {
+        let target = match &entry.designator {
+            Some(designator) => layers.iter_mut().find(|l| l.matches_selector(designator)),
+            None => layers.get_mut(position),
+        };
+        if let Some(layer) = target {
+            layer.encoders = entry.bindings;
+        }
+    }
+
+    Ok(layers)
+}
+
+/// Reads the layer names a keymap declares, from `enum layers { _BASE,
+/// _NAV, ... }` style blocks and `#define _NAV 1` aliases.
+///
+/// Only enums with a member used as a designator of the `keymaps` array
+/// (`[_NAV] = LAYOUT(...)`) or as the layer of a layer key (`MO(_NAV)`,
+/// `LT(_NAV, KC_SPC)`) name layers; others, such as `enum combos { CMB_JK }`
+/// or `enum custom_keycodes { MY_KEY = SAFE_RANGE }`, are skipped. Numeric
+/// defines count when their name starts with an underscore, the QMK
+/// convention for layers, or is used as a layer designator (`[NAV] =`) or
+/// keycode argument (`MO(NAV)`).
+///
+/// # Arguments
+///
+/// * `content` - The keymap.c source
+///
+/// # Returns
+///
+/// The index of every layer name, in declaration order
+pub fn layer_symbols(content: &str) -> Vec<(String, usize)> {
+    // Block comments could hide or fake declarations
+    let content = Regex::new(r"(?s)/\*.*?\*/")
+        .unwrap()
+        .replace_all(content, "");
+    let content = Regex::new(r"//[^\n]*").unwrap().replace_all(&content, "");
+
+    // Names used as layers: designators of the keymaps array and the layers
+    // of layer keys
+    let keymaps = keymaps_start_regex()
+        .find(&content)
+        .map(|start| {
+            let rest = &content[start.start()..];
+            &rest[..rest.find("};").unwrap_or(rest.len())]
+        })
+        .unwrap_or_default();
+    let mut layer_names: HashSet<&str> = Regex::new(r"\[\s*(\w+)\s*\]\s*=")
+        .unwrap()
+        .captures_iter(keymaps)
+        .map(|caps| caps.get(1).unwrap().as_str())
+        .collect();
+    layer_names.extend(
+        Regex::new(r"\b(?:MO|TO|TG|TT|OSL|DF|LT|LM)\s*\(\s*(\w+)")
+            .unwrap()
+            .captures_iter(&content)
+            .map(|caps| caps.get(1).unwrap().as_str()),
+    );
+
+    let mut symbols: Vec<(String, usize)> = Vec::new();
+    for members in numeric_enums(&content) {
+        if members
+            .iter()
+            .any(|(name, _)| layer_names.contains(name.as_str()))
+        {
+            symbols.extend(members);
+        }
+    }
+
+    let define_regex = Regex::new(r"(?m)^\s*#\s*define\s+(\w+)\s+(\d+)\s*$").unwrap();
+    for caps in define_regex.captures_iter(&content) {
+        let name = &caps[1];
+        let referenced = Regex::new(&format!(r"\[\s*{0}\s*\]|\(\s*{0}\s*[,)]", name))
+            .unwrap()
+            .is_match(&content);
+        if (name.starts_with('_') || referenced)
+            && let Ok(index) = caps[2].parse()
+            && !symbols.iter().any(|(known, _)| known == name)
+        {
+            symbols.push((name.to_string(), index));
         }
+    }
 
-        // Parse key data when inside a layer
-        if in_layer {
-            let keys = parse_keys_with_parens(trimmed);
+    symbols
+}
 
-            if !keys.is_empty() {
-                current_keys.push(keys);
+/// Reads the members of every enum whose values are all numbers, e.g.
+/// `enum layers { _BASE, _NAV = 3 }` gives `_BASE` 0 and `_NAV` 3.
+///
+/// Enums with other initializers, such as
+/// `enum custom_keycodes { MY_KEY = SAFE_RANGE }`, are skipped. Comments
+/// should be removed from `content` first.
+///
+/// # Arguments
+///
+/// * `content` - The keymap.c source, without comments
+///
+/// # Returns
+///
+/// The members of each enum with their values, in declaration order
+pub(crate) fn numeric_enums(content: &str) -> Vec<Vec<(String, usize)>> {
+    let enum_regex = Regex::new(r"\benum\b\s*\w*\s*\{([^}]*)\}").unwrap();
+    enum_regex
+        .captures_iter(content)
+        .filter_map(|caps| {
+            let mut members = Vec::new();
+            let mut next = 0;
+            for member in caps[1].split(',').map(str::trim).filter(|m| !m.is_empty()) {
+                let (name, value) = match member.split_once('=') {
+                    // Initializers such as SAFE_RANGE mark an enum of keycodes
+                    Some((name, value)) => (name.trim(), value.trim().parse::<usize>().ok()?),
+                    None => (member, next),
+                };
+                members.push((name.to_string(), value));
+                next = value + 1;
             }
+            Some(members)
+        })
+        .collect()
+}
+
+/// The title of a layer's section, e.g. `Layer 1 – NAV` for `_NAV`.
+///
+/// See [`i18n::Translations::layer_title`] for titles in other languages.
+pub fn layer_title(layer: &Layer) -> String {
+    i18n::Translations::default().layer_title(layer)
+}
+
+impl Layer {
+    /// Returns `true` if `selector` names this layer, either by its index or by
+    /// its name (ignoring leading underscores and case, so `NAV` selects `_NAV`).
+    pub fn matches_selector(&self, selector: &str) -> bool {
+        match selector.parse::<usize>() {
+            Ok(index) => index == self.index,
+            Err(_) => [&self.name, &self.title]
+                .into_iter()
+                .flatten()
+                .any(|name| normalize_layer_name(name) == normalize_layer_name(selector)),
         }
     }
 
-    Ok(layers)
+    /// The name to show for the layer: its title if it has one, else its
+    /// symbolic name.
+    pub fn display_name(&self) -> Option<&str> {
+        self.title.as_deref().or(self.name.as_deref())
+    }
+}
+
+/// Extracts `key=value` pairs from every `// viz:` comment in the source.
+///
+/// Values may be bare words or double-quoted strings with `\"` escapes, e.g.
+/// `// viz: layer=NAV note="One-hand navigation, hold right thumb"`.
+fn parse_directives(content: &str) -> Vec<HashMap<String, String>> {
+    let pair_regex = Regex::new(r#"(\w+)\s*=\s*(?:"((?:[^"\\]|\\.)*)"|(\S+))"#).unwrap();
+
+    content
+        .lines()
+        .filter_map(|line| line.trim().strip_prefix("//"))
+        .filter_map(|comment| comment.trim_start().strip_prefix("viz:"))
+        .map(|directive| {
+            pair_regex
+                .captures_iter(directive)
+                .map(|caps| {
+                    let value = match caps.get(2) {
+                        Some(quoted) => quoted.as_str().replace("\\\"", "\""),
+                        None => caps[3].to_string(),
+                    };
+                    (caps[1].to_string(), value)
+                })
+                .collect()
+        })
+        .collect()
 }
 
 /// Checks if a key string represents an empty key.
@@ -116,7 +590,24 @@ pub fn is_empty_key(key: &str) -> bool {
     key.chars().all(|c| c == '_')
 }
 
-fn parse_keys_with_parens(line: &str) -> Vec<String> {
+/// The body of the `{ ... }` initializer following `from`, without the braces.
+pub(crate) fn initializer(content: &str, from: usize) -> Option<&str> {
+    let open = from + content[from..].find('{')? + 1;
+    let mut depth = 1;
+    for (offset, c) in content[open..].char_indices() {
+        match c {
+            '{' => depth += 1,
+            '}' => depth -= 1,
+            _ => {}
+        }
+        if depth == 0 {
+            return Some(&content[open..open + offset]);
+        }
+    }
+    None
+}
+
+pub(crate) fn parse_keys_with_parens(line: &str) -> Vec<String> {
     let mut keys = Vec::new();
     let mut current_key = String::new();
     let mut paren_depth = 0;
@@ -133,17 +624,13 @@ fn parse_keys_with_parens(line: &str) -> Vec<String> {
                 paren_depth -= 1;
                 current_key.push(ch);
             }
-            ',' => {
-                if paren_depth == 0 {
-                    // Only split on commas outside of parentheses
-                    let key = current_key.trim().to_string();
-                    if !key.is_empty() {
-                        keys.push(key);
-                    }
-                    current_key.clear();
-                } else {
-                    current_key.push(ch);
+            // Only split on commas outside of parentheses
+            ',' if paren_depth == 0 => {
+                let key = current_key.trim().to_string();
+                if !key.is_empty() {
+                    keys.push(key);
                 }
+                current_key.clear();
             }
             _ => {
                 current_key.push(ch);
@@ -160,76 +647,560 @@ fn parse_keys_with_parens(line: &str) -> Vec<String> {
     keys
 }
 
-fn add_gradients(document: Document) -> Document {
-    let mut defs = Definitions::new();
-
-    // Default key gradient (GMK WoB/BoW style - light grey)
-    let key_gradient = LinearGradient::new()
-        .set("id", "keyGradient")
-        .set("x1", "0%")
-        .set("y1", "0%")
-        .set("x2", "0%")
-        .set("y2", "100%")
-        .add(Stop::new().set("offset", "0%").set("stop-color", "#e8e8e8"))
-        .add(
-            Stop::new()
-                .set("offset", "100%")
-                .set("stop-color", "#d0d0d0"),
-        );
+/// Adds the key fill gradients of the theme's colors, angle, and fill mode.
+fn add_gradients(document: Document, theme: &Theme) -> Document {
+    let (x1, y1, x2, y2) = theme.gradient_vector();
+    let gradient = |id: String, fill: &Gradient| {
+        let bottom = match theme.fill {
+            Fill::Gradient => &fill.bottom,
+            Fill::Solid => &fill.top,
+        };
+        LinearGradient::new()
+            .set("id", id)
+            .set("x1", format!("{}%", x1))
+            .set("y1", format!("{}%", y1))
+            .set("x2", format!("{}%", x2))
+            .set("y2", format!("{}%", y2))
+            .add(
+                Stop::new()
+                    .set("offset", "0%")
+                    .set("stop-color", fill.top.as_str()),
+            )
+            .add(
+                Stop::new()
+                    .set("offset", "100%")
+                    .set("stop-color", bottom.as_str()),
+            )
+    };
 
-    // Layer-specific gradients using GMK-inspired colors
-    let layer_colors = vec![
-        ("#7cb0d9", "#5a8fb8"), // Layer 1 - GMK Blue (Dolch/Nautilus blue)
-        ("#b888c4", "#9668a8"), // Layer 2 - GMK Purple (Laser purple)
-        ("#d97c7c", "#c25858"), // Layer 3 - GMK Red (Red Samurai red)
-        ("#e8a87c", "#d18a58"), // Layer 4 - GMK Orange (Camping orange)
-        ("#7ec4a8", "#5ca888"), // Layer 5 - GMK Teal (Cyan/Miami teal)
-        ("#88c47c", "#68a858"), // Layer 6 - GMK Green (Botanical green)
-        ("#d4c47c", "#b8a858"), // Layer 7 - GMK Yellow (Honey yellow)
-        ("#a8a8a8", "#888888"), // Layer 8 - GMK Dark Grey (modifier grey)
-    ];
+    let mut defs = Definitions::new().add(gradient("keyGradient".to_string(), &theme.key));
 
-    for (idx, (light, dark)) in layer_colors.iter().enumerate() {
-        let gradient = LinearGradient::new()
-            .set("id", format!("layer{}Gradient", idx + 1))
-            .set("x1", "0%")
-            .set("y1", "0%")
-            .set("x2", "0%")
-            .set("y2", "100%")
-            .add(Stop::new().set("offset", "0%").set("stop-color", *light))
-            .add(Stop::new().set("offset", "100%").set("stop-color", *dark));
-        defs = defs.add(gradient);
-    }
-
-    // Special key gradient (GMK accent teal)
-    let special_gradient = LinearGradient::new()
-        .set("id", "specialGradient")
-        .set("x1", "0%")
-        .set("y1", "0%")
-        .set("x2", "0%")
-        .set("y2", "100%")
-        .add(Stop::new().set("offset", "0%").set("stop-color", "#7ec4a8"))
-        .add(
-            Stop::new()
-                .set("offset", "100%")
-                .set("stop-color", "#5ca888"),
-        );
+    // Palette slots, then one slot per accent
+    for slot in 1..=PALETTE_SIZE + theme.accents.len() {
+        defs = defs.add(gradient(
+            format!("layer{}Gradient", slot),
+            &theme.layer_fill(slot),
+        ));
+    }
+
+    for (slot, fill) in theme.keys.values().enumerate() {
+        defs = defs.add(gradient(format!("keycode{}Gradient", slot), fill));
+    }
 
-    defs = defs.add(key_gradient);
-    defs = defs.add(special_gradient);
+    defs = defs.add(gradient("specialGradient".to_string(), &theme.special));
+    defs = defs.add(gradient("systemGradient".to_string(), &theme.system));
 
     document.add(defs)
 }
 
-fn get_key_class(key: &str, layer_index: usize) -> String {
+/// Number of layer colors in the palette.
+pub const PALETTE_SIZE: usize = 8;
+
+/// Options controlling how layers are rendered.
+#[derive(Debug, Clone, Default)]
+pub struct RenderOptions {
+    /// Palette slot (1 through `PALETTE_SIZE`) overrides keyed by layer name
+    pub layer_colors: HashMap<String, usize>,
+    /// Label/value pairs shown in a metadata panel below the layers
+    pub metadata: Vec<(String, String)>,
+    /// Action/effect pairs explaining the layer keys, shown in a panel below
+    /// the metadata (see [`analysis::layer_key_docs`])
+    pub layer_key_docs: Vec<(String, String)>,
+    /// Keys/output pairs of the keymap's combos, shown in a panel below the
+    /// layer keys (see [`combos::Combo::entry`])
+    pub combos: Vec<(String, String)>,
+    /// Thumb key assignments of every layer, shown in a table below the
+    /// combos (see [`analysis::thumb_keys`])
+    pub thumb_keys: analysis::ThumbKeys,
+    /// Tap dances the keymap's `TD(...)` keys trigger, drawn as a stacked
+    /// legend per action (see [`tap_dance::parse_tap_dances`])
+    pub tap_dances: tap_dance::TapDances,
+    /// Color scales of the overlays, each explained by a legend below the
+    /// panels
+    pub scales: Vec<scale::ValueScale>,
+    /// QR code drawn at the bottom of the document, e.g. linking to the
+    /// keymap's repository
+    pub qr: Option<qr::QrFooter>,
+    /// Titles, headings, and labels written into the document
+    pub strings: i18n::Translations,
+    /// Key sizes and gaps
+    pub metrics: LayoutMetrics,
+    /// How individual keys are drawn
+    pub style: RenderStyle,
+    /// Physical arrangement of the board's keys
+    pub geometry: Geometry,
+    /// How keys without an assignment are drawn
+    pub empty_keys: EmptyKeys,
+    /// Colors and key shapes
+    pub theme: Theme,
+    /// Warnings shown in a banner above the layers (e.g. that the board
+    /// geometry is a fallback)
+    pub warnings: Vec<String>,
+    /// Callback adding custom nodes to every drawn key
+    pub decorate: Option<KeyDecorator>,
+    /// Draw each key's position name (see [`Geometry::position_names`])
+    /// instead of its legend, to debug geometries
+    pub label_positions: bool,
+    /// Tint the background of each half of a split board and label it L/R
+    pub half_tint: bool,
+    /// Mirrored layers to draw with arcs joining each base key's position
+    /// to its mirror image (see [`analysis::mirrored_layers`])
+    pub mirrors: Vec<analysis::MirroredLayer>,
+    /// Clusters of keys outlined with a dashed line and labeled on the
+    /// layers they are drawn on
+    pub key_groups: groups::KeyGroups,
+    /// Tune the document for embedding in notes apps such as Obsidian or
+    /// Notion: transparent background, capped width, no shadows, and text
+    /// colors following the reader's light or dark scheme
+    pub embed: bool,
+    /// Legends drawn instead of the keycodes, e.g. a locale's legend table
+    pub legends: keycodes::LegendOverrides,
+    /// Draw readable legends for basic keycodes (`KC_BSPC` as ⌫, `KC_LSFT`
+    /// as Shift), keeping the keycode in the tooltip
+    pub readable_legends: bool,
+    /// Draw icons for common keys such as arrows, Backspace, Shift, and media
+    /// keys instead of their text legends (see [`icons::icon`])
+    pub icons: bool,
+    /// Turn legends vertical on keys taller than wide when they don't fit
+    /// across
+    pub vertical_legends: bool,
+    /// How legends too wide for their key are drawn; the full legend stays in
+    /// the key's tooltip
+    pub overflow: LegendOverflow,
+    /// Keep every key `metrics.key_size` wide instead of widening all keys
+    /// to fit the longest label; labels that don't fit are abbreviated, then
+    /// drawn by the `overflow` policy
+    pub fixed_key_width: bool,
+    /// Measures legends with the installed fonts; without it, widths are
+    /// estimated from a fixed character width
+    pub text_metrics: Option<Arc<raster::TextMeasurer>>,
+}
+
+/// A callback invoked for every drawn key whose SVG nodes are added to the
+/// key's group, e.g. to draw badges marking keys that are still being learned.
+///
+/// The key's bounds are in document coordinates, so nodes can be positioned
+/// relative to them directly.
+#[derive(Clone)]
+pub struct KeyDecorator(Arc<DecorateFn>);
+
+/// Signature of the callback wrapped by [`KeyDecorator`].
+type DecorateFn = dyn Fn(&PlacedKey) -> Vec<Box<dyn Node>> + Send + Sync;
+
+impl KeyDecorator {
+    /// Wraps a decoration callback.
+    ///
+    /// # Arguments
+    ///
+    /// * `decorate` - Returns the nodes to draw on top of a key; an empty
+    ///   vector leaves the key as it is
+    pub fn new(
+        decorate: impl Fn(&PlacedKey) -> Vec<Box<dyn Node>> + Send + Sync + 'static,
+    ) -> Self {
+        KeyDecorator(Arc::new(decorate))
+    }
+
+    /// Combines two decorators, drawing the nodes of `other` after this one's.
+    pub fn and(self, other: KeyDecorator) -> Self {
+        let (KeyDecorator(first), KeyDecorator(second)) = (self, other);
+        KeyDecorator::new(move |key| {
+            let mut nodes = first(key);
+            nodes.extend(second(key));
+            nodes
+        })
+    }
+}
+
+impl fmt::Debug for KeyDecorator {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("KeyDecorator(..)")
+    }
+}
+
+/// A decorator printing each key's effort at its top edge on a badge colored
+/// by its place on a color scale.
+///
+/// # Arguments
+///
+/// * `efforts` - One effort per key in LAYOUT order, as returned by
+///   [`analysis::EffortModel::efforts`]; keys without one are left undecorated
+/// * `scale` - The color scale, which should also be passed in
+///   [`RenderOptions::scales`] so its legend is drawn
+pub fn effort_overlay(efforts: Vec<Option<f32>>, scale: scale::ValueScale) -> KeyDecorator {
+    const BADGE_WIDTH: f32 = 18.0;
+    const BADGE_HEIGHT: f32 = 10.0;
+
+    KeyDecorator::new(move |key| {
+        let Some(effort) = efforts.get(key.index).copied().flatten() else {
+            return Vec::new();
+        };
+        let color = scale.color(effort);
+        let center = key.bounds.x + key.bounds.width / 2.0;
+        let badge = Rectangle::new()
+            .set("class", "key-effort-badge")
+            .set("x", center - BADGE_WIDTH / 2.0)
+            .set("y", key.bounds.y + 2.0)
+            .set("width", BADGE_WIDTH)
+            .set("height", BADGE_HEIGHT)
+            .set("rx", 3)
+            .set("fill", color.as_str());
+        let text = Text::new(format!("{:.1}", effort))
+            .set("class", "key-effort")
+            .set("x", center)
+            .set("y", key.bounds.y + 10.0)
+            .set("fill", scale::text_color(&color));
+        vec![Box::new(badge) as Box<dyn Node>, Box::new(text)]
+    })
+}
+
+/// A decorator outlining the keys that differ from an earlier keymap, colored
+/// by whether they were added, removed, or changed, with the change in the
+/// tooltip. Keys changed in some of their arguments are dashed, and their
+/// tooltips name the changed arguments.
+///
+/// # Arguments
+///
+/// * `changes` - The changed keys, from [`diff::diff_layers`] with the drawn
+///   keymap as the new one
+pub fn diff_overlay(changes: Vec<diff::KeyChange>) -> KeyDecorator {
+    KeyDecorator::new(move |key| {
+        let Some(change) = changes
+            .iter()
+            .find(|change| change.layer == key.layer && change.position == key.index)
+        else {
+            return Vec::new();
+        };
+        let show = |key: &str| {
+            if key.is_empty() {
+                "(none)".to_string()
+            } else {
+                key.to_string()
+            }
+        };
+        // Keys changed in some of their arguments get a dashed outline and
+        // the changed arguments in their tooltip
+        let (argument_class, details) = if change.is_whole_key() {
+            ("", String::new())
+        } else {
+            (
+                " key-diff-argument",
+                format!("\n{}", change.details.join("\n")),
+            )
+        };
+        let outline = Rectangle::new()
+            .set(
+                "class",
+                format!(
+                    "key-diff key-diff-{}{}",
+                    change.kind().name(),
+                    argument_class
+                ),
+            )
+            .set("x", key.bounds.x - 1.5)
+            .set("y", key.bounds.y - 1.5)
+            .set("width", key.bounds.width + 3.0)
+            .set("height", key.bounds.height + 3.0)
+            .set("rx", 6)
+            .add(Title::new(format!(
+                "{}: {} → {}{}",
+                change.kind().name(),
+                show(&change.old),
+                show(&change.new),
+                details
+            )));
+        vec![Box::new(outline) as Box<dyn Node>]
+    })
+}
+
+/// A decorator marking keys that Auto Shift shifts when held with a double
+/// arrow in their bottom right corner.
+///
+/// # Arguments
+///
+/// * `auto_shift` - The keymap's Auto Shift settings
+/// * `strings` - The language of the badges' tooltips
+pub fn autoshift_badges(
+    auto_shift: autoshift::AutoShift,
+    strings: &i18n::Translations,
+) -> KeyDecorator {
+    let tooltip = strings.auto_shift_badge(auto_shift.timeout_ms);
+    KeyDecorator::new(move |key| {
+        if key.ghost || !auto_shift.is_shifted(key.label) {
+            return Vec::new();
+        }
+        let badge = Text::new("⇈")
+            .set("class", "key-autoshift")
+            .set("x", key.bounds.x + key.bounds.width - 5.0)
+            .set("y", key.bounds.y + key.bounds.height - 5.0)
+            .add(Title::new(tooltip.as_str()));
+        vec![Box::new(badge) as Box<dyn Node>]
+    })
+}
+
+/// A decorator marking keys that have a note with a dot in their top right
+/// corner, the note in its tooltip.
+///
+/// # Arguments
+///
+/// * `notes` - Notes keyed by layer index and LAYOUT index (see
+///   [`notes::KeymapNotes::key_notes`])
+pub fn note_badges(notes: HashMap<(usize, usize), String>) -> KeyDecorator {
+    KeyDecorator::new(move |key| {
+        let Some(note) = notes.get(&(key.layer, key.index)) else {
+            return Vec::new();
+        };
+        let badge = Circle::new()
+            .set("class", "key-note")
+            .set("cx", key.bounds.x + key.bounds.width - 6.0)
+            .set("cy", key.bounds.y + 6.0)
+            .set("r", 3)
+            .add(Title::new(note.as_str()));
+        vec![Box::new(badge) as Box<dyn Node>]
+    })
+}
+
+/// Adds the nodes of `options.decorate` for `key` to its group.
+fn decorate(mut group: Group, key: &PlacedKey, options: &RenderOptions) -> Group {
+    if let Some(KeyDecorator(decorate)) = &options.decorate {
+        for node in decorate(key) {
+            group = group.add(node);
+        }
+    }
+    group
+}
+
+/// Turns a key's group by the key's rotation about its center.
+fn rotate(group: Group, key: &PlacedKey) -> Group {
+    if key.rotation == 0.0 {
+        return group;
+    }
+    let b = key.bounds;
+    group.set(
+        "transform",
+        format!(
+            "rotate({} {} {})",
+            key.rotation,
+            b.x + b.width / 2.0,
+            b.y + b.height / 2.0
+        ),
+    )
+}
+
+/// Visual style used to draw each key.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum RenderStyle {
+    /// Flat rounded rectangles with a drop shadow
+    #[default]
+    Flat,
+    /// Pseudo-3D keycaps with side walls, an inset top surface, and per-row sculpting
+    Keycap,
+}
+
+impl RenderStyle {
+    /// Every built-in style, in the order they are previewed.
+    pub const ALL: [RenderStyle; 2] = [RenderStyle::Flat, RenderStyle::Keycap];
+
+    /// The style's name as accepted by `--style`.
+    pub fn name(self) -> &'static str {
+        match self {
+            RenderStyle::Flat => "flat",
+            RenderStyle::Keycap => "keycap",
+        }
+    }
+}
+
+/// How empty (`_______`) keys are drawn.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum EmptyKeys {
+    /// Draw a faded key with the placeholder legend
+    #[default]
+    Show,
+    /// Leave the key's position blank
+    Hide,
+    /// Draw a dashed outline without a legend
+    Ghost,
+}
+
+/// What happens to a legend too wide for its key.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum LegendOverflow {
+    /// Cut the legend short with an ellipsis
+    #[default]
+    Ellipsis,
+    /// Set the legend in a smaller font, cutting it short if the smallest
+    /// size still doesn't fit
+    Shrink,
+    /// Break the legend into lines at underscores, spaces, and brackets
+    Wrap,
+    /// Let the legend run over the key's edges
+    Visible,
+}
+
+/// Normalizes a layer name for color lookups (`_NAV` and `nav` both become `NAV`).
+fn normalize_layer_name(name: &str) -> String {
+    name.trim_start_matches('_').to_ascii_uppercase()
+}
+
+/// The palette slot of each layer of a keymap, and the layer indices its
+/// layer keys may refer to by name.
+#[derive(Debug, Clone, Default)]
+struct LayerSlots {
+    slots: HashMap<usize, usize>,
+    symbols: HashMap<String, usize>,
+}
+
+impl LayerSlots {
+    /// The palette slot of the layer at `index`.
+    fn slot(&self, index: usize) -> Option<usize> {
+        self.slots.get(&index).copied()
+    }
+
+    /// The layer a layer-switching keycode targets, by number or by name.
+    fn target(&self, key: &str) -> Option<usize> {
+        extract_layer_number(key, &self.symbols)
+    }
+}
+
+/// Palette slots of the layers with the theme's accents applied: layers with
+/// an accent use its slot after `PALETTE_SIZE` instead.
+fn color_slots(layers: &[Layer], options: &RenderOptions) -> LayerSlots {
+    let mut slots = layer_color_slots(layers, &options.layer_colors);
+    for layer in layers {
+        if let Some(slot) = layer
+            .name
+            .as_deref()
+            .and_then(|name| options.theme.accent_slot(name))
+            .or_else(|| options.theme.accent_slot(&layer.index.to_string()))
+        {
+            slots.insert(layer.index, slot);
+        }
+    }
+    LayerSlots {
+        slots,
+        symbols: layer_symbol_table(layers),
+    }
+}
+
+/// 32-bit FNV-1a hash, used instead of `DefaultHasher` because its output
+/// must not change between Rust releases.
+fn fnv1a(text: &str) -> u32 {
+    text.bytes().fold(0x811c_9dc5, |hash, byte| {
+        (hash ^ byte as u32).wrapping_mul(0x0100_0193)
+    })
+}
+
+/// The palette slot of a layer by its index, wrapping past the palette: the
+/// base layer keeps slot 0, which has no layer color.
+fn index_slot(index: usize) -> usize {
+    match index {
+        0 => 0,
+        index => (index - 1) % PALETTE_SIZE + 1,
+    }
+}
+
+/// Assigns a palette slot to each layer.
+///
+/// Named layers are keyed by their name so that inserting or reordering layers
+/// doesn't shift their colors between revisions: an explicit override wins,
+/// otherwise the name's hash selects a slot, probing forward past slots already
+/// claimed by overrides, unnamed layers (which take the slot of their index),
+/// and other named layers. Names claim slots in the order of their hashes
+/// rather than their layers, so reordering never changes who wins a slot.
+///
+/// # Arguments
+///
+/// * `layers` - The layers to assign colors to
+/// * `overrides` - Palette slots keyed by layer name (leading underscores and case are ignored)
+///
+/// # Returns
+///
+/// A map from layer index to palette slot in `1..=PALETTE_SIZE`
+pub fn layer_color_slots(
+    layers: &[Layer],
+    overrides: &HashMap<String, usize>,
+) -> HashMap<usize, usize> {
+    let overrides: HashMap<String, usize> = overrides
+        .iter()
+        .map(|(name, slot)| (normalize_layer_name(name), *slot))
+        .collect();
+    let mut slots = HashMap::new();
+    let mut hashed = Vec::new();
+
+    // Fixed assignments first so hashed names steer around them
+    for layer in layers {
+        match layer.name.as_deref().map(normalize_layer_name) {
+            Some(name) => match overrides.get(&name) {
+                Some(slot) => {
+                    slots.insert(layer.index, *slot);
+                }
+                None => hashed.push((layer.index, name)),
+            },
+            None => {
+                slots.insert(layer.index, index_slot(layer.index));
+            }
+        }
+    }
+
+    hashed.sort_by_cached_key(|(index, name)| (fnv1a(name), name.clone(), *index));
+    for (index, name) in hashed {
+        // The base layer isn't drawn with a layer color, so it doesn't compete for slots
+        let taken: Vec<usize> = slots
+            .iter()
+            .filter(|(index, _)| **index != 0)
+            .map(|(_, slot)| *slot)
+            .collect();
+        let preferred = fnv1a(&name) as usize % PALETTE_SIZE;
+        let slot = (0..PALETTE_SIZE)
+            .map(|probe| (preferred + probe) % PALETTE_SIZE + 1)
+            .find(|slot| !taken.contains(slot))
+            .unwrap_or(preferred + 1);
+        slots.insert(index, slot);
+    }
+
+    slots
+}
+
+/// CSS class of a placed key, accounting for ghosted empty keys.
+fn key_class(key: &PlacedKey, layer_index: usize, slots: &LayerSlots) -> String {
+    if key.ghost {
+        return "key key-ghost".to_string();
+    }
+    get_key_class(key.label, layer_index, slots)
+}
+
+fn get_key_class(key: &str, layer_index: usize, slots: &LayerSlots) -> String {
+    // Layer keys may target layers the keymap doesn't define
+    let slot = |index: usize| slots.slot(index).unwrap_or_else(|| index_slot(index));
+
     if is_empty_key(key) {
         return "key key-empty".to_string();
     }
 
+    if keycodes::system_keycode(key).is_some() {
+        return "key key-system".to_string();
+    }
+
+    // LM holds its target layer on every layer, so it takes that layer's color
+    if let Some(layer_mod) = keycodes::layer_mod(key) {
+        return format!("key key-layer{}", slot(layer_mod.layer));
+    }
+
+    // Layer lock holds the layer it is on, so it takes that layer's color
+    if keycodes::is_layer_lock(key) {
+        return match layer_index {
+            0 => "key key-lock".to_string(),
+            _ => format!("key key-layer{} key-lock", slot(layer_index)),
+        };
+    }
+
     // For Layer 0, check if it's a layer switch modifier
     if layer_index == 0 {
-        if let Some(layer_num) = extract_layer_number(key) {
-            return format!("key key-layer{}", layer_num);
+        if let Some(layer_num) = slots.target(key) {
+            return format!("key key-layer{}", slot(layer_num));
+        }
+
+        if keycodes::pointer_keycode(key).is_some() {
+            return "key key-special key-pointer".to_string();
         }
 
         // Check for special functions
@@ -246,12 +1217,13 @@ fn get_key_class(key: &str, layer_index: usize) -> String {
     }
 
     // For other layers, all non-empty keys get the layer color
-    format!("key key-layer{}", layer_index)
+    format!("key key-layer{}", slot(layer_index))
 }
 
-fn extract_layer_number(key: &str) -> Option<usize> {
-    // Extract layer number from layer switching functions
-    if key.starts_with("MO(")
+/// The layer a layer-switching keycode refers to as written, e.g. `2` for
+/// `MO(2)` or `_NAV` for `LT(_NAV, KC_SPC)`.
+pub(crate) fn layer_argument(key: &str) -> Option<&str> {
+    let argument = if key.starts_with("MO(")
         || key.starts_with("TO(")
         || key.starts_with("TG(")
         || key.starts_with("TT(")
@@ -260,97 +1232,68 @@ fn extract_layer_number(key: &str) -> Option<usize> {
     {
         let start = key.find('(')? + 1;
         let end = key.find(')')?;
-        key[start..end].parse().ok()
+        key.get(start..end)?
     } else if key.starts_with("LT(") || key.starts_with("LM(") {
         let start = key.find('(')? + 1;
         let comma = key.find(',')?;
-        key[start..comma].trim().parse().ok()
+        key.get(start..comma)?
     } else {
-        None
-    }
+        return None;
+    };
+
+    let argument = argument.trim();
+    let is_symbol = !argument.is_empty()
+        && argument
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_');
+    is_symbol.then_some(argument)
 }
 
-/// Generates an SVG visualization of keyboard layers.
-///
-/// Creates a comprehensive SVG document displaying multiple keyboard layers with
-/// proper spacing, gradients, and interactive styling. Each layer is rendered
-/// separately with its keys arranged according to the Keyball44 layout specification.
+/// Resolves the layer a layer-switching keycode targets, given by number or
+/// by a layer name such as `_NAV`.
 ///
 /// # Arguments
 ///
-/// * `layers` - A slice of `Layer` structs containing the keyboard layout data
+/// * `key` - The keycode, e.g. `MO(_NAV)`
+/// * `symbols` - Layer indices by name, from [`layer_symbol_table`]
 ///
 /// # Returns
 ///
-/// A `String` containing the complete SVG document
-pub fn generate_svg(layers: &[Layer]) -> String {
-    const KEY_HEIGHT: f32 = 60.0;
-    const KEY_SPACING: f32 = 5.0;
-    const SPLIT_GAP: f32 = 40.0;
-    const LAYER_SPACING: f32 = 120.0;
-    const MARGIN: f32 = 20.0;
-    const FONT_SIZE: f32 = 11.0;
-    const CHAR_WIDTH: f32 = 7.0; // Approximate width per character in monospace font
-    const KEY_PADDING: f32 = 10.0; // Padding inside the key
-
-    // Calculate the width needed for the longest key label
-    let max_label_len = layers
-        .iter()
-        .flat_map(|l| l.keys.iter())
-        .flat_map(|row| row.iter())
-        .map(|key| key.len())
-        .max()
-        .unwrap_or(8);
-
-    let key_width = (max_label_len as f32 * CHAR_WIDTH + KEY_PADDING * 2.0).max(60.0);
-
-    // Keyball44 layout structure
-    // Each row: (left_keys, left_offset, right_keys, right_offset)
-    // Left half bottom row staggered right by 2, right half bottom row staggered left by 1
-    let layout: [(i32, f32, i32, f32); 4] = [
-        (6, 0.0, 6, 0.0),  // Row 0: 6 left + 6 right
-        (6, 0.0, 6, 0.0),  // Row 1: 6 left + 6 right
-        (6, 0.0, 6, 0.0),  // Row 2: 6 left + 6 right
-        (5, 2.0, 3, -1.0), // Row 3: 5 left (offset +2) + 3 right (offset -1)
-    ];
-
-    let left_width = 8.0 * (key_width + KEY_SPACING); // Account for stagger
-    let right_width = 6.0 * (key_width + KEY_SPACING);
-    let svg_width = MARGIN * 2.0 + left_width + SPLIT_GAP + right_width;
+/// The target layer's index, or `None` if `key` is not a layer key or names
+/// an unknown layer
+pub(crate) fn extract_layer_number(key: &str, symbols: &HashMap<String, usize>) -> Option<usize> {
+    let argument = layer_argument(key)?;
+    argument
+        .parse()
+        .ok()
+        .or_else(|| symbols.get(&normalize_layer_name(argument)).copied())
+}
 
-    let mut total_height = MARGIN;
+/// Indices of a keymap's named layers keyed by their normalized name, for
+/// resolving symbolic layer keys. Layers of a keymap.c are named from its
+/// layer enum and defines by [`parse_layers`].
+pub(crate) fn layer_symbol_table(layers: &[Layer]) -> HashMap<String, usize> {
+    layers
+        .iter()
+        .filter_map(|layer| Some((normalize_layer_name(layer.name.as_deref()?), layer.index)))
+        .collect()
+}
 
-    // Calculate total height
-    for _ in layers {
-        let layer_height = 4.0 * (KEY_HEIGHT + KEY_SPACING) + 50.0;
-        total_height += layer_height + LAYER_SPACING;
-    }
+const FONT_SIZE: f32 = 11.0;
+const CORNER_FONT_SIZE: f32 = 8.0;
+/// Smallest font size `LegendOverflow::Shrink` sets legends in.
+const MIN_FONT_SIZE: f32 = 6.0;
+/// Space kept between a legend and the edges of its key's surface.
+const LEGEND_MARGIN: f32 = 3.0;
+/// Side of the icons drawn instead of legends with `--icons`.
+const ICON_SIZE: f32 = 16.0;
 
-    // Create SVG document
-    let mut document = Document::new()
-        .set("width", svg_width as i32)
-        .set("height", total_height as i32)
-        .set("viewBox", (0, 0, svg_width as i32, total_height as i32));
-
-    // Add background
-    let background = Rectangle::new()
-        .set("width", "100%")
-        .set("height", "100%")
-        .set("fill", "#faf8f3");
-    document = document.add(background);
-
-    // Add enhanced styles with gradients, shadows, and color coding
-    let style = Style::new(
-        r#"
+const STYLE: &str = r#"
         .key {
             fill: url(#keyGradient);
-            stroke: #2c3e50;
-            stroke-width: 2;
-            filter: drop-shadow(2px 2px 3px rgba(0,0,0,0.2));
             transition: all 0.3s ease;
         }
-        .key:hover {
-            filter: drop-shadow(3px 3px 5px rgba(0,0,0,0.3));
+        .key-group:hover .key {
             transform: translateY(-2px);
         }
         .key-layer1 { fill: url(#layer1Gradient); }
@@ -362,140 +1305,2225 @@ pub fn generate_svg(layers: &[Layer]) -> String {
         .key-layer7 { fill: url(#layer7Gradient); }
         .key-layer8 { fill: url(#layer8Gradient); }
         .key-special { fill: url(#specialGradient); }
+        .key-system { fill: url(#systemGradient); }
+        .key-lock { stroke-dasharray: 6 2; }
         .key-empty { fill: #ecf0f1; opacity: 0.5; }
+        .key-ghost { fill: none; stroke-dasharray: 4 3; }
+        .key-variant-other { stroke-dasharray: 5 3; }
         
         .key-text {
-            fill: #2c3e50;
             font-family: 'SF Mono', 'Monaco', 'Inconsolata', 'Fira Code', monospace;
             font-size: 11px;
             font-weight: 500;
             text-anchor: middle;
+            user-select: text;
+        }
+        .key-icon {
+            fill: none;
+            stroke-width: 2;
+            stroke-linecap: round;
+            stroke-linejoin: round;
+        }
+        .key-hold {
+            font-size: 8px;
+            font-weight: 700;
+            opacity: 0.75;
+        }
+        .key-corner {
+            font-family: 'SF Mono', 'Monaco', 'Inconsolata', 'Fira Code', monospace;
+            font-size: 8px;
+            font-weight: 600;
+            user-select: text;
+        }
+        .key-effort {
+            font-family: 'SF Mono', 'Monaco', 'Inconsolata', 'Fira Code', monospace;
+            font-size: 8px;
+            font-weight: 600;
+            text-anchor: middle;
+        }
+        .key-heat {
+            mix-blend-mode: multiply;
+            opacity: 0.6;
             pointer-events: none;
         }
-        .layer-title {
-            fill: #34495e;
+        .key-diff { fill: none; stroke-width: 3; }
+        .key-diff-added { stroke: #27ae60; }
+        .key-diff-removed { stroke: #c0392b; }
+        .key-diff-changed { stroke: #f39c12; }
+        .key-diff-argument { stroke-dasharray: 6 3; }
+        .key-autoshift {
+            fill: #5d6d7e;
+            font-size: 10px;
+            font-weight: 700;
+            text-anchor: end;
+        }
+        .key-note { fill: #e67e22; }
+        .trackball { fill: #a93226; stroke: #2c3e50; stroke-width: 1.5; }
+        .trackball-shine { fill: #ffffff; opacity: 0.3; pointer-events: none; }
+        .encoder-text { font-size: 8px; }
+        .scale-title {
+            fill: #2c3e50;
             font-family: -apple-system, BlinkMacSystemFont, 'Segoe UI', sans-serif;
-            font-size: 20px;
+            font-size: 12px;
             font-weight: 600;
-            letter-spacing: -0.5px;
         }
-    "#,
+        .scale-label {
+            fill: #5d6d7e;
+            font-family: 'SF Mono', 'Monaco', 'Inconsolata', 'Fira Code', monospace;
+            font-size: 10px;
+        }
+        .scale-bar {
+            stroke: #bdc3c7;
+            stroke-width: 0.5;
+        }
+        .corner-layer1 { fill: #3f6f96; }
+        .corner-layer2 { fill: #76508a; }
+        .corner-layer3 { fill: #a24040; }
+        .corner-layer4 { fill: #b06c3c; }
+        .corner-layer5 { fill: #3d8a6c; }
+        .corner-layer6 { fill: #4a8a3c; }
+        .corner-layer7 { fill: #8a7a30; }
+        .corner-layer8 { fill: #5a5a5a; }
+        .layer-title {
+            fill: #34495e;
+            font-family: -apple-system, BlinkMacSystemFont, 'Segoe UI', sans-serif;
+            font-size: 20px;
+            font-weight: 600;
+            letter-spacing: -0.5px;
+        }
+        .layer-note {
+            fill: #5d6d7e;
+            font-family: -apple-system, BlinkMacSystemFont, 'Segoe UI', sans-serif;
+            font-size: 13px;
+            font-style: italic;
+        }
+        .metadata-panel {
+            fill: #ffffff;
+            stroke: #bdc3c7;
+            stroke-width: 1;
+        }
+        .metadata-text {
+            fill: #2c3e50;
+            font-family: -apple-system, BlinkMacSystemFont, 'Segoe UI', sans-serif;
+            font-size: 13px;
+        }
+        .metadata-label { font-weight: 600; }
+        .warning-banner {
+            fill: #fdf2e9;
+            stroke: #e67e22;
+            stroke-width: 1.5;
+        }
+        .warning-text {
+            fill: #a04000;
+            font-family: -apple-system, BlinkMacSystemFont, 'Segoe UI', sans-serif;
+            font-size: 13px;
+            font-weight: 600;
+        }
+        .keycap-shade { fill: #000000; opacity: 0.18; pointer-events: none; }
+        .keycap-top {
+            stroke: rgba(255,255,255,0.45);
+            stroke-width: 1;
+            filter: none;
+        }
+        .card-legend {
+            fill: #34495e;
+            font-family: -apple-system, BlinkMacSystemFont, 'Segoe UI', sans-serif;
+            font-size: 12px;
+        }
+        .half-tint { stroke-width: 1; stroke-dasharray: 4 3; }
+        .mirror-arc {
+            fill: none;
+            stroke: #8e44ad;
+            stroke-width: 1.5;
+            stroke-opacity: 0.6;
+            stroke-dasharray: 5 3;
+            pointer-events: none;
+        }
+        .group-outline {
+            fill: none;
+            stroke: #2c3e50;
+            stroke-width: 1.5;
+            stroke-dasharray: 6 3;
+            pointer-events: none;
+        }
+        .group-tab { fill: #2c3e50; }
+        .group-label {
+            fill: #ffffff;
+            font-family: -apple-system, BlinkMacSystemFont, 'Segoe UI', sans-serif;
+            font-size: 10px;
+            font-weight: 600;
+        }
+        .half-left { fill: rgba(52,152,219,0.07); stroke: rgba(52,152,219,0.4); }
+        .half-right { fill: rgba(230,126,34,0.07); stroke: rgba(230,126,34,0.4); }
+        .half-label {
+            fill: #7f8c8d;
+            font-family: -apple-system, BlinkMacSystemFont, 'Segoe UI', sans-serif;
+            font-size: 12px;
+            font-weight: 600;
+            text-anchor: middle;
+        }
+    "#;
+
+/// Widest an embedded document is drawn, in pixels.
+const EMBED_MAX_WIDTH: f32 = 800.0;
+
+/// Rules for documents embedded in notes apps: no shadows, and text that
+/// stays readable on the dark backgrounds of a dark color scheme.
+const EMBED_STYLE: &str = r#"
+        .key, .key-group:hover .key { filter: none; }
+        @media (prefers-color-scheme: dark) {
+            .layer-title, .card-legend, .metadata-text { fill: #ecf0f1; }
+            .layer-note, .half-label { fill: #bdc3c7; }
+            .metadata-panel { fill: #2c3e50; stroke: #566573; }
+            .key-empty { fill: #566573; }
+            .key-ghost { stroke: #7f8c8d; }
+            .group-outline { stroke: #ecf0f1; }
+            .group-tab { fill: #ecf0f1; }
+            .group-label { fill: #2c3e50; }
+        }
+"#;
+
+/// Keycap profile insets per row as (top, bottom), sculpted like an OEM set:
+/// the number row leans back the most and the thumb row sits the flattest.
+const KEYCAP_PROFILE: [(f32, f32); 4] = [(3.0, 11.0), (4.0, 9.0), (5.0, 8.0), (4.0, 10.0)];
+/// Side wall width of the keycap style.
+const KEYCAP_SIDE: f32 = 6.0;
+
+/// Area of the key that holds its legends: the whole key for flat keys, the
+/// inset top surface for keycaps.
+fn top_surface(bounds: KeyBox, row_idx: usize, style: RenderStyle) -> KeyBox {
+    match style {
+        RenderStyle::Flat => bounds,
+        RenderStyle::Keycap => {
+            let (top, bottom) = KEYCAP_PROFILE[row_idx.min(KEYCAP_PROFILE.len() - 1)];
+            KeyBox {
+                x: bounds.x + KEYCAP_SIDE,
+                y: bounds.y + top,
+                width: bounds.width - KEYCAP_SIDE * 2.0,
+                height: bounds.height - top - bottom,
+            }
+        }
+    }
+}
+
+/// Stretches `other` until it meets `surface`, closing the gap that the
+/// keycap insets leave between the two top surfaces of a non-rectangular key.
+fn bridge(surface: KeyBox, other: KeyBox) -> KeyBox {
+    let mut b = other;
+    if b.y > surface.y + surface.height {
+        b.height += b.y - (surface.y + surface.height);
+        b.y = surface.y + surface.height;
+    } else if b.y + b.height < surface.y {
+        b.height = surface.y - b.y;
+    }
+    if b.x > surface.x + surface.width {
+        b.width += b.x - (surface.x + surface.width);
+        b.x = surface.x + surface.width;
+    } else if b.x + b.width < surface.x {
+        b.width = surface.x - b.x;
+    }
+    b
+}
+
+/// Outline of a non-rectangular key (the union of its two rectangles) as a
+/// `<path>` with corners rounded by `radius`.
+fn key_outline(class: String, bounds: KeyBox, secondary: KeyBox, radius: f32) -> Path {
+    let points = union_outline(&[bounds, secondary]);
+    let n = points.len();
+    // Point `distance` along the edge from `from` towards `to`, capped at half the edge
+    let toward = |from: (f32, f32), to: (f32, f32), distance: f32| {
+        let length = (to.0 - from.0).hypot(to.1 - from.1);
+        let t = (distance / length).min(0.5);
+        (from.0 + (to.0 - from.0) * t, from.1 + (to.1 - from.1) * t)
+    };
+
+    let mut data = Data::new();
+    for k in 0..n {
+        let (prev, corner, next) = (points[(k + n - 1) % n], points[k], points[(k + 1) % n]);
+        let (start, end) = (toward(corner, prev, radius), toward(corner, next, radius));
+        data = if k == 0 {
+            data.move_to(start)
+        } else {
+            data.line_to(start)
+        };
+        data = data.quadratic_curve_to((corner.0, corner.1, end.0, end.1));
+    }
+
+    Path::new().set("class", class).set("d", data.close())
+}
+
+/// Builds a key's group: its shape and centered label in the given render
+/// style. Labels stay as `<text>` so they can be selected and copied.
+///
+/// Keys with a `secondary` rectangle (e.g. ISO Enter) are drawn as the
+/// outline of both rectangles; legends stay on the main rectangle.
+/// Looks up the legend and description of a system or pointing device
+/// keycode, or of a basic keycode when `options.readable_legends` is set.
+fn recognized_keycode(key: &str, options: &RenderOptions) -> Option<keycodes::KeycodeInfo> {
+    keycodes::system_keycode(key)
+        .or_else(|| keycodes::pointer_keycode(key))
+        .or_else(|| {
+            options
+                .readable_legends
+                .then(|| keycodes::basic_keycode(key))
+                .flatten()
+        })
+}
+
+fn key_group(
+    id: String,
+    class: String,
+    label: &str,
+    bounds: KeyBox,
+    secondary: Option<KeyBox>,
+    row_idx: usize,
+    options: &RenderOptions,
+) -> Group {
+    let style = options.style;
+    let radius = options.theme.shapes.radius(&class);
+    // Keycodes with a fill of their own keep their category's shape
+    let class = match options.theme.keycode_slot(label) {
+        Some(slot) => format!("{} key-keycode{}", class, slot),
+        None => class,
+    };
+    let rect = |class: String, b: KeyBox, rx: f32| {
+        Rectangle::new()
+            .set("class", class)
+            .set("x", b.x)
+            .set("y", b.y)
+            .set("width", b.width)
+            .set("height", b.height)
+            .set("rx", rx)
+    };
+
+    let group = Group::new().set("id", id).set("class", "key-group");
+    let surface = top_surface(bounds, row_idx, style);
+    let group = match (style, secondary) {
+        (RenderStyle::Flat, None) => group.add(rect(class, bounds, radius)),
+        (RenderStyle::Flat, Some(secondary)) => {
+            group.add(key_outline(class, bounds, secondary, radius))
+        }
+        (RenderStyle::Keycap, None) => group
+            // Side walls: the key color darkened by a translucent shade
+            .add(rect(class.clone(), bounds, radius + 1.0))
+            .add(rect("keycap-shade".to_string(), bounds, radius + 1.0))
+            .add(rect(
+                format!("{} keycap-top", class),
+                surface,
+                (radius - 1.0).max(0.0),
+            )),
+        (RenderStyle::Keycap, Some(secondary)) => {
+            let secondary_surface = bridge(surface, top_surface(secondary, row_idx, style));
+            group
+                .add(key_outline(class.clone(), bounds, secondary, radius + 1.0))
+                .add(key_outline(
+                    "keycap-shade".to_string(),
+                    bounds,
+                    secondary,
+                    radius + 1.0,
+                ))
+                .add(key_outline(
+                    format!("{} keycap-top", class),
+                    surface,
+                    secondary_surface,
+                    (radius - 1.0).max(0.0),
+                ))
+        }
+    };
+
+    if label.is_empty() {
+        return group;
+    }
+
+    // Tap dance keys stack a small legend per action; dances handled by a
+    // function only get a tooltip
+    let overridden = options.legends.legends.get(label);
+    if let (None, Some(dance)) = (overridden, options.tap_dances.get(label)) {
+        let group = group.add(Title::new(dance.describe(label)));
+        if dance.actions.is_empty() {
+            let legend = options.theme.typography.case.apply(label);
+            return group.add(legend_text(&legend, legend_kind(label), surface, options));
+        }
+        return group.add(tap_dance_text(&dance.actions, surface, options));
+    }
+
+    // Layer-tap and mod-tap keys show what they tap, with the hold action
+    // in a smaller label below it
+    let action = keycodes::key_action(label);
+    if let (None, Some(hold)) = (overridden, &action.hold) {
+        let case = options.theme.typography.case;
+        let legend = case.apply(&tap_legend(&action.tap, options));
+        return group
+            .add(Title::new(format!(
+                "{}: tap {}, hold {}",
+                label, action.tap, hold
+            )))
+            .add(KeyLegend::of(&action.tap, legend, options).draw(
+                legend_kind(&action.tap),
+                surface,
+                options,
+            ))
+            .add(hold_text(&case.apply(hold), surface));
+    }
+
+    // Overridden and recognized keycodes get a readable legend and a tooltip
+    let (legend, group) = match (
+        overridden,
+        recognized_keycode(label, options),
+        keycodes::layer_mod(label),
+    ) {
+        (Some(o), _, _) => {
+            let title = match &o.description {
+                Some(description) => format!("{}: {}", label, description),
+                None => label.to_string(),
+            };
+            (o.legend.clone(), group.add(Title::new(title)))
+        }
+        (None, Some(info), _) => (
+            info.legend.to_string(),
+            group.add(Title::new(format!("{}: {}", label, info.description))),
+        ),
+        (None, None, Some(layer_mod)) => (
+            layer_mod.legend(),
+            group.add(Title::new(format!(
+                "{}: {}",
+                label,
+                layer_mod.description()
+            ))),
+        ),
+        // Icons of keys without a readable legend still name the key
+        (None, None, None) if options.icons && icons::icon(label).is_some() => {
+            let title = match keycodes::basic_keycode(label) {
+                Some(info) => format!("{}: {}", label, info.description),
+                None => label.to_string(),
+            };
+            (label.to_string(), group.add(Title::new(title)))
+        }
+        (None, None, None) => (label.to_string(), group),
+    };
+
+    let legend = options.theme.typography.case.apply(&legend);
+    group.add(KeyLegend::of(label, legend, options).draw(legend_kind(label), surface, options))
+}
+
+/// What a key's legend is drawn as.
+enum KeyLegend {
+    /// Text, set by [`legend_text`]
+    Text(String),
+    /// An icon, drawn with `<use>` from the document's icon symbols
+    Icon(icons::Icon),
+}
+
+impl KeyLegend {
+    /// The legend of a keycode: its icon with `options.icons`, unless a legend
+    /// table overrides it, otherwise its text legend.
+    fn of(key: &str, text: String, options: &RenderOptions) -> KeyLegend {
+        let icon = options
+            .icons
+            .then(|| icons::icon(key))
+            .flatten()
+            .filter(|_| !options.legends.legends.contains_key(key.trim()));
+        match icon {
+            Some(icon) => KeyLegend::Icon(icon),
+            None => KeyLegend::Text(text),
+        }
+    }
+
+    /// Draws the legend centered on `surface`, classed by its `kind` for the
+    /// theme's typography rules.
+    fn draw(&self, kind: LegendKind, surface: KeyBox, options: &RenderOptions) -> Box<dyn Node> {
+        match self {
+            KeyLegend::Text(text) => legend_text(text, kind, surface, options),
+            KeyLegend::Icon(icon) => {
+                let size = ICON_SIZE
+                    .min(surface.width - LEGEND_MARGIN * 2.0)
+                    .min(surface.height - LEGEND_MARGIN * 2.0);
+                let class = match kind.class() {
+                    Some(kind) => format!("key-icon {}", kind),
+                    None => "key-icon".to_string(),
+                };
+                Box::new(
+                    Use::new()
+                        .set("class", class)
+                        .set("href", format!("#{}", icon.id()))
+                        .set("x", surface.x + (surface.width - size) / 2.0)
+                        .set("y", surface.y + (surface.height - size) / 2.0)
+                        .set("width", size)
+                        .set("height", size),
+                )
+            }
+        }
+    }
+}
+
+/// The legend of a dual-role key's tap keycode: its override or readable
+/// legend when it has one.
+fn tap_legend(tap: &str, options: &RenderOptions) -> String {
+    match options.legends.legends.get(tap) {
+        Some(overridden) => overridden.legend.clone(),
+        None => {
+            recognized_keycode(tap, options).map_or(tap.to_string(), |info| info.legend.to_string())
+        }
+    }
+}
+
+/// Line height of the stacked legends of a tap dance key.
+const TAP_DANCE_LINE_HEIGHT: f32 = 10.0;
+
+/// Draws the actions of a tap dance key as small legends stacked in the
+/// middle of `surface`, e.g. `ESC` over `2× CAPS`.
+fn tap_dance_text(
+    actions: &[(tap_dance::TapDanceStep, String)],
+    surface: KeyBox,
+    options: &RenderOptions,
+) -> Text {
+    const SIZE: f32 = 8.0;
+    let case = options.theme.typography.case;
+    let room = surface.width - LEGEND_MARGIN * 2.0;
+    let top = surface.y + surface.height / 2.0
+        - (actions.len() as f32 - 1.0) * TAP_DANCE_LINE_HEIGHT / 2.0
+        + SIZE / 3.0;
+    let mut text = Text::new("").set("class", "key-text key-hold");
+    for (line, (step, keycode)) in actions.iter().enumerate() {
+        let legend = format!("{}{}", step.prefix(), tap_legend(keycode, options));
+        text = text.add(
+            svg::node::element::TSpan::new("")
+                .set("x", surface.x + surface.width / 2.0)
+                .set("y", top + line as f32 * TAP_DANCE_LINE_HEIGHT)
+                .add(svg::node::Text::new(truncate_legend(
+                    &case.apply(&legend),
+                    room,
+                    SIZE,
+                    options,
+                ))),
+        );
+    }
+    text
+}
+
+/// Draws the hold action of a dual-role key along the bottom of `surface`.
+fn hold_text(hold: &str, surface: KeyBox) -> Text {
+    Text::new("")
+        .set("class", "key-text key-hold")
+        .set("x", surface.x + surface.width / 2.0)
+        .set("y", surface.y + surface.height - 5.0)
+        .add(svg::node::Text::new(hold))
+}
+
+/// Classifies a keycode for the theme's typography rules.
+fn legend_kind(key: &str) -> LegendKind {
+    if keycodes::system_keycode(key).is_some() {
+        LegendKind::System
+    } else if keycodes::is_modifier(key) {
+        LegendKind::Modifier
+    } else if layer_argument(key).is_some()
+        || keycodes::layer_mod(key).is_some()
+        || keycodes::is_layer_lock(key)
+    {
+        LegendKind::Layer
+    } else {
+        LegendKind::Key
+    }
+}
+
+/// Returns `true` if the text contains right-to-left letters (Hebrew,
+/// Arabic, Syriac, ...).
+fn is_rtl(text: &str) -> bool {
+    text.chars()
+        .any(|c| matches!(c as u32, 0x0590..=0x08FF | 0xFB1D..=0xFDFF | 0xFE70..=0xFEFF))
+}
+
+/// Returns `true` for Arabic-family text, whose letters join and so can't be
+/// stacked one above the other.
+fn is_joining(text: &str) -> bool {
+    text.chars()
+        .any(|c| matches!(c as u32, 0x0600..=0x08FF | 0xFB50..=0xFDFF | 0xFE70..=0xFEFF))
+}
+
+/// Returns `true` for combining marks (accents, Hebrew points) drawn on the
+/// character before them.
+fn is_combining(c: char) -> bool {
+    matches!(c as u32, 0x0300..=0x036F | 0x0591..=0x05C7 | 0x064B..=0x065F | 0x3099..=0x309A)
+}
+
+/// Width of a key legend, measured when `options.text_metrics` is set.
+fn legend_width(legend: &str, options: &RenderOptions) -> f32 {
+    options
+        .text_metrics
+        .as_ref()
+        .and_then(|metrics| metrics.width(legend, FONT_SIZE))
+        .unwrap_or(legend.chars().filter(|c| !is_combining(*c)).count() as f32 * CHAR_WIDTH)
+}
+
+/// Draws a key's legend centered on `surface`, classed by its `kind` for the
+/// theme's typography rules.
+///
+/// Right-to-left legends are marked as such so viewers lay them out from the
+/// right. With `options.vertical_legends`, legends too wide for a key taller
+/// than it is wide are stacked one character per line, or turned sideways
+/// for scripts whose letters join. Other legends too wide for the key are
+/// drawn by the `options.overflow` policy (see [`overflowing_legend`]).
+fn legend_text(
+    legend: &str,
+    kind: LegendKind,
+    surface: KeyBox,
+    options: &RenderOptions,
+) -> Box<dyn Node> {
+    let (cx, cy) = (
+        surface.x + surface.width / 2.0,
+        surface.y + surface.height / 2.0,
     );
-    document = document.add(style);
+    let class = match kind.class() {
+        Some(kind) => format!("key-text {}", kind),
+        None => "key-text".to_string(),
+    };
+    let mut text = Text::new("").set("class", class);
+    if is_rtl(legend) {
+        text = text.set("direction", "rtl").set("unicode-bidi", "embed");
+    }
 
-    // Add gradient definitions
-    document = add_gradients(document);
+    let vertical = options.vertical_legends
+        && surface.height > surface.width
+        && legend_width(legend, options) > surface.width - KEY_PADDING;
+    if !vertical {
+        let room = surface.width - LEGEND_MARGIN * 2.0;
+        if options.overflow != LegendOverflow::Visible && legend_width(legend, options) > room {
+            return overflowing_legend(legend, text, surface, room, options);
+        }
+        return Box::new(
+            text.set("x", cx)
+                .set("y", cy + FONT_SIZE / 3.0)
+                .add(svg::node::Text::new(legend)),
+        );
+    }
 
-    let mut y_offset = MARGIN;
+    if is_joining(legend) {
+        return Box::new(
+            text.set("x", cx)
+                .set("y", cy + FONT_SIZE / 3.0)
+                .set("transform", format!("rotate(-90 {} {})", cx, cy))
+                .add(svg::node::Text::new(legend)),
+        );
+    }
 
-    for layer in layers {
-        // Draw layer title
+    // One line per character, keeping combining marks with their base
+    let mut glyphs: Vec<String> = Vec::new();
+    for c in legend.chars() {
+        match glyphs.last_mut() {
+            Some(glyph) if is_combining(c) => glyph.push(c),
+            _ => glyphs.push(c.to_string()),
+        }
+    }
+    // Separate text elements, since whitespace between tspans would be drawn
+    let line_height = FONT_SIZE * 1.2;
+    let first = cy - line_height * (glyphs.len() as f32 - 1.0) / 2.0 + FONT_SIZE / 3.0;
+    let mut lines = Group::new();
+    for (line, glyph) in glyphs.into_iter().enumerate() {
+        lines = lines.add(
+            text.clone()
+                .set("x", cx)
+                .set("y", first + line as f32 * line_height)
+                .add(svg::node::Text::new(glyph)),
+        );
+    }
+    Box::new(lines)
+}
+
+/// Draws a legend wider than the `room` its key leaves: abbreviated if that
+/// makes it fit (see [`keycodes::abbreviate`]), otherwise by the
+/// `options.overflow` policy. The full legend moves to a tooltip and a
+/// `data-legend` attribute, so nothing spills over the key's edges.
+fn overflowing_legend(
+    full: &str,
+    text: Text,
+    surface: KeyBox,
+    room: f32,
+    options: &RenderOptions,
+) -> Box<dyn Node> {
+    let (cx, cy) = (
+        surface.x + surface.width / 2.0,
+        surface.y + surface.height / 2.0,
+    );
+    let abbreviated = keycodes::abbreviate(full);
+    let legend = abbreviated.as_deref().unwrap_or(full);
+    if legend_width(legend, options) <= room {
+        return Box::new(
+            text.set("x", cx)
+                .set("y", cy + FONT_SIZE / 3.0)
+                .set("data-legend", full)
+                .add(Title::new(full))
+                .add(svg::node::Text::new(legend)),
+        );
+    }
+    match options.overflow {
+        LegendOverflow::Ellipsis => Box::new(
+            text.set("x", cx)
+                .set("y", cy + FONT_SIZE / 3.0)
+                .set("data-legend", full)
+                .add(Title::new(full))
+                .add(svg::node::Text::new(truncate_legend(
+                    legend, room, FONT_SIZE, options,
+                ))),
+        ),
+        LegendOverflow::Shrink => {
+            let size = (FONT_SIZE * room / legend_width(legend, options)).max(MIN_FONT_SIZE);
+            // Inline, since the stylesheet's font size overrides attributes
+            Box::new(
+                text.set("x", cx)
+                    .set("y", cy + size / 3.0)
+                    .set("style", format!("font-size: {:.1}px", size))
+                    .set("data-legend", full)
+                    .add(Title::new(full))
+                    .add(svg::node::Text::new(truncate_legend(
+                        legend, room, size, options,
+                    ))),
+            )
+        }
+        LegendOverflow::Wrap => {
+            let line_height = FONT_SIZE * 1.2;
+            let max_lines = ((surface.height - KEY_PADDING) / line_height).max(1.0) as usize;
+            let mut lines = wrap_legend(legend, room, options);
+            if lines.len() > max_lines {
+                lines.truncate(max_lines);
+                let last = lines.pop().unwrap_or_default();
+                lines.push(truncate_legend(
+                    &format!("{}…", last),
+                    room,
+                    FONT_SIZE,
+                    options,
+                ));
+            }
+            let first = cy - line_height * (lines.len() as f32 - 1.0) / 2.0 + FONT_SIZE / 3.0;
+            let mut group = Group::new().set("data-legend", full).add(Title::new(full));
+            for (index, line) in lines.into_iter().enumerate() {
+                group = group.add(
+                    text.clone()
+                        .set("x", cx)
+                        .set("y", first + index as f32 * line_height)
+                        .add(svg::node::Text::new(line)),
+                );
+            }
+            Box::new(group)
+        }
+        LegendOverflow::Visible => Box::new(
+            text.set("x", cx)
+                .set("y", cy + FONT_SIZE / 3.0)
+                .add(svg::node::Text::new(legend)),
+        ),
+    }
+}
+
+/// Cuts a legend short with an ellipsis until it fits `room` at font size
+/// `size`, keeping combining marks with the character they sit on.
+fn truncate_legend(legend: &str, room: f32, size: f32, options: &RenderOptions) -> String {
+    let fits = |text: &str| legend_width(text, options) * size / FONT_SIZE <= room;
+    if fits(legend) {
+        return legend.to_string();
+    }
+    let chars: Vec<char> = legend.chars().collect();
+    (1..chars.len())
+        .rev()
+        .filter(|&end| !is_combining(chars[end]))
+        .map(|end| format!("{}…", chars[..end].iter().collect::<String>()))
+        .find(|shortened| fits(shortened))
+        .unwrap_or_else(|| "…".to_string())
+}
+
+/// Breaks a legend into lines no wider than `room`, after underscores,
+/// spaces, hyphens, and brackets (`LCTL_T(KC_ESC)` becomes `LCTL_T(` and
+/// `KC_ESC)`). Lines without a break that still don't fit are cut short.
+fn wrap_legend(legend: &str, room: f32, options: &RenderOptions) -> Vec<String> {
+    let mut pieces: Vec<String> = vec![String::new()];
+    for c in legend.chars() {
+        if let Some(piece) = pieces.last_mut() {
+            piece.push(c);
+        }
+        if matches!(c, '_' | ' ' | '-' | '(' | ',' | '+' | '/') {
+            pieces.push(String::new());
+        }
+    }
+
+    let mut lines: Vec<String> = Vec::new();
+    let mut line = String::new();
+    for piece in pieces.into_iter().filter(|piece| !piece.is_empty()) {
+        let joined = format!("{}{}", line, piece);
+        if line.is_empty() || legend_width(joined.trim_end(), options) <= room {
+            line = joined;
+        } else {
+            lines.push(std::mem::replace(&mut line, piece));
+        }
+    }
+    lines.push(line);
+    lines
+        .iter()
+        .map(|line| truncate_legend(line.trim(), room, FONT_SIZE, options))
+        .collect()
+}
+
+/// Width of every key: `key_size` with `options.fixed_key_width`, otherwise
+/// wide enough for the longest label in `layers`.
+fn key_width(layers: &[Layer], options: &RenderOptions) -> f32 {
+    if options.fixed_key_width {
+        options.metrics.key_size
+    } else {
+        compute_key_width(layers, &options.metrics)
+    }
+}
+
+/// Stable element id of a key, e.g. `layer-1-r2-c4`.
+fn key_id(layer_index: usize, key: &PlacedKey) -> String {
+    format!("layer-{}-r{}-c{}", layer_index, key.row, key.column)
+}
+
+/// Position name of a key, or `#<index>` for keys beyond the geometry.
+fn position_name(names: &[String], key: &PlacedKey) -> String {
+    names
+        .get(key.index)
+        .cloned()
+        .unwrap_or_else(|| format!("#{}", key.index))
+}
+
+/// The text drawn on a key: its legend, or its position name when debugging
+/// geometries with `options.label_positions`.
+fn key_legend(key: &PlacedKey, names: &[String], options: &RenderOptions) -> String {
+    if options.label_positions {
+        position_name(names, key)
+    } else {
+        key.legend().to_string()
+    }
+}
+
+/// Creates the SVG document with background, styles, and gradients applied.
+fn new_document(width: f32, height: f32, options: &RenderOptions) -> Document {
+    let theme = &options.theme;
+    // Embedded documents scale down to fit note columns
+    let scale = if options.embed {
+        (EMBED_MAX_WIDTH / width).min(1.0)
+    } else {
+        1.0
+    };
+    let mut document = Document::new()
+        .set("width", (width * scale) as i32)
+        .set("height", (height * scale) as i32)
+        .set("viewBox", (0, 0, width as i32, height as i32));
+
+    // Add background; monochrome output is printed on white paper, and
+    // embedded output takes the page's background
+    if !options.embed {
+        let background = Rectangle::new()
+            .set("width", "100%")
+            .set("height", "100%")
+            .set(
+                "fill",
+                if theme.monochrome {
+                    "#ffffff"
+                } else {
+                    theme.background.as_str()
+                },
+            );
+        document = document.add(background);
+    }
+
+    // Add enhanced styles with gradients, shadows, and color coding; the
+    // theme's key shapes come first so style-specific rules can refine them,
+    // and monochrome rules come last to replace the colors
+    let document = document.add(Style::new(format!(
+        "{}{}{}{}{}{}{}{}{}",
+        theme.shapes.css(&theme.outline),
+        STYLE,
+        theme.legend_css(),
+        theme.surface_css(),
+        theme.typography.css(),
+        theme.accent_css(),
+        theme.keycode_css(),
+        if options.embed { EMBED_STYLE } else { "" },
+        theme.monochrome_css()
+    )));
+
+    // Add gradient definitions, and the patterns replacing them in monochrome
+    let patterns = theme.pattern_definitions();
+    let mut document = add_gradients(document, theme);
+    if options.icons {
+        document = document.add(icons::definitions());
+    }
+    if patterns.is_empty() {
+        return document;
+    }
+    document.add(
+        patterns
+            .into_iter()
+            .fold(Definitions::new(), |defs, pattern| defs.add(pattern)),
+    )
+}
+
+const METADATA_LINE_HEIGHT: f32 = 20.0;
+const NOTE_LINE_HEIGHT: f32 = 18.0;
+
+/// Extra height taken by a layer's notes below its title.
+fn notes_height(layer: &Layer) -> f32 {
+    layer.notes.len() as f32 * NOTE_LINE_HEIGHT
+}
+
+/// Height taken by the warning banner up to the next title baseline, or zero
+/// if there are no warnings.
+fn warning_banner_height(warnings: &[String]) -> f32 {
+    if warnings.is_empty() {
+        return 0.0;
+    }
+    warnings.len() as f32 * METADATA_LINE_HEIGHT + 40.0
+}
+
+/// Draws the warning banner, starting level with a title whose baseline is
+/// at `y`.
+fn add_warning_banner(
+    mut document: Document,
+    y: f32,
+    width: f32,
+    warnings: &[String],
+    m: &LayoutMetrics,
+) -> Document {
+    if warnings.is_empty() {
+        return document;
+    }
+
+    let top = y - 15.0;
+    let banner = Rectangle::new()
+        .set("class", "warning-banner")
+        .set("x", m.margin)
+        .set("y", top)
+        .set("width", width - m.margin * 2.0)
+        .set(
+            "height",
+            warnings.len() as f32 * METADATA_LINE_HEIGHT + 10.0,
+        )
+        .set("rx", 5);
+    document = document.add(banner);
+
+    for (line, warning) in warnings.iter().enumerate() {
+        let text = Text::new("")
+            .set("class", "warning-text")
+            .set("x", m.margin + 10.0)
+            .set("y", top + (line as f32 + 1.0) * METADATA_LINE_HEIGHT)
+            .add(svg::node::Text::new(format!("⚠ {}", warning)));
+        document = document.add(text);
+    }
+
+    document
+}
+
+/// Draws a tinted area behind each half of a split board, labeled L and R
+/// below it, for keys whose first row starts at `y`.
+fn add_half_tints(
+    mut document: Document,
+    y: f32,
+    key_width: f32,
+    options: &RenderOptions,
+) -> Document {
+    if !options.half_tint {
+        return document;
+    }
+
+    let m = &options.metrics;
+    let pad = m.spacing.max(4.0);
+    for (half, bounds) in half_bounds(&options.geometry, key_width, m) {
+        let (class, label) = match half {
+            Half::Left => ("half-left", "L"),
+            Half::Right => ("half-right", "R"),
+        };
+        let bounds = bounds.offset_y(y);
+        let area = Rectangle::new()
+            .set("class", format!("half-tint {}", class))
+            .set("x", bounds.x - pad)
+            .set("y", bounds.y - pad)
+            .set("width", bounds.width + pad * 2.0)
+            .set("height", bounds.height + pad * 2.0)
+            .set("rx", 8);
+        let text = Text::new("")
+            .set("class", "half-label")
+            .set("x", bounds.x + bounds.width / 2.0)
+            .set("y", bounds.y + bounds.height + pad + 16.0)
+            .add(svg::node::Text::new(label));
+        document = document.add(area).add(text);
+    }
+
+    document
+}
+
+/// Draws arcs under a mirrored layer's keys joining each base key's position
+/// to its mirror image, for keys whose first row starts at `y`.
+fn add_mirror_arcs(
+    mut document: Document,
+    layer: &Layer,
+    y: f32,
+    key_width: f32,
+    options: &RenderOptions,
+) -> Document {
+    let Some(mirror) = options.mirrors.iter().find(|m| m.layer == layer.index) else {
+        return document;
+    };
+
+    let centers: HashMap<usize, (f32, f32)> = place_keys(
+        layer,
+        &options.geometry,
+        key_width,
+        &options.metrics,
+        EmptyKeys::Show,
+    )
+    .iter()
+    .map(|key| {
+        let b = &key.bounds;
+        (key.index, (b.x + b.width / 2.0, y + b.y + b.height / 2.0))
+    })
+    .collect();
+    for (from, to) in &mirror.pairs {
+        let (Some(&(x1, y1)), Some(&(x2, y2))) = (centers.get(from), centers.get(to)) else {
+            continue;
+        };
+        // Bow upward in proportion to the distance between the halves
+        let lift = (x2 - x1).abs() * 0.08;
+        let arc = Path::new().set("class", "mirror-arc").set(
+            "d",
+            format!(
+                "M{} {} Q{} {} {} {}",
+                x1,
+                y1,
+                (x1 + x2) / 2.0,
+                y1.min(y2) - lift,
+                x2,
+                y2
+            ),
+        );
+        document = document.add(arc);
+    }
+
+    document
+}
+
+/// Draws the board's trackballs and encoders, labeling each encoder with the
+/// layer's bindings, for keys whose first row starts at `y`.
+fn add_controls(
+    mut document: Document,
+    layer: &Layer,
+    y: f32,
+    key_width: f32,
+    options: &RenderOptions,
+) -> Document {
+    let legend = |key: &str| key.strip_prefix("KC_").unwrap_or(key).to_string();
+    for control in place_controls(&options.geometry, key_width, &options.metrics) {
+        let (cx, cy, r) = (control.cx, y + control.cy, control.radius);
+        let group = match control.encoder {
+            None => Group::new()
+                .set("class", "trackball-group")
+                .add(Title::new(options.strings.trackball.as_str()))
+                .add(
+                    Circle::new()
+                        .set("class", "trackball")
+                        .set("cx", cx)
+                        .set("cy", cy)
+                        .set("r", r),
+                )
+                .add(
+                    Circle::new()
+                        .set("class", "trackball-shine")
+                        .set("cx", cx - r * 0.35)
+                        .set("cy", cy - r * 0.35)
+                        .set("r", r * 0.2),
+                ),
+            Some(index) => {
+                let name = options.strings.encoder(index + 1);
+                let group = Group::new().set("class", "encoder-group").add(
+                    Circle::new()
+                        .set("class", "key encoder")
+                        .set("cx", cx)
+                        .set("cy", cy)
+                        .set("r", r),
+                );
+                match layer.encoders.get(index) {
+                    Some(binding) => group
+                        .add(Title::new(format!("{}: {}", name, binding.describe())))
+                        .add(
+                            Text::new(format!("↺ {}", legend(&binding.ccw)))
+                                .set("class", "key-text encoder-text")
+                                .set("x", cx)
+                                .set("y", cy - 3.0),
+                        )
+                        .add(
+                            Text::new(format!("↻ {}", legend(&binding.cw)))
+                                .set("class", "key-text encoder-text")
+                                .set("x", cx)
+                                .set("y", cy + 9.0),
+                        ),
+                    None => group.add(Title::new(name)),
+                }
+            }
+        };
+        document = document.add(group);
+    }
+    document
+}
+
+/// Draws a labeled dashed outline around each key group shown on a layer,
+/// for keys whose first row starts at `y`.
+fn add_key_groups(
+    mut document: Document,
+    layer: &Layer,
+    y: f32,
+    key_width: f32,
+    options: &RenderOptions,
+) -> Document {
+    const LABEL_FONT_SIZE: f32 = 10.0;
+    const TAB_HEIGHT: f32 = 14.0;
+    const TAB_PADDING: f32 = 5.0;
+
+    let groups = options.key_groups.on_layer(layer, &options.geometry);
+    if groups.is_empty() {
+        return document;
+    }
+    let bounds: HashMap<usize, KeyBox> = place_keys(
+        layer,
+        &options.geometry,
+        key_width,
+        &options.metrics,
+        EmptyKeys::Show,
+    )
+    .iter()
+    .map(|key| (key.index, key.bounds))
+    .collect();
+
+    // The outline runs through the middle of the gaps around the keys
+    let pad = options.metrics.spacing / 2.0;
+    for (label, indices) in groups {
+        let boxes: Vec<&KeyBox> = indices.iter().filter_map(|i| bounds.get(i)).collect();
+        if boxes.is_empty() {
+            continue;
+        }
+        let left = boxes.iter().map(|b| b.x).fold(f32::MAX, f32::min) - pad;
+        let top = y + boxes.iter().map(|b| b.y).fold(f32::MAX, f32::min) - pad;
+        let right = boxes.iter().map(|b| b.x + b.width).fold(f32::MIN, f32::max) + pad;
+        let bottom = y
+            + boxes
+                .iter()
+                .map(|b| b.y + b.height)
+                .fold(f32::MIN, f32::max)
+            + pad;
+        let outline = Rectangle::new()
+            .set("class", "group-outline")
+            .set("x", left)
+            .set("y", top)
+            .set("width", right - left)
+            .set("height", bottom - top)
+            .set("rx", 8);
+
+        // The label sits in a tab straddling the outline's top edge
+        let label_width =
+            legend_width(label, options) * LABEL_FONT_SIZE / FONT_SIZE + TAB_PADDING * 2.0;
+        let tab = Rectangle::new()
+            .set("class", "group-tab")
+            .set("x", left + 8.0)
+            .set("y", top - TAB_HEIGHT / 2.0)
+            .set("width", label_width)
+            .set("height", TAB_HEIGHT)
+            .set("rx", 3);
+        let text = Text::new("")
+            .set("class", "group-label")
+            .set("x", left + 8.0 + TAB_PADDING)
+            .set("y", top + LABEL_FONT_SIZE / 2.0 - 1.0)
+            .add(svg::node::Text::new(label));
+        document = document.add(outline).add(tab).add(text);
+    }
+
+    document
+}
+
+/// The stable anchor id of a layer's section, e.g. `layer-nav` for `_NAV` or
+/// `layer-3` for an unnamed layer, for links like `keymap.html#layer-nav`.
+pub fn layer_anchor(layer: &Layer) -> String {
+    let slug = layer.name.as_deref().map(|name| {
+        name.to_ascii_lowercase()
+            .split(|c: char| !c.is_ascii_alphanumeric())
+            .filter(|part| !part.is_empty())
+            .collect::<Vec<_>>()
+            .join("-")
+    });
+    match slug {
+        Some(slug) if !slug.is_empty() => format!("layer-{}", slug),
+        _ => format!("layer-{}", layer.index),
+    }
+}
+
+/// Height taken by the thumb key table, including its title and heading
+/// row, or zero if the board has no thumb keys.
+fn thumb_table_height(table: &analysis::ThumbKeys, m: &LayoutMetrics) -> f32 {
+    if table.rows.is_empty() {
+        return 0.0;
+    }
+    40.0 + (table.rows.len() + 1) as f32 * METADATA_LINE_HEIGHT + m.margin
+}
+
+/// Draws the thumb key table with its title baseline at `y`: a row per
+/// thumb position and a column per layer, transparent keys marked `▽`.
+fn add_thumb_table(
+    mut document: Document,
+    y: f32,
+    width: f32,
+    table: &analysis::ThumbKeys,
+    options: &RenderOptions,
+) -> Document {
+    if table.rows.is_empty() {
+        return document;
+    }
+    let m = &options.metrics;
+    // Panel text is 13px against the 11px legends `legend_width` measures
+    let text_width = |text: &str| legend_width(text, options) * 13.0 / FONT_SIZE;
+
+    let title = Text::new("")
+        .set("class", "layer-title")
+        .set("x", m.margin)
+        .set("y", y)
+        .add(svg::node::Text::new(options.strings.thumb_keys.as_str()));
+    let panel = Rectangle::new()
+        .set("class", "metadata-panel")
+        .set("x", m.margin)
+        .set("y", y + 15.0)
+        .set("width", width - m.margin * 2.0)
+        .set(
+            "height",
+            (table.rows.len() + 1) as f32 * METADATA_LINE_HEIGHT + 10.0,
+        )
+        .set("rx", 5);
+    document = document.add(title).add(panel);
+
+    let label_width = table
+        .rows
+        .iter()
+        .map(|(label, _)| text_width(label))
+        .fold(0.0, f32::max)
+        + 20.0;
+    let column_width = (width - m.margin * 2.0 - 10.0 - label_width) / table.layers.len() as f32;
+    let cell = |text: &str, class: &str, column: Option<usize>, line: usize| {
+        let x = m.margin
+            + 10.0
+            + column.map_or(0.0, |column| label_width + column as f32 * column_width);
+        let room = if column.is_some() {
+            column_width - 10.0
+        } else {
+            label_width
+        };
+        Text::new("")
+            .set("class", class)
+            .set("x", x)
+            .set("y", y + 15.0 + (line as f32 + 1.0) * METADATA_LINE_HEIGHT)
+            .add(svg::node::Text::new(truncate_legend(
+                text,
+                room * FONT_SIZE / 13.0,
+                FONT_SIZE,
+                options,
+            )))
+    };
+
+    for (column, layer) in table.layers.iter().enumerate() {
+        document = document.add(cell(layer, "metadata-text metadata-label", Some(column), 0));
+    }
+    for (line, (label, keys)) in table.rows.iter().enumerate() {
+        document = document.add(cell(label, "metadata-text metadata-label", None, line + 1));
+        for (column, key) in keys.iter().enumerate() {
+            let legend = if is_empty_key(key) {
+                "▽".to_string()
+            } else {
+                tap_legend(key, options)
+            };
+            document = document.add(cell(&legend, "metadata-text", Some(column), line + 1));
+        }
+    }
+    document
+}
+
+/// Height taken by a label/value panel, including its title, or zero if
+/// there are no entries.
+fn panel_height(entries: &[(String, String)], m: &LayoutMetrics) -> f32 {
+    if entries.is_empty() {
+        return 0.0;
+    }
+    40.0 + entries.len() as f32 * METADATA_LINE_HEIGHT + m.margin
+}
+
+/// Height of one color scale legend, including the gap after it.
+const SCALE_LEGEND_HEIGHT: f32 = 55.0;
+
+/// Height of everything drawn below the layers: the metadata, layer key, and
+/// combo panels, the thumb key table, and the color scale legends.
+fn panels_height(options: &RenderOptions) -> f32 {
+    let m = &options.metrics;
+    panel_height(&options.metadata, m)
+        + panel_height(&options.layer_key_docs, m)
+        + panel_height(&options.combos, m)
+        + thumb_table_height(&options.thumb_keys, m)
+        + options.scales.len() as f32 * SCALE_LEGEND_HEIGHT
+        + options.qr.as_ref().map_or(0.0, |_| qr::QR_SIZE + 20.0)
+}
+
+/// Draws a label/value panel, such as the metadata, with its title baseline
+/// at `y`.
+fn add_panel(
+    mut document: Document,
+    y: f32,
+    width: f32,
+    heading: &str,
+    entries: &[(String, String)],
+    m: &LayoutMetrics,
+) -> Document {
+    if entries.is_empty() {
+        return document;
+    }
+
+    let title = Text::new("")
+        .set("class", "layer-title")
+        .set("x", m.margin)
+        .set("y", y)
+        .add(svg::node::Text::new(heading));
+    let panel = Rectangle::new()
+        .set("class", "metadata-panel")
+        .set("x", m.margin)
+        .set("y", y + 15.0)
+        .set("width", width - m.margin * 2.0)
+        .set("height", entries.len() as f32 * METADATA_LINE_HEIGHT + 10.0)
+        .set("rx", 5);
+    document = document.add(title).add(panel);
+
+    for (line, (label, value)) in entries.iter().enumerate() {
+        let text = Text::new("")
+            .set("class", "metadata-text")
+            .set("x", m.margin + 10.0)
+            .set("y", y + 15.0 + (line as f32 + 1.0) * METADATA_LINE_HEIGHT)
+            .add(
+                svg::node::element::TSpan::new("")
+                    .set("class", "metadata-label")
+                    .add(svg::node::Text::new(format!("{}: ", label))),
+            )
+            .add(svg::node::Text::new(value.as_str()));
+        document = document.add(text);
+    }
+
+    document
+}
+
+/// Draws the metadata, layer key, and combo panels and the thumb key table,
+/// the first title baseline at `y`.
+fn add_panels(document: Document, y: f32, width: f32, options: &RenderOptions) -> Document {
+    let m = &options.metrics;
+    let strings = &options.strings;
+    let document = add_panel(document, y, width, &strings.metadata, &options.metadata, m);
+    let y = y + panel_height(&options.metadata, m);
+    let document = add_panel(
+        document,
+        y,
+        width,
+        &strings.layer_keys,
+        &options.layer_key_docs,
+        m,
+    );
+    let y = y + panel_height(&options.layer_key_docs, m);
+    let document = add_panel(document, y, width, &strings.combos, &options.combos, m);
+    let y = y + panel_height(&options.combos, m);
+    let mut document = add_thumb_table(document, y, width, &options.thumb_keys, options);
+    let y = y + thumb_table_height(&options.thumb_keys, m);
+    for (index, scale) in options.scales.iter().enumerate() {
+        let id = format!("scale{}Gradient", index);
+        let legend = scale.legend(&id, m.margin, y + index as f32 * SCALE_LEGEND_HEIGHT);
+        document = document.add(legend);
+    }
+    let y = y + options.scales.len() as f32 * SCALE_LEGEND_HEIGHT;
+    if let Some(qr) = &options.qr {
+        document = document.add(qr.draw(m.margin, y));
+    }
+    document
+}
+
+/// Generates an SVG visualization of keyboard layers.
+///
+/// Creates a comprehensive SVG document displaying multiple keyboard layers with
+/// proper spacing, gradients, and interactive styling. Each layer is rendered
+/// separately with its keys arranged according to the Keyball44 geometry.
+///
+/// # Arguments
+///
+/// * `layers` - A slice of `Layer` structs containing the keyboard layout data
+///
+/// # Returns
+///
+/// A `String` containing the complete SVG document
+pub fn generate_svg(layers: &[Layer]) -> String {
+    generate_svg_with_options(layers, &RenderOptions::default())
+}
+
+/// Generates an SVG visualization of keyboard layers using custom render options.
+///
+/// # Arguments
+///
+/// * `layers` - A slice of `Layer` structs containing the keyboard layout data
+/// * `options` - Options controlling colors and other rendering details
+///
+/// # Returns
+///
+/// A `String` containing the complete SVG document
+pub fn generate_svg_with_options(layers: &[Layer], options: &RenderOptions) -> String {
+    let renderers = layer_renderers(layers, options);
+    let comparison = analysis::base_variants(layers).and_then(|variants| {
+        let renderer = renderers.first()?;
+        Some(VariantSection::new(layers, variants, renderer))
+    });
+    combine(&renderers, comparison.as_ref(), options)
+}
+
+/// One layer of a keymap prepared for drawing: its title, notes, and keys,
+/// sized and colored consistently with the keymap's other layers.
+///
+/// Created by [`layer_renderers`]; [`combine_layers`] stacks them into a
+/// document.
+#[derive(Debug, Clone)]
+pub struct LayerRenderer<'a> {
+    layer: &'a Layer,
+    key_width: f32,
+    slots: LayerSlots,
+    names: Vec<String>,
+    options: &'a RenderOptions,
+}
+
+impl<'a> LayerRenderer<'a> {
+    /// The layer being drawn.
+    pub fn layer(&self) -> &'a Layer {
+        self.layer
+    }
+
+    /// Width of the document the layer is drawn in, the same for every
+    /// layer of a keymap.
+    pub fn width(&self) -> f32 {
+        svg_width(
+            self.key_width,
+            &self.options.geometry,
+            &self.options.metrics,
+        )
+    }
+
+    /// Height from the title baseline to the bottom of the keys.
+    pub fn height(&self) -> f32 {
+        40.0 + notes_height(self.layer) + keys_height(&self.options.geometry, &self.options.metrics)
+    }
+
+    /// Draws the layer with its title baseline at `y`.
+    ///
+    /// # Arguments
+    ///
+    /// * `document` - The document to draw on
+    /// * `y` - Position of the title baseline
+    ///
+    /// # Returns
+    ///
+    /// The document with the layer added
+    pub fn draw(&self, mut document: Document, y: f32) -> Document {
+        let (layer, options) = (self.layer, self.options);
+        let m = &options.metrics;
+        let mut y_offset = y;
+
+        // Draw layer title, the target of the layer's deep links
         let title = Text::new("")
             .set("class", "layer-title")
-            .set("x", MARGIN)
+            .set("id", layer_anchor(layer))
+            .set("x", m.margin)
             .set("y", y_offset)
-            .add(svg::node::Text::new(format!("Layer {}", layer.index)));
+            .add(svg::node::Text::new(
+                self.options.strings.layer_title(layer),
+            ));
         document = document.add(title);
         y_offset += 40.0;
 
-        // Draw keys for each row
-        for (row_idx, row) in layer.keys.iter().enumerate() {
-            if row_idx >= layout.len() {
-                continue;
-            }
+        // Draw layer notes under the title
+        for note in &layer.notes {
+            let text = Text::new("")
+                .set("class", "layer-note")
+                .set("x", m.margin)
+                .set("y", y_offset - 12.0)
+                .add(svg::node::Text::new(note.as_str()));
+            document = document.add(text);
+            y_offset += NOTE_LINE_HEIGHT;
+        }
 
-            let (left_count, left_offset, right_count, right_offset) = layout[row_idx];
-            let y = y_offset + row_idx as f32 * (KEY_HEIGHT + KEY_SPACING);
+        document = add_half_tints(document, y_offset, self.key_width, options);
+        document = add_mirror_arcs(document, layer, y_offset, self.key_width, options);
 
-            // Draw left half keys
-            for (col_idx, key) in row.iter().take(left_count as usize).enumerate() {
-                let x = MARGIN
-                    + left_offset * (key_width + KEY_SPACING)
-                    + col_idx as f32 * (key_width + KEY_SPACING);
+        // Draw keys at their geometry positions
+        for key in place_keys(
+            layer,
+            &options.geometry,
+            self.key_width,
+            m,
+            options.empty_keys,
+        ) {
+            let key = key.offset_y(y_offset);
+            let group = key_group(
+                key_id(layer.index, &key),
+                key_class(&key, layer.index, &self.slots),
+                &key_legend(&key, &self.names, options),
+                key.bounds,
+                key.secondary,
+                key.row,
+                options,
+            );
+            document = document.add(decorate(rotate(group, &key), &key, options));
+        }
 
-                // Draw key
-                let rect = Rectangle::new()
-                    .set("class", get_key_class(key, layer.index))
-                    .set("x", x)
-                    .set("y", y)
-                    .set("width", key_width)
-                    .set("height", KEY_HEIGHT)
-                    .set("rx", 5);
-                document = document.add(rect);
-
-                // Draw key label
-                let text = Text::new("")
-                    .set("class", "key-text")
-                    .set("x", x + key_width / 2.0)
-                    .set("y", y + KEY_HEIGHT / 2.0 + FONT_SIZE / 3.0)
-                    .add(svg::node::Text::new(key.as_str()));
-                document = document.add(text);
-            }
+        document = add_controls(document, layer, y_offset, self.key_width, options);
+        add_key_groups(document, layer, y_offset, self.key_width, options)
+    }
+}
 
-            // Draw right half keys
-            let right_start_idx = left_count as usize;
-            let right_base_x = MARGIN + left_width + SPLIT_GAP;
+/// A board showing only the keys that differ between the keymap's base
+/// variants, e.g. `Q / F` where QWERTY has Q and Colemak has F.
+struct VariantSection<'a> {
+    /// The base layer with every key replaced by its comparison legend, or
+    /// an empty key where the variants agree
+    layer: Layer,
+    /// Kind of difference, legend, and tooltip at each changed position
+    changes: HashMap<usize, (analysis::VariantChange, String, String)>,
+    note: String,
+    renderer: &'a LayerRenderer<'a>,
+}
 
-            for (col_idx, key) in row
+impl<'a> VariantSection<'a> {
+    /// Builds the comparison, sized like the layers `renderer` draws.
+    fn new(
+        layers: &[Layer],
+        variants: analysis::BaseVariants,
+        renderer: &'a LayerRenderer<'a>,
+    ) -> Self {
+        let reference = |index: &usize| {
+            layers
                 .iter()
-                .skip(right_start_idx)
-                .take(right_count as usize)
-                .enumerate()
-            {
-                let x = right_base_x
-                    + right_offset * (key_width + KEY_SPACING)
-                    + col_idx as f32 * (key_width + KEY_SPACING);
-
-                if !is_empty_key(key) || row_idx < 3 {
-                    // Draw key
-                    let rect = Rectangle::new()
-                        .set("class", get_key_class(key, layer.index))
-                        .set("x", x)
-                        .set("y", y)
-                        .set("width", key_width)
-                        .set("height", KEY_HEIGHT)
-                        .set("rx", 5);
-                    document = document.add(rect);
-
-                    // Draw key label
-                    let text = Text::new("")
-                        .set("class", "key-text")
-                        .set("x", x + key_width / 2.0)
-                        .set("y", y + KEY_HEIGHT / 2.0 + FONT_SIZE / 3.0)
-                        .add(svg::node::Text::new(key.as_str()));
-                    document = document.add(text);
+                .find(|layer| layer.index == *index)
+                .map(|layer| renderer.options.strings.layer_title(layer))
+                .unwrap_or_default()
+        };
+        let references: Vec<String> = variants.layers.iter().map(reference).collect();
+
+        let mut changes = HashMap::new();
+        for difference in variants.differences {
+            let tooltip = references
+                .iter()
+                .zip(&difference.keys)
+                .map(|(variant, key)| format!("{}: {}", variant, key))
+                .collect::<Vec<_>>()
+                .join(", ");
+            // Letters share their hold behavior, so only the taps are shown
+            let legend = difference
+                .keys
+                .iter()
+                .map(|key| match difference.change {
+                    analysis::VariantChange::Letters => analysis::split_tap(key).1,
+                    analysis::VariantChange::Other => key,
+                })
+                .map(|key| key.replace("KC_", ""))
+                .collect::<Vec<_>>()
+                .join(" / ");
+            changes.insert(difference.position, (difference.change, legend, tooltip));
+        }
+
+        // Keep the base layer's rows so keys land on the same positions
+        let base = renderer.layer;
+        let mut position = 0;
+        let keys = base
+            .keys
+            .iter()
+            .map(|row| {
+                row.iter()
+                    .map(|_| {
+                        let label = match changes.get(&position) {
+                            Some((_, legend, _)) => legend.clone(),
+                            None => "_______".to_string(),
+                        };
+                        position += 1;
+                        label
+                    })
+                    .collect()
+            })
+            .collect();
+
+        VariantSection {
+            layer: Layer {
+                keys,
+                notes: Vec::new(),
+                encoders: Vec::new(),
+                ..base.clone()
+            },
+            changes,
+            note: renderer.options.strings.base_variants_note(&references),
+            renderer,
+        }
+    }
+
+    /// Height from the title baseline to the bottom of the keys.
+    fn height(&self) -> f32 {
+        40.0 + NOTE_LINE_HEIGHT
+            + keys_height(
+                &self.renderer.options.geometry,
+                &self.renderer.options.metrics,
+            )
+    }
+
+    /// Draws the comparison with its title baseline at `y`.
+    fn draw(&self, mut document: Document, y: f32) -> Document {
+        let options = self.renderer.options;
+        let m = &options.metrics;
+        let key_width = self.renderer.key_width;
+
+        document = document
+            .add(
+                Text::new("")
+                    .set("class", "layer-title")
+                    .set("id", "base-variants")
+                    .set("x", m.margin)
+                    .set("y", y)
+                    .add(svg::node::Text::new(options.strings.base_variants.as_str())),
+            )
+            .add(
+                Text::new("")
+                    .set("class", "layer-note")
+                    .set("x", m.margin)
+                    .set("y", y + 28.0)
+                    .add(svg::node::Text::new(self.note.as_str())),
+            );
+        let y_offset = y + 40.0 + NOTE_LINE_HEIGHT;
+        document = add_half_tints(document, y_offset, key_width, options);
+
+        for key in place_keys(
+            &self.layer,
+            &options.geometry,
+            key_width,
+            m,
+            options.empty_keys,
+        ) {
+            let key = key.offset_y(y_offset);
+            let (class, tooltip) = match self.changes.get(&key.index) {
+                Some((analysis::VariantChange::Letters, _, tooltip)) => {
+                    ("key key-special key-variant".to_string(), Some(tooltip))
                 }
+                Some((analysis::VariantChange::Other, _, tooltip)) => (
+                    "key key-system key-variant-other".to_string(),
+                    Some(tooltip),
+                ),
+                None => (
+                    key_class(&key, self.layer.index, &LayerSlots::default()),
+                    None,
+                ),
+            };
+            let mut group = key_group(
+                format!("variants-r{}-c{}", key.row, key.column),
+                class,
+                key.label,
+                key.bounds,
+                key.secondary,
+                key.row,
+                options,
+            );
+            if let Some(tooltip) = tooltip {
+                group = group.add(Title::new(tooltip.as_str()));
             }
+            document = document.add(rotate(group, &key));
+        }
 
-            // Draw remaining keys from row (if any beyond the split)
-            let remaining_start = right_start_idx + right_count as usize;
-            if remaining_start < row.len() {
-                for (offset, key) in row.iter().skip(remaining_start).enumerate() {
-                    let x = right_base_x
-                        + (right_count as f32 + offset as f32) * (key_width + KEY_SPACING);
-
-                    // Draw key
-                    let rect = Rectangle::new()
-                        .set("class", get_key_class(key, layer.index))
-                        .set("x", x)
-                        .set("y", y)
-                        .set("width", key_width)
-                        .set("height", KEY_HEIGHT)
-                        .set("rx", 5);
-                    document = document.add(rect);
-
-                    // Draw key label
-                    let text = Text::new("")
-                        .set("class", "key-text")
-                        .set("x", x + key_width / 2.0)
-                        .set("y", y + KEY_HEIGHT / 2.0 + FONT_SIZE / 3.0)
-                        .add(svg::node::Text::new(key.as_str()));
-                    document = document.add(text);
-                }
+        document
+    }
+}
+
+/// Prepares every layer of a keymap for drawing, with key sizes and colors
+/// shared across the layers so they match when drawn separately.
+///
+/// # Arguments
+///
+/// * `layers` - The keymap's layers
+/// * `options` - Options controlling colors and other rendering details
+///
+/// # Returns
+///
+/// One renderer per layer, in order
+pub fn layer_renderers<'a>(
+    layers: &'a [Layer],
+    options: &'a RenderOptions,
+) -> Vec<LayerRenderer<'a>> {
+    let slots = color_slots(layers, options);
+    let names = options.geometry.position_names();
+    let key_width = key_width(layers, options);
+    layers
+        .iter()
+        .map(|layer| LayerRenderer {
+            layer,
+            key_width,
+            slots: slots.clone(),
+            names: names.clone(),
+            options,
+        })
+        .collect()
+}
+
+/// Stacks layers into one SVG document, under the warning banner and above
+/// the metadata and layer key panels.
+///
+/// Passing a single renderer gives a document of just that layer, e.g. to
+/// write one file per layer.
+///
+/// # Arguments
+///
+/// * `renderers` - The layers to draw, from [`layer_renderers`]
+/// * `options` - Options controlling colors and other rendering details
+///
+/// # Returns
+///
+/// A `String` containing the complete SVG document
+pub fn combine_layers(renderers: &[LayerRenderer], options: &RenderOptions) -> String {
+    combine(renderers, None, options)
+}
+
+/// Stacks layers and, when the keymap has several base variants, their
+/// comparison below them.
+fn combine(
+    renderers: &[LayerRenderer],
+    comparison: Option<&VariantSection>,
+    options: &RenderOptions,
+) -> String {
+    let m = &options.metrics;
+    let svg_width = renderers.first().map_or_else(
+        || svg_width(m.key_size, &options.geometry, m),
+        LayerRenderer::width,
+    );
+
+    // Layers are separated by the layer spacing, which also leads into the panels
+    let top = m.margin + warning_banner_height(&options.warnings);
+    let sections = renderers.len() + usize::from(comparison.is_some());
+    let layers_bottom = top
+        + renderers.iter().map(LayerRenderer::height).sum::<f32>()
+        + comparison.map_or(0.0, VariantSection::height)
+        + sections.saturating_sub(1) as f32 * m.layer_spacing;
+    let panels_height = panels_height(options);
+    let total_height = if panels_height == 0.0 {
+        layers_bottom + m.margin
+    } else {
+        layers_bottom + m.layer_spacing + panels_height
+    };
+
+    let mut document = new_document(svg_width, total_height, options);
+    document = add_warning_banner(document, m.margin, svg_width, &options.warnings, m);
+
+    let mut y_offset = top;
+    for renderer in renderers {
+        document = renderer.draw(document, y_offset);
+        y_offset += renderer.height() + m.layer_spacing;
+    }
+    if let Some(comparison) = comparison {
+        document = comparison.draw(document, y_offset);
+        y_offset += comparison.height() + m.layer_spacing;
+    }
+
+    document = add_panels(document, y_offset, svg_width, options);
+
+    document.to_string()
+}
+
+/// Generates a single-card cheatsheet SVG.
+///
+/// Only the base layer is drawn as a full keyboard. Each keycap hosts the base
+/// legend in its center and up to four corner mini-legends showing the key's
+/// assignment on layers 1 through 4 (top-left, top-right, bottom-left, and
+/// bottom-right respectively). Empty keys on those layers leave their corner blank.
+///
+/// # Arguments
+///
+/// * `layers` - A slice of `Layer` structs; the first is treated as the base layer
+///
+/// # Returns
+///
+/// A `String` containing the complete SVG document
+pub fn generate_single_card_svg(layers: &[Layer]) -> String {
+    generate_single_card_svg_with_options(layers, &RenderOptions::default())
+}
+
+/// Generates a single-card cheatsheet SVG using custom render options.
+///
+/// # Arguments
+///
+/// * `layers` - A slice of `Layer` structs; the first is treated as the base layer
+/// * `options` - Options controlling colors and other rendering details
+///
+/// # Returns
+///
+/// A `String` containing the complete SVG document
+pub fn generate_single_card_svg_with_options(layers: &[Layer], options: &RenderOptions) -> String {
+    const CORNER_INSET: f32 = 5.0;
+
+    let m = &options.metrics;
+    let geometry = &options.geometry;
+    let names = geometry.position_names();
+    let slots = color_slots(layers, options);
+
+    let Some(base) = layers.first() else {
+        return new_document(svg_width(m.key_size, geometry, m), m.margin * 2.0, options)
+            .to_string();
+    };
+
+    // Size keys for the base legend and every corner legend drawn on them
+    let key_width = key_width(&layers[..layers.len().min(5)], options);
+    let svg_width = svg_width(key_width, geometry, m);
+    let card_height =
+        m.margin + warning_banner_height(&options.warnings) + 60.0 + keys_height(geometry, m);
+    let panels_height = panels_height(options);
+    let total_height = if panels_height == 0.0 {
+        card_height + m.margin
+    } else {
+        card_height + 40.0 + panels_height
+    };
+
+    let mut document = new_document(svg_width, total_height, options);
+    document = add_warning_banner(document, m.margin, svg_width, &options.warnings, m);
+
+    let mut y_offset = m.margin + warning_banner_height(&options.warnings);
+    let title = Text::new("")
+        .set("class", "layer-title")
+        .set("x", m.margin)
+        .set("y", y_offset)
+        .add(svg::node::Text::new(options.strings.layer_title(base)));
+    document = document.add(title);
+    y_offset += 20.0;
+
+    // Describe which corner belongs to which layer
+    let legend = layers
+        .iter()
+        .skip(1)
+        .take(4)
+        .enumerate()
+        .map(|(corner, layer)| options.strings.corner_legend(corner, layer.index))
+        .collect::<Vec<_>>()
+        .join("   ");
+    document = document.add(
+        Text::new("")
+            .set("class", "card-legend")
+            .set("x", m.margin)
+            .set("y", y_offset)
+            .add(svg::node::Text::new(legend)),
+    );
+    y_offset += 20.0;
+
+    // Corner legends are looked up by LAYOUT argument position
+    let corner_layers: Vec<(&Layer, Vec<&String>)> = layers
+        .iter()
+        .skip(1)
+        .take(4)
+        .map(|layer| (layer, layer.keys.iter().flatten().collect()))
+        .collect();
+
+    document = add_half_tints(document, y_offset, key_width, options);
+
+    for key in place_keys(base, geometry, key_width, m, options.empty_keys) {
+        let key = key.offset_y(y_offset);
+        let bounds = key.bounds;
+        let mut group = key_group(
+            key_id(base.index, &key),
+            key_class(&key, base.index, &slots),
+            &key_legend(&key, &names, options),
+            bounds,
+            key.secondary,
+            key.row,
+            options,
+        );
+
+        // Layer keys name their target so HTML output can preview it on hover
+        if let Some(target) = slots.target(key.label) {
+            group = group.set("data-layer", target);
+        }
+
+        // Corner legends sit on the part of the key that holds legends
+        let surface = top_surface(bounds, key.row, options.style);
+        let (left, right) = (
+            surface.x + CORNER_INSET,
+            surface.x + surface.width - CORNER_INSET,
+        );
+        let (top, bottom) = (
+            surface.y + CORNER_INSET + CORNER_FONT_SIZE,
+            surface.y + surface.height - CORNER_INSET,
+        );
+
+        // (x, y, text-anchor) for each corner
+        let corners = [
+            (left, top, "start"),
+            (right, top, "end"),
+            (left, bottom, "start"),
+            (right, bottom, "end"),
+        ];
+
+        for (corner, (layer, keys)) in corner_layers.iter().enumerate() {
+            let Some(corner_key) = keys.get(key.index) else {
+                continue;
+            };
+            if is_empty_key(corner_key) {
+                continue;
             }
+
+            let (corner_x, corner_y, anchor) = corners[corner];
+            let slot = slots.slot(layer.index).unwrap_or(corner + 1);
+            let class = match legend_kind(corner_key).class() {
+                Some(kind) => format!("key-corner corner-layer{} {}", slot, kind),
+                None => format!("key-corner corner-layer{}", slot),
+            };
+            let corner_legend = options
+                .legends
+                .legends
+                .get(corner_key.as_str())
+                .map(|o| o.legend.clone())
+                .or_else(|| {
+                    recognized_keycode(corner_key, options).map(|info| info.legend.to_string())
+                })
+                .or_else(|| keycodes::layer_mod(corner_key).map(|lm| lm.legend()))
+                .unwrap_or_else(|| corner_key.to_string());
+            let text = Text::new("")
+                .set("class", class)
+                .set("x", corner_x)
+                .set("y", corner_y)
+                .set("text-anchor", anchor)
+                .add(svg::node::Text::new(
+                    options.theme.typography.case.apply(&corner_legend),
+                ));
+            group = group.add(text);
         }
+        document = document.add(decorate(rotate(group, &key), &key, options));
+    }
+
+    let panel_y = y_offset + keys_height(geometry, m) + 40.0;
+    document = add_panels(document, panel_y, svg_width, options);
+
+    document.to_string()
+}
+
+/// Generates a sample SVG showing every built-in style with the layer palette.
+///
+/// Each style gets a titled row of dummy keys: a plain key, a special key, a
+/// system key, an empty key, and one key per palette slot.
+///
+/// # Arguments
+///
+/// * `options` - Options providing the key metrics
+///
+/// # Returns
+///
+/// A `String` containing the complete SVG document
+pub fn generate_theme_preview_svg(options: &RenderOptions) -> String {
+    let m = &options.metrics;
+    let mut samples = vec![
+        ("key".to_string(), "KC_A".to_string()),
+        ("key key-special".to_string(), "QK_BOOT".to_string()),
+        ("key key-system".to_string(), "NK_TOGG".to_string()),
+        ("key key-empty".to_string(), "_______".to_string()),
+    ];
+    samples.extend(
+        (1..=PALETTE_SIZE)
+            .map(|slot| (format!("key key-layer{}", slot), format!("Layer {}", slot))),
+    );
+
+    let sample_layer = Layer {
+        index: 0,
+        name: None,
+        title: None,
+        keys: vec![samples.iter().map(|(_, label)| label.clone()).collect()],
+        notes: Vec::new(),
+        layout_macro: None,
+        source: None,
+        encoders: Vec::new(),
+    };
+    let key_width = key_width(std::slice::from_ref(&sample_layer), options);
+    let unit = key_width + m.spacing;
+
+    let section_height = 40.0 + m.row_pitch() + 30.0;
+    let width = m.margin * 2.0 + samples.len() as f32 * unit - m.spacing;
+    let height = m.margin + RenderStyle::ALL.len() as f32 * section_height;
+    let mut document = new_document(width, height, options);
+
+    let mut y_offset = m.margin;
+    for style in RenderStyle::ALL {
+        let title = Text::new("")
+            .set("class", "layer-title")
+            .set("x", m.margin)
+            .set("y", y_offset)
+            .add(svg::node::Text::new(style.name()));
+        document = document.add(title);
+        y_offset += 40.0;
 
-        y_offset += 4.0 * (KEY_HEIGHT + KEY_SPACING) + LAYER_SPACING;
+        let styled = RenderOptions {
+            style,
+            ..options.clone()
+        };
+        for (col, (class, label)) in samples.iter().enumerate() {
+            let bounds = KeyBox {
+                x: m.margin + col as f32 * unit,
+                y: y_offset,
+                width: key_width,
+                height: m.key_size,
+            };
+            document = document.add(key_group(
+                format!("{}-c{}", style.name(), col),
+                class.clone(),
+                label,
+                bounds,
+                None,
+                1,
+                &styled,
+            ));
+        }
+
+        y_offset += m.row_pitch() + 30.0;
+    }
+
+    document.to_string()
+}
+
+/// A single key rendered as a square image, e.g. for a macro pad display.
+#[derive(Debug, Clone)]
+pub struct KeyTile {
+    /// Stable key id, e.g. `layer-1-r2-c4`
+    pub id: String,
+    /// Canonical position name, e.g. `L-R3C4`
+    pub position: String,
+    /// Physical row of the key in the geometry
+    pub row: usize,
+    /// Position of the key within its physical row
+    pub column: usize,
+    /// The key's legend
+    pub label: String,
+    /// Standalone SVG document of `size`×`size` pixels
+    pub svg: String,
+}
+
+/// Renders every key of a layer as its own square SVG tile.
+///
+/// Keys keep their layer colors; the legend shrinks to fit the tile. Empty
+/// keys follow `options.empty_keys`.
+///
+/// # Arguments
+///
+/// * `layers` - All layers of the keymap, used to resolve layer colors
+/// * `layer` - The layer to slice into tiles
+/// * `options` - Options controlling colors, style, and geometry
+/// * `size` - Width and height of each tile in pixels
+///
+/// # Returns
+///
+/// One tile per drawn key, in LAYOUT argument order
+pub fn generate_key_tiles(
+    layers: &[Layer],
+    layer: &Layer,
+    options: &RenderOptions,
+    size: u32,
+) -> Vec<KeyTile> {
+    const TILE_INSET: f32 = 4.0;
+
+    let slots = color_slots(layers, options);
+    let names = options.geometry.position_names();
+    let size = size as f32;
+    // Tiles fit their legends with their own font size
+    let options = &RenderOptions {
+        overflow: LegendOverflow::Visible,
+        ..options.clone()
+    };
+    let bounds = KeyBox {
+        x: TILE_INSET,
+        y: TILE_INSET,
+        width: size - TILE_INSET * 2.0,
+        height: size - TILE_INSET * 2.0,
+    };
+
+    place_keys(
+        layer,
+        &options.geometry,
+        size,
+        &options.metrics,
+        options.empty_keys,
+    )
+    .iter()
+    .map(|key| {
+        // Shrink long legends so they fit on the tile
+        let label = key.legend();
+        let fit = (bounds.width - 8.0) / (label.chars().count().max(1) as f32 * 0.62);
+        let font_size = fit.clamp(6.0, size / 5.0);
+        let document = new_document(size, size, options)
+            .add(Style::new(format!(
+                ".key-text {{ font-size: {:.1}px; }}",
+                font_size
+            )))
+            .add(decorate(
+                key_group(
+                    key_id(layer.index, key),
+                    key_class(key, layer.index, &slots),
+                    label,
+                    bounds,
+                    None,
+                    key.row,
+                    options,
+                ),
+                &PlacedKey {
+                    bounds,
+                    secondary: None,
+                    ..key.clone()
+                },
+                options,
+            ));
+
+        KeyTile {
+            id: key_id(layer.index, key),
+            position: position_name(&names, key),
+            row: key.row,
+            column: key.column,
+            label: key.label.to_string(),
+            svg: document.to_string(),
+        }
+    })
+    .collect()
+}
+
+/// Renders a single key in isolation, in the same style as the full keymap,
+/// e.g. for keycap images in documentation or blog posts.
+///
+/// Same as [`render_row`] with one keycode.
+///
+/// # Arguments
+///
+/// * `keycode` - The key's keycode, e.g. `LT(1, KC_SPC)`
+/// * `options` - Options controlling colors, style, and legends
+///
+/// # Returns
+///
+/// A standalone SVG document
+pub fn render_key(keycode: &str, options: &RenderOptions) -> Document {
+    render_row(&[keycode], options)
+}
+
+/// Renders keys side by side in one row, in the same style as the full
+/// keymap, e.g. to illustrate a chord or a home row in documentation.
+///
+/// Keys are as wide as the keymap's keys would be for the same legends and
+/// spaced by `options.metrics.spacing`, inside `options.metrics.margin`.
+/// Layer keys take the colors of layers 1 through [`PALETTE_SIZE`] by their
+/// number, and empty keys follow `options.empty_keys`.
+///
+/// # Arguments
+///
+/// * `keycodes` - The keys from left to right
+/// * `options` - Options controlling colors, style, and legends
+///
+/// # Returns
+///
+/// A standalone SVG document
+pub fn render_row(keycodes: &[&str], options: &RenderOptions) -> Document {
+    let m = &options.metrics;
+    let layer = Layer {
+        index: 0,
+        name: None,
+        title: None,
+        keys: vec![keycodes.iter().map(|key| key.to_string()).collect()],
+        notes: Vec::new(),
+        layout_macro: None,
+        source: None,
+        encoders: Vec::new(),
+    };
+    let width = key_width(std::slice::from_ref(&layer), options);
+    let count = keycodes.len() as f32;
+    let slots = color_slots(&[], options);
+
+    let mut document = new_document(
+        m.margin * 2.0 + count * width + (count - 1.0).max(0.0) * m.spacing,
+        m.margin * 2.0 + m.key_size,
+        options,
+    );
+    for (column, keycode) in keycodes.iter().enumerate() {
+        let empty = is_empty_key(keycode);
+        if empty && options.empty_keys == EmptyKeys::Hide {
+            continue;
+        }
+        let key = PlacedKey {
+            layer: 0,
+            row: 0,
+            column,
+            index: column,
+            label: keycode,
+            bounds: KeyBox {
+                x: m.margin + column as f32 * (width + m.spacing),
+                y: m.margin,
+                width,
+                height: m.key_size,
+            },
+            secondary: None,
+            ghost: empty && options.empty_keys == EmptyKeys::Ghost,
+            rotation: 0.0,
+        };
+        let group = key_group(
+            key_id(0, &key),
+            key_class(&key, 0, &slots),
+            key.legend(),
+            key.bounds,
+            None,
+            key.row,
+            options,
+        );
+        document = document.add(decorate(group, &key, options));
+    }
+    document
+}
+
+/// Renders a miniature of one layer without its title, e.g. for previews in
+/// tooltips.
+///
+/// # Arguments
+///
+/// * `layers` - All layers of the keymap, used to resolve layer colors
+/// * `layer` - The layer to draw
+/// * `options` - Options controlling colors, style, and geometry; the key
+///   metrics are replaced by compact ones
+///
+/// # Returns
+///
+/// A `String` containing the complete SVG document
+pub fn generate_layer_thumbnail_svg(
+    layers: &[Layer],
+    layer: &Layer,
+    options: &RenderOptions,
+) -> String {
+    let m = LayoutMetrics {
+        key_size: 30.0,
+        spacing: 3.0,
+        split_gap: 20.0,
+        layer_spacing: 0.0,
+        margin: 6.0,
+    };
+    let slots = color_slots(layers, options);
+    let geometry = &options.geometry;
+    // Legends are set smaller than the overflow policies measure them
+    let options = &RenderOptions {
+        overflow: LegendOverflow::Visible,
+        ..options.clone()
+    };
+
+    let width = svg_width(m.key_size, geometry, &m);
+    let height = keys_height(geometry, &m) + m.margin * 2.0 - m.spacing;
+    let mut document = new_document(width, height, options).add(Style::new(
+        ".key { stroke-width: 1; } .key-text { font-size: 6px; }",
+    ));
+
+    for key in place_keys(layer, geometry, m.key_size, &m, options.empty_keys) {
+        let key = key.offset_y(m.margin);
+        let group = key_group(
+            key_id(layer.index, &key),
+            key_class(&key, layer.index, &slots),
+            key.legend(),
+            key.bounds,
+            key.secondary,
+            key.row,
+            options,
+        );
+        document = document.add(decorate(rotate(group, &key), &key, options));
     }
 
     document.to_string()
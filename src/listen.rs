@@ -0,0 +1,199 @@
+//! The `listen` subcommand: a live layer indicator driven by layer-change
+//! messages from the keyboard.
+//!
+//! Messages arrive either as QMK console lines piped to stdin (e.g. from
+//! `qmk console`) or as raw HID reports read from a hidraw device. The active
+//! layer is printed to the terminal and served, together with the rendered
+//! keymap, as a self-updating HTML page.
+use anyhow::{Context, Result, bail};
+use keyball44_viz::Layer;
+use keyball44_viz::analysis::layer_reference;
+use regex::Regex;
+use std::fs::File;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::path::Path;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+
+/// First byte of a raw HID report announcing a layer change; the second byte
+/// is the layer index.
+const RAW_HID_LAYER_COMMAND: u8 = b'L';
+
+/// Size of QMK raw HID reports.
+const RAW_HID_REPORT_SIZE: usize = 32;
+
+/// Stored while no layer change has been received yet.
+const NO_LAYER: usize = usize::MAX;
+
+/// Extracts the layer from a console line such as `layer: 2` or `layer=2`.
+fn parse_console_line(regex: &Regex, line: &str) -> Option<usize> {
+    regex.captures(line).and_then(|caps| caps[1].parse().ok())
+}
+
+/// Extracts the layer from a raw HID report.
+fn parse_raw_report(report: &[u8]) -> Option<usize> {
+    match report {
+        [RAW_HID_LAYER_COMMAND, layer, ..] => Some(*layer as usize),
+        _ => None,
+    }
+}
+
+/// Records a layer change and reports it on the terminal.
+fn update_layer(active: &AtomicUsize, layers: &[Layer], layer: usize) {
+    if active.swap(layer, Ordering::SeqCst) == layer {
+        return;
+    }
+
+    match layers.iter().find(|l| l.index == layer) {
+        Some(l) => println!("Active: {}", layer_reference(l)),
+        None => println!("Active: layer {} (not in the keymap)", layer),
+    }
+}
+
+/// Reads layer changes from console lines on stdin until it closes.
+fn read_console(active: &AtomicUsize, layers: &[Layer]) -> Result<()> {
+    let regex = Regex::new(r"(?i)\blayer\s*[:=]?\s*(\d+)").unwrap();
+
+    for line in std::io::stdin().lock().lines() {
+        let line = line.context("Failed to read console messages from stdin")?;
+        if let Some(layer) = parse_console_line(&regex, &line) {
+            update_layer(active, layers, layer);
+        }
+    }
+
+    Ok(())
+}
+
+/// Reads layer changes from raw HID reports until the device goes away.
+fn read_device(device: &Path, active: &AtomicUsize, layers: &[Layer]) -> Result<()> {
+    let mut file = File::open(device).context(format!("Failed to open HID device {:?}", device))?;
+    let mut report = [0u8; RAW_HID_REPORT_SIZE];
+
+    loop {
+        let read = file
+            .read(&mut report)
+            .context(format!("Failed to read from HID device {:?}", device))?;
+        if read == 0 {
+            bail!("HID device {:?} was disconnected", device);
+        }
+        if let Some(layer) = parse_raw_report(&report[..read]) {
+            update_layer(active, layers, layer);
+        }
+    }
+}
+
+/// The live page: the keymap with every layer but the active one dimmed.
+fn live_page(svg: &str) -> String {
+    format!(
+        r#"<!DOCTYPE html>
+<html>
+<head>
+<meta charset="utf-8">
+<title>Active layer</title>
+<style>
+  body {{ margin: 0; background: #faf8f3; }}
+  #status {{ font: 600 16px sans-serif; color: #2c3e50; padding: 8px 20px; }}
+  .key-group {{ transition: opacity 0.2s; }}
+  body.live .key-group {{ opacity: 0.25; }}
+</style>
+<style id="active"></style>
+</head>
+<body>
+<div id="status">Waiting for a layer change…</div>
+{svg}
+<script>
+  let current = null;
+  async function poll() {{
+    try {{
+      const state = await (await fetch("/layer")).json();
+      if (state.layer !== null && state.layer !== current) {{
+        current = state.layer;
+        document.body.classList.add("live");
+        document.getElementById("active").textContent =
+          `body.live [id^="layer-${{current}}-"] {{ opacity: 1; }}`;
+        document.getElementById("status").textContent = `Layer ${{current}}`;
+        const first = document.querySelector(`[id^="layer-${{current}}-"]`);
+        if (first) first.scrollIntoView({{ behavior: "smooth", block: "center" }});
+      }}
+    }} catch (e) {{}}
+    setTimeout(poll, 250);
+  }}
+  poll();
+</script>
+</body>
+</html>
+"#,
+        svg = svg
+    )
+}
+
+/// Answers a single HTTP request with the page or the current layer.
+fn handle(mut stream: TcpStream, page: &str, active: &AtomicUsize) -> Result<()> {
+    let mut request_line = String::new();
+    BufReader::new(&stream).read_line(&mut request_line)?;
+    let path = request_line.split_whitespace().nth(1).unwrap_or("/");
+
+    let (status, content_type, body) = match path {
+        "/" => ("200 OK", "text/html; charset=utf-8", page.to_string()),
+        "/layer" => {
+            let layer = match active.load(Ordering::SeqCst) {
+                NO_LAYER => serde_json::Value::Null,
+                layer => layer.into(),
+            };
+            (
+                "200 OK",
+                "application/json",
+                serde_json::json!({ "layer": layer }).to_string(),
+            )
+        }
+        _ => ("404 Not Found", "text/plain", "Not found".to_string()),
+    };
+
+    write!(
+        stream,
+        "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nCache-Control: no-store\r\nConnection: close\r\n\r\n{}",
+        status,
+        content_type,
+        body.len(),
+        body
+    )?;
+    Ok(())
+}
+
+/// Serves the live page and follows layer changes until the message source
+/// closes.
+///
+/// # Arguments
+///
+/// * `layers` - The parsed keymap layers, used to name the active layer
+/// * `svg` - The rendered keymap shown on the page
+/// * `device` - A hidraw device to read raw HID reports from, or `None` to
+///   read console lines from stdin
+/// * `port` - Local port the page is served on
+pub fn run(layers: &[Layer], svg: &str, device: Option<&Path>, port: u16) -> Result<()> {
+    let listener = TcpListener::bind(("127.0.0.1", port))
+        .context(format!("Failed to listen on port {}", port))?;
+    let page = Arc::new(live_page(svg));
+    let active = Arc::new(AtomicUsize::new(NO_LAYER));
+
+    {
+        let (page, active) = (Arc::clone(&page), Arc::clone(&active));
+        thread::spawn(move || {
+            for stream in listener.incoming().flatten() {
+                // A failed request only affects that browser poll
+                let _ = handle(stream, &page, &active);
+            }
+        });
+    }
+
+    eprintln!("Serving the live layer view on http://127.0.0.1:{}/", port);
+    match device {
+        Some(device) => read_device(device, &active, layers),
+        None => {
+            eprintln!("Reading QMK console messages from stdin (e.g. qmk console | ...)");
+            read_console(&active, layers)
+        }
+    }
+}
@@ -1,70 +1,2744 @@
-use anyhow::{Context, Result};
-use clap::Parser;
-use keyball44_viz::{generate_svg, is_empty_key, parse_layers, Layer};
+use anyhow::{Context, Result, anyhow};
+use clap::{Parser, Subcommand, ValueEnum};
+use failure::{ErrorFormat, ErrorKind, Failure, ResultExt};
+use keyball44_viz::defines::{self, KeycodeDefines};
+use keyball44_viz::geometry_cache::{self, GeometryCache};
+use keyball44_viz::i18n::Translations;
+use keyball44_viz::notes::{self, KeymapNotes};
+use keyball44_viz::output::{DEFAULT_PNG_DPI, HtmlPage, LayerPreview, OutputFormat};
+use keyball44_viz::publish::{self, PublishConfig};
+use keyball44_viz::qr::QrFooter;
+use keyball44_viz::scale::{ColorScale, ScaleRange, ValueScale};
+use keyball44_viz::theme::Gradient;
+use keyball44_viz::{
+    EmptyKeys, Geometry, KeyDecorator, Layer, LayoutMetrics, LegendOverflow, PALETTE_SIZE,
+    RenderOptions, RenderStyle, Theme, analysis, autoshift, autoshift_badges, combine_layers,
+    combos, config, custom_keycodes, diff, diff_overlay, effort_overlay, emit, firmware, format,
+    generate_key_tiles, generate_layer_thumbnail_svg, generate_single_card_svg_with_options,
+    generate_svg_with_options, generate_theme_preview_svg, geometry, git, groups, heatmap, history,
+    info_json, keycodes, keymap_json, keymap_toml, layer_anchor, layer_renderers, mock,
+    note_badges, oryx, parse_error, parse_layers, raster, redact, rules, tabs, tap_dance, theme,
+    zmk,
+};
+use std::collections::BTreeMap;
 use std::fs;
-use std::path::PathBuf;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use std::sync::Arc;
 
-#[derive(Parser, Debug)]
-#[command(version, about, long_about = None)]
+#[cfg(feature = "clipboard")]
+mod clipboard;
+mod diagnostics;
+mod doctor;
+mod failure;
+mod listen;
+mod watch;
+
+#[derive(Parser, Debug, Clone)]
+#[command(
+    version,
+    about,
+    long_about = None,
+    args_conflicts_with_subcommands = true,
+    subcommand_negates_reqs = true
+)]
 struct Args {
-    /// Path to the keymap.c file
-    keymap_file: PathBuf,
+    #[command(subcommand)]
+    command: Option<Command>,
+
+    /// Format of error messages and diagnostics printed on stderr
+    #[arg(long, global = true, value_enum, default_value_t = ErrorFormat::Text)]
+    error_format: ErrorFormat,
+
+    /// Silence a diagnostic by code or name (e.g. KV0030), or all of them with `warnings`
+    #[arg(long, global = true, value_name = "CODE", value_parser = diagnostics::parse_lint)]
+    allow: Vec<&'static str>,
+
+    /// Report a diagnostic as a warning, overriding `--allow warnings` or `--deny warnings`
+    #[arg(long, global = true, value_name = "CODE", value_parser = diagnostics::parse_lint)]
+    warn: Vec<&'static str>,
+
+    /// Report a diagnostic as an error and fail the run, e.g. --deny KV0012 or --deny warnings
+    #[arg(long, global = true, value_name = "CODE", value_parser = diagnostics::parse_lint)]
+    deny: Vec<&'static str>,
+
+    /// TOML or JSON file with `allow`, `warn`, and `deny` lists of diagnostics, applied
+    /// before the flags
+    #[arg(long, global = true, value_name = "FILE")]
+    diagnostics: Option<PathBuf>,
+
+    /// Path to the keymap.c, QMK keymap.json, or ZMK .keymap file (or an experimental
+    /// .hex/.uf2/.bin firmware image)
+    #[arg(required = true)]
+    keymap_file: Option<PathBuf>,
+
+    /// Format of the keymap file [default: from the extension, keymap.c otherwise]
+    #[arg(long, value_enum)]
+    input_format: Option<InputFormat>,
+
+    /// Read the keymap file as the TOML written by `export toml`, same as --input-format toml
+    #[arg(long, default_value_t = false, conflicts_with = "input_format")]
+    from_toml: bool,
+
+    /// Check that exporting the TOML layers again reproduces them (TOML input only)
+    #[arg(long, default_value_t = false)]
+    verify_roundtrip: bool,
+
+    /// Fail when a layer has more or fewer keys than the board, instead of warning
+    #[arg(long, default_value_t = false, conflicts_with = "lenient")]
+    strict: bool,
+
+    /// Pad short layers with empty keys and drop the extra keys of long ones, with a warning
+    #[arg(long, default_value_t = false)]
+    lenient: bool,
 
     /// Display statistics about the keymap
     #[arg(short, long, default_value_t = false)]
     show_stats: bool,
 
-    /// Output SVG file name
+    /// Print the parsed keymap to stdout as JSON, or write the layers as a tabbed HTML page,
+    /// instead of the usual render
+    #[arg(long, value_enum, value_name = "FORMAT")]
+    emit: Option<EmitFormat>,
+
+    /// Output file name, repeatable; the format follows the extension (.svg, .svgz, .png, .html)
     #[arg(short, long)]
-    output_file: Option<PathBuf>,
+    output_file: Vec<PathBuf>,
+
+    /// Also copy the drawing to the clipboard, as a PNG image or as SVG text [default: png]
+    #[arg(
+        long,
+        value_enum,
+        value_name = "FORMAT",
+        num_args = 0..=1,
+        default_missing_value = "png",
+        conflicts_with = "split_layers"
+    )]
+    copy: Option<CopyFormat>,
+
+    /// Keep running and render again each time the keymap (or a config file it is drawn
+    /// with) is saved; errors are printed without exiting
+    #[arg(long, default_value_t = false)]
+    watch: bool,
+
+    /// Shell command to run after each successful render in watch mode; the output path
+    /// is in $KEYBALL44_VIZ_OUTPUT (all of them in $KEYBALL44_VIZ_OUTPUTS)
+    #[arg(long, value_name = "COMMAND", requires = "watch")]
+    on_update: Option<String>,
+
+    /// Formats to write next to the output name, e.g. "svg,png,html" [default: svg]
+    #[arg(long, value_enum, value_delimiter = ',')]
+    output_format: Vec<OutputFormat>,
+
+    /// Resolution of PNG output; 96 is one pixel per SVG unit
+    #[arg(long, value_name = "DPI", default_value_t = DEFAULT_PNG_DPI, value_parser = parse_dpi)]
+    dpi: f32,
+
+    /// Hide legends of keycodes matching a glob pattern (e.g. "MY_PASS*"), repeatable
+    #[arg(long, value_name = "PATTERN")]
+    redact: Vec<String>,
+
+    /// Legend drawn in place of redacted keys
+    #[arg(long, value_name = "TEXT", default_value = redact::DEFAULT_REDACTION)]
+    redact_with: String,
+
+    /// Pin a named layer to a palette color slot (1-8), e.g. "NAV=1", repeatable
+    #[arg(long, value_name = "NAME=SLOT", value_parser = parse_layer_color)]
+    layer_color: Vec<(String, usize)>,
+
+    /// Fill keys by keycode with a color, e.g. "KC_ESC,QK_BOOT=#ff0000", on top of the
+    /// theme's colors, repeatable
+    #[arg(long, value_name = "KEYCODES=COLOR", value_parser = parse_key_color)]
+    color: Vec<(Vec<String>, String)>,
+
+    /// Path to a rules.mk whose enabled features are listed in the metadata panel
+    #[arg(long, value_name = "RULES_FILE")]
+    rules: Option<PathBuf>,
+
+    /// Key height and minimum key width in pixels
+    #[arg(long, value_name = "PX", default_value_t = LayoutMetrics::default().key_size)]
+    key_size: f32,
+
+    /// Gap between neighboring keys in pixels
+    #[arg(long, value_name = "PX", default_value_t = LayoutMetrics::default().spacing)]
+    spacing: f32,
+
+    /// Gap between the left and right halves in pixels
+    #[arg(long, value_name = "PX", default_value_t = LayoutMetrics::default().split_gap)]
+    split_gap: f32,
+
+    /// Vertical gap between layers in pixels
+    #[arg(long, value_name = "PX", default_value_t = LayoutMetrics::default().layer_spacing)]
+    layer_spacing: f32,
+
+    /// Outer margin in pixels
+    #[arg(long, value_name = "PX", default_value_t = LayoutMetrics::default().margin)]
+    margin: f32,
+
+    /// Keep keys --key-size wide instead of widening every key to fit the longest label; labels that don't fit are abbreviated, then drawn by --overflow
+    #[arg(long, default_value_t = false)]
+    fixed_key_width: bool,
+
+    /// Visual style used to draw keys
+    #[arg(long, value_enum, default_value_t = RenderStyle::Flat)]
+    style: RenderStyle,
+
+    /// Render only the base layer with layers 1-4 as corner legends
+    #[arg(long, default_value_t = false)]
+    single_card: bool,
+
+    /// Write one file per layer (keymap-layer0.svg, keymap-layer1.svg, ...) instead of one tall document
+    #[arg(long, default_value_t = false, conflicts_with = "single_card")]
+    split_layers: bool,
+
+    /// Also write a plain-text description of each layer, for use as alt text
+    #[arg(long, value_name = "FILE")]
+    alt_text: Option<PathBuf>,
+
+    /// How keys without an assignment are drawn
+    #[arg(long, value_enum, default_value_t = EmptyKeys::Show)]
+    empty_keys: EmptyKeys,
+
+    /// Draw position names (e.g. L-R3C4) instead of keycodes, to debug geometries
+    #[arg(long, default_value_t = false)]
+    label_positions: bool,
+
+    /// TOML or JSON legend table drawn instead of the keycodes (see `export keycodes --format legends`)
+    #[arg(long, value_name = "FILE")]
+    legends: Option<PathBuf>,
+
+    /// TOML or JSON table of display names for custom keycodes, e.g. MY_EMAIL = "Email"
+    #[arg(long, value_name = "FILE")]
+    aliases: Option<PathBuf>,
+
+    /// Label keys written as a #define alias (e.g. NAV for MO(1)) with the alias instead of
+    /// the expanded keycode
+    #[arg(long, default_value_t = false)]
+    define_labels: bool,
+
+    /// Draw readable legends for basic keycodes (KC_BSPC as ⌫, KC_LSFT as Shift); the keycode stays in the tooltip
+    #[arg(long, default_value_t = false)]
+    readable_legends: bool,
+
+    /// Draw icons instead of text legends for arrows, Backspace, Enter, Shift, GUI, and media,
+    /// volume, and brightness keys; the keycode stays in the tooltip
+    #[arg(long, default_value_t = false)]
+    icons: bool,
+
+    /// Turn legends that don't fit across narrow, tall keys vertical, measured with the installed fonts
+    #[arg(long, default_value_t = false)]
+    vertical_legends: bool,
+
+    /// How legends too wide for their key are drawn; the full legend moves to the key's tooltip
+    #[arg(long, value_enum, default_value_t = LegendOverflow::Ellipsis)]
+    overflow: LegendOverflow,
+
+    /// Tint the background of each half of a split board and label it L/R
+    #[arg(long, default_value_t = false)]
+    half_tint: bool,
+
+    /// Join the keys of layers mirroring the base layer's other hand to their base positions
+    #[arg(long, default_value_t = false)]
+    mirror_arcs: bool,
+
+    /// TOML or JSON file of labeled key groups (e.g. a numpad) outlined on the layers they list
+    #[arg(long, value_name = "FILE")]
+    groups: Option<PathBuf>,
+
+    /// Preset for embedding in notes apps (Obsidian, Notion): transparent, narrower, no shadows, dark-mode aware
+    #[arg(long, default_value_t = false)]
+    embed: bool,
+
+    /// Explain each layer key in plain language in a panel below the layers
+    #[arg(long, default_value_t = false)]
+    explain_layer_keys: bool,
+
+    /// List every layer's thumb keys side by side in a table below the layers
+    #[arg(long, default_value_t = false)]
+    thumb_keys: bool,
+
+    /// Print-friendly black and white output using fill patterns instead of colors
+    #[arg(long, alias = "no-color", default_value_t = false)]
+    monochrome: bool,
+
+    /// TOML or JSON theme file of colors, key shapes, and typography
+    #[arg(long, value_name = "FILE")]
+    theme: Option<PathBuf>,
+
+    /// Draw on a dark background with light text, for dark-themed pages
+    #[arg(long, default_value_t = false, conflicts_with_all = ["theme", "monochrome"])]
+    dark: bool,
+
+    /// Language of titles, headings, and labels: a built-in language (en, de, fr, es) or a
+    /// TOML or JSON translation table
+    #[arg(long, value_name = "LANG")]
+    lang: Option<String>,
+
+    /// Show each key's effort and score the base layer by English letter frequency
+    #[arg(long, default_value_t = false)]
+    effort: bool,
+
+    /// TOML or JSON file of effort per position name, replacing the built-in split 3x6 model
+    #[arg(long, value_name = "FILE")]
+    effort_model: Option<PathBuf>,
+
+    /// Color keys of the base layer by use: a .csv of key,count lines (position name, LAYOUT
+    /// index, or keycode) or any other file as text to count key presses in
+    #[arg(long, value_name = "FILE")]
+    heatmap: Option<PathBuf>,
+
+    /// Color scale of value overlays such as --effort and --heatmap
+    #[arg(long, value_enum, default_value_t = ColorScale::Viridis)]
+    color_scale: ColorScale,
+
+    /// Value at the low end of the color scale; lower values are clamped [default: smallest shown]
+    #[arg(long, value_name = "VALUE", allow_hyphen_values = true)]
+    scale_min: Option<f32>,
+
+    /// Value at the high end of the color scale; higher values are clamped [default: largest shown]
+    #[arg(long, value_name = "VALUE", allow_hyphen_values = true)]
+    scale_max: Option<f32>,
+
+    /// Draw a QR code linking to this URL (e.g. the keymap's repository) below the layers
+    #[arg(long, value_name = "URL")]
+    qr: Option<String>,
+
+    /// Keyboard geometry to draw (keyball44, corne, corne-3x5, lily58, keyball39) [default: detected]
+    #[arg(long, value_name = "NAME", value_parser = parse_board)]
+    board: Option<Geometry>,
+
+    /// TOML or JSON geometry file of the board's key positions, or a QMK info.json (a file, a keyboard directory of a QMK tree, or a URL), for boards without a built-in geometry
+    #[arg(long, value_name = "FILE", conflicts_with = "board")]
+    geometry: Option<PathBuf>,
+
+    /// Re-read the QMK info.json of --geometry instead of using its cached geometry
+    #[arg(long, requires = "geometry")]
+    refresh: bool,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+enum InputFormat {
+    /// QMK keymap.c source
+    C,
+    /// QMK keymap.json from QMK Configurator or `qmk c2json`
+    Json,
+    /// ZMK .keymap devicetree file
+    Zmk,
+    /// Layers written by `export toml`
+    Toml,
+    /// Compiled .hex/.uf2/.bin firmware image (experimental)
+    Firmware,
+}
+
+impl InputFormat {
+    /// The format implied by a file's extension, keymap.c for unknown ones.
+    fn from_path(path: &Path) -> Self {
+        let extension = path
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .map(str::to_ascii_lowercase);
+        match extension.as_deref() {
+            Some("json") => InputFormat::Json,
+            Some("keymap") => InputFormat::Zmk,
+            Some("toml") => InputFormat::Toml,
+            Some("hex" | "uf2" | "bin") => InputFormat::Firmware,
+            _ => InputFormat::C,
+        }
+    }
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug)]
+enum TileSize {
+    /// 72×72, Stream Deck MK.2 and Mini
+    #[value(name = "72")]
+    Px72,
+    /// 96×96, Stream Deck XL and Loupedeck
+    #[value(name = "96")]
+    Px96,
+}
+
+impl TileSize {
+    fn pixels(self) -> u32 {
+        match self {
+            TileSize::Px72 => 72,
+            TileSize::Px96 => 96,
+        }
+    }
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug)]
+enum KeycodeListFormat {
+    /// One keycode per line with its count and layers
+    Text,
+    /// A JSON array of {keycode, count, layers} objects
+    Json,
+    /// A legend-override file with every keycode mapped to itself, to edit
+    Legends,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug)]
+enum EmitFormat {
+    /// Layers with every key's keycode, kind, position, and target layer
+    Json,
+    /// A single-view HTML page with a tab per layer, written to the output file
+    Html,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+enum CopyFormat {
+    /// PNG image, for pasting into chat apps and documents
+    Png,
+    /// SVG document as text
+    Svg,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug)]
+enum StatsFormat {
+    /// One summary line per layer
+    Text,
+    /// Long-format CSV with one (layer, metric, value) row per statistic
+    Csv,
+    /// A JSON array of {layer, metric, value} objects
+    Json,
+}
+
+#[derive(Subcommand, Debug, Clone)]
+enum ExportCommand {
+    /// List every distinct keycode with how often and on which layers it is used
+    Keycodes {
+        /// Path to the keymap.c file (or an experimental .hex/.uf2/.bin firmware image)
+        keymap_file: PathBuf,
+
+        /// Output format
+        #[arg(long, value_enum, default_value_t = KeycodeListFormat::Text)]
+        format: KeycodeListFormat,
+
+        /// Check that loading the legend table back reproduces it (legends format only)
+        #[arg(long, default_value_t = false)]
+        verify_roundtrip: bool,
+    },
+    /// Key counts per layer, in long format for spreadsheets and plotting tools
+    Stats {
+        /// Path to the keymap.c file (or an experimental .hex/.uf2/.bin firmware image)
+        keymap_file: PathBuf,
+
+        /// Output format
+        #[arg(long, value_enum, default_value_t = StatsFormat::Csv)]
+        format: StatsFormat,
+
+        /// Value of a leading `label` column (e.g. a date or git revision), to
+        /// append runs to one file and trend them over time
+        #[arg(long)]
+        label: Option<String>,
+
+        /// Leave out the CSV header row, e.g. when appending to an existing file
+        #[arg(long, default_value_t = false)]
+        no_header: bool,
+    },
+    /// The layers as TOML for editing legends by hand; render the result with --from-toml
+    Toml {
+        /// Path to the keymap.c file (or an experimental .hex/.uf2/.bin firmware image)
+        keymap_file: PathBuf,
+
+        /// Check that loading the TOML back with --from-toml reproduces the layers
+        #[arg(long, default_value_t = false)]
+        verify_roundtrip: bool,
+    },
+}
+
+#[derive(Subcommand, Debug, Clone)]
+enum ImportCommand {
+    /// Convert a ZSA Oryx layout export (.json) into a keymap.c for another board
+    Oryx {
+        /// Path to the JSON export downloaded from Oryx
+        layout_json: PathBuf,
+
+        /// Keyboard geometry the layout is moved to
+        #[arg(long, value_name = "NAME", value_parser = parse_board, default_value = "keyball44")]
+        board: Geometry,
+
+        /// TOML or JSON file placing Oryx keys (by index) at target positions
+        #[arg(long, value_name = "FILE")]
+        mapping: Option<PathBuf>,
+
+        /// Print the position mapping in use as TOML instead of importing, as a
+        /// starting point for a mapping file
+        #[arg(long, default_value_t = false)]
+        print_mapping: bool,
+
+        /// Check that parsing the generated keymap.c reproduces the imported layers
+        #[arg(long, default_value_t = false)]
+        verify_roundtrip: bool,
+
+        /// Output keymap.c file name [default: <export>.c]
+        #[arg(short, long)]
+        output_file: Option<PathBuf>,
+    },
+}
+
+#[derive(Subcommand, Debug, Clone)]
+enum Command {
+    /// Check the keymap and environment for common problems
+    Doctor {
+        /// Path to the keymap.c file
+        keymap_file: PathBuf,
+
+        /// Output SVG file name to check the destination of
+        #[arg(short, long)]
+        output_file: Option<PathBuf>,
+
+        /// Keyboard geometry to check against [default: detected]
+        #[arg(long, value_name = "NAME", value_parser = parse_board)]
+        board: Option<Geometry>,
+    },
+    /// Report the keys that changed since an earlier git revision of the keymap
+    Diff {
+        /// Path to the keymap.c file, or the old keymap when comparing two files
+        keymap_file: PathBuf,
+
+        /// The new keymap to compare the first one with
+        #[arg(required_unless_present = "since", conflicts_with = "since")]
+        new_file: Option<PathBuf>,
+
+        /// Git revision to compare against, e.g. HEAD~1 or a tag
+        #[arg(long, alias = "since-git", value_name = "REV")]
+        since: Option<String>,
+
+        /// Also draw the new keymap with changed keys outlined: green added, red removed,
+        /// amber changed
+        #[arg(short, long)]
+        output_file: Option<PathBuf>,
+
+        /// Keyboard geometry to draw [default: detected]
+        #[arg(long, value_name = "NAME", value_parser = parse_board)]
+        board: Option<Geometry>,
+    },
+    /// Render a timeline page of how a keymap evolved across revisions
+    History {
+        /// One keymap.c to walk its git history, or several revisions of it, oldest first
+        #[arg(required = true)]
+        keymap_files: Vec<PathBuf>,
+
+        /// Number of most recent commits shown when walking git history
+        #[arg(long, default_value_t = 20)]
+        limit: usize,
+
+        /// Output HTML file name [default: <keymap>-history.html]
+        #[arg(short, long)]
+        output_file: Option<PathBuf>,
+
+        /// Keyboard geometry to draw [default: detected]
+        #[arg(long, value_name = "NAME", value_parser = parse_board)]
+        board: Option<Geometry>,
+    },
+    /// Align the keycodes of LAYOUT blocks into columns matching the physical rows
+    Fmt {
+        /// Path to the keymap.c file, rewritten in place
+        keymap_file: PathBuf,
+
+        /// Only check the formatting; exit with an error if the file would change
+        #[arg(long, default_value_t = false)]
+        check: bool,
+
+        /// Check that the formatted file parses to the same layers as the original
+        #[arg(long, default_value_t = false)]
+        verify_roundtrip: bool,
+
+        /// Keyboard geometry whose rows are used [default: detected]
+        #[arg(long, value_name = "NAME", value_parser = parse_board)]
+        board: Option<Geometry>,
+    },
+    /// Show the active layer live from the keyboard's console or raw HID messages
+    Listen {
+        /// Path to the keymap.c file (or an experimental .hex/.uf2/.bin firmware image)
+        keymap_file: PathBuf,
+
+        /// hidraw device sending raw HID layer reports [default: console lines on stdin]
+        #[arg(long, value_name = "DEVICE")]
+        device: Option<PathBuf>,
+
+        /// Local port the live view is served on
+        #[arg(long, default_value_t = 8044)]
+        port: u16,
+
+        /// Keyboard geometry to draw [default: detected]
+        #[arg(long, value_name = "NAME", value_parser = parse_board)]
+        board: Option<Geometry>,
+    },
+    /// Export data extracted from the keymap
+    Export {
+        #[command(subcommand)]
+        what: ExportCommand,
+    },
+    /// Import a layout from another configurator as a keymap.c
+    Import {
+        #[command(subcommand)]
+        what: ImportCommand,
+    },
+    /// Render a quick mockup from a text grid: keys split by spaces, rows by lines
+    Mock {
+        /// Grid file; layers are separated by blank lines and named by `[NAME]` lines [default: stdin]
+        grid_file: Option<PathBuf>,
+
+        /// Output file name; the extension picks SVG, PNG, or HTML
+        #[arg(short, long, default_value = "mock.svg")]
+        output_file: PathBuf,
+
+        /// Generate a single-card cheatsheet instead of one section per layer
+        #[arg(long, default_value_t = false)]
+        single_card: bool,
+
+        /// Keyboard geometry to draw [default: detected]
+        #[arg(long, value_name = "NAME", value_parser = parse_board)]
+        board: Option<Geometry>,
+    },
+    /// Render the keymap, link it from the README, and optionally commit, as set up in the
+    /// project's keyball44-viz.toml
+    Publish {
+        /// Project config file
+        #[arg(long, value_name = "FILE", default_value = publish::PROJECT_FILE)]
+        config: PathBuf,
+
+        /// Commit the rendered files and README, even if the config doesn't
+        #[arg(long, default_value_t = false)]
+        commit: bool,
+    },
+    /// Export one layer as per-key PNG tiles for Stream Deck / Loupedeck keys
+    Tiles {
+        /// Path to the keymap.c file (or an experimental .hex/.uf2/.bin firmware image)
+        keymap_file: PathBuf,
+
+        /// Layer to export, by index or name
+        #[arg(short, long, default_value = "0")]
+        layer: String,
+
+        /// Tile size in pixels
+        #[arg(long, value_enum, default_value_t = TileSize::Px72)]
+        size: TileSize,
+
+        /// Directory the tiles and manifest.json are written to [default: <keymap>-tiles]
+        #[arg(short, long)]
+        output_dir: Option<PathBuf>,
+
+        /// Keyboard geometry used to name the tiles [default: detected]
+        #[arg(long, value_name = "NAME", value_parser = parse_board)]
+        board: Option<Geometry>,
+    },
+    /// List key position names (e.g. L-R3C4, thumb-L1) with their keycodes
+    Positions {
+        /// Path to the keymap.c file (or an experimental .hex/.uf2/.bin firmware image)
+        keymap_file: PathBuf,
+
+        /// Show the keycode at this position on every layer instead
+        #[arg(long, value_name = "POSITION")]
+        at: Option<String>,
+
+        /// Keyboard geometry whose positions are named [default: detected]
+        #[arg(long, value_name = "NAME", value_parser = parse_board)]
+        board: Option<Geometry>,
+    },
+    /// Check a theme, geometry, legends, or translations config file for errors
+    ValidateConfig {
+        /// Kind of config file
+        #[arg(value_enum)]
+        kind: config::ConfigKind,
+
+        /// Path to the config file (.toml, or .json)
+        file: PathBuf,
+    },
+    /// Print the JSON Schema of a config file format
+    Schema {
+        /// Kind of config file
+        #[arg(value_enum)]
+        kind: config::ConfigKind,
+    },
+    /// Render a sample of every built-in style and the layer palette
+    Themes {
+        /// Output SVG file name
+        #[arg(short, long, default_value = "themes.svg")]
+        output_file: PathBuf,
+    },
+}
+
+fn parse_layer_color(value: &str) -> Result<(String, usize), String> {
+    let (name, slot) = value
+        .split_once('=')
+        .ok_or(format!("expected NAME=SLOT, got \"{}\"", value))?;
+    let slot: usize = slot
+        .trim()
+        .parse()
+        .map_err(|_| format!("invalid palette slot \"{}\"", slot))?;
+
+    if !(1..=PALETTE_SIZE).contains(&slot) {
+        return Err(format!(
+            "palette slot must be between 1 and {}",
+            PALETTE_SIZE
+        ));
+    }
+
+    Ok((name.trim().to_string(), slot))
+}
+
+fn parse_key_color(value: &str) -> Result<(Vec<String>, String), String> {
+    let (keycodes, color) = value
+        .rsplit_once('=')
+        .ok_or(format!("expected KEYCODES=COLOR, got \"{}\"", value))?;
+    let color = color.trim();
+    if !theme::is_valid_color(color) {
+        return Err(format!(
+            "expected a #rgb or #rrggbb color, got \"{}\"",
+            color
+        ));
+    }
+
+    let keycodes: Vec<String> = keycodes
+        .split(',')
+        .map(|keycode| keycode.trim().to_string())
+        .filter(|keycode| !keycode.is_empty())
+        .collect();
+    if keycodes.is_empty() {
+        return Err(format!("no keycodes before the color in \"{}\"", value));
+    }
+
+    Ok((keycodes, color.to_string()))
+}
+
+fn parse_dpi(value: &str) -> Result<f32, String> {
+    let dpi: f32 = value
+        .trim()
+        .parse()
+        .map_err(|_| format!("invalid resolution \"{}\"", value))?;
+    if !(dpi.is_finite() && dpi > 0.0) {
+        return Err("resolution must be a positive number of DPI".to_string());
+    }
+    Ok(dpi)
+}
+
+fn parse_board(value: &str) -> Result<Geometry, String> {
+    Geometry::by_name(value).ok_or_else(|| {
+        let names: Vec<String> = Geometry::builtins().into_iter().map(|g| g.name).collect();
+        format!(
+            "unknown board \"{}\", expected one of: {}",
+            value,
+            names.join(", ")
+        )
+    })
+}
+
+/// Reads and validates a `--geometry` file: a geometry config file, or a QMK
+/// `info.json` whose layout for `layout_macro` is used. QMK geometries may
+/// also come from a keyboard directory or a URL, and are cached unless
+/// `refresh` is set.
+fn load_geometry(
+    path: &Path,
+    layout_macro: Option<&str>,
+    refresh: bool,
+) -> Result<Geometry, Failure> {
+    let cache = GeometryCache::user(refresh);
+    let geometry = if geometry_cache::is_url(path) || path.is_dir() {
+        cache.load(path, layout_macro)
+    } else {
+        let content = fs::read_to_string(path)
+            .context(format!("Failed to read geometry file: {:?}", path))
+            .kind(ErrorKind::Io)?;
+        if info_json::is_info_json(&content) {
+            cache.load_content(path, &content, layout_macro)
+        } else {
+            config::parse::<Geometry>(path, &content)
+        }
+    }
+    .context(format!("Failed to load geometry: {:?}", path))
+    .kind(ErrorKind::Validation)?;
+    geometry
+        .validate()
+        .context(format!("{}: invalid geometry file", path.display()))
+        .kind(ErrorKind::Validation)?;
+    Ok(geometry)
+}
+
+/// Uses the `--board` geometry if given, otherwise infers one from the layers
+/// and reports what was inferred.
+///
+/// Keymaps matching no built-in board fall back to an ortholinear grid sized
+/// by the first layer's rows; the warning describing the fallback is returned
+/// so renders can show it in the image.
+fn select_geometry(board: Option<Geometry>, layers: &[Layer]) -> (Geometry, Option<String>) {
+    if let Some(geometry) = board {
+        return (geometry, None);
+    }
+
+    match geometry::detect(layers) {
+        Some(detection) => {
+            eprintln!(
+                "Info: detected board {} ({}); override with --board",
+                detection.geometry.name, detection.reason
+            );
+            (detection.geometry, None)
+        }
+        None => {
+            let fallback = geometry::fallback(layers);
+            let warning = format!(
+                "No built-in board has {} keys per layer; drawn as an ortholinear grid. \
+                 Pass --board to choose another geometry.",
+                fallback.key_count()
+            );
+            diagnostics::emit(&diagnostics::FALLBACK_BOARD, &warning);
+            (fallback, Some(warning))
+        }
+    }
 }
 
 fn print_stats(layers: &[Layer]) {
-    for (i, layer) in layers.iter().enumerate() {
-        let total_keys = layer.keys.iter().flatten().count();
-        let assigned_keys = layer
-            .keys
-            .iter()
-            .flatten()
-            .filter(|key| !is_empty_key(key))
-            .count();
-        let unassigned_keys = total_keys - assigned_keys;
+    for stats in analysis::layer_stats(layers) {
         println!(
             "Layer {}: Total Keys: {}, Assigned Keys: {}, Unassigned Keys: {}",
-            i, total_keys, assigned_keys, unassigned_keys
+            stats.layer, stats.total_keys, stats.assigned_keys, stats.unassigned_keys
         );
     }
 }
 
-fn main() -> Result<()> {
+/// Warns about layers that duplicate earlier ones and returns a one-line
+/// summary for the metadata panel, or `None` if there are none.
+fn report_duplicate_layers(layers: &[Layer]) -> Option<String> {
+    let duplicates = analysis::find_duplicate_layers(layers);
+    if duplicates.is_empty() {
+        return None;
+    }
+
+    let reference = |index: usize| {
+        layers
+            .iter()
+            .find(|layer| layer.index == index)
+            .map(analysis::layer_reference)
+            .unwrap_or_default()
+    };
+
+    let mut summary = Vec::new();
+    for duplicate in &duplicates {
+        let (relation, qualifier) = match duplicate.similarity {
+            analysis::Similarity::Identical => ("=", ""),
+            analysis::Similarity::IdenticalIgnoringTransparent => {
+                ("≈", " apart from transparent keys")
+            }
+        };
+        diagnostics::emit(
+            &diagnostics::DUPLICATE_LAYER,
+            format_args!(
+                "{} is identical to {}{}; consider consolidating them",
+                reference(duplicate.layer),
+                reference(duplicate.original),
+                qualifier
+            ),
+        );
+        summary.push(format!(
+            "{} {} {}",
+            reference(duplicate.layer),
+            relation,
+            reference(duplicate.original)
+        ));
+    }
+
+    Some(summary.join("; "))
+}
+
+/// Warns about symbols and modifiers that move between the base layers the
+/// keymap switches between with `DF()`, and returns a one-line summary for
+/// the metadata panel, or `None` if there is a single base layer.
+fn report_base_variants(layers: &[Layer], geometry: &Geometry) -> Option<String> {
+    let variants = analysis::base_variants(layers)?;
+    let names = geometry.position_names();
+    let reference = |index: &usize| {
+        layers
+            .iter()
+            .find(|layer| layer.index == *index)
+            .map(analysis::layer_reference)
+            .unwrap_or_default()
+    };
+    let references: Vec<String> = variants.layers.iter().map(reference).collect();
+
+    for difference in &variants.differences {
+        if difference.change != analysis::VariantChange::Other {
+            continue;
+        }
+        let keys: Vec<String> = references
+            .iter()
+            .zip(&difference.keys)
+            .map(|(variant, key)| format!("{} on {}", key, variant))
+            .collect();
+        diagnostics::emit(
+            &diagnostics::BASE_VARIANT_DIFFERENCE,
+            format_args!(
+                "base variants differ at {} beyond letters: {}",
+                names
+                    .get(difference.position)
+                    .cloned()
+                    .unwrap_or_else(|| format!("key {}", difference.position)),
+                keys.join(", ")
+            ),
+        );
+    }
+
+    Some(format!(
+        "{}: {} letter keys differ, {} other keys differ",
+        references.join(" / "),
+        variants.count(analysis::VariantChange::Letters),
+        variants.count(analysis::VariantChange::Other)
+    ))
+}
+
+/// Reads layers from keymap source, a QMK keymap.json, a ZMK .keymap, or a compiled firmware
+/// image, selected by file extension. The keymap source is returned alongside
+/// the layers when there is one.
+fn read_layers(path: &Path) -> Result<(Vec<Layer>, Option<String>), Failure> {
+    read_layers_as(path, InputFormat::from_path(path))
+}
+
+/// Reads the layers of a keymap file in the given format, returning its C
+/// source along with them when there is one.
+fn read_layers_as(
+    path: &Path,
+    format: InputFormat,
+) -> Result<(Vec<Layer>, Option<String>), Failure> {
+    if format == InputFormat::Firmware {
+        let data = fs::read(path)
+            .context(format!("Failed to read firmware file: {:?}", path))
+            .kind(ErrorKind::Io)?;
+        diagnostics::emit(
+            &diagnostics::FIRMWARE_EXTRACTION,
+            "firmware extraction is experimental, verify the recovered layout",
+        );
+        let layers = firmware::extract_layers(&data).kind(ErrorKind::Parse)?;
+        return Ok((layers, None));
+    }
+
+    let content = fs::read_to_string(path)
+        .context(format!("Failed to read keymap file: {:?}", path))
+        .kind(ErrorKind::Io)?;
+    if format == InputFormat::Json {
+        let layers = keymap_json::parse_json_keymap(&content).kind(ErrorKind::Parse)?;
+        return Ok((layers, None));
+    }
+    if format == InputFormat::Zmk {
+        let layers = zmk::parse_zmk_keymap(&content).kind(ErrorKind::Parse)?;
+        return Ok((layers, None));
+    }
+    if format == InputFormat::Toml {
+        let layers = keymap_toml::parse_toml_keymap(&content).kind(ErrorKind::Parse)?;
+        return Ok((layers, None));
+    }
+    let layers = parse_layers(&content).kind(ErrorKind::Parse)?;
+
+    Ok((layers, Some(content)))
+}
+
+/// Default output path: the keymap's file stem with the given extension.
+fn default_output_path(keymap_file: &Path, extension: &str) -> Result<PathBuf> {
+    let basename = keymap_file
+        .file_stem()
+        .and_then(|s| s.to_str())
+        .ok_or(anyhow::anyhow!("Invalid filename"))
+        .context("Unable to retrieve output file basename")?;
+    Ok(PathBuf::from(format!("{}.{}", basename, extension)))
+}
+
+fn main() -> ExitCode {
     let args = Args::parse();
+    let error_format = args.error_format;
+    // `publish` sets the levels from its project config once it's loaded
+    let configured = match args.command {
+        Some(Command::Publish { .. }) => Ok(()),
+        _ => configure_diagnostics(&args),
+    };
+
+    match configured.and_then(|()| run(args)) {
+        Ok(()) if diagnostics::denied() > 0 => {
+            let failure = Failure {
+                kind: ErrorKind::Validation,
+                error: anyhow::anyhow!("{} denied diagnostic(s) reported", diagnostics::denied()),
+            };
+            failure::report(&failure, error_format)
+        }
+        Ok(()) => ExitCode::SUCCESS,
+        Err(failure) => failure::report(&failure, error_format),
+    }
+}
+
+/// Sets the diagnostic levels from `--diagnostics`, then `--allow`, `--warn`,
+/// and `--deny`.
+fn configure_diagnostics(args: &Args) -> Result<(), Failure> {
+    let mut levels = diagnostics::Levels::default();
+    if let Some(path) = &args.diagnostics {
+        let config = config::load::<diagnostics::LevelConfig>(path)
+            .context(format!("Failed to load diagnostics: {:?}", path))
+            .kind(ErrorKind::Validation)?;
+        levels
+            .extend(&config)
+            .map_err(|err| anyhow::anyhow!("{}: {}", path.display(), err))
+            .kind(ErrorKind::Validation)?;
+    }
+    for (codes, level) in [
+        (&args.allow, diagnostics::Level::Allow),
+        (&args.warn, diagnostics::Level::Warn),
+        (&args.deny, diagnostics::Level::Deny),
+    ] {
+        for code in codes {
+            levels.set(code, level);
+        }
+    }
+    diagnostics::configure(levels, args.error_format);
+    Ok(())
+}
+
+fn run(args: Args) -> Result<(), Failure> {
+    match args.command {
+        Some(Command::Doctor {
+            keymap_file,
+            output_file,
+            board,
+        }) => {
+            let output_file = match output_file {
+                Some(output_file) => output_file,
+                None => default_output_path(&keymap_file, "svg")?,
+            };
+            if !doctor::run(&keymap_file, &output_file, board.as_ref()) {
+                return Err(anyhow::anyhow!("doctor found problems")).kind(ErrorKind::Validation);
+            }
+            Ok(())
+        }
+        Some(Command::Diff {
+            keymap_file,
+            new_file,
+            since,
+            output_file,
+            board,
+        }) => {
+            let (old_layers, new_layers, what) = match (new_file, since) {
+                (Some(new_file), _) => (
+                    read_layers(&keymap_file)?.0,
+                    read_layers(&new_file)?.0,
+                    format!("from {} to {}", keymap_file.display(), new_file.display()),
+                ),
+                (None, Some(rev)) => {
+                    let (old_layers, new_layers) = layers_since(&keymap_file, &rev)?;
+                    (old_layers, new_layers, format!("since {}", rev))
+                }
+                (None, None) => unreachable!("clap requires a second file or --since"),
+            };
+            diff_keymaps(
+                &old_layers,
+                &new_layers,
+                &what,
+                output_file.as_deref(),
+                board,
+            )
+        }
+        Some(Command::History {
+            keymap_files,
+            limit,
+            output_file,
+            board,
+        }) => keymap_history(&keymap_files, limit, output_file, board),
+        Some(Command::Fmt {
+            keymap_file,
+            check,
+            verify_roundtrip,
+            board,
+        }) => format_keymap(&keymap_file, check, verify_roundtrip, board),
+        Some(Command::Listen {
+            keymap_file,
+            device,
+            port,
+            board,
+        }) => {
+            let (layers, _) = read_layers(&keymap_file)?;
+            let (geometry, warning) = select_geometry(board, &layers);
+            let options = RenderOptions {
+                geometry,
+                warnings: warning.into_iter().collect(),
+                ..RenderOptions::default()
+            };
+            let svg = generate_svg_with_options(&layers, &options);
+            listen::run(&layers, &svg, device.as_deref(), port).kind(ErrorKind::Io)
+        }
+        Some(Command::Export {
+            what:
+                ExportCommand::Keycodes {
+                    keymap_file,
+                    format,
+                    verify_roundtrip,
+                },
+        }) => export_keycodes(&keymap_file, format, verify_roundtrip),
+        Some(Command::Export {
+            what:
+                ExportCommand::Stats {
+                    keymap_file,
+                    format,
+                    label,
+                    no_header,
+                },
+        }) => export_stats(&keymap_file, format, label.as_deref(), no_header),
+        Some(Command::Export {
+            what:
+                ExportCommand::Toml {
+                    keymap_file,
+                    verify_roundtrip,
+                },
+        }) => {
+            let (layers, _) = read_layers(&keymap_file)?;
+            let toml = keymap_toml::to_toml(&layers);
+            if verify_roundtrip {
+                let imported = keymap_toml::parse_toml_keymap(&toml).kind(ErrorKind::Parse)?;
+                check_roundtrip(&layers, &imported, "the TOML export")?;
+            }
+            print!("{}", toml);
+            Ok(())
+        }
+        Some(Command::Import {
+            what:
+                ImportCommand::Oryx {
+                    layout_json,
+                    board,
+                    mapping,
+                    print_mapping,
+                    verify_roundtrip,
+                    output_file,
+                },
+        }) => import_oryx(
+            &layout_json,
+            &board,
+            mapping.as_deref(),
+            print_mapping,
+            verify_roundtrip,
+            output_file,
+        ),
+        Some(Command::Mock {
+            grid_file,
+            output_file,
+            single_card,
+            board,
+        }) => render_mock(grid_file.as_deref(), &output_file, single_card, board),
+        Some(Command::Publish { config, commit }) => publish_keymap(&config, commit),
+        Some(Command::Tiles {
+            keymap_file,
+            layer,
+            size,
+            output_dir,
+            board,
+        }) => export_tiles(&keymap_file, &layer, size, output_dir, board),
+        Some(Command::Positions {
+            keymap_file,
+            at,
+            board,
+        }) => list_positions(&keymap_file, at.as_deref(), board),
+        Some(Command::ValidateConfig { kind, file }) => {
+            let content = fs::read_to_string(&file)
+                .context(format!("Failed to read config file: {:?}", file))
+                .kind(ErrorKind::Io)?;
+            config::validate(kind, &file, &content).kind(ErrorKind::Validation)?;
+            println!("{}: valid {} file", file.display(), kind.name());
+            Ok(())
+        }
+        Some(Command::Schema { kind }) => {
+            let schema = config::schema(kind);
+            println!(
+                "{}",
+                serde_json::to_string_pretty(&schema).context("Failed to encode schema")?
+            );
+            Ok(())
+        }
+        Some(Command::Themes { output_file }) => {
+            let svg = generate_theme_preview_svg(&RenderOptions::default());
+            fs::write(&output_file, svg)
+                .context(format!("Failed to write {:?}", output_file))
+                .kind(ErrorKind::Io)?;
+            Ok(())
+        }
+        None if args.watch => watch_render(args),
+        None => render(args).map(drop),
+    }
+}
+
+/// Renders the keymap, then again after every save of it or of a config file
+/// the render reads, reporting errors instead of exiting on them.
+fn watch_render(args: Args) -> Result<(), Failure> {
+    let keymap_file = args.keymap_file.clone().context("No keymap file given")?;
+    let files: Vec<PathBuf> = [
+        &args.keymap_file,
+        &args.rules,
+        &args.legends,
+        &args.aliases,
+        &args.groups,
+        &args.theme,
+        &args.effort_model,
+        &args.heatmap,
+        &args.geometry.as_ref().and_then(|geometry| {
+            if geometry_cache::is_url(geometry) {
+                None
+            } else if geometry.is_dir() {
+                geometry_cache::info_json_in(geometry).ok()
+            } else {
+                Some(geometry.clone())
+            }
+        }),
+        &args
+            .keymap_file
+            .as_ref()
+            .map(|file| file.with_file_name(notes::NOTES_FILE)),
+    ]
+    .into_iter()
+    .flatten()
+    .cloned()
+    .collect();
+
+    let rebuild = || match render(args.clone()) {
+        Ok(outputs) => {
+            eprintln!("Rendered {}", keymap_file.display());
+            if let Some(command) = &args.on_update
+                && let Err(error) = watch::run_hook(command, &outputs, &keymap_file)
+            {
+                failure::report(&Failure::from(error), args.error_format);
+            }
+        }
+        Err(failure) => {
+            failure::report(&failure, args.error_format);
+        }
+    };
+    eprintln!(
+        "Watching {} for changes; press Ctrl-C to stop",
+        keymap_file.display()
+    );
+    watch::watch(&files, rebuild).kind(ErrorKind::Io)
+}
+
+/// Reads a keymap and its version at a git revision, as `(old, new)` layers.
+fn layers_since(keymap_file: &Path, rev: &str) -> Result<(Vec<Layer>, Vec<Layer>), Failure> {
+    let (new_layers, source) = read_layers(keymap_file)?;
+    if source.is_none() {
+        return Err(anyhow::anyhow!(
+            "Comparing against git revisions needs a keymap.c source file"
+        ))
+        .kind(ErrorKind::Validation);
+    }
+
+    let old_source = git::show_file(rev, keymap_file).kind(ErrorKind::Io)?;
+    let old_layers = parse_layers(&old_source)
+        .context(format!("Failed to parse {:?} at {}", keymap_file, rev))
+        .kind(ErrorKind::Parse)?;
+    Ok((old_layers, new_layers))
+}
+
+/// Lists the keys that changed between two keymaps and, given an output
+/// file, draws the new keymap with the changed keys outlined.
+///
+/// `what` names the comparison in messages, e.g. `since HEAD~1`.
+fn diff_keymaps(
+    old_layers: &[Layer],
+    new_layers: &[Layer],
+    what: &str,
+    output_file: Option<&Path>,
+    board: Option<Geometry>,
+) -> Result<(), Failure> {
+    let changes = diff::diff_layers(old_layers, new_layers);
+    let layer_changes = diff::layer_changes(old_layers, new_layers);
+    if let Some(output_file) = output_file {
+        render_diff(new_layers, &changes, &layer_changes, output_file, board)?;
+    }
+    if changes.is_empty() && layer_changes.is_empty() {
+        println!("No key changes {}", what);
+        return Ok(());
+    }
+
+    for change in &layer_changes {
+        match &change.name {
+            Some(name) => println!("Layer {} ({}) {}", change.layer, name, change.kind.name()),
+            None => println!("Layer {} {}", change.layer, change.kind.name()),
+        }
+    }
+
+    for change in &changes {
+        let show = |key: &str| {
+            if key.is_empty() {
+                "(none)".to_string()
+            } else {
+                key.to_string()
+            }
+        };
+        println!(
+            "Layer {}, key {}: {} → {}",
+            change.layer,
+            change.position,
+            show(&change.old),
+            show(&change.new)
+        );
+        if !change.is_whole_key() {
+            for detail in &change.details {
+                println!("    {}", detail);
+            }
+        }
+    }
+    println!("{} key(s) changed {}", changes.len(), what);
+
+    Ok(())
+}
+
+/// Draws the new keymap of a comparison with its changed keys outlined by
+/// kind and a count of each kind in the metadata panel.
+fn render_diff(
+    layers: &[Layer],
+    changes: &[diff::KeyChange],
+    layer_changes: &[diff::LayerChange],
+    output_file: &Path,
+    board: Option<Geometry>,
+) -> Result<(), Failure> {
+    let count = |kind: diff::ChangeKind| changes.iter().filter(|c| c.kind() == kind).count();
+    let mut summary = format!(
+        "{} added, {} removed, {} changed",
+        count(diff::ChangeKind::Added),
+        count(diff::ChangeKind::Removed),
+        count(diff::ChangeKind::Changed)
+    );
+    for kind in [diff::ChangeKind::Added, diff::ChangeKind::Removed] {
+        let layers = layer_changes.iter().filter(|c| c.kind == kind).count();
+        if layers > 0 {
+            summary.push_str(&format!("; {} layer(s) {}", layers, kind.name()));
+        }
+    }
+
+    let (geometry, warning) = select_geometry(board, layers);
+    let options = RenderOptions {
+        geometry,
+        warnings: warning.into_iter().collect(),
+        metadata: vec![("Changes".to_string(), summary)],
+        decorate: Some(diff_overlay(changes.to_vec())),
+        ..RenderOptions::default()
+    };
+    let svg = generate_svg_with_options(layers, &options);
+
+    let page = HtmlPage {
+        title: "Keymap changes",
+        alt_text: "",
+        layer_previews: &BTreeMap::new(),
+        layer_links: &[],
+        background: &options.theme.background,
+    };
+    let content = OutputFormat::from_path(output_file)
+        .encode(&svg, &page, DEFAULT_PNG_DPI)
+        .kind(ErrorKind::Render)?;
+    fs::write(output_file, content)
+        .context(format!("Failed to write {:?}", output_file))
+        .kind(ErrorKind::Io)?;
+    eprintln!("Drew the changes to {}", output_file.display());
+
+    Ok(())
+}
+
+/// Formats the LAYOUT blocks of a keymap in place, or checks that they are
+/// formatted.
+/// Renders a timeline of keymap revisions, either the given files or the git
+/// history of a single file.
+fn keymap_history(
+    keymap_files: &[PathBuf],
+    limit: usize,
+    output_file: Option<PathBuf>,
+    board: Option<Geometry>,
+) -> Result<(), Failure> {
+    let mut revisions = Vec::new();
+
+    if let [keymap_file] = keymap_files {
+        for commit in git::file_history(keymap_file, limit).kind(ErrorKind::Io)? {
+            // Revisions from before a rename or that no longer parse are left out
+            let layers = match git::show_file(&commit.hash, keymap_file)
+                .and_then(|source| parse_layers(&source))
+            {
+                Ok(layers) => layers,
+                Err(e) => {
+                    diagnostics::emit(
+                        &diagnostics::SKIPPED_REVISION,
+                        format_args!("skipping {}: {:#}", commit.short_hash, e),
+                    );
+                    continue;
+                }
+            };
+            revisions.push(history::Revision {
+                label: commit.short_hash,
+                description: format!("{} · {}", commit.date, commit.subject),
+                layers,
+            });
+        }
+
+        let (layers, _) = read_layers(keymap_file)?;
+        if revisions.last().is_none_or(|last| {
+            !diff::diff_layers(&last.layers, &layers).is_empty()
+                || !diff::layer_changes(&last.layers, &layers).is_empty()
+        }) {
+            revisions.push(history::Revision {
+                label: "working copy".to_string(),
+                description: "Uncommitted changes".to_string(),
+                layers,
+            });
+        }
+    } else {
+        for keymap_file in keymap_files {
+            let (layers, _) = read_layers(keymap_file)?;
+            revisions.push(history::Revision {
+                label: keymap_file.display().to_string(),
+                description: String::new(),
+                layers,
+            });
+        }
+    }
+
+    let latest = revisions
+        .last()
+        .ok_or(anyhow::anyhow!("No revision of the keymap could be read"))
+        .kind(ErrorKind::Parse)?;
+    let (geometry, _) = select_geometry(board, &latest.layers);
+    let options = RenderOptions {
+        geometry,
+        ..RenderOptions::default()
+    };
+
+    let keymap_file = &keymap_files[keymap_files.len() - 1];
+    let title = format!("History of {}", keymap_file.display());
+    let html = history::timeline_html(&revisions, &title, &options);
+
+    let output_file = match output_file {
+        Some(output_file) => output_file,
+        None => {
+            let stem = keymap_file
+                .file_stem()
+                .map(|stem| stem.to_string_lossy().into_owned())
+                .unwrap_or_default();
+            keymap_file.with_file_name(format!("{}-history.html", stem))
+        }
+    };
+    fs::write(&output_file, html)
+        .context(format!("Failed to write {:?}", output_file))
+        .kind(ErrorKind::Io)?;
+    eprintln!(
+        "Wrote {} revision(s) to {}",
+        revisions.len(),
+        output_file.display()
+    );
+
+    Ok(())
+}
+
+fn format_keymap(
+    keymap_file: &Path,
+    check: bool,
+    verify_roundtrip: bool,
+    board: Option<Geometry>,
+) -> Result<(), Failure> {
+    let content = fs::read_to_string(keymap_file)
+        .context(format!("Failed to read keymap file: {:?}", keymap_file))
+        .kind(ErrorKind::Io)?;
+    let layers = parse_layers(&content).kind(ErrorKind::Parse)?;
+    let (geometry, _) = select_geometry(board, &layers);
+
+    let formatted = format::format_layouts(&content, &geometry).kind(ErrorKind::Parse)?;
+    for layer in &formatted.skipped_blocks {
+        diagnostics::emit(
+            &diagnostics::UNFORMATTED_LAYOUT,
+            format_args!(
+                "left layer {} unformatted because its LAYOUT block contains comments",
+                layer
+            ),
+        );
+    }
+
+    if verify_roundtrip {
+        let reparsed = parse_layers(&formatted.content).kind(ErrorKind::Parse)?;
+        check_roundtrip(&layers, &reparsed, "formatting")?;
+    }
+
+    if formatted.content == content {
+        return Ok(());
+    }
+
+    if check {
+        return Err(anyhow::anyhow!(
+            "{} is not formatted; run keyball44-viz fmt to fix it",
+            keymap_file.display()
+        ))
+        .kind(ErrorKind::Validation);
+    }
+
+    fs::write(keymap_file, formatted.content)
+        .context(format!("Failed to write {:?}", keymap_file))
+        .kind(ErrorKind::Io)?;
+    println!(
+        "Formatted {} LAYOUT block(s) in {}",
+        formatted.formatted_blocks,
+        keymap_file.display()
+    );
+
+    Ok(())
+}
+
+/// Fails with every loss if a conversion didn't reproduce the original
+/// layers.
+fn check_roundtrip(original: &[Layer], converted: &[Layer], what: &str) -> Result<(), Failure> {
+    let losses = diff::roundtrip_losses(original, converted);
+    if losses.is_empty() {
+        eprintln!("Round trip verified: {} keeps every layer and key", what);
+        return Ok(());
+    }
+    Err(anyhow::anyhow!(
+        "Round trip through {} lost or changed:\n  {}",
+        what,
+        losses.join("\n  ")
+    ))
+    .kind(ErrorKind::Validation)
+}
+
+/// Renders layers sketched as a text grid.
+fn render_mock(
+    grid_file: Option<&Path>,
+    output_file: &Path,
+    single_card: bool,
+    board: Option<Geometry>,
+) -> Result<(), Failure> {
+    let content = match grid_file {
+        Some(path) => fs::read_to_string(path)
+            .context(format!("Failed to read grid file: {:?}", path))
+            .kind(ErrorKind::Io)?,
+        None => io::read_to_string(io::stdin())
+            .context("Failed to read the grid from stdin")
+            .kind(ErrorKind::Io)?,
+    };
+    let layers = mock::parse_grid(&content).kind(ErrorKind::Parse)?;
+
+    let (geometry, warning) = select_geometry(board, &layers);
+    let options = RenderOptions {
+        geometry,
+        warnings: warning.into_iter().collect(),
+        ..RenderOptions::default()
+    };
+    let svg = if single_card {
+        generate_single_card_svg_with_options(&layers, &options)
+    } else {
+        generate_svg_with_options(&layers, &options)
+    };
+
+    let page = HtmlPage {
+        title: "Mockup",
+        alt_text: "",
+        layer_previews: &BTreeMap::new(),
+        layer_links: &[],
+        background: &options.theme.background,
+    };
+    let content = OutputFormat::from_path(output_file)
+        .encode(&svg, &page, DEFAULT_PNG_DPI)
+        .kind(ErrorKind::Render)?;
+    fs::write(output_file, content)
+        .context(format!("Failed to write {:?}", output_file))
+        .kind(ErrorKind::Io)?;
+    eprintln!(
+        "Rendered {} layer(s) to {}",
+        layers.len(),
+        output_file.display()
+    );
+
+    Ok(())
+}
+
+/// Converts an Oryx export into a keymap.c for the target board.
+fn import_oryx(
+    layout_json: &Path,
+    board: &Geometry,
+    mapping_file: Option<&Path>,
+    print_mapping: bool,
+    verify_roundtrip: bool,
+    output_file: Option<PathBuf>,
+) -> Result<(), Failure> {
+    let content = fs::read_to_string(layout_json)
+        .context(format!("Failed to read Oryx export: {:?}", layout_json))
+        .kind(ErrorKind::Io)?;
+    let layout = oryx::parse_export(&content).kind(ErrorKind::Parse)?;
+    let mapping = match mapping_file {
+        Some(path) => Some(
+            config::load::<oryx::PositionMapping>(path)
+                .context(format!("Failed to load position mapping: {:?}", path))
+                .kind(ErrorKind::Validation)?,
+        ),
+        None => None,
+    };
+
+    if print_mapping {
+        let indices = oryx::map_positions(&layout.keyboard, board, mapping.as_ref())
+            .kind(ErrorKind::Validation)?;
+        println!("[positions]");
+        for (name, index) in board.position_names().iter().zip(indices) {
+            match index {
+                Some(index) => println!("\"{}\" = {}", name, index),
+                None => println!("# \"{}\" = ", name),
+            }
+        }
+        return Ok(());
+    }
+
+    let layers =
+        oryx::import_layers(&layout, board, mapping.as_ref()).kind(ErrorKind::Validation)?;
+    let title = format!(
+        "Imported from Oryx layout \"{}\" ({}) for {}",
+        layout.title, layout.keyboard, board.name
+    );
+    let source = oryx::keymap_source(&layers, &title);
+    let source = format::format_layouts(&source, board)
+        .kind(ErrorKind::Parse)?
+        .content;
 
-    let content = fs::read_to_string(&args.keymap_file).context(format!(
-        "Failed to read keymap file: {:?}",
-        args.keymap_file
-    ))?;
+    if verify_roundtrip {
+        let reparsed = parse_layers(&source).kind(ErrorKind::Parse)?;
+        check_roundtrip(&layers, &reparsed, "the generated keymap.c")?;
+    }
+
+    let output_file = match output_file {
+        Some(output_file) => output_file,
+        None => default_output_path(layout_json, "c")?,
+    };
+    fs::write(&output_file, source)
+        .context(format!("Failed to write {:?}", output_file))
+        .kind(ErrorKind::Io)?;
+    eprintln!(
+        "Imported {} layer(s) from {} into {}",
+        layers.len(),
+        layout_json.display(),
+        output_file.display()
+    );
+
+    Ok(())
+}
+
+/// Prints the keycodes used by a keymap.
+/// Quotes a CSV field if it contains a delimiter, quote, or line break.
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+fn export_stats(
+    keymap_file: &Path,
+    format: StatsFormat,
+    label: Option<&str>,
+    no_header: bool,
+) -> Result<(), Failure> {
+    let (layers, _) = read_layers(keymap_file)?;
+    let stats = analysis::layer_stats(&layers);
+
+    match format {
+        StatsFormat::Text => print_stats(&layers),
+        StatsFormat::Csv => {
+            let prefix = label.map_or(String::new(), |label| format!("{},", csv_field(label)));
+            if !no_header {
+                let label_column = if label.is_some() { "label," } else { "" };
+                println!("{}layer,metric,value", label_column);
+            }
+            for s in &stats {
+                for (metric, value) in s.metrics() {
+                    println!("{}{},{},{}", prefix, s.layer, metric, value);
+                }
+            }
+        }
+        StatsFormat::Json => {
+            let rows: Vec<serde_json::Value> = stats
+                .iter()
+                .flat_map(|s| {
+                    s.metrics().map(|(metric, value)| {
+                        let mut row = serde_json::json!({
+                            "layer": s.layer,
+                            "metric": metric,
+                            "value": value,
+                        });
+                        if let Some(label) = label {
+                            row["label"] = label.into();
+                        }
+                        row
+                    })
+                })
+                .collect();
+            println!(
+                "{}",
+                serde_json::to_string_pretty(&rows).context("Failed to encode stats")?
+            );
+        }
+    }
+
+    Ok(())
+}
+
+fn export_keycodes(
+    keymap_file: &Path,
+    format: KeycodeListFormat,
+    verify_roundtrip: bool,
+) -> Result<(), Failure> {
+    if verify_roundtrip && !matches!(format, KeycodeListFormat::Legends) {
+        return Err(anyhow::anyhow!(
+            "--verify-roundtrip only applies to --format legends, which can be loaded back"
+        ))
+        .kind(ErrorKind::Validation);
+    }
+
+    let (layers, _) = read_layers(keymap_file)?;
+    let usage = analysis::keycode_usage(&layers);
+
+    match format {
+        KeycodeListFormat::Text => {
+            let width = usage.iter().map(|u| u.keycode.len()).max().unwrap_or(0);
+            for u in &usage {
+                let layers: Vec<String> = u.layers.iter().map(usize::to_string).collect();
+                println!(
+                    "{:<width$}  {:>3}  layers {}",
+                    u.keycode,
+                    u.count,
+                    layers.join(", "),
+                    width = width
+                );
+            }
+        }
+        KeycodeListFormat::Json => {
+            let entries: Vec<serde_json::Value> = usage
+                .iter()
+                .map(|u| {
+                    serde_json::json!({
+                        "keycode": u.keycode,
+                        "count": u.count,
+                        "layers": u.layers,
+                    })
+                })
+                .collect();
+            println!(
+                "{}",
+                serde_json::to_string_pretty(&entries).context("Failed to encode keycodes")?
+            );
+        }
+        KeycodeListFormat::Legends => {
+            let overrides = keycodes::LegendOverrides {
+                legends: usage
+                    .iter()
+                    .map(|u| {
+                        let legend = keycodes::LegendOverride {
+                            legend: u.keycode.clone(),
+                            description: None,
+                        };
+                        (u.keycode.clone(), legend)
+                    })
+                    .collect(),
+            };
+            let encoded = toml::to_string(&overrides).context("Failed to encode legends")?;
+            if verify_roundtrip {
+                let decoded: keycodes::LegendOverrides =
+                    toml::from_str(&encoded).context("Failed to load the exported legends")?;
+                if decoded != overrides {
+                    return Err(anyhow::anyhow!(
+                        "Round trip through the legend table changed its entries"
+                    ))
+                    .kind(ErrorKind::Validation);
+                }
+                eprintln!("Round trip verified: the legend table loads back unchanged");
+            }
+            print!("{}", encoded);
+        }
+    }
+
+    Ok(())
+}
+
+fn list_positions(
+    keymap_file: &Path,
+    at: Option<&str>,
+    board: Option<Geometry>,
+) -> Result<(), Failure> {
+    let (layers, _) = read_layers(keymap_file)?;
+    let (geometry, _) = select_geometry(board, &layers);
+    let names = geometry.position_names();
+
+    match at {
+        Some(at) => {
+            let index = names
+                .iter()
+                .position(|name| name.eq_ignore_ascii_case(at))
+                .ok_or(anyhow::anyhow!(
+                    "No position named \"{}\" on {}",
+                    at,
+                    geometry.name
+                ))
+                .kind(ErrorKind::Validation)?;
+            for layer in &layers {
+                let key = layer.keys.iter().flatten().nth(index);
+                println!(
+                    "{}: {}",
+                    analysis::layer_reference(layer),
+                    key.map_or("-", String::as_str)
+                );
+            }
+        }
+        None => {
+            let base: Vec<&String> = layers
+                .first()
+                .map_or(Vec::new(), |l| l.keys.iter().flatten().collect());
+            let width = names.iter().map(String::len).max().unwrap_or(0);
+            for (index, name) in names.iter().enumerate() {
+                println!(
+                    "{:>3}  {:<width$}  {}",
+                    index,
+                    name,
+                    base.get(index).map_or("-", |key| key.as_str()),
+                    width = width
+                );
+            }
+        }
+    }
 
-    let layers = parse_layers(&content)?;
+    Ok(())
+}
+
+fn export_tiles(
+    keymap_file: &Path,
+    selector: &str,
+    size: TileSize,
+    output_dir: Option<PathBuf>,
+    board: Option<Geometry>,
+) -> Result<(), Failure> {
+    let (layers, _) = read_layers(keymap_file)?;
+    let layer = layers
+        .iter()
+        .find(|layer| layer.matches_selector(selector))
+        .ok_or(anyhow::anyhow!("No layer matches \"{}\"", selector))
+        .kind(ErrorKind::Validation)?;
+
+    let output_dir = match output_dir {
+        Some(output_dir) => output_dir,
+        None => {
+            let stem = default_output_path(keymap_file, "tiles")?.with_extension("");
+            PathBuf::from(format!("{}-tiles", stem.display()))
+        }
+    };
+    fs::create_dir_all(&output_dir)
+        .context(format!("Failed to create {:?}", output_dir))
+        .kind(ErrorKind::Io)?;
+
+    let options = RenderOptions {
+        geometry: select_geometry(board, &layers).0,
+        ..RenderOptions::default()
+    };
+
+    let mut manifest = Vec::new();
+    for tile in generate_key_tiles(&layers, layer, &options, size.pixels()) {
+        let file_name = format!("{}.png", tile.id);
+        let png = raster::svg_to_png(&tile.svg, 1.0).kind(ErrorKind::Render)?;
+        fs::write(output_dir.join(&file_name), png)
+            .context(format!("Failed to write tile {}", file_name))
+            .kind(ErrorKind::Io)?;
+
+        manifest.push(serde_json::json!({
+            "file": file_name,
+            "position": tile.position,
+            "row": tile.row,
+            "column": tile.column,
+            "label": tile.label,
+        }));
+    }
+
+    let manifest = serde_json::json!({
+        "layer": layer.index,
+        "size": size.pixels(),
+        "tiles": manifest,
+    });
+    fs::write(
+        output_dir.join("manifest.json"),
+        serde_json::to_string_pretty(&manifest).context("Failed to encode manifest")?,
+    )
+    .context("Failed to write manifest.json")
+    .kind(ErrorKind::Io)?;
+
+    Ok(())
+}
+
+/// Loads the translations named by `--lang`: a built-in language code or
+/// the path of a translation table.
+fn load_translations(lang: &str) -> Result<Translations, Failure> {
+    if let Some(strings) = Translations::built_in(lang) {
+        return Ok(strings);
+    }
+    let path = Path::new(lang);
+    if !path.exists() {
+        return Err(anyhow::anyhow!(
+            "Unknown language \"{}\"; expected en, {}, or a translation table file",
+            lang,
+            Translations::languages().collect::<Vec<_>>().join(", ")
+        ))
+        .kind(ErrorKind::Validation);
+    }
+    let strings = config::load::<Translations>(path)
+        .context(format!("Failed to load translations: {:?}", path))
+        .kind(ErrorKind::Validation)?;
+    strings
+        .validate()
+        .context(format!("{}: invalid translations file", path.display()))
+        .kind(ErrorKind::Validation)?;
+    Ok(strings)
+}
+
+/// Runs the `publish` pipeline of a project config: parse and validate the
+/// keymap, render it, update the README, and commit.
+fn publish_keymap(config_file: &Path, commit: bool) -> Result<(), Failure> {
+    let config = config::load::<PublishConfig>(config_file)
+        .and_then(|config| {
+            config.validate()?;
+            Ok(config)
+        })
+        .context(format!("Failed to load project config: {:?}", config_file))
+        .kind(ErrorKind::Validation)?;
+    let root = match config_file.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
+    };
+
+    // Parse and validate: render with the configured checks, failing before
+    // anything is published if a diagnostic is denied
+    let keymap_file = root.join(&config.keymap);
+    eprintln!("Parsing {}", keymap_file.display());
+    read_layers(&keymap_file)?;
+
+    let outputs = config.outputs();
+    let mut command_line = vec![
+        "keyball44-viz".to_string(),
+        keymap_file.display().to_string(),
+    ];
+    if config.validate.strict {
+        command_line.push("--strict".to_string());
+    }
+    if config.validate.deny_warnings {
+        command_line.extend(["--deny".to_string(), "warnings".to_string()]);
+    }
+    command_line.extend(config.render.args.iter().cloned());
+    for output in &outputs {
+        command_line.extend(["-o".to_string(), root.join(output).display().to_string()]);
+    }
+    let args = Args::try_parse_from(&command_line)
+        .map_err(|err| anyhow!("render.args: {}", err.render().to_string().trim()))
+        .kind(ErrorKind::Validation)?;
+    configure_diagnostics(&args)?;
+
+    fs::create_dir_all(root.join(&config.render.output_dir))
+        .context(format!(
+            "Failed to create output directory: {:?}",
+            config.render.output_dir
+        ))
+        .kind(ErrorKind::Io)?;
+    for written in render(args)? {
+        eprintln!("Rendered {}", written.display());
+    }
+    if diagnostics::denied() > 0 {
+        return Err(anyhow!(
+            "{} denied diagnostic(s) reported; the README was not updated",
+            diagnostics::denied()
+        ))
+        .kind(ErrorKind::Validation);
+    }
+
+    let mut changed = outputs;
+    if let Some(readme) = &config.readme {
+        let path = root.join(&readme.file);
+        let content = match fs::read_to_string(&path) {
+            Ok(content) => content,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => String::new(),
+            Err(err) => {
+                return Err(
+                    anyhow::Error::new(err).context(format!("Failed to read README: {:?}", path))
+                )
+                .kind(ErrorKind::Io);
+            }
+        };
+        let updated = publish::update_readme(&content, &config.readme_snippet(readme));
+        if updated != content {
+            fs::write(&path, updated)
+                .context(format!("Failed to write README: {:?}", path))
+                .kind(ErrorKind::Io)?;
+            eprintln!("Updated {}", path.display());
+        }
+        changed.push(readme.file.clone());
+    }
+
+    if commit || config.commit.enabled {
+        if git::commit(root, &changed, &config.commit.message).kind(ErrorKind::Io)? {
+            eprintln!("Committed {}", config.commit.message);
+        } else {
+            eprintln!("Nothing to commit");
+        }
+    }
+    Ok(())
+}
+
+/// Renders the keymap as the arguments ask and returns the files written.
+fn render(args: Args) -> Result<Vec<PathBuf>, Failure> {
+    let keymap_file = args.keymap_file.context("No keymap file given")?;
+
+    let format = match args.input_format {
+        Some(format) => format,
+        None if args.from_toml => InputFormat::Toml,
+        None => InputFormat::from_path(&keymap_file),
+    };
+    let (mut layers, source) = read_layers_as(&keymap_file, format)?;
+    if args.verify_roundtrip {
+        if format != InputFormat::Toml {
+            return Err(anyhow::anyhow!(
+                "--verify-roundtrip only applies to --from-toml, whose layers can be exported back"
+            ))
+            .kind(ErrorKind::Validation);
+        }
+        let exported = keymap_toml::to_toml(&layers);
+        let reimported = keymap_toml::parse_toml_keymap(&exported).kind(ErrorKind::Parse)?;
+        check_roundtrip(&layers, &reimported, "export toml")?;
+    }
+    // Keycode macros are expanded before anything classifies the keys
+    let define_aliases = match &source {
+        Some(source) => {
+            let mut keycode_defines = KeycodeDefines::default();
+            for header in defines::local_includes(source) {
+                if let Some(content) = read_beside_keymap(&keymap_file, &header) {
+                    keycode_defines.extend(defines::parse_defines(&content));
+                }
+            }
+            keycode_defines.extend(defines::parse_defines(source));
+            keycode_defines.expand_layers(&mut layers)
+        }
+        None => BTreeMap::new(),
+    };
+    // Annotations kept next to the keymap apply without any flags
+    let notes_file = KeymapNotes::find(&keymap_file);
+    let keymap_notes = match &notes_file {
+        Some(path) => {
+            let mut keymap_notes = config::load::<KeymapNotes>(path)
+                .and_then(|keymap_notes| {
+                    keymap_notes.validate()?;
+                    keymap_notes.check_layers(&layers)?;
+                    Ok(keymap_notes)
+                })
+                .context(format!("Failed to load keymap notes: {:?}", path))
+                .kind(ErrorKind::Validation)?;
+            keymap_notes.apply(&mut layers);
+            keymap_notes
+        }
+        None => KeymapNotes::default(),
+    };
+    let strings = match &args.lang {
+        Some(lang) => load_translations(lang)?,
+        None => Translations::default(),
+    };
+    let mut metadata = Vec::new();
+    let mut decorate: Option<KeyDecorator> = None;
+    let mut scales = Vec::new();
+    let scale_range = ScaleRange {
+        min: args.scale_min,
+        max: args.scale_max,
+    };
+    if let (Some(min), Some(max)) = (scale_range.min, scale_range.max)
+        && min >= max
+    {
+        return Err(anyhow::anyhow!(
+            "--scale-min ({}) must be below --scale-max ({})",
+            min,
+            max
+        ))
+        .kind(ErrorKind::Validation);
+    }
+
+    let rules = match &args.rules {
+        Some(rules_file) => {
+            let content = fs::read_to_string(rules_file)
+                .context(format!("Failed to read rules file: {:?}", rules_file))
+                .kind(ErrorKind::Io)?;
+            Some(rules::parse_rules(&content))
+        }
+        None => None,
+    };
+    if let Some(rules) = &rules {
+        for warning in rules::feature_warnings(rules, &layers, source.as_deref()) {
+            diagnostics::emit(&diagnostics::DISABLED_FEATURE, warning);
+        }
+
+        let enabled = rules.enabled_features();
+        let features = if enabled.is_empty() {
+            "none".to_string()
+        } else {
+            enabled.join(", ")
+        };
+        metadata.push((strings.features.clone(), features));
+
+        if rules.is_enabled("AUTO_SHIFT") == Some(true) {
+            let config_h = read_beside_keymap(&keymap_file, "config.h");
+            let auto_shift = autoshift::parse_auto_shift(&config_sources(&source, &config_h));
+            metadata.push((strings.auto_shift.clone(), auto_shift.summary()));
+            decorate = Some(autoshift_badges(auto_shift, &strings));
+        }
+    }
+
+    // Keys remapped through VIA or Vial are stored on the board, not in the
+    // source; a rules.mk next to the keymap counts without --rules
+    let build_rules = rules
+        .or_else(|| read_beside_keymap(&keymap_file, "rules.mk").map(|c| rules::parse_rules(&c)))
+        .unwrap_or_default();
+    let config_h = read_beside_keymap(&keymap_file, "config.h");
+    if let Some(remapping) = rules::runtime_remapping(
+        &build_rules,
+        &config_sources(&source, &config_h),
+        &keymap_file,
+    ) {
+        metadata.push((
+            strings.runtime_remapping.clone(),
+            strings.runtime_remapping_note(remapping.name()),
+        ));
+    }
+
+    // The reachability walk only feeds the lint
+    let unreachable = if diagnostics::enabled(&diagnostics::UNREACHABLE_LAYER) {
+        analysis::unreachable_layers(&layers)
+    } else {
+        Vec::new()
+    };
+    for index in unreachable {
+        diagnostics::emit(
+            &diagnostics::UNREACHABLE_LAYER,
+            format_args!(
+                "layer {} is not reachable through layer keys from the base layer \
+                 (allow {} if it is activated from code)",
+                index,
+                diagnostics::UNREACHABLE_LAYER.code
+            ),
+        );
+    }
+
+    if let Some(summary) = report_duplicate_layers(&layers) {
+        metadata.push((strings.duplicate_layers.clone(), summary));
+    }
+
+    if !args.redact.is_empty() {
+        redact::redact_layers(&mut layers, &args.redact, &args.redact_with)
+            .kind(ErrorKind::Validation)?;
+    }
 
     if args.show_stats {
         print_stats(&layers);
     }
 
-    let svg = generate_svg(&layers);
+    let board = match &args.geometry {
+        Some(path) => {
+            let layout_macro = layers.first().and_then(|l| l.layout_macro.as_deref());
+            Some(load_geometry(path, layout_macro, args.refresh)?)
+        }
+        None => args.board,
+    };
+    let (geometry, geometry_warning) = select_geometry(board, &layers);
+
+    let board_keys = geometry.key_count();
+    for layer in &mut layers {
+        let count: usize = layer.keys.iter().map(Vec::len).sum();
+        if count == board_keys {
+            continue;
+        }
+        let consequence = match (args.strict, args.lenient, count > board_keys) {
+            (true, _, _) => None,
+            (_, true, true) => Some("the extra keys are dropped"),
+            (_, true, false) => Some("the layer is padded with empty keys"),
+            (_, false, true) => Some("the extra keys are not drawn"),
+            (_, false, false) => Some("the remaining positions are left empty"),
+        };
+        // Point at the uneven row when the layer came from a keymap.c
+        let error = source
+            .as_deref()
+            .and_then(|content| parse_error::key_count_error(layer, content, &geometry));
+        let Some(consequence) = consequence else {
+            let error = match error {
+                Some(error) => anyhow::Error::new(error),
+                None => anyhow!(parse_error::key_count_message(layer, &geometry).unwrap()),
+            };
+            return Err(error.context("The keymap doesn't fit the board (--strict)"))
+                .kind(ErrorKind::Validation);
+        };
+        match error {
+            Some(mut error) => {
+                error.message = format!("{}; {}", error.message, consequence);
+                diagnostics::emit(&diagnostics::KEY_COUNT_MISMATCH, error);
+            }
+            None => diagnostics::emit(
+                &diagnostics::KEY_COUNT_MISMATCH,
+                format_args!(
+                    "{}; {}",
+                    parse_error::key_count_message(layer, &geometry).unwrap(),
+                    consequence
+                ),
+            ),
+        }
+        if args.lenient {
+            geometry.fit_keys(&mut layer.keys, "_______");
+        }
+    }
+
+    if let Some(EmitFormat::Json) = args.emit {
+        let keymap = emit::Keymap::new(&layers, &geometry);
+        println!(
+            "{}",
+            serde_json::to_string_pretty(&keymap)
+                .context("Failed to serialize the keymap")
+                .kind(ErrorKind::Render)?
+        );
+        return Ok(Vec::new());
+    }
+
+    // Encoders the board doesn't draw are listed under the layer titles
+    let drawn_encoders = geometry.encoder_count();
+    for layer in &mut layers {
+        let notes: Vec<String> = layer
+            .encoders
+            .iter()
+            .enumerate()
+            .skip(drawn_encoders)
+            .map(|(index, binding)| {
+                format!("{}: {}", strings.encoder(index + 1), binding.describe())
+            })
+            .collect();
+        layer.notes.extend(notes);
+    }
+
+    let names = geometry.position_names();
+    for conflict in analysis::hold_conflicts(&layers) {
+        let position = names
+            .get(conflict.position)
+            .cloned()
+            .unwrap_or_else(|| format!("key {}", conflict.position));
+        diagnostics::emit(
+            &diagnostics::SHADOWED_HOLD,
+            format_args!(
+                "{} on layer {} holds layer {} with {}, but layer {} has {} there; \
+                 make it _______ so the held key stays transparent",
+                position,
+                conflict.layer,
+                conflict.target,
+                conflict.key,
+                conflict.target,
+                conflict.shadow
+            ),
+        );
+    }
+
+    if let Some(summary) = report_base_variants(&layers, &geometry) {
+        metadata.push((strings.base_variant_summary.clone(), summary));
+    }
+
+    let mut key_groups = match &args.groups {
+        Some(path) => config::load::<groups::KeyGroups>(path)
+            .and_then(|groups| {
+                groups.validate()?;
+                groups.check_positions(&geometry)?;
+                Ok(groups)
+            })
+            .context(format!("Failed to load key groups: {:?}", path))
+            .kind(ErrorKind::Validation)?,
+        None => groups::KeyGroups::default(),
+    };
+    if let Some(path) = &notes_file {
+        keymap_notes
+            .check_positions(&geometry)
+            .context(format!("Failed to load keymap notes: {:?}", path))
+            .kind(ErrorKind::Validation)?;
+    }
+    key_groups
+        .groups
+        .extend(keymap_notes.groups.iter().cloned());
+    let key_notes = keymap_notes.key_notes(&layers, &geometry);
+    if !key_notes.is_empty() {
+        let badges = note_badges(key_notes);
+        decorate = Some(match decorate {
+            Some(decorate) => decorate.and(badges),
+            None => badges,
+        });
+    }
+
+    let mirrors = analysis::mirrored_layers(&layers, &geometry);
+    for mirror in &mirrors {
+        let (to, from) = match mirror.half {
+            geometry::Half::Left => ("left", "right"),
+            geometry::Half::Right => ("right", "left"),
+        };
+        eprintln!(
+            "Info: layer {} mirrors the base layer's {} hand onto the {} ({} keys){}",
+            mirror.layer,
+            from,
+            to,
+            mirror.pairs.len(),
+            if args.mirror_arcs {
+                ""
+            } else {
+                "; --mirror-arcs draws the correspondence"
+            }
+        );
+    }
+
+    let qr = args
+        .qr
+        .as_deref()
+        .map(QrFooter::new)
+        .transpose()
+        .kind(ErrorKind::Validation)?;
+
+    let mut theme = match &args.theme {
+        Some(path) => {
+            let theme = config::load::<Theme>(path)
+                .context(format!("Failed to load theme: {:?}", path))
+                .kind(ErrorKind::Validation)?;
+            theme
+                .validate()
+                .context(format!("{}: invalid theme file", path.display()))
+                .kind(ErrorKind::Validation)?;
+            theme
+        }
+        None if args.dark => Theme::dark(),
+        None => Theme::default(),
+    };
+    for (keycodes, color) in &args.color {
+        for keycode in keycodes {
+            theme.keys.insert(keycode.clone(), Gradient::solid(color));
+        }
+    }
 
-    // Write SVG to the specified output file or default to keymap filename
-    if let Some(output_file) = args.output_file {
-        fs::write(output_file, svg).context("Failed to write SVG file")?;
+    let mut legends = match &args.legends {
+        Some(path) => config::load::<keycodes::LegendOverrides>(path)
+            .context(format!("Failed to load legends: {:?}", path))
+            .kind(ErrorKind::Validation)?,
+        None => keycodes::LegendOverrides::default(),
+    };
+
+    // Aliases win over the legend table, which wins over the custom keycodes' names
+    let aliases = match &args.aliases {
+        Some(path) => config::load::<BTreeMap<String, String>>(path)
+            .context(format!("Failed to load aliases: {:?}", path))
+            .kind(ErrorKind::Validation)?,
+        None => BTreeMap::new(),
+    };
+    let custom = source
+        .as_deref()
+        .map(custom_keycodes::parse_custom_keycodes)
+        .unwrap_or_default();
+    for (keycode, legend) in custom_keycodes::legend_overrides(&custom, &aliases).legends {
+        if aliases.contains_key(&keycode) || !legends.legends.contains_key(&keycode) {
+            legends.legends.insert(keycode, legend);
+        }
+    }
+    if args.define_labels {
+        for (keycode, alias) in define_aliases {
+            legends
+                .legends
+                .entry(keycode)
+                .or_insert_with(|| keycodes::LegendOverride {
+                    description: Some(format!("#define {}", alias)),
+                    legend: alias,
+                });
+        }
+    }
+
+    if args.effort || args.effort_model.is_some() {
+        let model = match &args.effort_model {
+            Some(path) => config::load::<analysis::EffortModel>(path)
+                .context(format!("Failed to load effort model: {:?}", path))
+                .kind(ErrorKind::Validation)?,
+            None => analysis::EffortModel::split_3x6(&geometry)
+                .ok_or(anyhow::anyhow!(
+                    "No built-in effort model for {}; pass --effort-model",
+                    geometry.name
+                ))
+                .kind(ErrorKind::Validation)?,
+        };
+
+        let score = layers
+            .first()
+            .and_then(|base| analysis::effort_score(base, &geometry, &model));
+        let summary = match score {
+            Some(score) if score.missing.is_empty() => format!("{:.2}", score.score),
+            Some(score) => format!(
+                "{:.2} (missing {})",
+                score.score,
+                score.missing.iter().collect::<String>()
+            ),
+            None => "no letters on scored keys".to_string(),
+        };
+        if args.show_stats {
+            println!("Base layer effort: {}", summary);
+        }
+        metadata.push((strings.effort.clone(), summary));
+        let efforts = model.efforts(&geometry);
+        let scale = ValueScale::fit(
+            &strings.effort,
+            args.color_scale,
+            scale_range,
+            efforts.iter().flatten().copied(),
+        );
+        scales.push(scale.clone());
+        let overlay = effort_overlay(efforts, scale);
+        decorate = Some(match decorate {
+            Some(decorate) => decorate.and(overlay),
+            None => overlay,
+        });
+    }
+
+    if let Some(path) = &args.heatmap
+        && let Some(base) = layers.first()
+    {
+        let content = fs::read_to_string(path)
+            .context(format!("Failed to read heatmap: {:?}", path))
+            .kind(ErrorKind::Io)?;
+        let counts = if path.extension().is_some_and(|ext| ext == "csv") {
+            heatmap::parse_counts(&content, base, &geometry)
+                .context(format!("Failed to parse heatmap: {:?}", path))
+                .kind(ErrorKind::Validation)?
+        } else {
+            heatmap::count_corpus(&content, base)
+        };
+        let scale = ValueScale::fit(
+            &strings.key_presses,
+            args.color_scale,
+            scale_range,
+            counts.iter().flatten().copied(),
+        );
+        scales.push(scale.clone());
+        let overlay = heatmap::heatmap_overlay(base.index, counts, scale);
+        decorate = Some(match decorate {
+            Some(decorate) => decorate.and(overlay),
+            None => overlay,
+        });
+    }
+
+    let layer_key_docs = if args.explain_layer_keys {
+        let config_h = read_beside_keymap(&keymap_file, "config.h");
+        let tapping_toggle = analysis::tapping_toggle(&config_sources(&source, &config_h));
+        analysis::layer_key_docs(&layers, &geometry, tapping_toggle)
     } else {
-        let basename = args
-            .keymap_file
+        Vec::new()
+    };
+
+    let thumb_keys = if args.thumb_keys {
+        analysis::thumb_keys(&layers, &geometry)
+    } else {
+        analysis::ThumbKeys::default()
+    };
+
+    let combos = source
+        .as_deref()
+        .map(combos::parse_combos)
+        .unwrap_or_default()
+        .iter()
+        .map(|combo| combo.entry(&strings))
+        .collect();
+
+    let metrics = LayoutMetrics {
+        key_size: args.key_size,
+        spacing: args.spacing,
+        split_gap: args.split_gap,
+        layer_spacing: args.layer_spacing,
+        margin: args.margin,
+    };
+    metrics.validate().kind(ErrorKind::Validation)?;
+
+    let warnings = geometry_warning
+        .map(|_| strings.fallback_board(geometry.key_count()))
+        .into_iter()
+        .collect();
+    let options = RenderOptions {
+        layer_colors: args.layer_color.into_iter().collect(),
+        metadata,
+        layer_key_docs,
+        combos,
+        thumb_keys,
+        tap_dances: source
+            .as_deref()
+            .map(tap_dance::parse_tap_dances)
+            .unwrap_or_default(),
+        scales,
+        qr,
+        strings,
+        metrics,
+        style: args.style,
+        geometry,
+        empty_keys: args.empty_keys,
+        theme: Theme {
+            monochrome: args.monochrome || theme.monochrome,
+            ..theme
+        },
+        warnings,
+        label_positions: args.label_positions,
+        half_tint: args.half_tint,
+        mirrors: if args.mirror_arcs {
+            mirrors
+        } else {
+            Vec::new()
+        },
+        key_groups,
+        embed: args.embed,
+        decorate,
+        legends,
+        readable_legends: args.readable_legends,
+        icons: args.icons,
+        vertical_legends: args.vertical_legends,
+        overflow: args.overflow,
+        fixed_key_width: args.fixed_key_width,
+        text_metrics: args
+            .vertical_legends
+            .then(|| Arc::new(raster::TextMeasurer::new())),
+    };
+
+    if let Some(EmitFormat::Html) = args.emit {
+        let output_file = match args.output_file.first() {
+            Some(path) => path.clone(),
+            None => default_output_path(&keymap_file, "html").kind(ErrorKind::Io)?,
+        };
+        let tabs: Vec<tabs::LayerTab> = layer_renderers(&layers, &options)
+            .iter()
+            .map(|renderer| tabs::LayerTab {
+                label: options.strings.layer_title(renderer.layer()),
+                svg: combine_layers(std::slice::from_ref(renderer), &options),
+                alt: analysis::layer_alt_text(renderer.layer(), &options.geometry),
+            })
+            .collect();
+        let title = keymap_file
             .file_stem()
-            .and_then(|s| s.to_str())
-            .ok_or(anyhow::anyhow!("Invalid filename"))
-            .context("Unable to retrieve output file basename")?;
-        let output_path = PathBuf::from(format!("{}.svg", basename));
-        fs::write(output_path, svg).context("Failed to write SVG file")?;
+            .map(|stem| stem.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        let background = if options.theme.monochrome {
+            "#ffffff"
+        } else {
+            options.theme.background.as_str()
+        };
+        fs::write(&output_file, tabs::tabbed_page(&title, &tabs, background))
+            .context(format!("Failed to write {:?}", output_file))
+            .kind(ErrorKind::Io)?;
+        return Ok(vec![output_file]);
     }
 
-    Ok(())
+    // Split documents are written next to the output name with the layer's suffix
+    let documents: Vec<(Option<usize>, String)> = if args.single_card {
+        vec![(
+            None,
+            generate_single_card_svg_with_options(&layers, &options),
+        )]
+    } else if args.split_layers {
+        layer_renderers(&layers, &options)
+            .iter()
+            .map(|renderer| {
+                (
+                    Some(renderer.layer().index),
+                    combine_layers(std::slice::from_ref(renderer), &options),
+                )
+            })
+            .collect()
+    } else {
+        vec![(None, generate_svg_with_options(&layers, &options))]
+    };
+
+    let title = keymap_file
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let targets = output_targets(&keymap_file, &args.output_file, &args.output_format)?;
+
+    let alt_texts: BTreeMap<usize, String> = layers
+        .iter()
+        .map(|layer| {
+            (
+                layer.index,
+                analysis::layer_alt_text(layer, &options.geometry),
+            )
+        })
+        .collect();
+    if let Some(path) = &args.alt_text {
+        let text: String = alt_texts.values().map(|alt| format!("{}\n", alt)).collect();
+        fs::write(path, text)
+            .context(format!("Failed to write {:?}", path))
+            .kind(ErrorKind::Io)?;
+    }
+
+    // HTML cards preview the layer a key activates when hovering it
+    let mut layer_previews = BTreeMap::new();
+    if args.single_card && targets.iter().any(|(_, f)| *f == OutputFormat::Html) {
+        for layer in layers.iter().skip(1) {
+            layer_previews.insert(
+                layer.index,
+                LayerPreview {
+                    svg: generate_layer_thumbnail_svg(&layers, layer, &options),
+                    alt: alt_texts[&layer.index].clone(),
+                },
+            );
+        }
+    }
+
+    // The single card and split layers have no layer sections to link to
+    let layer_links: Vec<(String, String)> = if args.single_card || args.split_layers {
+        Vec::new()
+    } else {
+        layers
+            .iter()
+            .map(|layer| (layer_anchor(layer), options.strings.layer_title(layer)))
+            .collect()
+    };
+
+    // Monochrome documents are drawn on white paper
+    let background = if options.theme.monochrome {
+        "#ffffff"
+    } else {
+        options.theme.background.as_str()
+    };
+    let mut written = Vec::new();
+    for (path, format) in targets {
+        for (layer_index, svg) in &documents {
+            let path = match layer_index {
+                Some(index) => layer_output_path(&path, *index),
+                None => path.clone(),
+            };
+            // A layer's own document describes that layer only
+            let alt_text = match layer_index {
+                Some(index) => alt_texts[index].clone(),
+                None => alt_texts.values().cloned().collect::<Vec<_>>().join(" "),
+            };
+            let page = HtmlPage {
+                title: &title,
+                alt_text: &alt_text,
+                layer_previews: &layer_previews,
+                layer_links: &layer_links,
+                background,
+            };
+            let content = format
+                .encode(svg, &page, args.dpi)
+                .kind(ErrorKind::Render)?;
+            fs::write(&path, content)
+                .context(format!("Failed to write {:?}", path))
+                .kind(ErrorKind::Io)?;
+            written.push(path);
+        }
+    }
+
+    if let (Some(format), Some((_, svg))) = (args.copy, documents.first()) {
+        copy_to_clipboard(svg, format, args.dpi)?;
+        eprintln!("Copied the {} to the clipboard", format_name(format));
+    }
+
+    Ok(written)
+}
+
+fn format_name(format: CopyFormat) -> &'static str {
+    match format {
+        CopyFormat::Png => "PNG image",
+        CopyFormat::Svg => "SVG document",
+    }
+}
+
+/// Places a rendered document on the clipboard as a PNG image or SVG text.
+#[cfg(feature = "clipboard")]
+fn copy_to_clipboard(svg: &str, format: CopyFormat, png_dpi: f32) -> Result<(), Failure> {
+    match format {
+        CopyFormat::Svg => clipboard::copy(clipboard::Content::Svg(svg)),
+        CopyFormat::Png => {
+            let page = HtmlPage {
+                title: "",
+                alt_text: "",
+                layer_previews: &BTreeMap::new(),
+                layer_links: &[],
+                background: "",
+            };
+            let png = OutputFormat::Png
+                .encode(svg, &page, png_dpi)
+                .kind(ErrorKind::Render)?;
+            clipboard::copy(clipboard::Content::Png(&png))
+        }
+    }
+    .kind(ErrorKind::Io)
+}
+
+#[cfg(not(feature = "clipboard"))]
+fn copy_to_clipboard(_svg: &str, _format: CopyFormat, _png_dpi: f32) -> Result<(), Failure> {
+    Err(anyhow::anyhow!(
+        "--copy needs a build with the clipboard feature"
+    ))
+    .kind(ErrorKind::Other)
+}
+
+/// The output path of one layer's document, e.g. `keymap-layer2.svg` for
+/// `keymap.svg`.
+fn layer_output_path(path: &Path, layer_index: usize) -> PathBuf {
+    let stem = path
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let name = match path.extension() {
+        Some(extension) => format!(
+            "{}-layer{}.{}",
+            stem,
+            layer_index,
+            extension.to_string_lossy()
+        ),
+        None => format!("{}-layer{}", stem, layer_index),
+    };
+    path.with_file_name(name)
+}
+
+/// Reads a file next to the keymap, such as the config.h where QMK features
+/// like Auto Shift and tap toggles are configured.
+fn read_beside_keymap(keymap_file: &Path, name: &str) -> Option<String> {
+    keymap_file
+        .parent()
+        .map(|dir| dir.join(name))
+        .and_then(|path| fs::read_to_string(path).ok())
+}
+
+/// The keymap and config.h sources that are available.
+fn config_sources<'a>(source: &'a Option<String>, config_h: &'a Option<String>) -> Vec<&'a str> {
+    [source.as_deref(), config_h.as_deref()]
+        .into_iter()
+        .flatten()
+        .collect()
+}
+
+/// Resolves the files a render is written to and their formats.
+///
+/// Each `-o` file takes the format of its extension. `--output-format` writes
+/// every listed format next to a single base name: the `-o` file if one is
+/// given, otherwise the keymap's file stem.
+fn output_targets(
+    keymap_file: &Path,
+    output_files: &[PathBuf],
+    formats: &[OutputFormat],
+) -> Result<Vec<(PathBuf, OutputFormat)>, Failure> {
+    if formats.is_empty() {
+        if output_files.is_empty() {
+            let path = default_output_path(keymap_file, OutputFormat::Svg.extension())?;
+            return Ok(vec![(path, OutputFormat::Svg)]);
+        }
+        return Ok(output_files
+            .iter()
+            .map(|path| (path.clone(), OutputFormat::from_path(path)))
+            .collect());
+    }
+
+    let base = match output_files {
+        [] => default_output_path(keymap_file, OutputFormat::Svg.extension())?,
+        [path] => path.clone(),
+        _ => {
+            return Err(anyhow::anyhow!(
+                "--output-format takes at most one --output-file as the base name"
+            ))
+            .kind(ErrorKind::Validation);
+        }
+    };
+
+    let mut targets: Vec<(PathBuf, OutputFormat)> = Vec::new();
+    for &format in formats {
+        if !targets.iter().any(|(_, f)| *f == format) {
+            targets.push((base.with_extension(format.extension()), format));
+        }
+    }
+    Ok(targets)
 }
@@ -0,0 +1,69 @@
+//! Ad-hoc layers sketched as plain text grids, for mockups without a keymap.c.
+use crate::Layer;
+use anyhow::{Result, bail};
+
+/// Parses layers from a plain text grid.
+///
+/// Keys are separated by whitespace and rows by line breaks. Blank lines
+/// separate layers, and a `[NAME]` line names the layer below it. Keys are
+/// kept as written, so they can be keycodes (`KC_A`, `MO(1)`) or plain
+/// legends (`Tab`, `⌫`); `_` marks an empty key.
+///
+/// # Arguments
+///
+/// * `content` - The grid text
+///
+/// # Returns
+///
+/// The layers in order, or an error if the grid has no keys
+pub fn parse_grid(content: &str) -> Result<Vec<Layer>> {
+    let mut layers: Vec<Layer> = Vec::new();
+    let mut rows: Vec<Vec<String>> = Vec::new();
+    let mut name = None;
+
+    let mut finish = |rows: &mut Vec<Vec<String>>, name: &mut Option<String>| {
+        if !rows.is_empty() {
+            layers.push(Layer {
+                index: layers.len(),
+                name: name.take(),
+                title: None,
+                keys: std::mem::take(rows),
+                notes: Vec::new(),
+                layout_macro: None,
+                source: None,
+                encoders: Vec::new(),
+            });
+        }
+    };
+
+    for (line_number, line) in content.lines().enumerate() {
+        let trimmed = line.trim();
+        if trimmed.is_empty() {
+            finish(&mut rows, &mut name);
+            continue;
+        }
+
+        if let Some(header) = trimmed
+            .strip_prefix('[')
+            .and_then(|rest| rest.strip_suffix(']'))
+            .filter(|header| !header.is_empty() && !header.contains(char::is_whitespace))
+        {
+            finish(&mut rows, &mut name);
+            if let Some(previous) = name.replace(header.to_string()) {
+                bail!("line {}: layer [{}] has no keys", line_number + 1, previous);
+            }
+            continue;
+        }
+
+        rows.push(trimmed.split_whitespace().map(str::to_string).collect());
+    }
+    finish(&mut rows, &mut name);
+    if let Some(name) = name {
+        bail!("layer [{}] has no keys", name);
+    }
+
+    if layers.is_empty() {
+        bail!("No keys found in the grid");
+    }
+    Ok(layers)
+}
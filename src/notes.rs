@@ -0,0 +1,192 @@
+//! Annotations kept in a `keymap.notes.toml` file next to the keymap: layer
+//! names and notes, notes on individual keys, and key groups, merged into the
+//! parsed layers without any flags.
+use crate::groups::{KeyGroup, KeyGroups};
+use crate::{Geometry, Layer};
+use anyhow::{Result, bail};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+
+/// File name of the notes file looked up next to a keymap.
+pub const NOTES_FILE: &str = "keymap.notes.toml";
+
+/// The contents of a notes file, e.g.
+///
+/// ```toml
+/// [layers.NAV]
+/// name = "Navigation"
+/// notes = ["Hold the right thumb"]
+///
+/// [[keys]]
+/// layer = "NAV"
+/// position = "R-R2C1"
+/// note = "Word left when held"
+///
+/// [[groups]]
+/// label = "Arrows"
+/// positions = ["R-R2C1", "R-R2C2", "R-R2C3", "R-R2C4"]
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct KeymapNotes {
+    /// Layer names and notes keyed by layer name or number; leading
+    /// underscores and case are ignored
+    #[serde(default)]
+    pub layers: BTreeMap<String, LayerNotes>,
+    /// Notes on individual keys, shown in a badge's tooltip
+    #[serde(default)]
+    pub keys: Vec<KeyNote>,
+    /// Key groups, drawn like those of `--groups`
+    #[serde(default)]
+    pub groups: Vec<KeyGroup>,
+}
+
+/// The name and notes of one layer.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct LayerNotes {
+    /// Name shown in the layer's title instead of the one in the keymap
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    /// Notes shown under the layer's title, after those of `// viz:` comments
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub notes: Vec<String>,
+}
+
+/// A note on one key.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct KeyNote {
+    /// Name or number of the key's layer; every layer when left out
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub layer: Option<String>,
+    /// Position name of the key (see [`Geometry::position_names`])
+    pub position: String,
+    pub note: String,
+}
+
+impl KeymapNotes {
+    /// The notes file next to a keymap, if there is one.
+    pub fn find(keymap_file: &Path) -> Option<PathBuf> {
+        let path = keymap_file.parent()?.join(NOTES_FILE);
+        path.is_file().then_some(path)
+    }
+
+    /// Checks that no note or group is empty.
+    pub fn validate(&self) -> Result<()> {
+        for (i, key) in self.keys.iter().enumerate() {
+            if key.note.trim().is_empty() {
+                bail!("keys[{}] ({}): note must not be empty", i, key.position);
+            }
+        }
+        self.key_groups().validate()
+    }
+
+    /// Checks that every layer the notes refer to exists.
+    ///
+    /// # Arguments
+    ///
+    /// * `layers` - The parsed layers, before [`KeymapNotes::apply`] renames
+    ///   them
+    ///
+    /// # Returns
+    ///
+    /// `Ok(())`, or an error naming the first unknown layer
+    pub fn check_layers(&self, layers: &[Layer]) -> Result<()> {
+        let known = |selector: &str| layers.iter().any(|layer| layer.matches_selector(selector));
+        let mut selectors = self
+            .layers
+            .keys()
+            .chain(self.keys.iter().filter_map(|key| key.layer.as_ref()))
+            .chain(self.groups.iter().flat_map(|group| &group.layers));
+        match selectors.find(|selector| !known(selector)) {
+            Some(unknown) => bail!("the keymap has no layer {}", unknown),
+            None => Ok(()),
+        }
+    }
+
+    /// Checks that every key position the notes refer to exists on a board.
+    pub fn check_positions(&self, geometry: &Geometry) -> Result<()> {
+        let names = geometry.position_names();
+        if let Some(key) = self.keys.iter().find(|key| !names.contains(&key.position)) {
+            bail!(
+                "{} has no position {}; list them with `keyball44-viz positions`",
+                geometry.name,
+                key.position
+            );
+        }
+        self.key_groups().check_positions(geometry)
+    }
+
+    /// Sets the titles of the layers and appends their notes.
+    ///
+    /// The layers of key notes and groups are first replaced by their
+    /// numbers, so they keep selecting the same layers after a rename.
+    pub fn apply(&mut self, layers: &mut [Layer]) {
+        let number = |selector: &mut String| {
+            if let Some(layer) = layers.iter().find(|l| l.matches_selector(selector)) {
+                *selector = layer.index.to_string();
+            }
+        };
+        self.keys
+            .iter_mut()
+            .filter_map(|key| key.layer.as_mut())
+            .for_each(number);
+        self.groups
+            .iter_mut()
+            .flat_map(|group| &mut group.layers)
+            .for_each(number);
+
+        for (selector, notes) in &self.layers {
+            for layer in layers.iter_mut().filter(|l| l.matches_selector(selector)) {
+                if let Some(name) = &notes.name {
+                    layer.title = Some(name.clone());
+                }
+                layer.notes.extend(notes.notes.iter().cloned());
+            }
+        }
+    }
+
+    /// The groups as a [`KeyGroups`] config.
+    pub fn key_groups(&self) -> KeyGroups {
+        KeyGroups {
+            groups: self.groups.clone(),
+        }
+    }
+
+    /// The notes of individual keys keyed by layer index and LAYOUT index,
+    /// with notes on the same key joined by line breaks.
+    ///
+    /// # Arguments
+    ///
+    /// * `layers` - The layers to note
+    /// * `geometry` - The board the layers are drawn on
+    pub fn key_notes(
+        &self,
+        layers: &[Layer],
+        geometry: &Geometry,
+    ) -> HashMap<(usize, usize), String> {
+        let names = geometry.position_names();
+        let mut notes: HashMap<(usize, usize), String> = HashMap::new();
+        for key in &self.keys {
+            let Some(index) = names.iter().position(|name| *name == key.position) else {
+                continue;
+            };
+            let on = layers.iter().filter(|layer| {
+                key.layer
+                    .as_deref()
+                    .is_none_or(|selector| layer.matches_selector(selector))
+            });
+            for layer in on {
+                let note = notes.entry((layer.index, index)).or_default();
+                if !note.is_empty() {
+                    note.push('\n');
+                }
+                note.push_str(&key.note);
+            }
+        }
+        notes
+    }
+}
@@ -0,0 +1,470 @@
+//! Import of ZSA Oryx layout exports (Voyager, Moonlander) onto another
+//! board's geometry.
+//!
+//! Oryx keys are converted to their QMK spelling and moved to the target
+//! board by position: alpha rows are matched from the outer edge and thumb
+//! keys from the inner edge. A mapping file can place any key explicitly.
+use crate::Layer;
+use crate::geometry::{Geometry, Half, KeyOrder, KeyPosition};
+use anyhow::{Context, Result, anyhow, bail};
+use serde::Deserialize;
+use serde_json::Value;
+use std::collections::BTreeMap;
+
+/// Explicit placement of Oryx keys on the target board.
+///
+/// ```toml
+/// # Target position name = index of the key in the Oryx export (0-based)
+/// [positions]
+/// "L-R1C1" = 13
+/// "thumb-L5" = 48
+/// ```
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PositionMapping {
+    /// Oryx key index by target position name (see
+    /// [`Geometry::position_names`])
+    pub positions: BTreeMap<String, usize>,
+}
+
+/// An Oryx export converted to QMK keycodes in Oryx key order.
+#[derive(Debug, Clone)]
+pub struct OryxLayout {
+    /// Title of the layout in Oryx
+    pub title: String,
+    /// The Oryx keyboard id, e.g. `voyager`
+    pub keyboard: String,
+    /// Layer titles and keys in Oryx key order
+    pub layers: Vec<(String, Vec<String>)>,
+}
+
+/// A ZSA board whose Oryx key order is known.
+struct SourceBoard {
+    geometry: Geometry,
+    /// Index of the top alpha (QWERTY) row
+    alpha_row: usize,
+}
+
+/// A row of keys at height `y` with the given x positions.
+fn row(y: f32, half: Half, xs: &[f32]) -> Vec<KeyPosition> {
+    xs.iter().map(|&x| KeyPosition::new(x, y, half)).collect()
+}
+
+/// `left` followed by `right`, like the LAYOUT macros list each row.
+fn split(left: Vec<KeyPosition>, right: Vec<KeyPosition>) -> Vec<KeyPosition> {
+    left.into_iter().chain(right).collect()
+}
+
+/// Boards whose Oryx key order follows their LAYOUT macro.
+fn source_board(keyboard: &str) -> Option<SourceBoard> {
+    let columns =
+        |start: f32, count: usize| -> Vec<f32> { (0..count).map(|i| start + i as f32).collect() };
+
+    match keyboard {
+        "voyager" => {
+            let main = |y| {
+                split(
+                    row(y, Half::Left, &columns(0.0, 6)),
+                    row(y, Half::Right, &columns(7.0, 6)),
+                )
+            };
+            Some(SourceBoard {
+                geometry: Geometry {
+                    name: "voyager".to_string(),
+                    description: "ZSA Voyager".to_string(),
+                    layout_macros: vec!["LAYOUT_voyager".to_string()],
+                    rows: vec![
+                        main(0.0),
+                        main(1.0),
+                        main(2.0),
+                        main(3.0),
+                        split(
+                            row(4.0, Half::Left, &[4.0, 5.0]),
+                            row(4.0, Half::Right, &[7.0, 8.0]),
+                        ),
+                    ],
+                    order: KeyOrder::RowMajor,
+                    controls: Vec::new(),
+                },
+                alpha_row: 1,
+            })
+        }
+        "moonlander" => {
+            let main = |y| {
+                split(
+                    row(y, Half::Left, &columns(0.0, 7)),
+                    row(y, Half::Right, &columns(8.0, 7)),
+                )
+            };
+            Some(SourceBoard {
+                geometry: Geometry {
+                    name: "moonlander".to_string(),
+                    description: "ZSA Moonlander".to_string(),
+                    layout_macros: vec!["LAYOUT_moonlander".to_string()],
+                    rows: vec![
+                        main(0.0),
+                        main(1.0),
+                        main(2.0),
+                        split(
+                            row(3.0, Half::Left, &columns(0.0, 6)),
+                            row(3.0, Half::Right, &columns(9.0, 6)),
+                        ),
+                        // The red thumb keys sit between the bottom row's halves
+                        split(
+                            row(4.0, Half::Left, &[0.0, 1.0, 2.0, 3.0, 4.0, 6.0]),
+                            row(4.0, Half::Right, &[8.0, 10.0, 11.0, 12.0, 13.0, 14.0]),
+                        ),
+                        split(
+                            row(5.0, Half::Left, &columns(4.0, 3)),
+                            row(5.0, Half::Right, &columns(8.0, 3)),
+                        ),
+                    ],
+                    order: KeyOrder::RowMajor,
+                    controls: Vec::new(),
+                },
+                alpha_row: 1,
+            })
+        }
+        _ => None,
+    }
+}
+
+/// Where a key sits relative to the edges of its board half.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Slot {
+    /// A row above the thumb row, counted from the top alpha row, and the
+    /// column counted from the outer edge
+    Main { row: isize, column: usize },
+    /// A thumb key counted from the inner edge
+    Thumb { column: usize },
+}
+
+/// The slot of every key of a split geometry, in LAYOUT order. The last row
+/// is the thumb row, as in [`Geometry::position_names`].
+fn slots(geometry: &Geometry, alpha_row: usize) -> Vec<(Half, Slot)> {
+    let thumb_row = geometry.rows.len().saturating_sub(1);
+    let mut slots = Vec::new();
+
+    for (row, positions) in geometry.rows.iter().enumerate() {
+        for p in positions {
+            let same_half = positions.iter().filter(|other| other.half == p.half);
+            // Keys further toward the outer (or, for thumbs, inner) edge
+            let toward_outer = |other: &&KeyPosition| match p.half {
+                Half::Left => other.x < p.x,
+                Half::Right => other.x > p.x,
+            };
+            let slot = if row == thumb_row && row > 0 {
+                Slot::Thumb {
+                    column: same_half
+                        .filter(|other| other.x != p.x && !toward_outer(other))
+                        .count(),
+                }
+            } else {
+                Slot::Main {
+                    row: row as isize - alpha_row as isize,
+                    column: same_half.filter(toward_outer).count(),
+                }
+            };
+            slots.push((p.half, slot));
+        }
+    }
+
+    geometry.in_argument_order(&slots)
+}
+
+/// Spells a modifier held with a key, e.g. `KC_LEFT_SHIFT` → `LSFT`.
+fn modifier_name(code: &str) -> Option<&'static str> {
+    let code = code.trim_start_matches("KC_");
+    let right = code.starts_with('R') && !code.starts_with("RGB");
+    let name = if code.contains("SHIFT") || code.contains("SFT") {
+        ["LSFT", "RSFT"]
+    } else if code.contains("CTRL") || code.contains("CTL") {
+        ["LCTL", "RCTL"]
+    } else if code.contains("ALT") || code.contains("OPT") {
+        ["LALT", "RALT"]
+    } else if code.contains("GUI") || code.contains("CMD") || code.contains("WIN") {
+        ["LGUI", "RGUI"]
+    } else {
+        return None;
+    };
+    Some(name[right as usize])
+}
+
+/// Layer functions Oryx writes as a code with a separate `layer` field.
+const LAYER_FUNCTIONS: [&str; 6] = ["MO", "TG", "TO", "TT", "OSL", "DF"];
+
+/// Converts one Oryx key object to its QMK keycode.
+fn convert_key(key: &Value) -> String {
+    let tap = key.get("tap").filter(|tap| !tap.is_null());
+    let hold = key.get("hold").filter(|hold| !hold.is_null());
+    let code = |action: Option<&Value>| {
+        action
+            .and_then(|a| a.get("code"))
+            .and_then(Value::as_str)
+            .map(str::to_string)
+    };
+    let layer =
+        |action: Option<&Value>| action.and_then(|a| a.get("layer")).and_then(Value::as_u64);
+
+    let mut tapped = match (code(tap), layer(tap)) {
+        (Some(code), Some(layer)) if LAYER_FUNCTIONS.contains(&code.as_str()) => {
+            format!("{}({})", code, layer)
+        }
+        (Some(code), _) => code,
+        (None, _) => "KC_NO".to_string(),
+    };
+
+    // Modifiers sent along with the tapped key, e.g. {"leftShift": true}
+    if let Some(modifiers) = tap
+        .and_then(|t| t.get("modifiers"))
+        .and_then(Value::as_object)
+    {
+        for (name, enabled) in modifiers {
+            if enabled.as_bool() != Some(true) {
+                continue;
+            }
+            let side = if name.starts_with("right") { "R" } else { "L" };
+            let modifier = name.trim_start_matches("left").trim_start_matches("right");
+            let wrapper = match modifier.to_ascii_lowercase().as_str() {
+                "shift" => "SFT",
+                "ctrl" | "control" => "CTL",
+                "alt" => "ALT",
+                "gui" | "cmd" | "win" => "GUI",
+                _ => continue,
+            };
+            tapped = format!("{}{}({})", side, wrapper, tapped);
+        }
+    }
+
+    let keycode = match (layer(hold), code(hold)) {
+        (Some(layer), _) => format!("LT({}, {})", layer, tapped),
+        (None, Some(code)) => match modifier_name(&code) {
+            Some(modifier) => format!("{}_T({})", modifier, tapped),
+            None => tapped,
+        },
+        (None, None) => tapped,
+    };
+
+    match keycode.as_str() {
+        "KC_TRANSPARENT" | "KC_TRNS" => "_______".to_string(),
+        "KC_NO" => "XXXXXXX".to_string(),
+        _ => keycode,
+    }
+}
+
+/// Parses an Oryx layout export.
+///
+/// Accepts the full API response as well as its `layout` or `revision`
+/// object. Tap dances and other multi-action keys keep their tap and hold
+/// actions only.
+///
+/// # Arguments
+///
+/// * `content` - The JSON export
+///
+/// # Returns
+///
+/// The layout with its layers sorted by position, or an error if the JSON
+/// has no layers
+pub fn parse_export(content: &str) -> Result<OryxLayout> {
+    let root: Value = serde_json::from_str(content).context("Invalid Oryx JSON")?;
+    let layout = root
+        .pointer("/data/layout")
+        .or_else(|| root.get("layout"))
+        .unwrap_or(&root);
+    let revision = layout.get("revision").unwrap_or(layout);
+
+    let keyboard = layout
+        .get("geometry")
+        .or_else(|| revision.get("geometry"))
+        .and_then(Value::as_str)
+        .unwrap_or_default()
+        .to_ascii_lowercase();
+    let title = layout
+        .get("title")
+        .and_then(Value::as_str)
+        .unwrap_or_default()
+        .to_string();
+
+    let mut layers: Vec<(u64, String, Vec<String>)> = revision
+        .get("layers")
+        .and_then(Value::as_array)
+        .ok_or(anyhow!("The Oryx export has no layers"))?
+        .iter()
+        .enumerate()
+        .map(|(idx, layer)| {
+            let position = layer
+                .get("position")
+                .and_then(Value::as_u64)
+                .unwrap_or(idx as u64);
+            let title = layer
+                .get("title")
+                .and_then(Value::as_str)
+                .unwrap_or_default()
+                .to_string();
+            let keys = layer
+                .get("keys")
+                .and_then(Value::as_array)
+                .map(|keys| keys.iter().map(convert_key).collect())
+                .unwrap_or_default();
+            (position, title, keys)
+        })
+        .collect();
+    layers.sort_by_key(|(position, _, _)| *position);
+
+    if layers.is_empty() {
+        bail!("The Oryx export has no layers");
+    }
+
+    Ok(OryxLayout {
+        title,
+        keyboard,
+        layers: layers
+            .into_iter()
+            .map(|(_, title, keys)| (title, keys))
+            .collect(),
+    })
+}
+
+/// Chooses the Oryx key shown at every position of the target geometry.
+///
+/// # Arguments
+///
+/// * `keyboard` - The Oryx keyboard id
+/// * `target` - The board the layout is moved to
+/// * `mapping` - Explicit placements, taking precedence over the defaults
+///
+/// # Returns
+///
+/// One Oryx key index (or `None` for positions left empty) per target key,
+/// in LAYOUT order, or an error if the mapping names an unknown position or
+/// the board is unknown and no mapping is given
+pub fn map_positions(
+    keyboard: &str,
+    target: &Geometry,
+    mapping: Option<&PositionMapping>,
+) -> Result<Vec<Option<usize>>> {
+    let names = target.position_names();
+    let mut indices: Vec<Option<usize>> = match source_board(keyboard) {
+        Some(source) => {
+            let source_slots = slots(&source.geometry, source.alpha_row);
+            let target_main_rows = target.rows.len().saturating_sub(1);
+            slots(target, target_main_rows.saturating_sub(3))
+                .into_iter()
+                .map(|slot| source_slots.iter().position(|s| *s == slot))
+                .collect()
+        }
+        None if mapping.is_some() => vec![None; names.len()],
+        None => bail!(
+            "No default key placement for Oryx board \"{}\"; pass a position mapping file",
+            keyboard
+        ),
+    };
+
+    for (name, &index) in mapping.map(|m| &m.positions).into_iter().flatten() {
+        let position = names
+            .iter()
+            .position(|n| n.eq_ignore_ascii_case(name))
+            .ok_or(anyhow!(
+                "positions.\"{}\": no such position on {}",
+                name,
+                target.name
+            ))?;
+        indices[position] = Some(index);
+    }
+
+    Ok(indices)
+}
+
+/// Moves an Oryx layout onto the target geometry.
+///
+/// # Arguments
+///
+/// * `layout` - The parsed Oryx export
+/// * `target` - The board the layout is moved to
+/// * `mapping` - Explicit placements, taking precedence over the defaults
+///
+/// # Returns
+///
+/// One layer per Oryx layer with the keys grouped in the target's rows;
+/// positions without an Oryx key are empty
+pub fn import_layers(
+    layout: &OryxLayout,
+    target: &Geometry,
+    mapping: Option<&PositionMapping>,
+) -> Result<Vec<Layer>> {
+    let indices = map_positions(&layout.keyboard, target, mapping)?;
+
+    Ok(layout
+        .layers
+        .iter()
+        .enumerate()
+        .map(|(index, (title, keys))| {
+            let mut mapped = indices.iter().map(|idx| {
+                idx.and_then(|idx| keys.get(idx))
+                    .cloned()
+                    .unwrap_or_else(|| "_______".to_string())
+            });
+            Layer {
+                index,
+                name: None,
+                title: None,
+                keys: target
+                    .row_lengths()
+                    .into_iter()
+                    .map(|len| mapped.by_ref().take(len).collect())
+                    .collect(),
+                notes: if title.is_empty() {
+                    Vec::new()
+                } else {
+                    vec![title.clone()]
+                },
+                layout_macro: target.layout_macros.first().cloned(),
+                source: None,
+                encoders: Vec::new(),
+            }
+        })
+        .collect())
+}
+
+/// Writes layers as a keymap.c, with the layer titles as `// viz:` notes.
+///
+/// # Arguments
+///
+/// * `layers` - The layers to write
+/// * `title` - A description for the header comment
+///
+/// # Returns
+///
+/// The keymap.c source, one line per geometry row
+pub fn keymap_source(layers: &[Layer], title: &str) -> String {
+    let mut source = format!("// {}\n#include QMK_KEYBOARD_H\n\n", title);
+
+    for layer in layers {
+        for note in &layer.notes {
+            source.push_str(&format!(
+                "// viz: layer={} note=\"{}\"\n",
+                layer.index,
+                note.replace('"', "\\\"")
+            ));
+        }
+    }
+    if layers.iter().any(|l| !l.notes.is_empty()) {
+        source.push('\n');
+    }
+
+    source.push_str("const uint16_t PROGMEM keymaps[][MATRIX_ROWS][MATRIX_COLS] = {\n");
+    for layer in layers {
+        let layout_macro = layer.layout_macro.as_deref().unwrap_or("LAYOUT");
+        source.push_str(&format!("    [{}] = {}(\n", layer.index, layout_macro));
+        let rows: Vec<String> = layer
+            .keys
+            .iter()
+            .map(|row| format!("        {}", row.join(", ")))
+            .collect();
+        source.push_str(&rows.join(",\n"));
+        source.push_str("\n    ),\n");
+    }
+    source.push_str("};\n");
+    source
+}
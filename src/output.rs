@@ -0,0 +1,256 @@
+//! Output file formats a rendered keymap can be written in.
+use crate::{raster, theme};
+use anyhow::{Context, Result};
+use clap::ValueEnum;
+use flate2::Compression;
+use flate2::write::GzEncoder;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::io::Write;
+use std::path::Path;
+
+/// Resolution of PNG output: twice the document size, for crisp text on
+/// HiDPI screens.
+pub const DEFAULT_PNG_DPI: f32 = 192.0;
+
+/// Resolution at which one SVG unit is one pixel.
+const SVG_DPI: f32 = 96.0;
+
+/// A miniature of a layer, shown in HTML output when hovering a key that
+/// activates the layer.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LayerPreview {
+    /// The miniature SVG document
+    pub svg: String,
+    /// Alt text describing the layer
+    pub alt: String,
+}
+
+/// What an HTML page shows around the embedded document.
+#[derive(Debug, Clone, Copy)]
+pub struct HtmlPage<'a> {
+    /// Page title
+    pub title: &'a str,
+    /// Description of the drawn layers, the accessible label of the
+    /// document; empty for none
+    pub alt_text: &'a str,
+    /// Layer miniatures by layer index, shown when hovering keys with a
+    /// matching `data-layer` attribute
+    pub layer_previews: &'a BTreeMap<usize, LayerPreview>,
+    /// `(anchor id, label)` of every layer section, listed in a navigation bar
+    pub layer_links: &'a [(String, String)],
+    /// Page background, the document's background color
+    pub background: &'a str,
+}
+
+/// A file format for rendered keymaps.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum OutputFormat {
+    /// The SVG document itself
+    Svg,
+    /// The SVG document compressed with gzip, which browsers and most SVG
+    /// viewers open directly
+    Svgz,
+    /// A PNG image of the document, at twice its size unless --dpi is given
+    Png,
+    /// A standalone web page embedding the SVG
+    Html,
+}
+
+impl OutputFormat {
+    /// File extension of the format.
+    pub fn extension(self) -> &'static str {
+        match self {
+            OutputFormat::Svg => "svg",
+            OutputFormat::Svgz => "svgz",
+            OutputFormat::Png => "png",
+            OutputFormat::Html => "html",
+        }
+    }
+
+    /// Picks the format from a file's extension; anything unrecognized is
+    /// written as SVG.
+    pub fn from_path(path: &Path) -> OutputFormat {
+        let extension = path
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .map(str::to_ascii_lowercase);
+
+        match extension.as_deref() {
+            Some("svgz") => OutputFormat::Svgz,
+            Some("png") => OutputFormat::Png,
+            Some("html" | "htm") => OutputFormat::Html,
+            _ => OutputFormat::Svg,
+        }
+    }
+
+    /// Encodes a rendered SVG document in this format.
+    ///
+    /// # Arguments
+    ///
+    /// * `svg` - The rendered document
+    /// * `page` - What HTML output shows around the document
+    /// * `png_dpi` - Resolution of PNG output; 96 DPI is one pixel per SVG
+    ///   unit
+    ///
+    /// # Returns
+    ///
+    /// The file contents, or an error if the SVG cannot be rasterized or
+    /// compressed
+    pub fn encode(self, svg: &str, page: &HtmlPage, png_dpi: f32) -> Result<Vec<u8>> {
+        match self {
+            OutputFormat::Svg => Ok(svg.as_bytes().to_vec()),
+            OutputFormat::Svgz => gzip(svg.as_bytes()),
+            OutputFormat::Png => raster::svg_to_png(svg, png_dpi / SVG_DPI)
+                .map(|png| raster::set_png_dpi(png, png_dpi)),
+            OutputFormat::Html => Ok(html_page(svg, page).into_bytes()),
+        }
+    }
+}
+
+/// Compresses data with gzip at the best compression level.
+fn gzip(data: &[u8]) -> Result<Vec<u8>> {
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::best());
+    encoder
+        .write_all(data)
+        .context("Failed to compress the SVG")?;
+    encoder.finish().context("Failed to compress the SVG")
+}
+
+/// Escapes text for use in HTML content and attribute values.
+pub(crate) fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// Encodes an SVG document as a `data:` URI.
+pub(crate) fn svg_data_uri(svg: &str) -> String {
+    let mut uri = String::from("data:image/svg+xml;charset=utf-8,");
+    for byte in svg.bytes() {
+        match byte {
+            b'A'..=b'Z'
+            | b'a'..=b'z'
+            | b'0'..=b'9'
+            | b' '
+            | b'-'
+            | b'_'
+            | b'.'
+            | b'='
+            | b':'
+            | b'/'
+            | b';'
+            | b','
+            | b'('
+            | b')' => uri.push(byte as char),
+            _ => uri.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    uri
+}
+
+/// Script showing a layer's preview next to the hovered layer key.
+const PREVIEW_SCRIPT: &str = r#"
+  const tip = document.createElement("div");
+  tip.id = "layer-preview";
+  document.body.appendChild(tip);
+  for (const key of document.querySelectorAll("[data-layer]")) {
+    const layer = key.getAttribute("data-layer");
+    if (!(layer in previews)) continue;
+    key.addEventListener("mouseenter", () => {
+      tip.innerHTML = `<div>Hold: Layer ${layer}</div>`;
+      const image = document.createElement("img");
+      image.src = previews[layer].src;
+      image.alt = previews[layer].alt;
+      tip.appendChild(image);
+      tip.style.display = "block";
+    });
+    key.addEventListener("mousemove", (e) => {
+      tip.style.left = `${e.clientX + 16}px`;
+      tip.style.top = `${e.clientY + 16}px`;
+    });
+    key.addEventListener("mouseleave", () => { tip.style.display = "none"; });
+  }
+"#;
+
+/// Wraps an SVG document in a minimal standalone HTML page, with hover
+/// previews of the page's layers and a navigation bar linking to its
+/// sections. A non-empty alt text labels the document for screen readers.
+fn html_page(svg: &str, page: &HtmlPage) -> String {
+    let HtmlPage {
+        title,
+        alt_text,
+        layer_previews,
+        layer_links,
+        background,
+    } = *page;
+    let title = escape_html(title);
+    // The navigation bar follows the document onto dark backgrounds
+    let (link, rule) = if theme::is_dark(background) {
+        ("#7cb0d9", "#566573")
+    } else {
+        ("#3f6f96", "#bdc3c7")
+    };
+    let svg = match svg.split_once("<svg") {
+        Some((before, after)) if !alt_text.is_empty() => format!(
+            "{}<svg role=\"img\" aria-label=\"{}\"{}",
+            before,
+            escape_html(alt_text),
+            after
+        ),
+        _ => svg.to_string(),
+    };
+
+    let nav = if layer_links.is_empty() {
+        String::new()
+    } else {
+        let links: Vec<String> = layer_links
+            .iter()
+            .map(|(anchor, label)| {
+                format!(
+                    "<a href=\"#{}\">{}</a>",
+                    escape_html(anchor),
+                    escape_html(label)
+                )
+            })
+            .collect();
+        format!("<nav>{}</nav>\n", links.join(" "))
+    };
+
+    let script = if layer_previews.is_empty() {
+        String::new()
+    } else {
+        let previews: serde_json::Map<String, serde_json::Value> = layer_previews
+            .iter()
+            .map(|(layer, preview)| {
+                (
+                    layer.to_string(),
+                    serde_json::json!({ "src": svg_data_uri(&preview.svg), "alt": preview.alt }),
+                )
+            })
+            .collect();
+        format!(
+            "<script>\n  const previews = {};{}</script>\n",
+            serde_json::Value::Object(previews),
+            PREVIEW_SCRIPT
+        )
+    };
+
+    format!(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>{}</title>\n\
+         <style>body {{ margin: 0; background: {background}; }} svg {{ max-width: 100%; height: auto; }} \
+         #layer-preview {{ display: none; position: fixed; pointer-events: none; padding: 6px; \
+         background: #ffffff; border: 1px solid #bdc3c7; border-radius: 6px; \
+         font: 600 12px sans-serif; color: #2c3e50; box-shadow: 0 4px 12px rgba(0,0,0,0.2); }} \
+         html {{ scroll-padding-top: 48px; }} \
+         nav {{ position: sticky; top: 0; padding: 8px 20px; background: {background}; \
+         border-bottom: 1px solid {rule}; font: 600 13px sans-serif; }} \
+         nav a {{ color: {link}; margin-right: 12px; text-decoration: none; }} \
+         svg :target {{ fill: #c0392b; }}</style>\n\
+         </head>\n<body>\n{}{}\n{}</body>\n</html>\n",
+        title, nav, svg, script
+    )
+}
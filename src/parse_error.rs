@@ -0,0 +1,192 @@
+//! Errors in keymap sources that point at the offending line, with the line
+//! quoted and the column marked.
+use crate::{Geometry, Layer};
+use std::fmt;
+
+/// What went wrong in a keymap source.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ParseErrorKind {
+    /// A `(` is never closed, or a `)` closes the LAYOUT block early
+    UnbalancedParens,
+    /// A LAYOUT block has no closing `)` before the next layer or the end of
+    /// the keymaps array
+    UnterminatedLayout,
+    /// A layer has more or fewer keys than the board
+    KeyCount,
+}
+
+/// Where a layer is written in the keymap source.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SourceLines {
+    /// Line of the LAYOUT macro, 1-based
+    pub layout: usize,
+    /// Line of each row of keys, 1-based
+    pub rows: Vec<usize>,
+}
+
+/// An error at a line and column of a keymap source.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseError {
+    pub kind: ParseErrorKind,
+    /// Line of the error, 1-based
+    pub line: usize,
+    /// Column of the error in characters, 1-based
+    pub column: usize,
+    pub message: String,
+    /// The source line, quoted below the message
+    pub snippet: String,
+}
+
+impl ParseError {
+    /// Creates an error, quoting its line from the source.
+    ///
+    /// # Arguments
+    ///
+    /// * `kind` - What went wrong
+    /// * `content` - The whole source
+    /// * `line` - Line of the error, 1-based
+    /// * `column` - Column of the error in characters, 1-based
+    /// * `message` - Description of the error
+    pub fn new(
+        kind: ParseErrorKind,
+        content: &str,
+        line: usize,
+        column: usize,
+        message: impl Into<String>,
+    ) -> Self {
+        let snippet = content
+            .lines()
+            .nth(line.saturating_sub(1))
+            .unwrap_or_default()
+            .trim_end()
+            .to_string();
+        ParseError {
+            kind,
+            line,
+            column,
+            message: message.into(),
+            snippet,
+        }
+    }
+
+    /// Creates an error at a byte offset into `line_text`, line `line` of
+    /// `content`.
+    pub(crate) fn at_byte(
+        kind: ParseErrorKind,
+        content: &str,
+        line: usize,
+        line_text: &str,
+        byte: usize,
+        message: impl Into<String>,
+    ) -> Self {
+        let column = line_text[..byte].chars().count() + 1;
+        ParseError::new(kind, content, line, column, message)
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let gutter = self.line.to_string().len();
+        let marker = self
+            .snippet
+            .chars()
+            .take(self.column.saturating_sub(1))
+            .map(|c| if c == '\t' { '\t' } else { ' ' })
+            .collect::<String>();
+        write!(
+            f,
+            "line {}, column {}: {}\n {} | {}\n {} | {}^",
+            self.line,
+            self.column,
+            self.message,
+            self.line,
+            self.snippet,
+            " ".repeat(gutter),
+            marker
+        )
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+/// Describes how a layer's key count differs from a board's, naming the
+/// first row whose length differs when the layer has the board's rows.
+///
+/// # Arguments
+///
+/// * `layer` - The layer to check
+/// * `geometry` - The board the layer is drawn on
+///
+/// # Returns
+///
+/// The description, or `None` if the count matches
+pub fn key_count_message(layer: &Layer, geometry: &Geometry) -> Option<String> {
+    let found: usize = layer.keys.iter().map(Vec::len).sum();
+    let expected = geometry.key_count();
+    if found == expected {
+        return None;
+    }
+
+    let message = format!(
+        "layer {} has {} keys but {} has {}",
+        layer.index, found, geometry.name, expected
+    );
+    Some(match geometry.uneven_row(&layer.keys) {
+        Some(row) => format!(
+            "{}; row {} has {} keys instead of {}",
+            message,
+            row + 1,
+            layer.keys[row].len(),
+            geometry.rows[row].len()
+        ),
+        None => message,
+    })
+}
+
+/// Checks a layer's key count against a board, pointing at the first row
+/// whose length differs from the board's row.
+///
+/// # Arguments
+///
+/// * `layer` - A layer parsed from `content`
+/// * `content` - The keymap source
+/// * `geometry` - The board the layer is drawn on
+///
+/// # Returns
+///
+/// The error, or `None` if the count matches or the layer wasn't parsed from
+/// a source with line numbers
+pub fn key_count_error(layer: &Layer, content: &str, geometry: &Geometry) -> Option<ParseError> {
+    let source = layer.source.as_ref()?;
+    let message = key_count_message(layer, geometry)?;
+    let line = geometry
+        .uneven_row(&layer.keys)
+        .and_then(|row| source.rows.get(row).copied());
+    let error = match line {
+        Some(line) => {
+            let text = content.lines().nth(line - 1).unwrap_or_default();
+            let start = text.len() - text.trim_start().len();
+            ParseError::at_byte(
+                ParseErrorKind::KeyCount,
+                content,
+                line,
+                text,
+                start,
+                message,
+            )
+        }
+        None => {
+            let text = content.lines().nth(source.layout - 1).unwrap_or_default();
+            let start = text.find("LAYOUT").unwrap_or(0);
+            ParseError::at_byte(
+                ParseErrorKind::KeyCount,
+                content,
+                source.layout,
+                text,
+                start,
+                message,
+            )
+        }
+    };
+    Some(error)
+}
@@ -0,0 +1,242 @@
+//! The `publish` pipeline of a keymap repository: render the keymap, link the
+//! images from a README, and optionally commit them, configured by a
+//! `keyball44-viz.toml` project file.
+use crate::output::OutputFormat;
+use anyhow::{Result, bail};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+/// File name of the project config looked up in the current directory.
+pub const PROJECT_FILE: &str = "keyball44-viz.toml";
+
+/// Marks the start of the README section `publish` rewrites.
+pub const README_START: &str = "<!-- keyball44-viz:start -->";
+/// Marks the end of the README section `publish` rewrites.
+pub const README_END: &str = "<!-- keyball44-viz:end -->";
+
+/// A project config, e.g.
+///
+/// ```toml
+/// keymap = "keymaps/mine/keymap.c"
+///
+/// [validate]
+/// strict = true
+///
+/// [render]
+/// output_dir = "images"
+/// args = ["--readable-legends"]
+///
+/// [readme]
+/// file = "README.md"
+///
+/// [commit]
+/// enabled = true
+/// ```
+///
+/// Paths are relative to the config file.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct PublishConfig {
+    /// The keymap to publish
+    pub keymap: PathBuf,
+    #[serde(default)]
+    pub validate: ValidateStage,
+    #[serde(default)]
+    pub render: RenderStage,
+    /// Where the images are linked from; the README stage is skipped when
+    /// left out
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub readme: Option<ReadmeStage>,
+    #[serde(default)]
+    pub commit: CommitStage,
+}
+
+/// How strictly the keymap is checked before anything is published.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(default, deny_unknown_fields)]
+pub struct ValidateStage {
+    /// Fail when a layer doesn't fit the board, as with `--strict`
+    pub strict: bool,
+    /// Fail on any diagnostic, as with `--deny warnings`
+    pub deny_warnings: bool,
+}
+
+/// The files rendered.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(default, deny_unknown_fields)]
+pub struct RenderStage {
+    /// Directory the files are written to
+    pub output_dir: PathBuf,
+    /// File name of the images without extension [default: the keymap's
+    /// file stem]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    /// Formats written, one file each
+    pub formats: Vec<OutputFormat>,
+    /// Further render options, as given on the command line (e.g.
+    /// `["--dark", "--board", "keyball44"]`)
+    pub args: Vec<String>,
+}
+
+impl Default for RenderStage {
+    fn default() -> Self {
+        RenderStage {
+            output_dir: PathBuf::from("."),
+            name: None,
+            formats: vec![OutputFormat::Svg, OutputFormat::Png, OutputFormat::Html],
+            args: Vec::new(),
+        }
+    }
+}
+
+/// The README linking the rendered images.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(default, deny_unknown_fields)]
+pub struct ReadmeStage {
+    pub file: PathBuf,
+    /// Alt text of the image
+    pub alt: String,
+}
+
+impl Default for ReadmeStage {
+    fn default() -> Self {
+        ReadmeStage {
+            file: PathBuf::from("README.md"),
+            alt: "Keymap".to_string(),
+        }
+    }
+}
+
+/// Committing the rendered files and README.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(default, deny_unknown_fields)]
+pub struct CommitStage {
+    /// Commit after publishing, as with `publish --commit`
+    pub enabled: bool,
+    pub message: String,
+}
+
+impl Default for CommitStage {
+    fn default() -> Self {
+        CommitStage {
+            enabled: false,
+            message: "Update keymap images".to_string(),
+        }
+    }
+}
+
+impl PublishConfig {
+    /// Checks that the config renders something and names its files.
+    pub fn validate(&self) -> Result<()> {
+        if self.keymap.as_os_str().is_empty() {
+            bail!("keymap: must not be empty");
+        }
+        if self.render.formats.is_empty() {
+            bail!("render.formats: list at least one format");
+        }
+        if self
+            .render
+            .name
+            .as_ref()
+            .is_some_and(|name| name.trim().is_empty())
+        {
+            bail!("render.name: must not be empty");
+        }
+        if self.commit.message.trim().is_empty() {
+            bail!("commit.message: must not be empty");
+        }
+        Ok(())
+    }
+
+    /// The files the render stage writes, relative to the config file.
+    pub fn outputs(&self) -> Vec<PathBuf> {
+        let name = self.render.name.clone().unwrap_or_else(|| {
+            self.keymap
+                .file_stem()
+                .map_or("keymap".to_string(), |stem| {
+                    stem.to_string_lossy().into_owned()
+                })
+        });
+        self.render
+            .formats
+            .iter()
+            .map(|format| {
+                self.render
+                    .output_dir
+                    .join(format!("{}.{}", name, format.extension()))
+            })
+            .collect()
+    }
+
+    /// The Markdown linking the rendered files from the README: the first
+    /// image format as an image, and an HTML page as a link.
+    ///
+    /// # Arguments
+    ///
+    /// * `readme` - The README stage
+    ///
+    /// # Returns
+    ///
+    /// The snippet between the section markers, without them
+    pub fn readme_snippet(&self, readme: &ReadmeStage) -> String {
+        let base = readme.file.parent().unwrap_or(Path::new(""));
+        let outputs: Vec<(OutputFormat, PathBuf)> = self
+            .render
+            .formats
+            .iter()
+            .copied()
+            .zip(self.outputs())
+            .collect();
+        let link = |path: &Path| relative_to(path, base).replace('\\', "/");
+        let mut lines = Vec::new();
+        if let Some((_, image)) = outputs
+            .iter()
+            .find(|(format, _)| matches!(format, OutputFormat::Svg | OutputFormat::Png))
+        {
+            lines.push(format!("![{}]({})", readme.alt, link(image)));
+        }
+        if let Some((_, page)) = outputs
+            .iter()
+            .find(|(format, _)| *format == OutputFormat::Html)
+        {
+            lines.push(format!("[Interactive version]({})", link(page)));
+        }
+        lines.join("\n\n")
+    }
+}
+
+/// `path` relative to the directory `base` when it lies inside it; both are
+/// relative to the same directory.
+fn relative_to(path: &Path, base: &Path) -> String {
+    let path = path.strip_prefix(".").unwrap_or(path);
+    path.strip_prefix(base)
+        .unwrap_or(path)
+        .to_string_lossy()
+        .into_owned()
+}
+
+/// Puts a snippet between the README's section markers, or appends the
+/// section if the README has none.
+///
+/// # Arguments
+///
+/// * `content` - The README, empty if it doesn't exist yet
+/// * `snippet` - The section's new contents
+///
+/// # Returns
+///
+/// The updated README
+pub fn update_readme(content: &str, snippet: &str) -> String {
+    let section = format!("{}\n{}\n{}", README_START, snippet, README_END);
+    if let Some(start) = content.find(README_START)
+        && let Some(end) = content[start..].find(README_END)
+    {
+        let end = start + end + README_END.len();
+        return format!("{}{}{}", &content[..start], section, &content[end..]);
+    }
+    if content.trim().is_empty() {
+        return format!("{}\n", section);
+    }
+    format!("{}\n\n{}\n", content.trim_end(), section)
+}
@@ -0,0 +1,97 @@
+//! QR codes linking printed keymaps back to their source.
+use anyhow::{Context, Result};
+use qrcode::{Color, QrCode};
+use svg::node::element::{Anchor, Group, Path, Rectangle, Text};
+
+/// Modules of blank margin around the code, as the QR standard requires.
+const QUIET_ZONE: usize = 4;
+
+/// Width and height of a drawn code, quiet zone included.
+pub const QR_SIZE: f32 = 110.0;
+
+/// A QR code encoding a URL, drawn in the document footer.
+#[derive(Debug, Clone, PartialEq)]
+pub struct QrFooter {
+    /// The encoded URL, also written next to the code
+    pub url: String,
+    /// Number of modules per side
+    width: usize,
+    /// Dark modules, row by row
+    modules: Vec<bool>,
+}
+
+impl QrFooter {
+    /// Encodes a URL.
+    ///
+    /// # Arguments
+    ///
+    /// * `url` - The address the code links to
+    ///
+    /// # Returns
+    ///
+    /// The code, or an error if the URL is too long for a QR code
+    pub fn new(url: &str) -> Result<Self> {
+        let code = QrCode::new(url.as_bytes())
+            .map_err(|err| anyhow::anyhow!("{}", err))
+            .context(format!("Cannot encode {:?} as a QR code", url))?;
+        Ok(QrFooter {
+            url: url.to_string(),
+            width: code.width(),
+            modules: code
+                .to_colors()
+                .into_iter()
+                .map(|color| color == Color::Dark)
+                .collect(),
+        })
+    }
+
+    /// Draws the code on a white square with the URL beside it, linking to
+    /// the URL in viewers that follow links.
+    ///
+    /// # Arguments
+    ///
+    /// * `x` - Left edge of the code
+    /// * `y` - Top edge of the code
+    pub fn draw(&self, x: f32, y: f32) -> Anchor {
+        let module = QR_SIZE / (self.width + QUIET_ZONE * 2) as f32;
+        let origin = |index: usize| (index + QUIET_ZONE) as f32 * module;
+        // One square subpath per dark module keeps the document small
+        let data: String = self
+            .modules
+            .iter()
+            .enumerate()
+            .filter(|(_, dark)| **dark)
+            .map(|(index, _)| {
+                format!(
+                    "M{:.2} {:.2}h{:.2}v{:.2}h-{:.2}z",
+                    x + origin(index % self.width),
+                    y + origin(index / self.width),
+                    module,
+                    module,
+                    module
+                )
+            })
+            .collect();
+
+        Anchor::new().set("href", self.url.as_str()).add(
+            Group::new()
+                .set("class", "qr-code")
+                .add(
+                    Rectangle::new()
+                        .set("x", x)
+                        .set("y", y)
+                        .set("width", QR_SIZE)
+                        .set("height", QR_SIZE)
+                        .set("fill", "#ffffff"),
+                )
+                .add(Path::new().set("d", data).set("fill", "#000000"))
+                .add(
+                    Text::new("")
+                        .set("class", "metadata-text")
+                        .set("x", x + QR_SIZE + 10.0)
+                        .set("y", y + QR_SIZE / 2.0 + 4.0)
+                        .add(svg::node::Text::new(self.url.as_str())),
+                ),
+        )
+    }
+}
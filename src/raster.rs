@@ -0,0 +1,188 @@
+//! Rasterization of generated SVG documents to PNG.
+use anyhow::{Context, Result, anyhow};
+use resvg::{tiny_skia, usvg};
+
+/// Font families tried, in order, for the monospace key legends.
+const MONOSPACE_FAMILIES: [&str; 7] = [
+    "SF Mono",
+    "Monaco",
+    "Inconsolata",
+    "Fira Code",
+    "DejaVu Sans Mono",
+    "Liberation Mono",
+    "Noto Sans Mono",
+];
+
+/// Font families tried, in order, for titles and other sans-serif text.
+const SANS_SERIF_FAMILIES: [&str; 5] = [
+    "Segoe UI",
+    "Helvetica",
+    "DejaVu Sans",
+    "Liberation Sans",
+    "Noto Sans",
+];
+
+/// Returns the first of `families` that has an installed face.
+fn first_installed(fontdb: &usvg::fontdb::Database, families: &[&str]) -> Option<String> {
+    families
+        .iter()
+        .find(|family| {
+            fontdb
+                .faces()
+                .any(|face| face.families.iter().any(|(name, _)| name == *family))
+        })
+        .map(|family| family.to_string())
+}
+
+/// Builds parse options with system fonts loaded and the generic families
+/// mapped to fonts that are actually installed.
+fn options() -> usvg::Options<'static> {
+    let mut options = usvg::Options::default();
+    let fontdb = options.fontdb_mut();
+    fontdb.load_system_fonts();
+
+    if let Some(family) = first_installed(fontdb, &MONOSPACE_FAMILIES) {
+        fontdb.set_monospace_family(family);
+    }
+    if let Some(family) = first_installed(fontdb, &SANS_SERIF_FAMILIES) {
+        fontdb.set_sans_serif_family(family);
+    }
+
+    options
+}
+
+/// Renders an SVG document to PNG.
+///
+/// # Arguments
+///
+/// * `svg` - The SVG document
+/// * `scale` - Factor applied to the document's size (1.0 keeps one pixel per SVG unit)
+///
+/// # Returns
+///
+/// The encoded PNG image, or an error if the SVG cannot be parsed or the
+/// scaled size is empty
+pub fn svg_to_png(svg: &str, scale: f32) -> Result<Vec<u8>> {
+    let tree = usvg::Tree::from_str(svg, &options()).context("Failed to parse generated SVG")?;
+    let size = tree
+        .size()
+        .to_int_size()
+        .scale_by(scale)
+        .ok_or(anyhow!("Invalid raster scale {}", scale))?;
+    let mut pixmap = tiny_skia::Pixmap::new(size.width(), size.height()).ok_or(anyhow!(
+        "Cannot allocate a {}x{} image",
+        size.width(),
+        size.height()
+    ))?;
+
+    resvg::render(
+        &tree,
+        tiny_skia::Transform::from_scale(scale, scale),
+        &mut pixmap.as_mut(),
+    );
+
+    pixmap.encode_png().context("Failed to encode PNG")
+}
+
+/// Records the resolution of a PNG image in a `pHYs` chunk, so viewers and
+/// printers size it physically instead of assuming 96 DPI.
+///
+/// # Arguments
+///
+/// * `png` - An encoded PNG image
+/// * `dpi` - Pixels per inch
+///
+/// # Returns
+///
+/// The image with the chunk inserted after its header, or unchanged if it
+/// is not a PNG
+pub fn set_png_dpi(png: Vec<u8>, dpi: f32) -> Vec<u8> {
+    // Signature (8 bytes) and IHDR chunk (4 length + 4 type + 13 data + 4 CRC)
+    const HEADER_END: usize = 33;
+    if png.len() < HEADER_END || &png[12..16] != b"IHDR" {
+        return png;
+    }
+
+    let pixels_per_meter = (dpi / 0.0254).round() as u32;
+    let mut chunk = b"pHYs".to_vec();
+    chunk.extend(pixels_per_meter.to_be_bytes());
+    chunk.extend(pixels_per_meter.to_be_bytes());
+    // Unit: meter
+    chunk.push(1);
+
+    let mut out = Vec::with_capacity(png.len() + 21);
+    out.extend(&png[..HEADER_END]);
+    out.extend(9u32.to_be_bytes());
+    out.extend(&chunk);
+    out.extend(crc32(&chunk).to_be_bytes());
+    out.extend(&png[HEADER_END..]);
+    out
+}
+
+/// The CRC-32 checksum PNG chunks end with.
+fn crc32(bytes: &[u8]) -> u32 {
+    let mut crc = 0xFFFF_FFFFu32;
+    for &byte in bytes {
+        crc ^= u32::from(byte);
+        for _ in 0..8 {
+            crc = if crc & 1 == 1 {
+                (crc >> 1) ^ 0xEDB8_8320
+            } else {
+                crc >> 1
+            };
+        }
+    }
+    !crc
+}
+
+/// Measures text with the installed fonts, as the rasterizer would draw it.
+pub struct TextMeasurer {
+    options: usvg::Options<'static>,
+}
+
+impl std::fmt::Debug for TextMeasurer {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("TextMeasurer(..)")
+    }
+}
+
+impl Default for TextMeasurer {
+    fn default() -> Self {
+        TextMeasurer::new()
+    }
+}
+
+impl TextMeasurer {
+    /// Loads the system fonts; this takes a moment, so reuse the measurer.
+    pub fn new() -> Self {
+        TextMeasurer { options: options() }
+    }
+
+    /// Width of a line of monospace text.
+    ///
+    /// # Arguments
+    ///
+    /// * `text` - The text, in logical order; right-to-left runs are shaped
+    ///   and reordered like a browser would
+    /// * `font_size` - Font size in pixels
+    ///
+    /// # Returns
+    ///
+    /// The width of the drawn glyphs, or `None` if no installed font could
+    /// draw the text
+    pub fn width(&self, text: &str, font_size: f32) -> Option<f32> {
+        let escaped = text
+            .replace('&', "&amp;")
+            .replace('<', "&lt;")
+            .replace('>', "&gt;");
+        let svg = format!(
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"2000\" height=\"100\">\
+             <text x=\"0\" y=\"50\" font-family=\"monospace\" font-size=\"{}\" \
+             font-weight=\"500\">{}</text></svg>",
+            font_size, escaped
+        );
+        let tree = usvg::Tree::from_str(&svg, &self.options).ok()?;
+        let width = tree.root().bounding_box().width();
+        (tree.root().has_children() && width > 0.0).then_some(width)
+    }
+}
@@ -0,0 +1,65 @@
+//! Legend redaction for sharing layouts that contain sensitive macros.
+use crate::Layer;
+use anyhow::{Context, Result};
+use regex::Regex;
+
+/// Default legend drawn in place of redacted keys.
+pub const DEFAULT_REDACTION: &str = "•••";
+
+/// Replaces the legends of keys matching any of the given patterns.
+///
+/// Patterns are keycode globs where `*` matches any run of characters (e.g.
+/// `MY_PASS*` or `SECRET_*`). A key is redacted when the whole key or any
+/// identifier nested inside it matches, so `LT(1, MY_PASS)` is hidden as well.
+///
+/// # Arguments
+///
+/// * `layers` - The layers whose keys should be redacted in place
+/// * `patterns` - Keycode glob patterns selecting the keys to hide
+/// * `replacement` - The legend drawn in place of each redacted key
+///
+/// # Returns
+///
+/// * `Result<usize>` - The number of keys redacted, or an error if a pattern is invalid
+pub fn redact_layers(
+    layers: &mut [Layer],
+    patterns: &[String],
+    replacement: &str,
+) -> Result<usize> {
+    let matchers = patterns
+        .iter()
+        .map(|pattern| glob_to_regex(pattern))
+        .collect::<Result<Vec<_>>>()?;
+    let identifier = Regex::new(r"[A-Za-z_][A-Za-z0-9_]*").unwrap();
+    let mut redacted = 0;
+
+    for key in layers
+        .iter_mut()
+        .flat_map(|layer| layer.keys.iter_mut())
+        .flat_map(|row| row.iter_mut())
+    {
+        let matches = matchers.iter().any(|matcher| {
+            matcher.is_match(key)
+                || identifier
+                    .find_iter(key)
+                    .any(|token| matcher.is_match(token.as_str()))
+        });
+
+        if matches {
+            *key = replacement.to_string();
+            redacted += 1;
+        }
+    }
+
+    Ok(redacted)
+}
+
+fn glob_to_regex(pattern: &str) -> Result<Regex> {
+    let escaped = pattern
+        .split('*')
+        .map(regex::escape)
+        .collect::<Vec<_>>()
+        .join(".*");
+
+    Regex::new(&format!("^{}$", escaped)).context(format!("Invalid redaction pattern: {}", pattern))
+}
@@ -0,0 +1,238 @@
+//! Parsing of QMK `rules.mk` build options.
+use crate::{Layer, is_empty_key, parse_keys_with_parens};
+use std::collections::BTreeMap;
+use std::path::Path;
+
+/// Feature flags parsed from a `rules.mk` file.
+#[derive(Debug, Clone, Default)]
+pub struct Rules {
+    /// Each `*_ENABLE` option keyed by feature name (e.g. `MOUSEKEY`) and whether it is on
+    pub features: BTreeMap<String, bool>,
+}
+
+impl Rules {
+    /// Returns the names of all enabled features in alphabetical order.
+    pub fn enabled_features(&self) -> Vec<&str> {
+        self.features
+            .iter()
+            .filter(|(_, enabled)| **enabled)
+            .map(|(name, _)| name.as_str())
+            .collect()
+    }
+
+    /// Whether the named feature (without the `_ENABLE` suffix) is on, or
+    /// `None` if rules.mk doesn't say: QMK enables some features by default,
+    /// and the keyboard's own rules.mk may enable others.
+    pub fn is_enabled(&self, feature: &str) -> Option<bool> {
+        self.features.get(feature).copied()
+    }
+}
+
+/// Parses the `*_ENABLE` options from `rules.mk` content.
+///
+/// Values of `yes` and `true` enable a feature and `no` and `false` disable
+/// it; other values, such as make variables, leave it unset. Later assignments override earlier ones, as they do in make; `?=` only
+/// applies when the feature hasn't been assigned yet.
+///
+/// # Arguments
+///
+/// * `content` - A string slice containing the rules.mk source
+///
+/// # Returns
+///
+/// The parsed `Rules`
+pub fn parse_rules(content: &str) -> Rules {
+    let mut rules = Rules::default();
+
+    for line in content.lines() {
+        let line = line.split('#').next().unwrap_or("").trim();
+
+        let Some((name, value, conditional)) = ["?=", ":=", "+=", "="]
+            .iter()
+            .find_map(|op| line.split_once(op).map(|(n, v)| (n, v, *op == "?=")))
+        else {
+            continue;
+        };
+
+        let Some(feature) = name.trim().strip_suffix("_ENABLE") else {
+            continue;
+        };
+
+        if conditional && rules.features.contains_key(feature) {
+            continue;
+        }
+
+        let enabled = match value.trim().to_ascii_lowercase().as_str() {
+            "yes" | "true" => true,
+            "no" | "false" => false,
+            _ => continue,
+        };
+        rules.features.insert(feature.to_string(), enabled);
+    }
+
+    rules
+}
+
+/// Keycode prefixes that only work when a feature is enabled.
+const FEATURE_KEYCODES: [(&str, &[&str]); 7] = [
+    ("MOUSEKEY", &["KC_MS_", "KC_BTN", "KC_WH_", "KC_ACL", "MS_"]),
+    ("TAP_DANCE", &["TD("]),
+    ("CAPS_WORD", &["CW_TOGG", "QK_CAPS_WORD"]),
+    ("KEY_LOCK", &["QK_LOCK", "KC_LOCK"]),
+    ("LEADER", &["QK_LEAD", "KC_LEAD"]),
+    ("AUTO_SHIFT", &["AS_"]),
+    ("RGBLIGHT", &["RGB_", "UG_"]),
+];
+
+/// Source snippets that only compile when a feature is enabled.
+const FEATURE_SOURCE: [(&str, &str); 2] =
+    [("COMBO", "combo_t"), ("TAP_DANCE", "tap_dance_action_t")];
+
+/// A keycode and every keycode nested in its arguments, outermost first, e.g.
+/// `LT(1, KC_BTN1)`, `1`, and `KC_BTN1`.
+fn nested_keycodes(key: &str) -> Vec<String> {
+    let key = key.trim();
+    let mut keys = vec![key.to_string()];
+    if let Some(args) = key
+        .strip_suffix(')')
+        .and_then(|rest| rest.split_once('('))
+        .map(|(_, args)| args)
+    {
+        for arg in parse_keys_with_parens(args) {
+            keys.extend(nested_keycodes(&arg));
+        }
+    }
+    keys
+}
+
+/// Finds keymap features that are used but disabled in `rules`. Features
+/// rules.mk doesn't set may be on by default or in the keyboard's rules.mk,
+/// so only explicit `no` and `false` values are reported.
+///
+/// Keycodes nested in the arguments of others, such as the tap of
+/// `LT(1, KC_BTN1)`, count as used too.
+///
+/// # Arguments
+///
+/// * `rules` - The parsed rules.mk options
+/// * `layers` - The parsed keymap layers
+/// * `source` - The keymap.c source, if available, for non-keycode features such as combos
+///
+/// # Returns
+///
+/// A human-readable warning for every disabled feature the keymap relies on
+pub fn feature_warnings(rules: &Rules, layers: &[Layer], source: Option<&str>) -> Vec<String> {
+    let mut warnings = Vec::new();
+
+    for (feature, prefixes) in FEATURE_KEYCODES {
+        if rules.is_enabled(feature) != Some(false)
+            || (feature == "RGBLIGHT" && rules.is_enabled("RGB_MATRIX") == Some(true))
+        {
+            continue;
+        }
+
+        let used = layers
+            .iter()
+            .flat_map(|layer| {
+                layer
+                    .keys
+                    .iter()
+                    .flatten()
+                    .map(move |key| (layer.index, key))
+            })
+            .find_map(|(index, key)| {
+                nested_keycodes(key)
+                    .into_iter()
+                    .find(|code| {
+                        !is_empty_key(code) && prefixes.iter().any(|p| code.starts_with(p))
+                    })
+                    .map(|code| (index, key.trim(), code))
+            });
+
+        if let Some((index, key, code)) = used {
+            let within = if code == key {
+                String::new()
+            } else {
+                format!(" in {}", key)
+            };
+            warnings.push(format!(
+                "Layer {} uses {}{} but {}_ENABLE appears disabled",
+                index, code, within, feature
+            ));
+        }
+    }
+
+    if let Some(source) = source {
+        for (feature, snippet) in FEATURE_SOURCE {
+            if rules.is_enabled(feature) == Some(false) && source.contains(snippet) {
+                warnings.push(format!(
+                    "Keymap defines {} but {}_ENABLE appears disabled",
+                    snippet, feature
+                ));
+            }
+        }
+    }
+
+    warnings
+}
+
+/// A configurator that remaps keys on the board at runtime, storing the
+/// keymap in EEPROM where the source doesn't see it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RuntimeRemapping {
+    Via,
+    Vial,
+}
+
+impl RuntimeRemapping {
+    /// The configurator's name, e.g. `VIA`.
+    pub fn name(self) -> &'static str {
+        match self {
+            RuntimeRemapping::Via => "VIA",
+            RuntimeRemapping::Vial => "Vial",
+        }
+    }
+}
+
+/// Detects whether a build lets VIA or Vial remap keys at runtime.
+///
+/// Vial is recognized by `VIAL_ENABLE`, a `VIAL_KEYBOARD_UID` define, or a
+/// `vial.json` next to the keymap. VIA is recognized by `VIA_ENABLE` or by a
+/// keymap in a `via` directory, as QMK names its VIA keymaps.
+///
+/// # Arguments
+///
+/// * `rules` - The build's rules.mk options
+/// * `sources` - The keymap and config.h sources, for defines
+/// * `keymap_file` - Path of the keymap, whose directory is checked
+///
+/// # Returns
+///
+/// The configurator, Vial over VIA since Vial builds also enable VIA, or
+/// `None` if the keymap is compile-time only
+pub fn runtime_remapping(
+    rules: &Rules,
+    sources: &[&str],
+    keymap_file: &Path,
+) -> Option<RuntimeRemapping> {
+    let directory = keymap_file.parent();
+    let vial = rules.is_enabled("VIAL") == Some(true)
+        || sources.iter().any(|source| {
+            source
+                .lines()
+                .any(|line| line.trim_start().starts_with("#define VIAL_KEYBOARD_UID"))
+        })
+        || directory.is_some_and(|dir| dir.join("vial.json").is_file());
+    let via = rules.is_enabled("VIA") == Some(true)
+        || directory
+            .and_then(Path::file_name)
+            .is_some_and(|name| name.eq_ignore_ascii_case("via"));
+
+    if vial {
+        Some(RuntimeRemapping::Vial)
+    } else if via {
+        Some(RuntimeRemapping::Via)
+    } else {
+        None
+    }
+}
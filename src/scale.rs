@@ -0,0 +1,252 @@
+//! Color scales mapping values such as key effort to colors, shared by the
+//! overlays that color keys by a value, and the legend explaining them.
+use clap::ValueEnum;
+use svg::node::element::{Definitions, Group, LinearGradient, Rectangle, Stop, Text};
+
+/// Width of the gradient bar of a scale legend.
+const LEGEND_BAR_WIDTH: f32 = 240.0;
+
+/// Height of the gradient bar of a scale legend.
+const LEGEND_BAR_HEIGHT: f32 = 12.0;
+
+/// Evenly spaced colors of viridis, from low to high.
+const VIRIDIS: [(u8, u8, u8); 9] = [
+    (0x44, 0x01, 0x54),
+    (0x47, 0x2d, 0x7b),
+    (0x3b, 0x52, 0x8b),
+    (0x2c, 0x72, 0x8e),
+    (0x21, 0x91, 0x8c),
+    (0x28, 0xae, 0x80),
+    (0x5e, 0xc9, 0x62),
+    (0xad, 0xdc, 0x30),
+    (0xfd, 0xe7, 0x25),
+];
+
+/// Evenly spaced colors of magma, from low to high.
+const MAGMA: [(u8, u8, u8); 9] = [
+    (0x00, 0x00, 0x04),
+    (0x1c, 0x10, 0x44),
+    (0x4f, 0x12, 0x7b),
+    (0x81, 0x25, 0x81),
+    (0xb5, 0x36, 0x7a),
+    (0xe5, 0x50, 0x64),
+    (0xfb, 0x87, 0x61),
+    (0xfe, 0xc2, 0x87),
+    (0xfc, 0xfd, 0xbf),
+];
+
+/// A blue to red diverging scale that stays distinguishable with red-green
+/// color blindness, from low to high.
+const RED_GREEN_SAFE: [(u8, u8, u8); 7] = [
+    (0x21, 0x66, 0xac),
+    (0x67, 0xa9, 0xcf),
+    (0xd1, 0xe5, 0xf0),
+    (0xf7, 0xf7, 0xf7),
+    (0xfd, 0xdb, 0xc7),
+    (0xef, 0x8a, 0x62),
+    (0xb2, 0x18, 0x2b),
+];
+
+/// A sequence of colors that values are mapped onto.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, ValueEnum)]
+pub enum ColorScale {
+    /// Dark purple through green to yellow
+    #[default]
+    Viridis,
+    /// Black through purple and orange to pale yellow
+    Magma,
+    /// Blue through white to red, readable with red-green color blindness
+    RedGreenSafe,
+}
+
+impl ColorScale {
+    /// The scale's colors, evenly spaced from low to high.
+    fn stops(self) -> &'static [(u8, u8, u8)] {
+        match self {
+            ColorScale::Viridis => &VIRIDIS,
+            ColorScale::Magma => &MAGMA,
+            ColorScale::RedGreenSafe => &RED_GREEN_SAFE,
+        }
+    }
+
+    /// The scale's name as given on the command line.
+    pub fn name(self) -> &'static str {
+        match self {
+            ColorScale::Viridis => "viridis",
+            ColorScale::Magma => "magma",
+            ColorScale::RedGreenSafe => "red-green-safe",
+        }
+    }
+
+    /// Returns the color at a point of the scale.
+    ///
+    /// # Arguments
+    ///
+    /// * `position` - From 0.0 (low) to 1.0 (high); values outside are clamped
+    ///
+    /// # Returns
+    ///
+    /// The color as a `#rrggbb` hex string, interpolated between the two
+    /// nearest stops
+    pub fn color(self, position: f32) -> String {
+        let stops = self.stops();
+        let scaled = position.clamp(0.0, 1.0) * (stops.len() - 1) as f32;
+        let lower = (scaled.floor() as usize).min(stops.len() - 2);
+        let fraction = scaled - lower as f32;
+        let (from, to) = (stops[lower], stops[lower + 1]);
+        let mix = |a: u8, b: u8| (a as f32 + (b as f32 - a as f32) * fraction).round() as u8;
+        format!(
+            "#{:02x}{:02x}{:02x}",
+            mix(from.0, to.0),
+            mix(from.1, to.1),
+            mix(from.2, to.2)
+        )
+    }
+}
+
+/// Bounds that values are clamped to before being colored. Unset bounds
+/// follow the smallest and largest value shown.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct ScaleRange {
+    pub min: Option<f32>,
+    pub max: Option<f32>,
+}
+
+/// A color scale fitted to the values of one overlay, with the title its
+/// legend is drawn under.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ValueScale {
+    pub title: String,
+    pub scale: ColorScale,
+    pub min: f32,
+    pub max: f32,
+}
+
+impl ValueScale {
+    /// Fits a color scale to the values of an overlay.
+    ///
+    /// # Arguments
+    ///
+    /// * `title` - What the values are, shown above the legend
+    /// * `scale` - The colors to map values onto
+    /// * `range` - Bounds overriding the smallest and largest value
+    /// * `values` - The overlay's values
+    ///
+    /// # Returns
+    ///
+    /// The fitted scale; without values or bounds it spans 0.0 to 1.0
+    pub fn fit(
+        title: &str,
+        scale: ColorScale,
+        range: ScaleRange,
+        values: impl IntoIterator<Item = f32>,
+    ) -> Self {
+        let (low, high) = values
+            .into_iter()
+            .filter(|value| value.is_finite())
+            .fold(None, |bounds: Option<(f32, f32)>, value| {
+                Some(bounds.map_or((value, value), |(low, high)| {
+                    (low.min(value), high.max(value))
+                }))
+            })
+            .unwrap_or((0.0, 1.0));
+        ValueScale {
+            title: title.to_string(),
+            scale,
+            min: range.min.unwrap_or(low),
+            max: range.max.unwrap_or(high),
+        }
+    }
+
+    /// Returns the color of a value, clamped to the scale's bounds.
+    pub fn color(&self, value: f32) -> String {
+        let span = self.max - self.min;
+        let position = if span > 0.0 {
+            (value - self.min) / span
+        } else {
+            0.5
+        };
+        self.scale.color(position)
+    }
+
+    /// Draws the legend: the title, a bar running through the scale's colors,
+    /// and the bounds at its ends.
+    ///
+    /// # Arguments
+    ///
+    /// * `id` - Unique id of the bar's gradient within the document
+    /// * `x` - Left edge of the legend
+    /// * `y` - Baseline of the title
+    pub fn legend(&self, id: &str, x: f32, y: f32) -> Group {
+        let stops = self.scale.stops();
+        let gradient = stops.iter().enumerate().fold(
+            LinearGradient::new().set("id", id),
+            |gradient, (index, &(r, g, b))| {
+                gradient.add(
+                    Stop::new()
+                        .set("offset", format!("{}%", index * 100 / (stops.len() - 1)))
+                        .set("stop-color", format!("#{:02x}{:02x}{:02x}", r, g, b)),
+                )
+            },
+        );
+        let bar_y = y + 10.0;
+        let label = |value: f32, x: f32, anchor: &str| {
+            Text::new("")
+                .set("class", "scale-label")
+                .set("x", x)
+                .set("y", bar_y + LEGEND_BAR_HEIGHT + 12.0)
+                .set("text-anchor", anchor)
+                .add(svg::node::Text::new(format!("{:.1}", value)))
+        };
+
+        Group::new()
+            .set("class", "scale-legend")
+            .add(Definitions::new().add(gradient))
+            .add(
+                Text::new("")
+                    .set("class", "scale-title")
+                    .set("x", x)
+                    .set("y", y)
+                    .add(svg::node::Text::new(format!(
+                        "{} ({})",
+                        self.title,
+                        self.scale.name()
+                    ))),
+            )
+            .add(
+                Rectangle::new()
+                    .set("class", "scale-bar")
+                    .set("x", x)
+                    .set("y", bar_y)
+                    .set("width", LEGEND_BAR_WIDTH)
+                    .set("height", LEGEND_BAR_HEIGHT)
+                    .set("fill", format!("url(#{})", id)),
+            )
+            .add(label(self.min, x, "start"))
+            .add(label(self.max, x + LEGEND_BAR_WIDTH, "end"))
+    }
+}
+
+/// Picks a legible text color for a background color.
+///
+/// # Arguments
+///
+/// * `background` - A `#rrggbb` hex color
+///
+/// # Returns
+///
+/// A dark color on light backgrounds and white on dark ones
+pub fn text_color(background: &str) -> &'static str {
+    let channel = |start: usize| {
+        background
+            .get(start..start + 2)
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok())
+            .unwrap_or(0) as f32
+    };
+    let luminance = 0.299 * channel(1) + 0.587 * channel(3) + 0.114 * channel(5);
+    if luminance > 140.0 {
+        "#2c3e50"
+    } else {
+        "#ffffff"
+    }
+}
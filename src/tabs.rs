@@ -0,0 +1,118 @@
+//! A single-view HTML page showing one layer at a time, with tabs to switch
+//! between layers drawn in the same place instead of one tall document.
+use crate::output::escape_html;
+use crate::theme;
+
+/// One layer of a tabbed page.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LayerTab {
+    /// Label of the layer's tab
+    pub label: String,
+    /// The layer's own SVG document, e.g. from [`crate::combine_layers`]
+    /// with that layer's renderer
+    pub svg: String,
+    /// Description of the layer for screen readers; empty for none
+    pub alt: String,
+}
+
+/// Script switching panels when a tab is clicked, with the arrow keys on the
+/// tab bar, or with the digit keys anywhere on the page.
+const TAB_SCRIPT: &str = r#"
+  const tabs = [...document.querySelectorAll("[role=tab]")];
+  const panels = [...document.querySelectorAll("[role=tabpanel]")];
+  function select(index) {
+    tabs.forEach((tab, i) => {
+      const selected = i === index;
+      tab.setAttribute("aria-selected", selected);
+      tab.tabIndex = selected ? 0 : -1;
+      panels[i].classList.toggle("active", selected);
+    });
+    history.replaceState(null, "", `#${panels[index].id}`);
+  }
+  tabs.forEach((tab, i) => tab.addEventListener("click", () => select(i)));
+  document.querySelector("[role=tablist]").addEventListener("keydown", (e) => {
+    const current = tabs.findIndex((tab) => tab.getAttribute("aria-selected") === "true");
+    const step = { ArrowRight: 1, ArrowLeft: -1 }[e.key];
+    if (step === undefined) return;
+    const next = (current + step + tabs.length) % tabs.length;
+    select(next);
+    tabs[next].focus();
+  });
+  document.addEventListener("keydown", (e) => {
+    if (e.ctrlKey || e.metaKey || e.altKey) return;
+    const index = "1234567890".indexOf(e.key);
+    if (index >= 0 && index < tabs.length) select(index);
+  });
+  // Links to a panel or to a layer anchor inside it open that layer
+  const target = location.hash && document.getElementById(location.hash.slice(1));
+  const linked = panels.findIndex((panel) => target && panel.contains(target));
+  select(linked >= 0 ? linked : 0);
+"#;
+
+/// Builds a self-contained HTML page stacking the layers in the same spot,
+/// one visible at a time, with a tab per layer. Without JavaScript the first
+/// layer is shown.
+///
+/// # Arguments
+///
+/// * `title` - Page title
+/// * `layers` - The layers, in tab order
+/// * `background` - Page background, the documents' background color
+///
+/// # Returns
+///
+/// The HTML document
+pub fn tabbed_page(title: &str, layers: &[LayerTab], background: &str) -> String {
+    // The tab bar follows the documents onto dark backgrounds
+    let (text, accent, rule) = if theme::is_dark(background) {
+        ("#d5d8dc", "#7cb0d9", "#566573")
+    } else {
+        ("#2c3e50", "#3f6f96", "#bdc3c7")
+    };
+
+    let mut tabs = String::new();
+    let mut panels = String::new();
+    for (i, layer) in layers.iter().enumerate() {
+        let selected = i == 0;
+        tabs.push_str(&format!(
+            "<button role=\"tab\" id=\"tab-{i}\" aria-controls=\"panel-{i}\" \
+             aria-selected=\"{selected}\" tabindex=\"{}\">{}</button>\n",
+            if selected { 0 } else { -1 },
+            escape_html(&layer.label)
+        ));
+        let svg = match layer.svg.split_once("<svg") {
+            Some((before, after)) if !layer.alt.is_empty() => format!(
+                "{}<svg role=\"img\" aria-label=\"{}\"{}",
+                before,
+                escape_html(&layer.alt),
+                after
+            ),
+            _ => layer.svg.clone(),
+        };
+        panels.push_str(&format!(
+            "<section role=\"tabpanel\" id=\"panel-{i}\" aria-labelledby=\"tab-{i}\"{}>\n{}\n</section>\n",
+            if selected { " class=\"active\"" } else { "" },
+            svg
+        ));
+    }
+
+    format!(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>{}</title>\n\
+         <style>body {{ margin: 0; background: {background}; }} \
+         [role=tablist] {{ position: sticky; top: 0; display: flex; flex-wrap: wrap; gap: 4px; \
+         padding: 8px 20px; background: {background}; border-bottom: 1px solid {rule}; }} \
+         [role=tab] {{ padding: 6px 12px; border: 1px solid {rule}; border-radius: 6px; \
+         background: transparent; color: {text}; font: 600 13px sans-serif; cursor: pointer; }} \
+         [role=tab][aria-selected=true] {{ background: {accent}; border-color: {accent}; color: #ffffff; }} \
+         main {{ display: grid; }} \
+         [role=tabpanel] {{ grid-area: 1 / 1; visibility: hidden; }} \
+         [role=tabpanel].active {{ visibility: visible; }} \
+         svg {{ max-width: 100%; height: auto; }}</style>\n\
+         </head>\n<body>\n<nav role=\"tablist\">\n{}</nav>\n<main>\n{}</main>\n\
+         <script>{}</script>\n</body>\n</html>\n",
+        escape_html(title),
+        tabs,
+        panels,
+        TAB_SCRIPT
+    )
+}
@@ -0,0 +1,172 @@
+//! Tap dances declared in a keymap.c's `tap_dance_actions` array, looked up
+//! by the `TD(...)` keycodes that use them.
+use crate::{initializer, numeric_enums, parse_keys_with_parens};
+use regex::Regex;
+use std::collections::HashMap;
+
+/// How a tap dance key is pressed to trigger one of its actions.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TapDanceStep {
+    Tap,
+    DoubleTap,
+    Hold,
+}
+
+impl TapDanceStep {
+    /// Lowercase name of the step, as used in tooltips.
+    pub fn name(self) -> &'static str {
+        match self {
+            TapDanceStep::Tap => "tap",
+            TapDanceStep::DoubleTap => "double-tap",
+            TapDanceStep::Hold => "hold",
+        }
+    }
+
+    /// Prefix of the step's legend on the key: nothing for a tap, `2×` for a
+    /// double tap, and `hold` for a hold.
+    pub fn prefix(self) -> &'static str {
+        match self {
+            TapDanceStep::Tap => "",
+            TapDanceStep::DoubleTap => "2× ",
+            TapDanceStep::Hold => "hold ",
+        }
+    }
+}
+
+/// A tap dance and the keycode each way of pressing it sends.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TapDance {
+    /// The designator or position of the dance in `tap_dance_actions`
+    pub name: String,
+    /// The actions in tap, double tap, hold order; empty for dances handled
+    /// by a function (`ACTION_TAP_DANCE_FN` and friends)
+    pub actions: Vec<(TapDanceStep, String)>,
+}
+
+impl TapDance {
+    /// Tooltip of a key using the dance, e.g. `TD(TD_ESC): tap KC_ESC,
+    /// double-tap KC_CAPS`.
+    pub fn describe(&self, key: &str) -> String {
+        if self.actions.is_empty() {
+            return format!("{}: tap dance {} (custom function)", key, self.name);
+        }
+        let actions: Vec<String> = self
+            .actions
+            .iter()
+            .map(|(step, keycode)| format!("{} {}", step.name(), keycode))
+            .collect();
+        format!("{}: {}", key, actions.join(", "))
+    }
+}
+
+/// The tap dances of a keymap, by the argument `TD(...)` keycodes refer to
+/// them with.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TapDances {
+    dances: Vec<TapDance>,
+    /// Index into `dances` of each designator and position
+    lookup: HashMap<String, usize>,
+}
+
+impl TapDances {
+    /// Looks up the tap dance a `TD(...)` keycode triggers.
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - A keycode, e.g. `TD(TD_ESC_CAPS)` or `TD(0)`
+    ///
+    /// # Returns
+    ///
+    /// The tap dance, or `None` if the key isn't a `TD` keycode of a
+    /// declared dance
+    pub fn get(&self, key: &str) -> Option<&TapDance> {
+        let argument = key.trim().strip_prefix("TD(")?.strip_suffix(')')?;
+        self.lookup
+            .get(argument.trim())
+            .map(|&index| &self.dances[index])
+    }
+}
+
+/// The actions of one `ACTION_TAP_DANCE_*` initializer.
+fn actions(function: &str, args: &[String]) -> Option<Vec<(TapDanceStep, String)>> {
+    use TapDanceStep::{DoubleTap, Hold, Tap};
+    Some(match (function, args) {
+        ("ACTION_TAP_DANCE_DOUBLE", [tap, double]) => {
+            vec![(Tap, tap.clone()), (DoubleTap, double.clone())]
+        }
+        ("ACTION_TAP_DANCE_LAYER_MOVE", [tap, layer]) => {
+            vec![(Tap, tap.clone()), (DoubleTap, format!("TO({})", layer))]
+        }
+        ("ACTION_TAP_DANCE_LAYER_TOGGLE", [tap, layer]) => {
+            vec![(Tap, tap.clone()), (DoubleTap, format!("TG({})", layer))]
+        }
+        // The tap-hold helper macro from the QMK tap dance examples
+        ("ACTION_TAP_DANCE_TAP_HOLD", [tap, hold]) => {
+            vec![(Tap, tap.clone()), (Hold, hold.clone())]
+        }
+        (function, _) if function.starts_with("ACTION_TAP_DANCE_FN") => Vec::new(),
+        _ => return None,
+    })
+}
+
+/// Parses the tap dances of a keymap.c.
+///
+/// Entries of `tap_dance_action_t tap_dance_actions[]` (or the older
+/// `qk_tap_dance_action_t`) are read with or without designators such as
+/// `[TD_ESC_CAPS] = ...`. `ACTION_TAP_DANCE_DOUBLE`, `_LAYER_MOVE`,
+/// `_LAYER_TOGGLE`, and the `_TAP_HOLD` helper macro from the QMK docs list
+/// their actions; `ACTION_TAP_DANCE_FN` dances are kept without actions.
+/// Dances are found by designator and by number: the designator's value in
+/// its enum, the designator itself when it is a number, or else the dance's
+/// position in the array.
+///
+/// # Arguments
+///
+/// * `content` - The keymap.c source
+///
+/// # Returns
+///
+/// The tap dances, empty if the keymap has none
+pub fn parse_tap_dances(content: &str) -> TapDances {
+    let content = Regex::new(r"(?s)/\*.*?\*/")
+        .unwrap()
+        .replace_all(content, "");
+    let content = Regex::new(r"//[^\n]*").unwrap().replace_all(&content, "");
+
+    let Some(start) =
+        Regex::new(r"\b(?:qk_)?tap_dance_action_t\s+tap_dance_actions\s*\[[^\]]*\]\s*=")
+            .unwrap()
+            .find(&content)
+    else {
+        return TapDances::default();
+    };
+    let Some(body) = initializer(&content, start.end()) else {
+        return TapDances::default();
+    };
+
+    let entry_regex =
+        Regex::new(r"(?s)^(?:\[\s*(\w+)\s*\]\s*=\s*)?(ACTION_TAP_DANCE_\w+)\s*\((.*)\)$").unwrap();
+    let values: HashMap<String, usize> = numeric_enums(&content).into_iter().flatten().collect();
+    let mut dances = TapDances::default();
+    for (position, entry) in parse_keys_with_parens(body).iter().enumerate() {
+        let Some(caps) = entry_regex.captures(entry) else {
+            continue;
+        };
+        let Some(actions) = actions(&caps[2], &parse_keys_with_parens(&caps[3])) else {
+            continue;
+        };
+        let name = caps
+            .get(1)
+            .map_or(position.to_string(), |name| name.as_str().to_string());
+        let number = values
+            .get(&name)
+            .copied()
+            .or_else(|| name.parse().ok())
+            .unwrap_or(position);
+        let index = dances.dances.len();
+        dances.lookup.insert(number.to_string(), index);
+        dances.lookup.insert(name.clone(), index);
+        dances.dances.push(TapDance { name, actions });
+    }
+    dances
+}
@@ -0,0 +1,1049 @@
+//! Color themes for rendered keymaps.
+use crate::PALETTE_SIZE;
+use anyhow::{Result, bail};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use svg::node::element::{Circle, Path, Pattern as SvgPattern, Rectangle};
+
+/// Pattern accepted for theme colors: `#rgb` or `#rrggbb`.
+const COLOR_PATTERN: &str = r"^#([0-9a-fA-F]{3}|[0-9a-fA-F]{6})$";
+
+/// A vertical two-stop gradient used to fill keys.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct Gradient {
+    /// Color at the top of the key
+    #[schemars(regex(pattern = COLOR_PATTERN))]
+    pub top: String,
+    /// Color at the bottom of the key
+    #[schemars(regex(pattern = COLOR_PATTERN))]
+    pub bottom: String,
+}
+
+impl Gradient {
+    fn new(top: &str, bottom: &str) -> Self {
+        Gradient {
+            top: top.to_string(),
+            bottom: bottom.to_string(),
+        }
+    }
+
+    /// A gradient of a single color, for fills given as one color.
+    pub fn solid(color: &str) -> Self {
+        Gradient::new(color, color)
+    }
+}
+
+/// How key fills use their gradient's colors.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum Fill {
+    /// Blend from the first color to the second along `gradient_angle`
+    #[default]
+    Gradient,
+    /// A single flat color, the gradient's first color
+    Solid,
+}
+
+/// A drop shadow cast by keys.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(default, deny_unknown_fields)]
+pub struct Shadow {
+    /// Horizontal offset in pixels
+    pub dx: f32,
+    /// Vertical offset in pixels
+    pub dy: f32,
+    /// Blur radius in pixels
+    pub blur: f32,
+    /// Opacity of the black shadow from 0 to 1; 0 turns the shadow off
+    #[schemars(range(min = 0.0, max = 1.0))]
+    pub opacity: f32,
+}
+
+impl Default for Shadow {
+    fn default() -> Self {
+        Shadow {
+            dx: 2.0,
+            dy: 2.0,
+            blur: 3.0,
+            opacity: 0.2,
+        }
+    }
+}
+
+impl Shadow {
+    /// CSS `filter` value drawing the shadow.
+    fn filter(&self) -> String {
+        if self.opacity <= 0.0 {
+            return "none".to_string();
+        }
+        format!(
+            "drop-shadow({}px {}px {}px rgba(0,0,0,{}))",
+            self.dx, self.dy, self.blur, self.opacity
+        )
+    }
+
+    /// The stronger shadow of a hovered key.
+    fn raised(&self) -> Shadow {
+        if self.opacity <= 0.0 {
+            return self.clone();
+        }
+        Shadow {
+            dx: self.dx + 1.0,
+            dy: self.dy + 1.0,
+            blur: self.blur + 2.0,
+            opacity: (self.opacity + 0.1).min(1.0),
+        }
+    }
+}
+
+/// Outline and shape of one key category. Unset fields fall back to the
+/// regular key's shape.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(default, deny_unknown_fields)]
+pub struct KeyShape {
+    /// Outline color
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[schemars(regex(pattern = COLOR_PATTERN))]
+    pub stroke: Option<String>,
+    /// Outline width in pixels
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stroke_width: Option<f32>,
+    /// Corner radius in pixels
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub radius: Option<f32>,
+    /// Drop shadow
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub shadow: Option<Shadow>,
+}
+
+/// Key shapes per category.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(default, deny_unknown_fields)]
+pub struct KeyShapes {
+    /// Regular keys, and the fallback for every other category
+    pub key: KeyShape,
+    /// Special keys (boot, RGB, and backlight controls)
+    pub special: KeyShape,
+    /// System keys (Magic, NKRO, and EEPROM controls)
+    pub system: KeyShape,
+    /// Keys colored by the layer they activate
+    pub layer: KeyShape,
+    /// Layer lock keys
+    pub lock: KeyShape,
+    /// Keys without an assignment
+    pub empty: KeyShape,
+    /// Empty keys drawn as ghost outlines
+    pub ghost: KeyShape,
+}
+
+impl Default for KeyShapes {
+    fn default() -> Self {
+        KeyShapes {
+            key: KeyShape {
+                stroke: None,
+                stroke_width: Some(2.0),
+                radius: Some(5.0),
+                shadow: Some(Shadow::default()),
+            },
+            special: KeyShape::default(),
+            system: KeyShape::default(),
+            layer: KeyShape::default(),
+            lock: KeyShape {
+                stroke_width: Some(3.0),
+                ..KeyShape::default()
+            },
+            empty: KeyShape::default(),
+            ghost: KeyShape {
+                stroke: Some("#95a5a6".to_string()),
+                shadow: Some(Shadow {
+                    opacity: 0.0,
+                    ..Shadow::default()
+                }),
+                ..KeyShape::default()
+            },
+        }
+    }
+}
+
+impl KeyShapes {
+    /// Categories with their names and CSS selectors, in cascade order.
+    fn categories(&self) -> [(&'static str, String, &KeyShape); 6] {
+        let layers: Vec<String> = (1..=PALETTE_SIZE)
+            .map(|slot| format!(".key-layer{}", slot))
+            .collect();
+        [
+            ("layer", layers.join(", "), &self.layer),
+            ("special", ".key-special".to_string(), &self.special),
+            ("system", ".key-system".to_string(), &self.system),
+            ("lock", ".key-lock".to_string(), &self.lock),
+            ("empty", ".key-empty".to_string(), &self.empty),
+            ("ghost", ".key-ghost".to_string(), &self.ghost),
+        ]
+    }
+
+    /// The regular key's shape with unset fields taken from the default theme,
+    /// so a theme may change a single property of it.
+    fn base(&self) -> KeyShape {
+        let default = KeyShapes::default().key;
+        KeyShape {
+            stroke: self.key.stroke.clone().or(default.stroke),
+            stroke_width: self.key.stroke_width.or(default.stroke_width),
+            radius: self.key.radius.or(default.radius),
+            shadow: self.key.shadow.clone().or(default.shadow),
+        }
+    }
+
+    /// Corner radius of keys with the given CSS class list.
+    ///
+    /// The most specific category in the list wins, matching the cascade
+    /// order of the generated CSS.
+    pub fn radius(&self, class: &str) -> f32 {
+        let base = self.base().radius.unwrap_or_default();
+        let classes: Vec<&str> = class.split_whitespace().collect();
+        self.categories()
+            .iter()
+            .rev()
+            .find(|(_, selector, shape)| {
+                shape.radius.is_some()
+                    && selector
+                        .split(", ")
+                        .any(|s| classes.contains(&s.trim_start_matches('.')))
+            })
+            .and_then(|(_, _, shape)| shape.radius)
+            .unwrap_or(base)
+    }
+
+    /// CSS rules for the outlines and shadows of every category.
+    ///
+    /// # Arguments
+    ///
+    /// * `outline` - Outline color of regular keys without a stroke of their own
+    pub fn css(&self, outline: &str) -> String {
+        let mut css = String::new();
+        let declarations = |shape: &KeyShape| {
+            let mut rules = Vec::new();
+            if let Some(stroke) = &shape.stroke {
+                rules.push(format!("stroke: {};", stroke));
+            }
+            if let Some(width) = shape.stroke_width {
+                rules.push(format!("stroke-width: {};", width));
+            }
+            if let Some(shadow) = &shape.shadow {
+                rules.push(format!("filter: {};", shadow.filter()));
+            }
+            rules.join(" ")
+        };
+
+        let base = KeyShape {
+            stroke: self.key.stroke.clone().or(Some(outline.to_string())),
+            ..self.base()
+        };
+        css.push_str(&format!("        .key {{ {} }}\n", declarations(&base)));
+        if let Some(shadow) = &base.shadow {
+            css.push_str(&format!(
+                "        .key-group:hover .key {{ filter: {}; }}\n",
+                shadow.raised().filter()
+            ));
+        }
+        for (_, selector, shape) in self.categories() {
+            let rules = declarations(shape);
+            if !rules.is_empty() {
+                css.push_str(&format!("        {} {{ {} }}\n", selector, rules));
+            }
+        }
+        css
+    }
+
+    /// Checks colors and that sizes are non-negative.
+    fn validate(&self) -> Result<()> {
+        let mut shapes = vec![("key", &self.key)];
+        shapes.extend(
+            self.categories()
+                .into_iter()
+                .map(|(name, _, shape)| (name, shape)),
+        );
+
+        for (name, shape) in shapes {
+            if let Some(stroke) = &shape.stroke
+                && !is_valid_color(stroke)
+            {
+                bail!(
+                    "shapes.{}.stroke: expected a #rgb or #rrggbb color, got \"{}\"",
+                    name,
+                    stroke
+                );
+            }
+            let sizes = [
+                ("stroke_width", shape.stroke_width),
+                ("radius", shape.radius),
+                ("shadow.blur", shape.shadow.as_ref().map(|s| s.blur)),
+            ];
+            for (field, value) in sizes {
+                if let Some(value) = value
+                    && !(value.is_finite() && value >= 0.0)
+                {
+                    bail!(
+                        "shapes.{}.{}: must be zero or more, got {}",
+                        name,
+                        field,
+                        value
+                    );
+                }
+            }
+            if let Some(shadow) = &shape.shadow
+                && !(0.0..=1.0).contains(&shadow.opacity)
+            {
+                bail!(
+                    "shapes.{}.shadow.opacity: must be between 0 and 1, got {}",
+                    name,
+                    shadow.opacity
+                );
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// A black-and-white fill standing in for a color in monochrome output.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum Pattern {
+    /// Plain white
+    Plain,
+    /// Plain white with a doubled outline
+    Outline,
+    /// Diagonal lines rising to the right
+    Hatch,
+    /// Diagonal lines falling to the right
+    Backhatch,
+    /// Both diagonals
+    Crosshatch,
+    /// Horizontal lines
+    Horizontal,
+    /// Vertical lines
+    Vertical,
+    /// Small filled dots
+    Dots,
+    /// Horizontal and vertical lines
+    Grid,
+    /// Light gray checkerboard
+    Checker,
+    /// Small open circles
+    Rings,
+}
+
+/// Color of the marks drawn by patterns.
+const PATTERN_INK: &str = "#555555";
+/// Size of one pattern tile in pixels.
+const PATTERN_TILE: f32 = 8.0;
+
+impl Pattern {
+    /// Every pattern, in declaration order.
+    pub const ALL: [Pattern; 11] = [
+        Pattern::Plain,
+        Pattern::Outline,
+        Pattern::Hatch,
+        Pattern::Backhatch,
+        Pattern::Crosshatch,
+        Pattern::Horizontal,
+        Pattern::Vertical,
+        Pattern::Dots,
+        Pattern::Grid,
+        Pattern::Checker,
+        Pattern::Rings,
+    ];
+
+    /// Name used in theme files and pattern ids.
+    pub fn name(self) -> &'static str {
+        match self {
+            Pattern::Plain => "plain",
+            Pattern::Outline => "outline",
+            Pattern::Hatch => "hatch",
+            Pattern::Backhatch => "backhatch",
+            Pattern::Crosshatch => "crosshatch",
+            Pattern::Horizontal => "horizontal",
+            Pattern::Vertical => "vertical",
+            Pattern::Dots => "dots",
+            Pattern::Grid => "grid",
+            Pattern::Checker => "checker",
+            Pattern::Rings => "rings",
+        }
+    }
+
+    /// CSS declarations filling a key with the pattern.
+    fn css(self, base_stroke_width: f32) -> String {
+        match self {
+            Pattern::Plain => "fill: #ffffff;".to_string(),
+            Pattern::Outline => {
+                format!("fill: #ffffff; stroke-width: {};", base_stroke_width * 2.0)
+            }
+            _ => format!("fill: url(#pattern-{});", self.name()),
+        }
+    }
+
+    /// The `<pattern>` definition referenced by [`Pattern::css`]: a white tile
+    /// with the pattern's marks.
+    pub fn definition(self) -> SvgPattern {
+        let lines = |d: &str| {
+            Path::new()
+                .set("d", d)
+                .set("stroke", PATTERN_INK)
+                .set("stroke-width", 1)
+        };
+        let circle = |fill: &str, stroke: &str, r: f32| {
+            Circle::new()
+                .set("cx", PATTERN_TILE / 2.0)
+                .set("cy", PATTERN_TILE / 2.0)
+                .set("r", r)
+                .set("fill", fill)
+                .set("stroke", stroke)
+        };
+        let square = |x: f32, y: f32| {
+            Rectangle::new()
+                .set("x", x)
+                .set("y", y)
+                .set("width", PATTERN_TILE / 2.0)
+                .set("height", PATTERN_TILE / 2.0)
+                .set("fill", "#cccccc")
+        };
+
+        let pattern = SvgPattern::new()
+            .set("id", format!("pattern-{}", self.name()))
+            .set("patternUnits", "userSpaceOnUse")
+            .set("width", PATTERN_TILE)
+            .set("height", PATTERN_TILE)
+            .add(
+                Rectangle::new()
+                    .set("width", PATTERN_TILE)
+                    .set("height", PATTERN_TILE)
+                    .set("fill", "#ffffff"),
+            );
+
+        match self {
+            Pattern::Plain | Pattern::Outline => pattern,
+            Pattern::Hatch => pattern.add(lines("M-2,2 l4,-4 M0,8 l8,-8 M6,10 l4,-4")),
+            Pattern::Backhatch => pattern.add(lines("M-2,6 l4,4 M0,0 l8,8 M6,-2 l4,4")),
+            Pattern::Crosshatch => pattern.add(lines(
+                "M-2,2 l4,-4 M0,8 l8,-8 M6,10 l4,-4 M-2,6 l4,4 M0,0 l8,8 M6,-2 l4,4",
+            )),
+            Pattern::Horizontal => pattern.add(lines("M0,4 h8")),
+            Pattern::Vertical => pattern.add(lines("M4,0 v8")),
+            Pattern::Dots => pattern.add(circle(PATTERN_INK, "none", 1.3)),
+            Pattern::Grid => pattern.add(lines("M0,4 h8 M4,0 v8")),
+            Pattern::Checker => pattern.add(square(0.0, 0.0)).add(square(4.0, 4.0)),
+            Pattern::Rings => pattern.add(circle("none", PATTERN_INK, 2.2)),
+        }
+    }
+}
+
+/// Fill patterns per key category, used instead of colors in monochrome
+/// output so printed sheets still tell categories apart.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(default, deny_unknown_fields)]
+pub struct Patterns {
+    /// Regular keys
+    pub key: Pattern,
+    /// Special keys (boot, RGB, and backlight controls)
+    pub special: Pattern,
+    /// System keys (Magic, NKRO, and EEPROM controls)
+    pub system: Pattern,
+    /// One pattern per layer palette slot; missing slots use the default
+    #[schemars(length(max = 8))]
+    pub layers: Vec<Pattern>,
+}
+
+impl Default for Patterns {
+    fn default() -> Self {
+        Patterns {
+            key: Pattern::Plain,
+            special: Pattern::Outline,
+            system: Pattern::Checker,
+            layers: vec![
+                Pattern::Hatch,
+                Pattern::Dots,
+                Pattern::Backhatch,
+                Pattern::Crosshatch,
+                Pattern::Horizontal,
+                Pattern::Vertical,
+                Pattern::Grid,
+                Pattern::Rings,
+            ],
+        }
+    }
+}
+
+impl Patterns {
+    /// Pattern of a layer palette slot; accent slots after `PALETTE_SIZE`
+    /// reuse the palette's patterns.
+    fn layer(&self, slot: usize) -> Pattern {
+        let default = Patterns::default().layers;
+        let index = (slot - 1) % PALETTE_SIZE;
+        self.layers
+            .get(index)
+            .or(default.get(index))
+            .copied()
+            .unwrap_or(Pattern::Plain)
+    }
+}
+
+/// Letter case applied to legends.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum LegendCase {
+    /// Legends as written
+    #[default]
+    Keep,
+    /// `SHIFT`
+    Upper,
+    /// `shift`
+    Lower,
+    /// `Shift`, capitalizing every word
+    Title,
+}
+
+impl LegendCase {
+    /// Applies the case to a legend.
+    pub fn apply(self, legend: &str) -> String {
+        match self {
+            LegendCase::Keep => legend.to_string(),
+            LegendCase::Upper => legend.to_uppercase(),
+            LegendCase::Lower => legend.to_lowercase(),
+            LegendCase::Title => {
+                let mut titled = String::with_capacity(legend.len());
+                let mut word_start = true;
+                for c in legend.chars() {
+                    if word_start {
+                        titled.extend(c.to_uppercase());
+                    } else {
+                        titled.extend(c.to_lowercase());
+                    }
+                    word_start = c.is_whitespace();
+                }
+                titled
+            }
+        }
+    }
+}
+
+/// Font families legends can be drawn in.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum Typeface {
+    /// Fixed-width coding fonts
+    #[default]
+    Monospace,
+    /// The system's interface font
+    Proportional,
+}
+
+impl Typeface {
+    /// CSS font stack of the typeface.
+    fn font_family(self) -> &'static str {
+        match self {
+            Typeface::Monospace => "'SF Mono', 'Monaco', 'Inconsolata', 'Fira Code', monospace",
+            Typeface::Proportional => "-apple-system, BlinkMacSystemFont, 'Segoe UI', sans-serif",
+        }
+    }
+}
+
+/// The kinds of legends typography rules tell apart.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LegendKind {
+    /// Letters, digits, symbols, and anything not listed below
+    Key,
+    /// Modifier keys such as Shift and one-shot modifiers
+    Modifier,
+    /// Keys that switch or lock layers
+    Layer,
+    /// System keys (Magic, NKRO, and EEPROM controls)
+    System,
+}
+
+impl LegendKind {
+    /// CSS class marking legends of this kind, if it has one.
+    pub fn class(self) -> Option<&'static str> {
+        match self {
+            LegendKind::Key => None,
+            LegendKind::Modifier => Some("legend-modifier"),
+            LegendKind::Layer => Some("legend-layer"),
+            LegendKind::System => Some("legend-system"),
+        }
+    }
+}
+
+/// Typeface per kind of legend; unset kinds use the `key` typeface.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(default, deny_unknown_fields)]
+pub struct LegendFonts {
+    /// Letters, digits, symbols, and other legends
+    pub key: Typeface,
+    /// Modifier legends
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub modifier: Option<Typeface>,
+    /// Layer key legends
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub layer: Option<Typeface>,
+    /// System key legends
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub system: Option<Typeface>,
+}
+
+/// How legend text is cased and set.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(default, deny_unknown_fields)]
+pub struct Typography {
+    /// Letter case of key and corner legends
+    pub case: LegendCase,
+    /// Set modifier legends in small caps
+    pub small_caps_modifiers: bool,
+    /// Typeface per kind of legend
+    pub fonts: LegendFonts,
+    /// CSS font stack replacing the `key` typeface, e.g. `"'Iosevka', monospace"`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub font_family: Option<String>,
+}
+
+impl Typography {
+    /// CSS rules setting the typeface of each kind of legend and the small
+    /// caps of modifiers; legends in the style sheet's monospace need no rule.
+    pub fn css(&self) -> String {
+        let mut css = String::new();
+        if let Some(family) = &self.font_family {
+            css.push_str(&format!(
+                "        .key-text, .key-corner {{ font-family: {}; }}\n",
+                family
+            ));
+        } else if self.fonts.key != Typeface::Monospace {
+            css.push_str(&format!(
+                "        .key-text, .key-corner {{ font-family: {}; }}\n",
+                self.fonts.key.font_family()
+            ));
+        }
+        let kinds = [
+            (LegendKind::Modifier, self.fonts.modifier),
+            (LegendKind::Layer, self.fonts.layer),
+            (LegendKind::System, self.fonts.system),
+        ];
+        for (kind, typeface) in kinds {
+            if let Some(typeface) = typeface
+                && (typeface != self.fonts.key || self.font_family.is_some())
+                && let Some(class) = kind.class()
+            {
+                css.push_str(&format!(
+                    "        .{} {{ font-family: {}; }}\n",
+                    class,
+                    typeface.font_family()
+                ));
+            }
+        }
+        if self.small_caps_modifiers {
+            css.push_str("        .legend-modifier { font-variant: small-caps; }\n");
+        }
+        css
+    }
+}
+
+/// Colors and key shapes used to draw a keymap.
+///
+/// Every field is optional in theme files; missing fields keep the default
+/// (GMK-inspired) look.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(default, deny_unknown_fields)]
+pub struct Theme {
+    /// Document background
+    #[schemars(regex(pattern = COLOR_PATTERN))]
+    pub background: String,
+    /// Key legend text
+    #[schemars(regex(pattern = COLOR_PATTERN))]
+    pub legend: String,
+    /// Key outlines
+    #[schemars(regex(pattern = COLOR_PATTERN))]
+    pub outline: String,
+    /// Fill of regular keys
+    pub key: Gradient,
+    /// Fill of special keys (boot, RGB, and backlight controls)
+    pub special: Gradient,
+    /// Fill of system keys (Magic, NKRO, and EEPROM controls)
+    pub system: Gradient,
+    /// Layer palette, one fill per color slot
+    #[schemars(length(max = 8))]
+    pub layers: Vec<Gradient>,
+    /// Fills of specific layers by name or number (e.g. `NAV` or `3`; leading
+    /// underscores and case are ignored), taking precedence over the palette
+    pub accents: BTreeMap<String, Gradient>,
+    /// Fills of specific keycodes (e.g. `KC_ESC`), taking precedence over
+    /// their category and layer colors
+    pub keys: BTreeMap<String, Gradient>,
+    /// Direction of the gradients in degrees, as in CSS: 180 runs top to
+    /// bottom, 90 left to right
+    pub gradient_angle: f32,
+    /// Gradient or solid key fills
+    pub fill: Fill,
+    /// Outlines, corner radii, and shadows per key category
+    pub shapes: KeyShapes,
+    /// Draw in black and white, filling keys with `patterns` instead of colors
+    pub monochrome: bool,
+    /// Fill patterns per key category used by monochrome output
+    pub patterns: Patterns,
+    /// Legend casing and typefaces
+    pub typography: Typography,
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Theme {
+            background: "#faf8f3".to_string(),
+            legend: "#2c3e50".to_string(),
+            outline: "#2c3e50".to_string(),
+            // GMK WoB/BoW style - light grey
+            key: Gradient::new("#e8e8e8", "#d0d0d0"),
+            // GMK accent teal
+            special: Gradient::new("#7ec4a8", "#5ca888"),
+            // GMK slate
+            system: Gradient::new("#8e9aaf", "#6c7a93"),
+            layers: vec![
+                Gradient::new("#7cb0d9", "#5a8fb8"), // GMK Blue (Dolch/Nautilus blue)
+                Gradient::new("#b888c4", "#9668a8"), // GMK Purple (Laser purple)
+                Gradient::new("#d97c7c", "#c25858"), // GMK Red (Red Samurai red)
+                Gradient::new("#e8a87c", "#d18a58"), // GMK Orange (Camping orange)
+                Gradient::new("#7ec4a8", "#5ca888"), // GMK Teal (Cyan/Miami teal)
+                Gradient::new("#88c47c", "#68a858"), // GMK Green (Botanical green)
+                Gradient::new("#d4c47c", "#b8a858"), // GMK Yellow (Honey yellow)
+                Gradient::new("#a8a8a8", "#888888"), // GMK Dark Grey (modifier grey)
+            ],
+            accents: BTreeMap::new(),
+            keys: BTreeMap::new(),
+            gradient_angle: 180.0,
+            fill: Fill::Gradient,
+            shapes: KeyShapes::default(),
+            monochrome: false,
+            patterns: Patterns::default(),
+            typography: Typography::default(),
+        }
+    }
+}
+
+/// Returns `true` if `color` is a dark `#rgb` or `#rrggbb` hex color, one
+/// that light text stands out against.
+pub fn is_dark(color: &str) -> bool {
+    let hex = color.trim_start_matches('#');
+    let hex = if hex.len() == 3 {
+        hex.chars().flat_map(|c| [c, c]).collect()
+    } else {
+        hex.to_string()
+    };
+    let channel = |start: usize| {
+        hex.get(start..start + 2)
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok())
+            .unwrap_or(255) as f32
+    };
+    0.299 * channel(0) + 0.587 * channel(2) + 0.114 * channel(4) < 128.0
+}
+
+/// Returns `true` if `color` is a `#rgb` or `#rrggbb` hex color.
+pub fn is_valid_color(color: &str) -> bool {
+    color
+        .strip_prefix('#')
+        .is_some_and(|hex| matches!(hex.len(), 3 | 6) && hex.chars().all(|c| c.is_ascii_hexdigit()))
+}
+
+impl Theme {
+    /// The dark preset: a near-black background with light legends, deeper
+    /// key fills, and stronger shadows, for pages with a dark color scheme.
+    pub fn dark() -> Self {
+        let default = KeyShapes::default();
+        Theme {
+            background: "#1e2126".to_string(),
+            legend: "#ecf0f1".to_string(),
+            outline: "#111316".to_string(),
+            key: Gradient::new("#4a4f58", "#3a3e45"),
+            special: Gradient::new("#3d8a6c", "#2f7459"),
+            system: Gradient::new("#5c6a82", "#4a566b"),
+            layers: vec![
+                Gradient::new("#4f83ad", "#3f6f96"),
+                Gradient::new("#8a5f9e", "#76508a"),
+                Gradient::new("#b54f4f", "#a24040"),
+                Gradient::new("#c27c48", "#b06c3c"),
+                Gradient::new("#4a9c7c", "#3d8a6c"),
+                Gradient::new("#5a9c4a", "#4a8a3c"),
+                Gradient::new("#9c8a3c", "#8a7a30"),
+                Gradient::new("#6a6a6a", "#5a5a5a"),
+            ],
+            shapes: KeyShapes {
+                key: KeyShape {
+                    shadow: Some(Shadow {
+                        opacity: 0.5,
+                        ..Shadow::default()
+                    }),
+                    ..default.key
+                },
+                ghost: KeyShape {
+                    stroke: Some("#7f8c8d".to_string()),
+                    ..default.ghost
+                },
+                ..default
+            },
+            ..Theme::default()
+        }
+    }
+
+    /// Fill of a layer palette slot; slots after `PALETTE_SIZE` are the
+    /// accents in name order.
+    pub fn layer_fill(&self, slot: usize) -> Gradient {
+        if slot > PALETTE_SIZE
+            && let Some(accent) = self.accents.values().nth(slot - PALETTE_SIZE - 1)
+        {
+            return accent.clone();
+        }
+        let default = Theme::default().layers;
+        let index = (slot.max(1) - 1) % PALETTE_SIZE;
+        self.layers
+            .get(index)
+            .or(default.get(index))
+            .cloned()
+            .unwrap_or_else(|| self.key.clone())
+    }
+
+    /// The accent slot of a layer name or number, if the theme has an accent
+    /// for it.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The layer's name or index; leading underscores and case are
+    ///   ignored
+    ///
+    /// # Returns
+    ///
+    /// A slot after `PALETTE_SIZE`, or `None` if the layer keeps its palette color
+    pub fn accent_slot(&self, name: &str) -> Option<usize> {
+        let normalize = |name: &str| name.trim_start_matches('_').to_ascii_uppercase();
+        let name = normalize(name);
+        self.accents
+            .keys()
+            .position(|accent| normalize(accent) == name)
+            .map(|idx| PALETTE_SIZE + 1 + idx)
+    }
+
+    /// The keycode slot of a key, if the theme has a fill for its keycode.
+    ///
+    /// # Arguments
+    ///
+    /// * `keycode` - The key's keycode as written in the keymap
+    ///
+    /// # Returns
+    ///
+    /// The position of the keycode in `keys`, or `None` if the key keeps its
+    /// category's fill
+    pub fn keycode_slot(&self, keycode: &str) -> Option<usize> {
+        let keycode = keycode.trim();
+        self.keys.keys().position(|key| key == keycode)
+    }
+
+    /// Start and end points of the gradients as `(x1, y1, x2, y2)`
+    /// percentages of the key's box.
+    pub fn gradient_vector(&self) -> (f32, f32, f32, f32) {
+        let (sin, cos) = self.gradient_angle.to_radians().sin_cos();
+        let round = |v: f32| (v * 100.0).round() / 100.0;
+        (
+            round(50.0 - 50.0 * sin),
+            round(50.0 + 50.0 * cos),
+            round(50.0 + 50.0 * sin),
+            round(50.0 - 50.0 * cos),
+        )
+    }
+
+    /// CSS rules coloring key legends and icons.
+    pub fn legend_css(&self) -> String {
+        format!(
+            "        .key-text {{ fill: {legend}; }}\n        .key-icon {{ stroke: {legend}; }}\n",
+            legend = self.legend
+        )
+    }
+
+    /// CSS rules lightening titles, panels, and corner legends on a dark
+    /// background, or an empty string on a light one.
+    pub fn surface_css(&self) -> String {
+        if self.monochrome || !is_dark(&self.background) {
+            return String::new();
+        }
+
+        // Corner legends take the light palette, which reads on dark keys
+        let light = Theme::default();
+        let mut css = String::from(
+            "        .layer-title, .card-legend, .metadata-text, .scale-title { fill: #ecf0f1; }\n\
+             \x20       .layer-note, .half-label, .scale-label, .key-autoshift { fill: #bdc3c7; }\n\
+             \x20       .metadata-panel { fill: #2c3e50; stroke: #566573; }\n\
+             \x20       .warning-banner { fill: #3b2a1c; }\n\
+             \x20       .warning-text { fill: #f0b27a; }\n\
+             \x20       .key-empty { fill: #566573; }\n\
+             \x20       .group-outline { stroke: #ecf0f1; }\n\
+             \x20       .group-tab { fill: #ecf0f1; }\n\
+             \x20       .group-label { fill: #2c3e50; }\n",
+        );
+        for slot in 1..=PALETTE_SIZE {
+            css.push_str(&format!(
+                "        .corner-layer{} {{ fill: {}; }}\n",
+                slot,
+                light.layer_fill(slot).top
+            ));
+        }
+        css
+    }
+
+    /// CSS rules filling the accent slots' keys and corner legends.
+    pub fn accent_css(&self) -> String {
+        (0..self.accents.len())
+            .map(|idx| {
+                let slot = PALETTE_SIZE + 1 + idx;
+                format!(
+                    "        .key-layer{slot} {{ fill: url(#layer{slot}Gradient); }}\n\
+                     \x20       .corner-layer{slot} {{ fill: {}; }}\n",
+                    self.layer_fill(slot).bottom
+                )
+            })
+            .collect()
+    }
+
+    /// CSS rules filling the keys whose keycodes have their own fill.
+    pub fn keycode_css(&self) -> String {
+        (0..self.keys.len())
+            .map(|slot| {
+                format!("        .key-keycode{slot} {{ fill: url(#keycode{slot}Gradient); }}\n")
+            })
+            .collect()
+    }
+
+    /// CSS rules turning the document black and white, or an empty string
+    /// unless the theme is monochrome.
+    ///
+    /// The rules come after the regular style sheet so they take precedence.
+    pub fn monochrome_css(&self) -> String {
+        if !self.monochrome {
+            return String::new();
+        }
+
+        let stroke_width = self.shapes.base().stroke_width.unwrap_or_default();
+        let mut css = format!(
+            "        .key {{ {} stroke: #000000; filter: none; }}\n",
+            self.patterns.key.css(stroke_width)
+        );
+        for slot in 1..=PALETTE_SIZE + self.accents.len() {
+            css.push_str(&format!(
+                "        .key-layer{} {{ {} }}\n",
+                slot,
+                self.patterns.layer(slot).css(stroke_width)
+            ));
+        }
+        css.push_str(&format!(
+            "        .key-special {{ {} }}\n        .key-system {{ {} }}\n",
+            self.patterns.special.css(stroke_width),
+            self.patterns.system.css(stroke_width)
+        ));
+        css.push_str(
+            "        .key-empty { fill: #ffffff; }\n\
+             \x20       .key-ghost { fill: none; stroke: #000000; }\n\
+             \x20       .key-text, .key-corner { fill: #000000; paint-order: stroke; \
+             stroke: #ffffff; stroke-width: 3px; stroke-linejoin: round; }\n\
+             \x20       .key-icon { stroke: #000000; }\n\
+             \x20       .layer-title, .layer-note, .metadata-text, .card-legend, .warning-text, \
+             .half-label { fill: #000000; }\n\
+             \x20       .metadata-panel, .warning-banner { fill: #ffffff; stroke: #000000; }\n\
+             \x20       .half-tint { fill: none; stroke: #000000; }\n",
+        );
+        css
+    }
+
+    /// `<pattern>` definitions used by [`Theme::monochrome_css`], or none
+    /// unless the theme is monochrome.
+    pub fn pattern_definitions(&self) -> Vec<SvgPattern> {
+        if !self.monochrome {
+            return Vec::new();
+        }
+        Pattern::ALL
+            .into_iter()
+            .filter(|pattern| !matches!(pattern, Pattern::Plain | Pattern::Outline))
+            .map(Pattern::definition)
+            .collect()
+    }
+
+    /// Checks every color, the palette size, and the font stack.
+    pub fn validate(&self) -> Result<()> {
+        let mut colors = vec![
+            ("background".to_string(), &self.background),
+            ("legend".to_string(), &self.legend),
+            ("outline".to_string(), &self.outline),
+        ];
+        let mut gradients = vec![
+            ("key".to_string(), &self.key),
+            ("special".to_string(), &self.special),
+            ("system".to_string(), &self.system),
+        ];
+        gradients.extend(
+            self.layers
+                .iter()
+                .enumerate()
+                .map(|(idx, gradient)| (format!("layers[{}]", idx), gradient)),
+        );
+        gradients.extend(
+            self.accents
+                .iter()
+                .map(|(name, gradient)| (format!("accents.{}", name), gradient)),
+        );
+        gradients.extend(
+            self.keys
+                .iter()
+                .map(|(keycode, gradient)| (format!("keys.{}", keycode), gradient)),
+        );
+        for (name, gradient) in gradients {
+            colors.push((format!("{}.top", name), &gradient.top));
+            colors.push((format!("{}.bottom", name), &gradient.bottom));
+        }
+
+        for (name, color) in colors {
+            if !is_valid_color(color) {
+                bail!(
+                    "{}: expected a #rgb or #rrggbb color, got \"{}\"",
+                    name,
+                    color
+                );
+            }
+        }
+
+        self.shapes.validate()?;
+
+        if !self.gradient_angle.is_finite() {
+            bail!("gradient_angle: expected a number of degrees");
+        }
+
+        if self.patterns.layers.len() > PALETTE_SIZE {
+            bail!(
+                "patterns.layers: at most {} patterns are supported, got {}",
+                PALETTE_SIZE,
+                self.patterns.layers.len()
+            );
+        }
+
+        if self.layers.len() > PALETTE_SIZE {
+            bail!(
+                "layers: at most {} palette colors are supported, got {}",
+                PALETTE_SIZE,
+                self.layers.len()
+            );
+        }
+
+        // The font stack is written into the style sheet as is
+        if let Some(family) = &self.typography.font_family
+            && (family.trim().is_empty() || family.contains([';', '{', '}', '<', '>']))
+        {
+            bail!(
+                "typography.font_family: expected a CSS font stack, got \"{}\"",
+                family
+            );
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,130 @@
+//! Watch mode: renders the keymap again each time its file is saved, for a
+//! live preview while editing firmware, and runs a hook after each render.
+use anyhow::{Context, Result, bail};
+use notify::{Event, EventKind, RecursiveMode, Watcher};
+use std::env;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::time::Duration;
+
+/// How long the files must stay quiet after a change before rendering; editors
+/// often save with several writes, or by writing a new file and renaming it.
+const DEBOUNCE: Duration = Duration::from_millis(200);
+
+/// The path events for `file` carry: its canonical directory joined with its
+/// name.
+fn watched_path(file: &Path) -> Result<PathBuf> {
+    let name = file
+        .file_name()
+        .context(format!("Cannot watch {:?}: not a file", file))?;
+    let directory = match file.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    };
+    let directory = directory
+        .canonicalize()
+        .context(format!("Cannot watch {:?}", file))?;
+    Ok(directory.join(name))
+}
+
+/// Returns `true` if an event writes or replaces one of the watched files.
+fn touches(event: &Event, files: &[PathBuf]) -> bool {
+    matches!(
+        event.kind,
+        EventKind::Create(_) | EventKind::Modify(_) | EventKind::Any
+    ) && event.paths.iter().any(|path| files.contains(path))
+}
+
+/// Calls `rebuild`, then watches files and calls it again once after each
+/// burst of changes to them, until the watcher fails.
+///
+/// The files' directories are watched rather than the files themselves, so
+/// saves that replace a file keep being seen. Watching starts before the
+/// first call, so saves while it runs aren't missed.
+///
+/// # Arguments
+///
+/// * `files` - The files to watch
+/// * `rebuild` - Called after the files changed and have stayed quiet for a
+///   moment
+///
+/// # Returns
+///
+/// An error if the files cannot be watched or the watcher stops; it does not
+/// return otherwise
+pub fn watch(files: &[PathBuf], mut rebuild: impl FnMut()) -> Result<()> {
+    let files = files
+        .iter()
+        .map(|file| watched_path(file))
+        .collect::<Result<Vec<_>>>()?;
+    let mut directories: Vec<&Path> = files.iter().filter_map(|file| file.parent()).collect();
+    directories.sort();
+    directories.dedup();
+
+    let (sender, receiver) = mpsc::channel();
+    let mut watcher =
+        notify::recommended_watcher(sender).context("Failed to start the file watcher")?;
+    for directory in directories {
+        watcher
+            .watch(directory, RecursiveMode::NonRecursive)
+            .context(format!("Failed to watch {:?}", directory))?;
+    }
+
+    rebuild();
+    loop {
+        let event = receiver
+            .recv()
+            .context("The file watcher stopped")?
+            .context("The file watcher failed")?;
+        if !touches(&event, &files) {
+            continue;
+        }
+        loop {
+            match receiver.recv_timeout(DEBOUNCE) {
+                Ok(_) => continue,
+                Err(RecvTimeoutError::Timeout) => break,
+                Err(RecvTimeoutError::Disconnected) => bail!("The file watcher stopped"),
+            }
+        }
+        rebuild();
+    }
+}
+
+/// Runs an `--on-update` command through the shell after a successful render.
+///
+/// The command sees the rendered files in `KEYBALL44_VIZ_OUTPUT` (the first
+/// one) and `KEYBALL44_VIZ_OUTPUTS` (all of them, separated like `PATH`), and
+/// the keymap in `KEYBALL44_VIZ_KEYMAP`. Its output goes to the terminal.
+///
+/// # Arguments
+///
+/// * `command` - The shell command
+/// * `outputs` - The files the render wrote
+/// * `keymap_file` - The rendered keymap
+///
+/// # Returns
+///
+/// `Ok(())`, or an error if the command cannot be started or fails
+pub fn run_hook(command: &str, outputs: &[PathBuf], keymap_file: &Path) -> Result<()> {
+    let (shell, flag) = if cfg!(windows) {
+        ("cmd", "/C")
+    } else {
+        ("sh", "-c")
+    };
+    let all_outputs = env::join_paths(outputs).context("Output paths cannot be passed on")?;
+    let status = Command::new(shell)
+        .args([flag, command])
+        .env(
+            "KEYBALL44_VIZ_OUTPUT",
+            outputs.first().map_or(Path::new(""), PathBuf::as_path),
+        )
+        .env("KEYBALL44_VIZ_OUTPUTS", all_outputs)
+        .env("KEYBALL44_VIZ_KEYMAP", keymap_file)
+        .status()
+        .with_context(|| format!("Failed to run --on-update command: {}", command))?;
+    if !status.success() {
+        bail!("--on-update command failed ({}): {}", status, command);
+    }
+    Ok(())
+}
@@ -0,0 +1,306 @@
+//! Parsing of ZMK `.keymap` devicetree files into the same layers QMK
+//! keymaps produce.
+use crate::Layer;
+use crate::encoder::EncoderBinding;
+use crate::keymap_json::split_rows;
+use anyhow::{Result, bail};
+use regex::Regex;
+use std::collections::HashMap;
+
+/// ZMK key names and the QMK keycodes they match. Names not listed here are
+/// read as `KC_` plus the name, which covers letters, `F1` to `F24`, and keys
+/// named alike in both firmwares such as `TAB` or `HOME`.
+const KEY_NAMES: &[(&[&str], &str)] = &[
+    (&["N0", "NUMBER_0"], "KC_0"),
+    (&["N1", "NUMBER_1"], "KC_1"),
+    (&["N2", "NUMBER_2"], "KC_2"),
+    (&["N3", "NUMBER_3"], "KC_3"),
+    (&["N4", "NUMBER_4"], "KC_4"),
+    (&["N5", "NUMBER_5"], "KC_5"),
+    (&["N6", "NUMBER_6"], "KC_6"),
+    (&["N7", "NUMBER_7"], "KC_7"),
+    (&["N8", "NUMBER_8"], "KC_8"),
+    (&["N9", "NUMBER_9"], "KC_9"),
+    (&["RET", "ENTER", "RETURN"], "KC_ENT"),
+    (&["ESC", "ESCAPE"], "KC_ESC"),
+    (&["BSPC", "BACKSPACE"], "KC_BSPC"),
+    (&["SPACE", "SPC"], "KC_SPC"),
+    (&["MINUS"], "KC_MINS"),
+    (&["EQUAL"], "KC_EQL"),
+    (&["LBKT", "LEFT_BRACKET"], "KC_LBRC"),
+    (&["RBKT", "RIGHT_BRACKET"], "KC_RBRC"),
+    (&["BSLH", "BACKSLASH"], "KC_BSLS"),
+    (&["SEMI", "SEMICOLON"], "KC_SCLN"),
+    (&["SQT", "APOS", "APOSTROPHE", "SINGLE_QUOTE"], "KC_QUOT"),
+    (&["GRAVE"], "KC_GRV"),
+    (&["COMMA"], "KC_COMM"),
+    (&["DOT", "PERIOD"], "KC_DOT"),
+    (&["FSLH", "SLASH"], "KC_SLSH"),
+    (&["CAPS", "CAPSLOCK"], "KC_CAPS"),
+    (&["LEFT", "LEFT_ARROW"], "KC_LEFT"),
+    (&["RIGHT", "RIGHT_ARROW"], "KC_RIGHT"),
+    (&["UP", "UP_ARROW"], "KC_UP"),
+    (&["DOWN", "DOWN_ARROW"], "KC_DOWN"),
+    (&["PG_UP", "PAGE_UP"], "KC_PGUP"),
+    (&["PG_DN", "PAGE_DOWN"], "KC_PGDN"),
+    (&["DEL", "DELETE"], "KC_DEL"),
+    (&["INS", "INSERT"], "KC_INS"),
+    (&["PSCRN", "PRINTSCREEN"], "KC_PSCR"),
+    (&["SLCK", "SCROLLLOCK"], "KC_SCRL"),
+    (&["PAUSE_BREAK"], "KC_PAUSE"),
+    (&["LSHFT", "LSHIFT", "LEFT_SHIFT"], "KC_LSFT"),
+    (&["RSHFT", "RSHIFT", "RIGHT_SHIFT"], "KC_RSFT"),
+    (&["LCTRL", "LEFT_CONTROL"], "KC_LCTL"),
+    (&["RCTRL", "RIGHT_CONTROL"], "KC_RCTL"),
+    (&["LALT", "LEFT_ALT"], "KC_LALT"),
+    (&["RALT", "RIGHT_ALT"], "KC_RALT"),
+    (&["LGUI", "LCMD", "LWIN", "LEFT_GUI"], "KC_LGUI"),
+    (&["RGUI", "RCMD", "RWIN", "RIGHT_GUI"], "KC_RGUI"),
+    (&["EXCL", "EXCLAMATION"], "KC_EXLM"),
+    (&["AT", "AT_SIGN"], "KC_AT"),
+    (&["HASH", "POUND"], "KC_HASH"),
+    (&["DLLR", "DOLLAR"], "KC_DLR"),
+    (&["PRCNT", "PERCENT"], "KC_PERC"),
+    (&["CARET"], "KC_CIRC"),
+    (&["AMPS", "AMPERSAND"], "KC_AMPR"),
+    (&["STAR", "ASTRK", "ASTERISK"], "KC_ASTR"),
+    (&["LPAR", "LEFT_PARENTHESIS"], "KC_LPRN"),
+    (&["RPAR", "RIGHT_PARENTHESIS"], "KC_RPRN"),
+    (&["UNDER", "UNDERSCORE"], "KC_UNDS"),
+    (&["PLUS"], "KC_PLUS"),
+    (&["LBRC", "LEFT_BRACE"], "KC_LCBR"),
+    (&["RBRC", "RIGHT_BRACE"], "KC_RCBR"),
+    (&["PIPE"], "KC_PIPE"),
+    (&["COLON"], "KC_COLN"),
+    (&["DQT", "DOUBLE_QUOTES"], "KC_DQUO"),
+    (&["TILDE"], "KC_TILD"),
+    (&["LT", "LESS_THAN"], "KC_LT"),
+    (&["GT", "GREATER_THAN"], "KC_GT"),
+    (&["QMARK", "QUESTION"], "KC_QUES"),
+    (&["C_VOL_UP", "C_VOLUME_UP", "K_VOL_UP"], "KC_VOLU"),
+    (&["C_VOL_DN", "C_VOLUME_DOWN", "K_VOL_DN"], "KC_VOLD"),
+    (&["C_MUTE", "K_MUTE"], "KC_MUTE"),
+    (&["C_PP", "C_PLAY_PAUSE"], "KC_MPLY"),
+    (&["C_NEXT"], "KC_MNXT"),
+    (&["C_PREV"], "KC_MPRV"),
+    (&["C_BRI_UP", "C_BRIGHTNESS_INC"], "KC_BRIU"),
+    (&["C_BRI_DN", "C_BRIGHTNESS_DEC"], "KC_BRID"),
+];
+
+/// ZMK modifier functions such as `LS(A)` and the QMK modifier wrappers they
+/// match.
+const MODIFIER_FUNCTIONS: [(&str, &str); 8] = [
+    ("LS", "S"),
+    ("LC", "C"),
+    ("LA", "A"),
+    ("LG", "G"),
+    ("RS", "RSFT"),
+    ("RC", "RCTL"),
+    ("RA", "RALT"),
+    ("RG", "RGUI"),
+];
+
+/// Behaviors without parameters and the QMK keycodes they match.
+const PLAIN_BEHAVIORS: [(&str, &str); 8] = [
+    ("trans", "_______"),
+    ("none", "XXXXXXX"),
+    ("bootloader", "QK_BOOT"),
+    ("sys_reset", "QK_RBT"),
+    ("caps_word", "CW_TOGG"),
+    ("key_repeat", "QK_REP"),
+    ("gresc", "QK_GESC"),
+    ("studio_unlock", "XXXXXXX"),
+];
+
+/// Translates a ZMK key name, possibly wrapped in modifier functions such as
+/// `LC(LS(T))`, to a QMK keycode.
+fn keycode(name: &str) -> String {
+    let name = name.trim();
+    if let Some((function, inner)) = name.split_once('(')
+        && let Some(inner) = inner.strip_suffix(')')
+        && let Some((_, wrapper)) = MODIFIER_FUNCTIONS.iter().find(|(f, _)| *f == function)
+    {
+        return format!("{}({})", wrapper, keycode(inner));
+    }
+    KEY_NAMES
+        .iter()
+        .find(|(names, _)| names.contains(&name))
+        .map(|(_, keycode)| keycode.to_string())
+        .unwrap_or_else(|| format!("KC_{}", name))
+}
+
+/// The QMK modifier name of a ZMK modifier key, e.g. `LSFT` for `LSHIFT`.
+fn modifier(name: &str) -> String {
+    keycode(name).trim_start_matches("KC_").to_string()
+}
+
+/// Translates one binding, such as `&lt NAV SPACE`, to a QMK keycode.
+///
+/// Behaviors without a QMK counterpart (Bluetooth profiles, output
+/// selection, RGB underglow, ...) keep their ZMK text so they still show up
+/// on the key.
+fn translate_binding(behavior: &str, params: &[&str], layers: &HashMap<String, usize>) -> String {
+    let layer = |name: &str| {
+        layers
+            .get(name)
+            .map(usize::to_string)
+            .unwrap_or_else(|| name.to_string())
+    };
+    match (behavior, params) {
+        ("kp", [key]) => keycode(key),
+        ("mo", [target]) => format!("MO({})", layer(target)),
+        ("tog", [target]) => format!("TG({})", layer(target)),
+        ("to", [target]) => format!("TO({})", layer(target)),
+        ("sl", [target]) => format!("OSL({})", layer(target)),
+        ("lt", [target, key]) => format!("LT({}, {})", layer(target), keycode(key)),
+        ("mt", [hold, key]) => format!("{}_T({})", modifier(hold), keycode(key)),
+        ("sk", [key]) => format!("OSM(MOD_{})", modifier(key)),
+        ("mkp", [button]) => match *button {
+            "LCLK" | "MB1" => "KC_BTN1".to_string(),
+            "RCLK" | "MB2" => "KC_BTN2".to_string(),
+            "MCLK" | "MB3" => "KC_BTN3".to_string(),
+            other => format!("KC_{}", other),
+        },
+        (behavior, []) => PLAIN_BEHAVIORS
+            .iter()
+            .find(|(name, _)| *name == behavior)
+            .map(|(_, keycode)| keycode.to_string())
+            .unwrap_or_else(|| format!("&{}", behavior)),
+        (behavior, params) => format!("&{} {}", behavior, params.join(" ")),
+    }
+}
+
+/// Splits a `bindings` cell list into its bindings, each a behavior and its
+/// parameters.
+fn split_bindings(cells: &str) -> Vec<(String, Vec<String>)> {
+    cells
+        .split('&')
+        .map(str::split_whitespace)
+        .filter_map(|mut words| {
+            let behavior = words.next()?.to_string();
+            Some((behavior, words.map(str::to_string).collect()))
+        })
+        .collect()
+}
+
+/// The body of the node declaring `compatible = "zmk,keymap"`, without
+/// behaviors or combos declared around it.
+fn keymap_node(content: &str) -> Option<&str> {
+    let compatible = content.find("\"zmk,keymap\"")?;
+    let open = content[..compatible].rfind('{')? + 1;
+    let mut depth = 1;
+    for (offset, c) in content[open..].char_indices() {
+        match c {
+            '{' => depth += 1,
+            '}' => depth -= 1,
+            _ => {}
+        }
+        if depth == 0 {
+            return Some(&content[open..open + offset]);
+        }
+    }
+    None
+}
+
+/// Parses a ZMK `.keymap` devicetree file into the same layers
+/// [`crate::parse_layers`] reads from a keymap.c.
+///
+/// Every child of the `zmk,keymap` node with `bindings` is a layer, in
+/// declaration order, named by its `display-name` (or `label`) or else its
+/// node name. Behaviors are translated to the QMK keycodes they match:
+/// `&kp`, `&mo`, `&lt`, `&mt`, `&tog`, `&to`, `&sl`, `&sk`, `&mkp`, `&trans`,
+/// `&none`, and a few system behaviors. Layer parameters may be numbers,
+/// `#define`d names, or layer node names. Keys are split into the rows of
+/// the built-in board with the same number of keys, and `sensor-bindings`
+/// become encoder bindings.
+///
+/// # Arguments
+///
+/// * `content` - The .keymap contents
+///
+/// # Returns
+///
+/// The layers in order, or an error if there is no keymap node with layers
+pub fn parse_zmk_keymap(content: &str) -> Result<Vec<Layer>> {
+    let content = Regex::new(r"(?s)/\*.*?\*/")
+        .unwrap()
+        .replace_all(content, "");
+    let content = Regex::new(r"//[^\n]*").unwrap().replace_all(&content, "");
+
+    let Some(keymap) = keymap_node(&content) else {
+        bail!("No keymap node with compatible = \"zmk,keymap\" found");
+    };
+    let layer_regex = Regex::new(r"(?s)([\w-]+)\s*\{([^{}]*)\}").unwrap();
+    let bindings_regex = Regex::new(r"(?s)(?:^|[\s;])bindings\s*=\s*<([^>]*)>").unwrap();
+    let sensor_regex = Regex::new(r"(?s)sensor-bindings\s*=\s*<([^>]*)>").unwrap();
+    let name_regex = Regex::new(r#"(?:display-name|label)\s*=\s*"([^"]*)""#).unwrap();
+
+    // Layer nodes: children of the keymap node that have bindings
+    let nodes: Vec<(String, String)> = layer_regex
+        .captures_iter(keymap)
+        .filter(|caps| bindings_regex.is_match(&caps[2]))
+        .map(|caps| (caps[1].to_string(), caps[2].to_string()))
+        .collect();
+    if nodes.is_empty() {
+        bail!("The ZMK keymap has no layers");
+    }
+
+    // Layer parameters may name a layer by define or by node name
+    let mut layer_numbers: HashMap<String, usize> = Regex::new(r"(?m)^\s*#define\s+(\w+)\s+(\d+)")
+        .unwrap()
+        .captures_iter(&content)
+        .filter_map(|caps| Some((caps[1].to_string(), caps[2].parse().ok()?)))
+        .collect();
+    for (index, (node, _)) in nodes.iter().enumerate() {
+        layer_numbers.entry(node.clone()).or_insert(index);
+    }
+
+    let translate = |cells: &str| -> Vec<String> {
+        split_bindings(cells)
+            .iter()
+            .map(|(behavior, params)| {
+                let params: Vec<&str> = params.iter().map(String::as_str).collect();
+                translate_binding(behavior, &params, &layer_numbers)
+            })
+            .collect()
+    };
+
+    Ok(nodes
+        .iter()
+        .enumerate()
+        .map(|(index, (node, body))| {
+            let keys = translate(&bindings_regex.captures(body).unwrap()[1]);
+            let name = name_regex
+                .captures(body)
+                .map(|caps| caps[1].to_string())
+                .unwrap_or_else(|| node.trim_end_matches("_layer").to_string());
+            // ZMK lists the clockwise binding of a sensor first
+            let encoders = sensor_regex
+                .captures(body)
+                .map(|caps| {
+                    split_bindings(&caps[1])
+                        .into_iter()
+                        .filter(|(behavior, _)| behavior == "inc_dec_kp")
+                        .filter_map(|(_, params)| match params.as_slice() {
+                            [cw, ccw] => Some(EncoderBinding {
+                                ccw: keycode(ccw),
+                                cw: keycode(cw),
+                            }),
+                            _ => None,
+                        })
+                        .collect()
+                })
+                .unwrap_or_default();
+            Layer {
+                index,
+                name: Some(name),
+                title: None,
+                keys: split_rows(keys, None),
+                notes: Vec::new(),
+                layout_macro: None,
+                source: None,
+                encoders,
+            }
+        })
+        .collect())
+}
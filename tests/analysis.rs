@@ -0,0 +1,94 @@
+//! Checks of the static analyses over parsed layers.
+use keyball44_viz::Geometry;
+use keyball44_viz::analysis::{
+    EdgeKind, Similarity, find_duplicate_layers, layer_alt_text, layer_edges, unreachable_layers,
+};
+use keyball44_viz::emit::Keymap;
+
+mod common;
+use common::parse_keymap;
+
+#[test]
+fn complementary_sparse_layers_are_not_duplicates() {
+    let layers = parse_keymap(&[
+        "KC_A, KC_B, KC_C, KC_D",
+        "KC_1, KC_2, _______, _______",
+        "_______, _______, KC_3, KC_4",
+    ]);
+    assert_eq!(find_duplicate_layers(&layers), vec![]);
+}
+
+#[test]
+fn a_layer_contained_in_another_is_a_duplicate() {
+    let layers = parse_keymap(&[
+        "KC_A, KC_B, KC_C, KC_D",
+        "KC_1, KC_2, KC_3, _______",
+        "KC_1, _______, KC_3, _______",
+    ]);
+    let duplicates = find_duplicate_layers(&layers);
+    assert_eq!(duplicates.len(), 1);
+    assert_eq!((duplicates[0].layer, duplicates[0].original), (2, 1));
+    assert_eq!(
+        duplicates[0].similarity,
+        Similarity::IdenticalIgnoringTransparent
+    );
+}
+
+#[test]
+fn identical_layers_are_duplicates() {
+    let layers = parse_keymap(&["KC_A, KC_B", "KC_A, KC_B"]);
+    let duplicates = find_duplicate_layers(&layers);
+    assert_eq!(duplicates.len(), 1);
+    assert_eq!(duplicates[0].similarity, Similarity::Identical);
+}
+
+#[test]
+fn numbered_rows_are_described_without_an_article() {
+    let layers = parse_keymap(&[
+        "KC_A, KC_B, KC_C, KC_D, KC_E, KC_F, KC_G, KC_H, KC_I, KC_J",
+        "_______, _______, KC_LEFT, KC_DOWN, _______, _______, _______, _______, _______, _______",
+    ]);
+    let alt = layer_alt_text(&layers[1], &Geometry::ortho(&[2, 2, 2, 2, 2]));
+    assert!(!alt.contains("the row"), "{}", alt);
+    assert!(alt.contains("arrows on row 2;"), "{}", alt);
+}
+
+#[test]
+fn reachability_ignores_self_loops() {
+    let layers = parse_keymap(&[
+        "KC_A, MO(1), KC_B",
+        "QK_LLCK, MO(1), TO(0)",
+        "TG(2), LLOCK, _______",
+    ]);
+    let edges: Vec<(usize, usize, EdgeKind)> = layer_edges(&layers)
+        .iter()
+        .map(|edge| (edge.from, edge.to, edge.kind))
+        .collect();
+    assert_eq!(
+        edges,
+        vec![
+            (0, 1, EdgeKind::Momentary),
+            (1, 1, EdgeKind::Lock),
+            (1, 1, EdgeKind::Momentary),
+            (1, 0, EdgeKind::Switch),
+            (2, 2, EdgeKind::Toggle),
+            (2, 2, EdgeKind::Lock),
+        ]
+    );
+    // Layer 2 only activates itself, so nothing reaches it
+    assert_eq!(unreachable_layers(&layers), vec![2]);
+}
+
+#[test]
+fn layer_mod_edges_keep_their_modifier() {
+    let layers = parse_keymap(&["KC_A, LM(1, MOD_LSFT), MO(1)", "KC_B, _______, _______"]);
+    let edges = layer_edges(&layers);
+    assert_eq!(edges[0].modifier.as_deref(), Some("MOD_LSFT"));
+    assert_eq!(edges[0].label(), "momentary + MOD_LSFT");
+    assert_eq!(edges[1].modifier, None);
+
+    let json = serde_json::to_value(Keymap::new(&layers, &Geometry::ortho(&[3]))).unwrap();
+    assert_eq!(json["edges"][0]["modifier"], "MOD_LSFT");
+    assert_eq!(json["edges"][0]["label"], "momentary + MOD_LSFT");
+    assert!(json["edges"][1].get("modifier").is_none());
+}
@@ -0,0 +1,50 @@
+//! Keymaps and render options shared by the integration tests and the render
+//! benchmarks, which include this file by path. Each of them uses only some of
+//! these.
+#![allow(dead_code)]
+
+use keyball44_viz::{Layer, RenderOptions, geometry, oryx, parse_layers};
+
+/// The demo keymap: six Keyball44 layers.
+pub const DEMO_KEYMAP: &str = include_str!("../../demo/keymap.c");
+
+/// A keymap.c with one LAYOUT block per layer, each given as its keys.
+pub fn keymap(layers: &[&str]) -> String {
+    let blocks: Vec<String> = layers
+        .iter()
+        .enumerate()
+        .map(|(index, keys)| format!("    [{}] = LAYOUT(\n        {}\n    )", index, keys))
+        .collect();
+    format!(
+        "const uint16_t PROGMEM keymaps[][MATRIX_ROWS][MATRIX_COLS] = {{\n{}\n}};\n",
+        blocks.join(",\n")
+    )
+}
+
+/// The layers of a keymap with one layer per entry of `layers`, each given
+/// as its keys.
+pub fn parse_keymap(layers: &[&str]) -> Vec<Layer> {
+    parse_layers(&keymap(layers)).expect("the keymap parses")
+}
+
+/// A keymap.c with `count` layers, repeating the demo keymap's layers.
+pub fn large_keymap(count: usize) -> String {
+    let demo = parse_layers(DEMO_KEYMAP).expect("demo keymap parses");
+    let layers: Vec<Layer> = (0..count)
+        .map(|index| Layer {
+            index,
+            ..demo[index % demo.len()].clone()
+        })
+        .collect();
+    oryx::keymap_source(&layers, "Generated keymap")
+}
+
+/// Render options for `layers` with the detected board, as the CLI builds them.
+pub fn options_for(layers: &[Layer]) -> RenderOptions {
+    RenderOptions {
+        geometry: geometry::detect(layers)
+            .map(|detection| detection.geometry)
+            .unwrap_or_else(|| geometry::fallback(layers)),
+        ..RenderOptions::default()
+    }
+}
@@ -0,0 +1,146 @@
+//! Regression checks against real-world keymaps: each must parse, match its
+//! board's key count, and render to an SVG that rasterizes.
+//!
+//! The demo keymap is always checked. Community keymaps are read from a
+//! directory of cloned keymap repositories named by `KEYMAP_CORPUS`, and only
+//! run when ignored tests are asked for:
+//!
+//! ```sh
+//! git clone --depth 1 https://github.com/Yowkees/keyball target/keymap-corpus/keyball
+//! KEYMAP_CORPUS=target/keymap-corpus cargo test --test community_keymaps -- --ignored
+//! ```
+//!
+//! An optional `expected.toml` at the top of the directory pins the number of
+//! layers of keymaps by their path relative to it, e.g.
+//! `"keyball/qmk_firmware/keyboards/keyball/keyball44/keymaps/default/keymap.c" = 4`.
+use keyball44_viz::{
+    Geometry, RenderOptions, generate_svg_with_options, geometry, parse_layers, raster,
+};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+const DEMO_KEYMAP: &str = include_str!("../demo/keymap.c");
+
+/// Environment variable naming the directory of community keymaps.
+const CORPUS_VAR: &str = "KEYMAP_CORPUS";
+
+/// Parses and renders one keymap.
+///
+/// # Returns
+///
+/// The number of layers, or a description of the first problem
+fn check_keymap(source: &str) -> Result<usize, String> {
+    let layers = parse_layers(source).map_err(|err| format!("parse failed: {:#}", err))?;
+    if layers.is_empty() {
+        return Err("no layers".to_string());
+    }
+
+    // Keymaps for boards without a built-in geometry are drawn on a grid
+    let geometry: Geometry = match geometry::detect(&layers) {
+        Some(detection) => {
+            let expected = detection.geometry.key_count();
+            for layer in &layers {
+                let count: usize = layer.keys.iter().map(Vec::len).sum();
+                if count != expected {
+                    return Err(format!(
+                        "layer {} has {} keys, {} has {}",
+                        layer.index, count, detection.geometry.name, expected
+                    ));
+                }
+            }
+            detection.geometry
+        }
+        None => geometry::fallback(&layers),
+    };
+    geometry
+        .validate()
+        .map_err(|err| format!("invalid geometry: {:#}", err))?;
+
+    let options = RenderOptions {
+        geometry,
+        ..RenderOptions::default()
+    };
+    let svg = generate_svg_with_options(&layers, &options);
+    raster::svg_to_png(&svg, 0.25).map_err(|err| format!("render failed: {:#}", err))?;
+    Ok(layers.len())
+}
+
+/// Every `keymap.c` below `dir`, sorted by path.
+fn find_keymaps(dir: &Path) -> Vec<PathBuf> {
+    let mut keymaps = Vec::new();
+    let Ok(entries) = fs::read_dir(dir) else {
+        return keymaps;
+    };
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if path.is_dir() {
+            keymaps.extend(find_keymaps(&path));
+        } else if path.file_name().is_some_and(|name| name == "keymap.c") {
+            keymaps.push(path);
+        }
+    }
+    keymaps.sort();
+    keymaps
+}
+
+#[test]
+fn demo_keymap_parses_and_renders() {
+    assert_eq!(check_keymap(DEMO_KEYMAP), Ok(6));
+}
+
+#[test]
+#[ignore = "needs a directory of community keymaps named by KEYMAP_CORPUS"]
+fn community_keymaps_parse_and_render() {
+    let corpus = PathBuf::from(
+        std::env::var(CORPUS_VAR)
+            .unwrap_or_else(|_| panic!("set {} to a directory of keymaps", CORPUS_VAR)),
+    );
+    let expected: BTreeMap<String, usize> = match fs::read_to_string(corpus.join("expected.toml")) {
+        Ok(content) => toml::from_str(&content).expect("expected.toml maps paths to layer counts"),
+        Err(_) => BTreeMap::new(),
+    };
+
+    let keymaps = find_keymaps(&corpus);
+    assert!(
+        !keymaps.is_empty(),
+        "no keymap.c files below {}",
+        corpus.display()
+    );
+
+    let mut failures = Vec::new();
+    for path in &keymaps {
+        let relative = path
+            .strip_prefix(&corpus)
+            .unwrap_or(path)
+            .to_string_lossy()
+            .replace('\\', "/");
+        let Ok(source) = fs::read_to_string(path) else {
+            failures.push(format!("{}: not UTF-8", relative));
+            continue;
+        };
+        match check_keymap(&source) {
+            Ok(count) => {
+                if let Some(&want) = expected.get(&relative)
+                    && count != want
+                {
+                    failures.push(format!("{}: {} layers, expected {}", relative, count, want));
+                }
+            }
+            Err(problem) => failures.push(format!("{}: {}", relative, problem)),
+        }
+    }
+    for path in expected.keys() {
+        if !corpus.join(path).is_file() {
+            failures.push(format!("{}: listed in expected.toml but missing", path));
+        }
+    }
+
+    assert!(
+        failures.is_empty(),
+        "{} of {} keymaps failed:\n{}",
+        failures.len(),
+        keymaps.len(),
+        failures.join("\n")
+    );
+}
@@ -0,0 +1,95 @@
+//! Checks of keymap comparisons and the overlay drawing them.
+use keyball44_viz::diff::{ChangeKind, LayerChange, diff_layers, layer_changes};
+use keyball44_viz::{RenderOptions, diff_overlay, generate_svg_with_options, parse_layers};
+
+mod common;
+use common::parse_keymap;
+
+#[test]
+fn changed_arguments_are_drawn() {
+    let old = parse_keymap(&["LT(2, KC_SPC), KC_A"]);
+    let new = parse_keymap(&["LT(3, KC_SPC), KC_B"]);
+    let changes = diff_layers(&old, &new);
+    assert_eq!(changes[0].details, vec!["hold layer changed 2→3"]);
+
+    let options = RenderOptions {
+        decorate: Some(diff_overlay(changes)),
+        ..RenderOptions::default()
+    };
+    let svg = generate_svg_with_options(&new, &options);
+    assert!(
+        svg.contains("class=\"key-diff key-diff-changed key-diff-argument\""),
+        "the argument change is dashed"
+    );
+    assert!(svg.contains("LT(3, KC_SPC)\nhold layer changed 2→3"));
+    assert!(
+        svg.contains("class=\"key-diff key-diff-changed\""),
+        "the whole-key change is not dashed"
+    );
+}
+
+#[test]
+fn unchanged_keymaps_have_no_changes() {
+    let layers = parse_keymap(&["KC_A, LT(1, KC_SPC)", "KC_1, _______"]);
+    let respaced = parse_keymap(&["KC_A, LT(1,KC_SPC)", "KC_1, _______"]);
+    assert_eq!(diff_layers(&layers, &respaced), vec![]);
+    assert_eq!(layer_changes(&layers, &respaced), vec![]);
+}
+
+#[test]
+fn keys_past_the_end_of_a_shorter_layer_are_added_or_removed() {
+    let old = parse_keymap(&["KC_A, KC_B"]);
+    let new = parse_keymap(&["KC_A, KC_B, KC_C"]);
+    let changes = diff_layers(&old, &new);
+    assert_eq!(changes.len(), 1);
+    assert_eq!(
+        (changes[0].position, changes[0].kind()),
+        (2, ChangeKind::Added)
+    );
+
+    let changes = diff_layers(&new, &old);
+    assert_eq!(changes.len(), 1);
+    assert_eq!(changes[0].kind(), ChangeKind::Removed);
+}
+
+#[test]
+fn a_layer_inserted_in_the_middle_is_added_as_a_whole() {
+    let keymap = |layers: &[(&str, &str)]| {
+        let names: Vec<&str> = layers.iter().map(|(name, _)| *name).collect();
+        let blocks: Vec<String> = layers
+            .iter()
+            .map(|(name, keys)| format!("    [{}] = LAYOUT(\n        {}\n    )", name, keys))
+            .collect();
+        let source = format!(
+            "enum layers {{ {} }};\n\
+             const uint16_t PROGMEM keymaps[][MATRIX_ROWS][MATRIX_COLS] = {{\n{}\n}};\n",
+            names.join(", "),
+            blocks.join(",\n")
+        );
+        parse_layers(&source).expect("the keymap parses")
+    };
+    let old = keymap(&[("_BASE", "KC_A, MO(_NAV)"), ("_NAV", "KC_LEFT, KC_RGHT")]);
+    let new = keymap(&[
+        ("_BASE", "KC_A, MO(_NAV)"),
+        ("_NUM", "KC_1, KC_2"),
+        ("_NAV", "KC_LEFT, KC_DOWN"),
+    ]);
+
+    let changes = diff_layers(&old, &new);
+    assert_eq!(
+        changes.len(),
+        1,
+        "only the changed key is reported: {:?}",
+        changes
+    );
+    assert_eq!((changes[0].layer, changes[0].position), (2, 1));
+    assert_eq!(
+        layer_changes(&old, &new),
+        vec![LayerChange {
+            layer: 1,
+            name: Some("_NUM".to_string()),
+            kind: ChangeKind::Added,
+        }]
+    );
+    assert_eq!(layer_changes(&new, &old)[0].kind, ChangeKind::Removed);
+}
@@ -0,0 +1,98 @@
+//! Checks of keymaps declaring an `encoder_map` array alongside `keymaps`.
+use keyball44_viz::encoder::EncoderBinding;
+use keyball44_viz::{Layer, parse_layers};
+
+const KEYMAPS: &str = "\
+const uint16_t PROGMEM keymaps[][MATRIX_ROWS][MATRIX_COLS] = {
+    [_BASE] = LAYOUT(
+        KC_A, KC_B, MO(_NAV)
+    ),
+    [_NAV] = LAYOUT(
+        KC_1, KC_2, _______
+    )
+};
+";
+
+const ENCODER_MAP: &str = "\
+#if defined(ENCODER_MAP_ENABLE)
+const uint16_t PROGMEM encoder_map[][NUM_ENCODERS][NUM_DIRECTIONS] = {
+    [_NAV] = { ENCODER_CCW_CW(KC_MPRV, KC_MNXT), ENCODER_CCW_CW(KC_PGUP, KC_PGDN) },
+    [_BASE] = { ENCODER_CCW_CW(KC_VOLD, KC_VOLU), ENCODER_CCW_CW(C(KC_Z), C(KC_Y)) },
+};
+#endif
+";
+
+fn binding(ccw: &str, cw: &str) -> EncoderBinding {
+    EncoderBinding {
+        ccw: ccw.to_string(),
+        cw: cw.to_string(),
+    }
+}
+
+/// Checks the layers of `KEYMAPS`, with the bindings of `ENCODER_MAP`.
+fn assert_layers(layers: &[Layer]) {
+    let keys: Vec<Vec<String>> = layers
+        .iter()
+        .map(|layer| layer.keys.iter().flatten().cloned().collect())
+        .collect();
+    assert_eq!(
+        keys,
+        vec![
+            vec!["KC_A", "KC_B", "MO(_NAV)"],
+            vec!["KC_1", "KC_2", "_______"]
+        ]
+    );
+    assert_eq!(
+        layers[0].encoders,
+        vec![binding("KC_VOLD", "KC_VOLU"), binding("C(KC_Z)", "C(KC_Y)")]
+    );
+    assert_eq!(
+        layers[1].encoders,
+        vec![binding("KC_MPRV", "KC_MNXT"), binding("KC_PGUP", "KC_PGDN")]
+    );
+}
+
+#[test]
+fn an_encoder_map_after_the_keymaps_is_not_a_layer() {
+    let source = format!(
+        "enum layers {{ _BASE, _NAV }};\n{}\n{}",
+        KEYMAPS, ENCODER_MAP
+    );
+    assert_layers(&parse_layers(&source).expect("the keymap parses"));
+}
+
+#[test]
+fn an_encoder_map_before_the_keymaps_is_not_a_layer() {
+    let source = format!(
+        "enum layers {{ _BASE, _NAV }};\n{}\n{}",
+        ENCODER_MAP, KEYMAPS
+    );
+    assert_layers(&parse_layers(&source).expect("the keymap parses"));
+}
+
+#[test]
+fn arrays_named_like_keymaps_are_skipped() {
+    let backup = KEYMAPS
+        .replace("keymaps[]", "keymaps_backup[]")
+        .replace("KC_", "KC_X");
+    let source = format!(
+        "enum layers {{ _BASE, _NAV }};\n{}\n{}\n{}",
+        backup, KEYMAPS, ENCODER_MAP
+    );
+    assert_layers(&parse_layers(&source).expect("the keymap parses"));
+}
+
+#[test]
+fn undesignated_encoder_entries_follow_layer_order() {
+    let source = format!(
+        "{}\n\
+         const uint16_t PROGMEM encoder_map[][NUM_ENCODERS][NUM_DIRECTIONS] = {{\n\
+         \x20   {{ ENCODER_CCW_CW(KC_VOLD, KC_VOLU) }},\n\
+         \x20   {{ ENCODER_CCW_CW(KC_MPRV, KC_MNXT) }},\n\
+         }};\n",
+        KEYMAPS.replace("_BASE", "0").replace("[_NAV]", "[1]")
+    );
+    let layers = parse_layers(&source).expect("the keymap parses");
+    assert_eq!(layers[0].encoders, vec![binding("KC_VOLD", "KC_VOLU")]);
+    assert_eq!(layers[1].encoders, vec![binding("KC_MPRV", "KC_MNXT")]);
+}
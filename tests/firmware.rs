@@ -0,0 +1,60 @@
+//! Checks of the keymap table search in raw firmware images.
+use keyball44_viz::firmware::extract_layers;
+
+/// Keycodes per layer in the Keyball44 matrix.
+const MATRIX_SIZE: usize = 48;
+/// Matrix slots without a switch, always `KC_NO` in a keymap table.
+const HOLES: [usize; 2] = [18, 44];
+
+/// A matrix block of letters and numbers, as a base layer has.
+fn base_block() -> Vec<u16> {
+    (0..MATRIX_SIZE)
+        .map(|idx| {
+            if HOLES.contains(&idx) {
+                0
+            } else {
+                0x04 + (idx % 36) as u16
+            }
+        })
+        .collect()
+}
+
+/// A matrix block of transparent keys.
+fn transparent_block() -> Vec<u16> {
+    (0..MATRIX_SIZE)
+        .map(|idx| if HOLES.contains(&idx) { 0 } else { 0x01 })
+        .collect()
+}
+
+/// A raw image of erased flash followed by the blocks, ending with them.
+fn image(blocks: &[Vec<u16>]) -> Vec<u8> {
+    std::iter::repeat_n(0xFFFF, 7)
+        .chain(blocks.iter().flatten().copied())
+        .flat_map(u16::to_le_bytes)
+        .collect()
+}
+
+#[test]
+fn a_table_ending_the_image_is_found() {
+    let layers =
+        extract_layers(&image(&[base_block(), transparent_block()])).expect("the table is found");
+    assert_eq!(layers.len(), 2);
+    assert_eq!(layers[0].keys[0][0], "KC_A");
+    assert_eq!(layers[1].keys[0][0], "_______");
+
+    let layers = extract_layers(&image(&[base_block()])).expect("the table is found");
+    assert_eq!(layers.len(), 1);
+}
+
+#[test]
+fn a_base_layer_that_does_not_decode_is_an_error() {
+    let mut block = base_block();
+    block[0] = 0xFFFF;
+    assert!(extract_layers(&image(&[block])).is_err());
+}
+
+#[test]
+fn images_without_a_table_are_an_error() {
+    assert!(extract_layers(&image(&[transparent_block()])).is_err());
+    assert!(extract_layers(&image(&[])).is_err());
+}
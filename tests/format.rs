@@ -0,0 +1,77 @@
+//! Checks of `fmt`: formatting must keep every layer and key of a keymap,
+//! whatever the layout of its LAYOUT blocks.
+use keyball44_viz::format::format_layouts;
+use keyball44_viz::{Geometry, parse_layers};
+
+/// A Corne keymap of two layers whose LAYOUT blocks close with `)` right
+/// after their last key, as many keymaps generated by configurators do.
+fn keymap_closing_on_key_lines() -> String {
+    let layer = |prefix: &str| {
+        let rows: Vec<String> = [6, 6, 6, 6, 6, 6, 3, 3]
+            .iter()
+            .scan(0, |count, &len| {
+                let keys: Vec<String> = (*count..*count + len)
+                    .map(|i| format!("{}{}", prefix, i))
+                    .collect();
+                *count += len;
+                Some(keys.join(", "))
+            })
+            .collect();
+        format!("        {})", rows.join(",\n        "))
+    };
+    format!(
+        "const uint16_t PROGMEM keymaps[][MATRIX_ROWS][MATRIX_COLS] = {{\n\
+         \x20   [0] = LAYOUT_split_3x6_3(\n{},\n\
+         \x20   [1] = LAYOUT_split_3x6_3(\n{}\n\
+         }};\n",
+        layer("KC_A"),
+        layer("KC_B")
+    )
+}
+
+#[test]
+fn formatting_keeps_blocks_closed_on_a_key_line() {
+    let source = keymap_closing_on_key_lines();
+    let layers = parse_layers(&source).expect("the keymap parses");
+    assert_eq!(layers.len(), 2);
+
+    let formatted = format_layouts(&source, &Geometry::corne()).expect("formatting succeeds");
+    assert_eq!(formatted.formatted_blocks, 2);
+    assert!(
+        formatted
+            .content
+            .contains("    [1] = LAYOUT_split_3x6_3(\n"),
+        "the second LAYOUT line is kept:\n{}",
+        formatted.content
+    );
+
+    let reparsed = parse_layers(&formatted.content).expect("the formatted keymap parses");
+    let keys = |layers: &[keyball44_viz::Layer]| -> Vec<Vec<String>> {
+        layers
+            .iter()
+            .map(|layer| layer.keys.iter().flatten().cloned().collect())
+            .collect()
+    };
+    assert_eq!(keys(&reparsed), keys(&layers));
+}
+
+#[test]
+fn formatting_is_idempotent() {
+    let source = keymap_closing_on_key_lines();
+    let once = format_layouts(&source, &Geometry::corne())
+        .expect("formatting succeeds")
+        .content;
+    let twice = format_layouts(&once, &Geometry::corne())
+        .expect("formatting succeeds")
+        .content;
+    assert_eq!(once, twice);
+}
+
+#[test]
+fn blocks_with_block_comments_are_left_as_written() {
+    let source = keymap_closing_on_key_lines().replacen("KC_A1,", "KC_A1, /* hold :) */", 1);
+    let formatted = format_layouts(&source, &Geometry::corne()).expect("formatting succeeds");
+    assert_eq!(formatted.skipped_blocks, vec![0]);
+    assert_eq!(formatted.formatted_blocks, 1);
+    assert!(formatted.content.contains("KC_A1, /* hold :) */ KC_A2"));
+}
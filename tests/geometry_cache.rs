@@ -0,0 +1,83 @@
+//! Checks of the cache of geometries read from QMK info.json files.
+use keyball44_viz::geometry_cache::{GeometryCache, info_json_in};
+use std::fs;
+use std::path::PathBuf;
+
+const INFO_JSON: &str = r#"{
+    "keyboard_name": "test",
+    "layouts": {
+        "LAYOUT": {
+            "layout": [{"x": 0, "y": 0}, {"x": 1, "y": 0}, {"x": 3, "y": 0}, {"x": 4, "y": 0}]
+        }
+    }
+}"#;
+
+/// A scratch QMK tree with a keyboard `test` whose revision `rev1` shares the
+/// keyboard's layouts, and an empty cache directory next to it.
+fn scratch_tree(name: &str) -> (PathBuf, PathBuf) {
+    let root = std::env::temp_dir().join(format!("keyball44-viz-{}-{}", name, std::process::id()));
+    let _ = fs::remove_dir_all(&root);
+    let revision = root.join("keyboards").join("test").join("rev1");
+    fs::create_dir_all(&revision).expect("the tree is created");
+    fs::write(revision.join("keyboard.json"), r#"{"usb": {}}"#).expect("rev1 is written");
+    fs::write(root.join("keyboards/test/info.json"), INFO_JSON).expect("info.json is written");
+    (revision, root.join("cache"))
+}
+
+#[test]
+fn keyboard_directories_use_the_layouts_of_their_parents() {
+    let (revision, _) = scratch_tree("lookup");
+    let file = info_json_in(&revision).expect("the layouts are found");
+    assert!(file.ends_with("keyboards/test/info.json"));
+}
+
+#[test]
+fn geometries_are_read_from_the_cache_until_refreshed() {
+    let (revision, cache_dir) = scratch_tree("cache");
+    let geometry = GeometryCache::at(cache_dir.clone(), false)
+        .load(&revision, Some("LAYOUT"))
+        .expect("the geometry loads");
+    assert_eq!(geometry.key_count(), 4);
+
+    let entries: Vec<PathBuf> = fs::read_dir(&cache_dir)
+        .expect("the cache is written")
+        .map(|entry| entry.expect("the entry is readable").path())
+        .collect();
+    assert_eq!(entries.len(), 1);
+    let entry = &entries[0];
+    assert!(
+        entry
+            .file_name()
+            .is_some_and(|name| name.to_string_lossy().starts_with("test@")),
+        "the entry is named after the keyboard: {:?}",
+        entry
+    );
+
+    // Tag the cached geometry to tell cache hits from parses
+    let mut cached: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string(entry).unwrap()).unwrap();
+    cached["name"] = "cached".into();
+    fs::write(entry, cached.to_string()).unwrap();
+
+    let hit = GeometryCache::at(cache_dir.clone(), false)
+        .load(&revision, Some("LAYOUT"))
+        .expect("the geometry loads");
+    assert_eq!(hit.name, "cached");
+
+    let refreshed = GeometryCache::at(cache_dir, true)
+        .load(&revision, Some("LAYOUT"))
+        .expect("the geometry loads");
+    assert_eq!(refreshed.name, geometry.name);
+}
+
+#[test]
+fn edited_files_are_parsed_again() {
+    let (revision, cache_dir) = scratch_tree("edit");
+    let cache = GeometryCache::at(cache_dir, false);
+    cache.load(&revision, None).expect("the geometry loads");
+
+    let file = info_json_in(&revision).unwrap();
+    fs::write(&file, INFO_JSON.replace(r#", {"x": 4, "y": 0}"#, "")).unwrap();
+    let edited = cache.load(&revision, None).expect("the geometry loads");
+    assert_eq!(edited.key_count(), 3);
+}
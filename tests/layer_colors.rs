@@ -0,0 +1,66 @@
+//! Checks of layer colors: a layer's color must not depend on where its
+//! layer sits in the keymap, and every layer key gets one.
+use keyball44_viz::{
+    PALETTE_SIZE, RenderOptions, generate_svg_with_options, layer_color_slots, parse_layers,
+};
+use std::collections::HashMap;
+
+mod common;
+use common::parse_keymap;
+
+/// The palette slot of each layer of a keymap whose layers are named `names`,
+/// keyed by name.
+fn slots_by_name(names: &[&str]) -> HashMap<String, usize> {
+    let layers: Vec<String> = names
+        .iter()
+        .map(|name| format!("    [{}] = LAYOUT(\n        KC_A, KC_B\n    )", name))
+        .collect();
+    let source = format!(
+        "enum layers {{ {} }};\n\
+         const uint16_t PROGMEM keymaps[][MATRIX_ROWS][MATRIX_COLS] = {{\n{}\n}};\n",
+        names.join(", "),
+        layers.join(",\n")
+    );
+    let layers = parse_layers(&source).expect("the keymap parses");
+    let slots = layer_color_slots(&layers, &HashMap::new());
+    layers
+        .iter()
+        .filter(|layer| layer.index != 0)
+        .map(|layer| (layer.name.clone().unwrap(), slots[&layer.index]))
+        .collect()
+}
+
+#[test]
+fn reordering_named_layers_keeps_their_colors() {
+    let names = [
+        "_BASE", "_NAV", "_SYM", "_NUM", "_FN", "_MOUSE", "_MEDIA", "_GAME",
+    ];
+    let reordered = [
+        "_BASE", "_GAME", "_MEDIA", "_MOUSE", "_FN", "_NUM", "_SYM", "_NAV",
+    ];
+    assert_eq!(slots_by_name(&names), slots_by_name(&reordered));
+}
+
+#[test]
+fn unnamed_layers_past_the_palette_wrap_around() {
+    let layers = parse_keymap(&["KC_A, KC_B"; PALETTE_SIZE + 2]);
+    let slots = layer_color_slots(&layers, &HashMap::new());
+    assert_eq!(slots[&1], 1);
+    assert_eq!(slots[&PALETTE_SIZE], PALETTE_SIZE);
+    assert_eq!(slots[&(PALETTE_SIZE + 1)], 1);
+}
+
+#[test]
+fn keys_targeting_undefined_layers_take_a_palette_color() {
+    let layers = parse_keymap(&["KC_A, MO(1), MO(12)", "KC_B, _______, _______"]);
+    let svg = generate_svg_with_options(&layers, &RenderOptions::default());
+    assert!(svg.contains("class=\"key key-layer1\""), "MO(1) is colored");
+    assert!(
+        svg.contains("class=\"key key-layer4\""),
+        "MO(12) wraps to the fourth color"
+    );
+    assert!(
+        !svg.contains("key-layer12"),
+        "no class without a color rule"
+    );
+}
@@ -0,0 +1,153 @@
+//! Checks of the limits `LayoutMetrics::validate` puts on sizes and gaps, and
+//! of renders at those limits.
+use keyball44_viz::{LayoutMetrics, RenderOptions, generate_svg_with_options, parse_layers};
+use regex::Regex;
+use resvg::usvg;
+
+mod common;
+use common::DEMO_KEYMAP;
+
+/// A metric by its name in `BOUNDS`.
+fn field<'a>(metrics: &'a mut LayoutMetrics, name: &str) -> &'a mut f32 {
+    match name {
+        "key-size" => &mut metrics.key_size,
+        "spacing" => &mut metrics.spacing,
+        "split-gap" => &mut metrics.split_gap,
+        "layer-spacing" => &mut metrics.layer_spacing,
+        "margin" => &mut metrics.margin,
+        _ => unreachable!("unknown metric {}", name),
+    }
+}
+
+/// The default metrics with one of them set by name.
+fn with(name: &str, value: f32) -> LayoutMetrics {
+    with_metric(LayoutMetrics::default(), name, value)
+}
+
+#[test]
+fn the_defaults_are_valid() {
+    LayoutMetrics::default()
+        .validate()
+        .expect("the defaults are valid");
+}
+
+#[test]
+fn the_bounds_themselves_are_valid() {
+    for (name, min, max) in LayoutMetrics::BOUNDS {
+        for value in [min, max] {
+            with(name, value)
+                .validate()
+                .unwrap_or_else(|err| panic!("{} = {} is rejected: {}", name, value, err));
+        }
+    }
+}
+
+#[test]
+fn values_beyond_the_bounds_are_rejected() {
+    for (name, min, max) in LayoutMetrics::BOUNDS {
+        for value in [min - 0.5, max + 0.5, -1.0e30, 1.0e30, f32::MIN, f32::MAX] {
+            let err = with(name, value)
+                .validate()
+                .expect_err(&format!("{} = {} is rejected", name, value));
+            assert!(
+                err.to_string()
+                    .starts_with(&format!("{} must be between", name)),
+                "unexpected error: {}",
+                err
+            );
+        }
+    }
+}
+
+#[test]
+fn non_finite_values_are_rejected() {
+    for (name, _, _) in LayoutMetrics::BOUNDS {
+        for value in [f32::NAN, f32::INFINITY, f32::NEG_INFINITY] {
+            assert!(
+                with(name, value).validate().is_err(),
+                "{} = {} is rejected",
+                name,
+                value
+            );
+        }
+    }
+}
+
+/// The metrics with every one of them at its lower or upper bound.
+fn at_bounds(upper: bool) -> LayoutMetrics {
+    LayoutMetrics::BOUNDS
+        .iter()
+        .fold(LayoutMetrics::default(), |metrics, &(name, min, max)| {
+            let value = if upper { max } else { min };
+            with_metric(metrics, name, value)
+        })
+}
+
+/// `metrics` with one of them set by name.
+fn with_metric(mut metrics: LayoutMetrics, name: &str, value: f32) -> LayoutMetrics {
+    *field(&mut metrics, name) = value;
+    metrics
+}
+
+/// Renders the demo keymap with `metrics` and checks that the document
+/// parses and that no two key rectangles overlap.
+fn assert_renders_without_overlaps(metrics: LayoutMetrics) {
+    let layers = parse_layers(DEMO_KEYMAP).expect("the demo keymap parses");
+    let options = RenderOptions {
+        metrics: metrics.clone(),
+        ..common::options_for(&layers)
+    };
+    let svg = generate_svg_with_options(&layers, &options);
+    usvg::Tree::from_str(&svg, &usvg::Options::default())
+        .unwrap_or_else(|err| panic!("the SVG at {:?} is invalid: {}", metrics, err));
+
+    let attribute = |rect: &str, name: &str| -> f32 {
+        Regex::new(&format!(r#" {}="([^"]+)""#, name))
+            .unwrap()
+            .captures(rect)
+            .and_then(|captures| captures[1].parse().ok())
+            .unwrap_or_else(|| panic!("{} has no {}", rect, name))
+    };
+    let keys: Vec<(f32, f32, f32, f32)> = Regex::new(r#"<rect class="key[ "][^>]*>"#)
+        .unwrap()
+        .find_iter(&svg)
+        .map(|rect| {
+            let rect = rect.as_str();
+            let (x, y) = (attribute(rect, "x"), attribute(rect, "y"));
+            (
+                x,
+                y,
+                x + attribute(rect, "width"),
+                y + attribute(rect, "height"),
+            )
+        })
+        .collect();
+    let key_count: usize = layers
+        .iter()
+        .map(|layer| layer.keys.iter().flatten().count())
+        .sum();
+    assert_eq!(keys.len(), key_count, "every key is drawn");
+
+    for (i, a) in keys.iter().enumerate() {
+        for b in &keys[i + 1..] {
+            // Keys may touch when the spacing is 0, but not overlap
+            let overlaps =
+                a.0 < b.2 - 0.01 && b.0 < a.2 - 0.01 && a.1 < b.3 - 0.01 && b.1 < a.3 - 0.01;
+            assert!(
+                !overlaps,
+                "keys {:?} and {:?} overlap at {:?}",
+                a, b, metrics
+            );
+        }
+    }
+}
+
+#[test]
+fn renders_at_the_lower_bounds_have_no_overlapping_keys() {
+    assert_renders_without_overlaps(at_bounds(false));
+}
+
+#[test]
+fn renders_at_the_upper_bounds_have_no_overlapping_keys() {
+    assert_renders_without_overlaps(at_bounds(true));
+}
@@ -0,0 +1,33 @@
+//! Checks of keymap notes files applied to parsed layers.
+use keyball44_viz::analysis::unreachable_layers;
+use keyball44_viz::notes::KeymapNotes;
+use keyball44_viz::{generate_svg, parse_layers};
+
+const KEYMAP: &str = "\
+enum layers { _BASE, _NAV };
+const uint16_t PROGMEM keymaps[][MATRIX_ROWS][MATRIX_COLS] = {
+    [_BASE] = LAYOUT(
+        KC_A, KC_B, MO(_NAV)
+    ),
+    [_NAV] = LAYOUT(
+        KC_1, KC_2, _______
+    )
+};
+";
+
+#[test]
+fn renamed_layers_keep_resolving_by_designator() {
+    let mut layers = parse_layers(KEYMAP).expect("the keymap parses");
+    let mut notes: KeymapNotes =
+        toml::from_str("[layers.NAV]\nname = \"Navigation\"\n").expect("the notes parse");
+    notes.apply(&mut layers);
+
+    assert_eq!(layers[1].name.as_deref(), Some("_NAV"));
+    assert_eq!(layers[1].display_name(), Some("Navigation"));
+    assert!(layers[1].matches_selector("_NAV"));
+    assert!(layers[1].matches_selector("Navigation"));
+    assert_eq!(unreachable_layers(&layers), Vec::<usize>::new());
+
+    let svg = generate_svg(&layers);
+    assert!(svg.contains("Navigation"), "the title uses the new name");
+}
@@ -0,0 +1,113 @@
+//! Checks of `parse_layers` on LAYOUT blocks written in unusual ways.
+use keyball44_viz::{layer_symbols, parse_layers};
+
+/// The keys of every layer, flattened in LAYOUT order.
+fn keys(source: &str) -> Vec<Vec<String>> {
+    parse_layers(source)
+        .expect("the keymap parses")
+        .iter()
+        .map(|layer| layer.keys.iter().flatten().cloned().collect())
+        .collect()
+}
+
+#[test]
+fn block_comments_inside_a_layout_are_ignored() {
+    let source = "\
+const uint16_t PROGMEM keymaps[][MATRIX_ROWS][MATRIX_COLS] = {
+    [0] = LAYOUT(
+        KC_A, KC_B, /* hold :) */ KC_C,
+        KC_D, /* (unbalanced */ KC_E, KC_F
+    ),
+    [1] = LAYOUT(
+        KC_1, KC_2, KC_3, /* a comment
+        spanning lines ( */
+        KC_4, KC_5, KC_6
+    )
+};
+";
+    assert_eq!(
+        keys(source),
+        vec![
+            vec!["KC_A", "KC_B", "KC_C", "KC_D", "KC_E", "KC_F"],
+            vec!["KC_1", "KC_2", "KC_3", "KC_4", "KC_5", "KC_6"],
+        ]
+    );
+}
+
+#[test]
+fn block_comment_markers_in_line_comments_are_ignored() {
+    let source = "\
+const uint16_t PROGMEM keymaps[][MATRIX_ROWS][MATRIX_COLS] = {
+    [0] = LAYOUT(
+        KC_A, KC_B, // see /* here
+        KC_C, KC_D
+    )
+};
+";
+    assert_eq!(keys(source), vec![vec!["KC_A", "KC_B", "KC_C", "KC_D"]]);
+}
+
+#[test]
+fn a_stray_paren_is_still_reported() {
+    let source = "\
+const uint16_t PROGMEM keymaps[][MATRIX_ROWS][MATRIX_COLS] = {
+    [0] = LAYOUT(
+        KC_A, KC_B), KC_C,
+        KC_D
+    )
+};
+";
+    let err = parse_layers(source).expect_err("the early `)` is an error");
+    assert!(
+        format!("{:#}", err).contains("before the end of the line"),
+        "unexpected error: {:#}",
+        err
+    );
+}
+
+#[test]
+fn only_enums_used_as_layers_name_layers() {
+    let source = "\
+enum combos { CMB_JK, CMB_DF };
+enum layers { _BASE, _NAV };
+const uint16_t PROGMEM keymaps[][MATRIX_ROWS][MATRIX_COLS] = {
+    [0] = LAYOUT(
+        KC_A, MO(_NAV)
+    ),
+    [1] = LAYOUT(
+        KC_1, _______
+    )
+};
+const uint16_t PROGMEM jk_combo[] = {KC_J, KC_K, COMBO_END};
+combo_t key_combos[] = {
+    [CMB_JK] = COMBO(jk_combo, KC_ESC),
+};
+";
+    let symbols = layer_symbols(source);
+    assert_eq!(
+        symbols,
+        vec![("_BASE".to_string(), 0), ("_NAV".to_string(), 1)]
+    );
+    let names: Vec<Option<String>> = parse_layers(source)
+        .expect("the keymap parses")
+        .into_iter()
+        .map(|layer| layer.name)
+        .collect();
+    assert_eq!(names, vec![Some("_BASE".into()), Some("_NAV".into())]);
+}
+
+#[test]
+fn enums_not_used_as_layers_are_ignored() {
+    let source = "\
+enum combos { CMB_JK, CMB_DF };
+const uint16_t PROGMEM keymaps[][MATRIX_ROWS][MATRIX_COLS] = {
+    [0] = LAYOUT(
+        KC_A, MO(1)
+    ),
+    [1] = LAYOUT(
+        KC_1, _______
+    )
+};
+";
+    assert_eq!(layer_symbols(source), vec![]);
+}
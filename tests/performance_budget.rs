@@ -0,0 +1,61 @@
+//! The performance budget: rendering must stay fast enough for watch mode to
+//! redraw on every save. The budgets hold for unoptimized test builds, so
+//! they fail on regressions of an order of magnitude rather than on noise;
+//! `cargo bench --bench render` gives precise numbers.
+use keyball44_viz::{
+    generate_single_card_svg_with_options, generate_svg_with_options, parse_layers,
+};
+use std::time::{Duration, Instant};
+
+mod common;
+use common::{DEMO_KEYMAP, large_keymap, options_for};
+
+/// A 10-layer keymap from keymap.c source to SVG.
+const END_TO_END_BUDGET: Duration = Duration::from_millis(500);
+
+/// The demo keymap's single card.
+const SINGLE_CARD_BUDGET: Duration = Duration::from_millis(50);
+
+/// The fastest of a few runs, so one slow run on a busy machine doesn't fail
+/// the budget.
+fn fastest(mut run: impl FnMut()) -> Duration {
+    (0..3)
+        .map(|_| {
+            let start = Instant::now();
+            run();
+            start.elapsed()
+        })
+        .min()
+        .unwrap()
+}
+
+#[test]
+fn ten_layer_keymap_renders_within_budget() {
+    let source = large_keymap(10);
+    let elapsed = fastest(|| {
+        let layers = parse_layers(&source).unwrap();
+        assert_eq!(layers.len(), 10);
+        generate_svg_with_options(&layers, &options_for(&layers));
+    });
+    assert!(
+        elapsed <= END_TO_END_BUDGET,
+        "10-layer render took {:?}, over the {:?} budget",
+        elapsed,
+        END_TO_END_BUDGET
+    );
+}
+
+#[test]
+fn single_card_renders_within_budget() {
+    let layers = parse_layers(DEMO_KEYMAP).unwrap();
+    let options = options_for(&layers);
+    let elapsed = fastest(|| {
+        generate_single_card_svg_with_options(&layers, &options);
+    });
+    assert!(
+        elapsed <= SINGLE_CARD_BUDGET,
+        "single card took {:?}, over the {:?} budget",
+        elapsed,
+        SINGLE_CARD_BUDGET
+    );
+}
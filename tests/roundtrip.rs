@@ -0,0 +1,62 @@
+//! Export→import round trips of the demo keymap through every format the
+//! tool both writes and reads, checked with `roundtrip_losses` as
+//! `--verify-roundtrip` does.
+use keyball44_viz::diff::roundtrip_losses;
+use keyball44_viz::format::format_layouts;
+use keyball44_viz::keymap_toml::{parse_toml_keymap, to_toml};
+use keyball44_viz::{Geometry, Layer, oryx, parse_layers};
+
+const DEMO: &str = include_str!("../demo/keymap.c");
+
+fn demo_layers() -> Vec<Layer> {
+    parse_layers(DEMO).expect("the demo keymap parses")
+}
+
+#[test]
+fn toml_export_round_trips() {
+    let layers = demo_layers();
+    let imported = parse_toml_keymap(&to_toml(&layers)).expect("the export loads back");
+    assert_eq!(roundtrip_losses(&layers, &imported), Vec::<String>::new());
+}
+
+#[test]
+fn generated_keymap_source_round_trips() {
+    let layers = demo_layers();
+    let source = oryx::keymap_source(&layers, "Round trip");
+    let reparsed = parse_layers(&source).expect("the generated keymap.c parses");
+    assert_eq!(roundtrip_losses(&layers, &reparsed), Vec::<String>::new());
+}
+
+#[test]
+fn formatting_round_trips() {
+    let layers = demo_layers();
+    let formatted = format_layouts(DEMO, &Geometry::keyball44()).expect("formatting succeeds");
+    let reparsed = parse_layers(&formatted.content).expect("the formatted keymap parses");
+    assert_eq!(roundtrip_losses(&layers, &reparsed), Vec::<String>::new());
+}
+
+#[test]
+fn lost_keys_names_and_layers_are_reported() {
+    let layers = demo_layers();
+    let mut imported = parse_toml_keymap(&to_toml(&layers).replacen("\"KC_Q\"", "\"KC_W\"", 1))
+        .expect("the edited export loads back");
+    imported[1].name = Some("_RENAMED".to_string());
+    imported.pop();
+
+    let losses = roundtrip_losses(&layers, &imported);
+    assert!(
+        losses.iter().any(|loss| loss.contains("layer(s) became")),
+        "{:?}",
+        losses
+    );
+    assert!(
+        losses.iter().any(|loss| loss.contains("name changed")),
+        "{:?}",
+        losses
+    );
+    assert!(
+        losses.iter().any(|loss| loss.contains("changed KC_Q→KC_W")),
+        "{:?}",
+        losses
+    );
+}
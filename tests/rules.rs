@@ -0,0 +1,39 @@
+//! Checks of `feature_warnings`: keycodes of disabled features are found
+//! wherever they appear in a key.
+use keyball44_viz::rules::{feature_warnings, parse_rules};
+
+mod common;
+use common::parse_keymap;
+
+#[test]
+fn keycodes_nested_in_arguments_are_checked() {
+    let rules = parse_rules("MOUSEKEY_ENABLE = no\n");
+    let warnings = feature_warnings(&rules, &parse_keymap(&["KC_A, LT(1, KC_MS_BTN1)"]), None);
+    assert_eq!(
+        warnings,
+        vec!["Layer 0 uses KC_MS_BTN1 in LT(1, KC_MS_BTN1) but MOUSEKEY_ENABLE appears disabled"]
+    );
+}
+
+#[test]
+fn keycodes_nested_twice_are_checked() {
+    let rules = parse_rules("MOUSEKEY_ENABLE = no\n");
+    let warnings = feature_warnings(&rules, &parse_keymap(&["KC_A, LCTL(LSFT(KC_BTN2))"]), None);
+    assert_eq!(
+        warnings,
+        vec!["Layer 0 uses KC_BTN2 in LCTL(LSFT(KC_BTN2)) but MOUSEKEY_ENABLE appears disabled"]
+    );
+}
+
+#[test]
+fn enabled_features_are_not_reported() {
+    let rules = parse_rules("MOUSEKEY_ENABLE = yes\n");
+    assert!(feature_warnings(&rules, &parse_keymap(&["KC_A, LT(1, KC_MS_BTN1)"]), None).is_empty());
+}
+
+#[test]
+fn features_rules_mk_does_not_set_are_not_reported() {
+    let rules = parse_rules("VIA_ENABLE = yes\nTAP_DANCE_ENABLE = $(TAP_DANCE)\n");
+    let layers = parse_keymap(&["KC_BTN1, TD(0), RGB_TOG"]);
+    assert!(feature_warnings(&rules, &layers, Some("tap_dance_action_t")).is_empty());
+}
@@ -0,0 +1,35 @@
+//! Checks of the lookup of tap dances by the `TD(...)` keycodes using them.
+use keyball44_viz::tap_dance::parse_tap_dances;
+
+#[test]
+fn designated_dances_are_numbered_by_their_enum_value() {
+    let source = "\
+enum tap_dances { TD_ESC_CAPS, TD_LAYER = 3 };
+tap_dance_action_t tap_dance_actions[] = {
+    [TD_LAYER] = ACTION_TAP_DANCE_LAYER_TOGGLE(KC_1, 2),
+    [TD_ESC_CAPS] = ACTION_TAP_DANCE_DOUBLE(KC_ESC, KC_CAPS),
+};
+";
+    let dances = parse_tap_dances(source);
+    let name = |key: &str| dances.get(key).map(|dance| dance.name.as_str());
+    assert_eq!(name("TD(TD_ESC_CAPS)"), Some("TD_ESC_CAPS"));
+    assert_eq!(name("TD(0)"), Some("TD_ESC_CAPS"));
+    assert_eq!(name("TD(3)"), Some("TD_LAYER"));
+    assert_eq!(name("TD(1)"), None);
+}
+
+#[test]
+fn undesignated_dances_are_numbered_by_position() {
+    let source = "\
+tap_dance_action_t tap_dance_actions[] = {
+    ACTION_TAP_DANCE_DOUBLE(KC_ESC, KC_CAPS),
+    ACTION_TAP_DANCE_DOUBLE(KC_SCLN, KC_COLN),
+};
+";
+    let dances = parse_tap_dances(source);
+    let describe = |key: &str| dances.get(key).map(|dance| dance.describe(key));
+    assert_eq!(
+        describe("TD(1)").as_deref(),
+        Some("TD(1): tap KC_SCLN, double-tap KC_COLN")
+    );
+}